@@ -1,24 +1,56 @@
-use crate::{GuildSpeakerHandle, GuildSpeakerRef, SongMetadata, Speaker};
-use futures::prelude::*;
+use crate::events::PlaybackEvents;
+use crate::speaker::GuildFiltersMap;
+use crate::{
+    GuildFilters, GuildSpeakerHandle, GuildSpeakerRef, PlaybackEvent, SongMetadata, Speaker,
+};
 use serenity::model::prelude::*;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
 pub struct Brain {
-    pub speakers: Vec<Arc<Speaker>>,
+    speakers: RwLock<Vec<Arc<Speaker>>>,
+    pub(crate) filters: GuildFiltersMap,
+    pub(crate) events: PlaybackEvents,
 }
 
 impl Brain {
     pub fn new() -> Self {
+        // Uptime is counted from when the brain is created
+        lazy_static::initialize(&crate::counters::COUNTERS);
         Brain {
-            speakers: Vec::new(),
+            speakers: RwLock::new(Vec::new()),
+            filters: GuildFiltersMap::default(),
+            events: PlaybackEvents::default(),
         }
     }
 
+    /// The speakers that are currently registered. Speakers can be added and removed while the bot
+    /// is running, so this is only a snapshot.
+    pub fn speakers(&self) -> Vec<Arc<Speaker>> {
+        self.speakers.read().unwrap().clone()
+    }
+
+    pub(crate) fn add_speaker(&self, speaker: Arc<Speaker>) {
+        self.speakers.write().unwrap().push(speaker);
+    }
+
+    /// Stops a speaker from being used for anything new. Songs it's already playing aren't
+    /// stopped.
+    pub fn remove_speaker(&self, speaker: &Arc<Speaker>) {
+        self.speakers
+            .write()
+            .unwrap()
+            .retain(|other| !Arc::ptr_eq(other, speaker));
+    }
+
     pub fn guild_speakers(&self, guild_id: GuildId) -> BrainSpeakersHandle {
         let guild_speaker_handles: Vec<_> = self
             .speakers
+            .read()
+            .unwrap()
             .iter()
+            .filter(|speaker| !speaker.is_stopped())
             .map(|speaker| speaker.get(guild_id))
             .collect();
 
@@ -26,6 +58,17 @@ impl Brain {
             guild_speaker_handles,
         }
     }
+
+    /// The audio filters applied to songs played in a guild, which can be changed while songs are
+    /// playing.
+    pub fn guild_filters(&self, guild_id: GuildId) -> Arc<GuildFilters> {
+        self.filters.entry(guild_id).or_default().clone()
+    }
+
+    /// Hears about songs starting, pausing, resuming and stopping on every speaker.
+    pub fn subscribe_playback_events(&self) -> broadcast::Receiver<PlaybackEvent> {
+        self.events.subscribe()
+    }
 }
 
 impl Default for Brain {
@@ -39,84 +82,146 @@ pub struct BrainSpeakersHandle {
 }
 
 impl BrainSpeakersHandle {
-    pub async fn lock(&self) -> BrainSpeakersRef<'_> {
-        let guild_speaker_refs = future::join_all(
-            self.guild_speaker_handles
-                .iter()
-                .map(|handle| handle.lock()),
-        )
-        .await;
-        BrainSpeakersRef { guild_speaker_refs }
+    /// Speakers aren't locked until they're looked at, so this doesn't wait on anything.
+    pub fn lock(&self) -> BrainSpeakersRef<'_> {
+        BrainSpeakersRef {
+            guild_speaker_handles: &self.guild_speaker_handles,
+            guild_speaker_refs: self.guild_speaker_handles.iter().map(|_| None).collect(),
+        }
     }
 }
 
+/// A guild's speakers, locked one at a time as they're needed. Speakers that are found stay locked
+/// until this is dropped, and the others are unlocked again straight away so songs in other
+/// channels aren't held up.
 pub struct BrainSpeakersRef<'handle> {
-    guild_speaker_refs: Vec<GuildSpeakerRef<'handle>>,
+    guild_speaker_handles: &'handle [GuildSpeakerHandle],
+    guild_speaker_refs: Vec<Option<GuildSpeakerRef<'handle>>>,
 }
 
 impl<'handle> BrainSpeakersRef<'handle> {
-    pub fn find_active_in_channel(
+    /// Looks at each speaker in turn until one matches. Speakers are always locked in the same
+    /// order, so two searches can't end up waiting on each other.
+    async fn find_index(
         &mut self,
-        channel_id: ChannelId,
-    ) -> Option<(&mut GuildSpeakerRef<'handle>, SongMetadata)> {
-        for guild_speaker in &mut self.guild_speaker_refs {
-            if let (Some(current_channel_id), Some(metadata)) = (
-                guild_speaker.current_channel(),
-                guild_speaker.active_metadata(),
-            ) {
-                if current_channel_id == channel_id {
-                    return Some((guild_speaker, metadata));
+        mut predicate: impl FnMut(usize, &GuildSpeakerRef<'handle>) -> bool,
+    ) -> Option<usize> {
+        let guild_speaker_handles = self.guild_speaker_handles;
+        for (index, handle) in guild_speaker_handles.iter().enumerate() {
+            match &self.guild_speaker_refs[index] {
+                Some(guild_speaker) => {
+                    if predicate(index, guild_speaker) {
+                        return Some(index);
+                    }
+                }
+                None => {
+                    let guild_speaker = handle.lock().await;
+                    if predicate(index, &guild_speaker) {
+                        self.guild_speaker_refs[index] = Some(guild_speaker);
+                        return Some(index);
+                    }
                 }
             }
         }
         None
     }
 
-    pub fn find_active_song(
+    fn get_mut(&mut self, index: usize) -> &mut GuildSpeakerRef<'handle> {
+        self.guild_speaker_refs[index]
+            .as_mut()
+            .expect("Found speakers should be locked")
+    }
+
+    pub async fn find_active_in_channel(
         &mut self,
-        song_id: Uuid,
+        channel_id: ChannelId,
     ) -> Option<(&mut GuildSpeakerRef<'handle>, SongMetadata)> {
-        for guild_speaker in &mut self.guild_speaker_refs {
-            if let Some(metadata) = guild_speaker.active_metadata() {
-                if metadata.id == song_id {
-                    return Some((guild_speaker, metadata));
-                }
+        let index = self
+            .find_index(|_, guild_speaker| {
+                guild_speaker.current_channel() == Some(channel_id) && guild_speaker.is_active()
+            })
+            .await?;
+        let guild_speaker = self.get_mut(index);
+        let metadata = guild_speaker.active_metadata()?;
+        Some((guild_speaker, metadata))
+    }
+
+    /// Locks every speaker, for when all of them need to be looked at.
+    pub async fn lock_all(&mut self) -> impl Iterator<Item = &mut GuildSpeakerRef<'handle>> {
+        for (index, handle) in self.guild_speaker_handles.iter().enumerate() {
+            if self.guild_speaker_refs[index].is_none() {
+                self.guild_speaker_refs[index] = Some(handle.lock().await);
             }
         }
-        None
+        self.guild_speaker_refs.iter_mut().flatten()
     }
 
-    pub fn find_to_play_in_channel(
+    pub async fn find_in_channel(
         &mut self,
         channel_id: ChannelId,
     ) -> Option<&mut GuildSpeakerRef<'handle>> {
-        // Look for a speaker already in the channel
-        // The weird way of doing this is a workaround for
-        // https://users.rust-lang.org/t/solved-borrow-doesnt-drop-returning-this-value-requires-that/24182
-        let already_in_channel_index = self
-            .guild_speaker_refs
-            .iter()
-            .position(|guild_speaker| guild_speaker.current_channel() == Some(channel_id));
-        if let Some(index) = already_in_channel_index {
-            return Some(&mut self.guild_speaker_refs[index]);
-        }
+        let index = self
+            .find_index(|_, guild_speaker| guild_speaker.current_channel() == Some(channel_id))
+            .await?;
+        Some(self.get_mut(index))
+    }
 
-        // Look for a speaker not in any channel
-        let not_in_channel_index = self
-            .guild_speaker_refs
-            .iter()
-            .position(|guild_speaker| guild_speaker.current_channel().is_none());
-        if let Some(index) = not_in_channel_index {
-            return Some(&mut self.guild_speaker_refs[index]);
+    pub async fn find_active_song(
+        &mut self,
+        song_id: Uuid,
+    ) -> Option<(&mut GuildSpeakerRef<'handle>, SongMetadata)> {
+        let index = self
+            .find_index(|_, guild_speaker| {
+                guild_speaker
+                    .active_metadata()
+                    .is_some_and(|metadata| metadata.id == song_id)
+            })
+            .await?;
+        let guild_speaker = self.get_mut(index);
+        let metadata = guild_speaker.active_metadata()?;
+        Some((guild_speaker, metadata))
+    }
+
+    pub async fn find_to_play_in_channel(
+        &mut self,
+        channel_id: ChannelId,
+    ) -> Option<&mut GuildSpeakerRef<'handle>> {
+        // Look for a speaker already in the channel, while noting down the ones that are free.
+        // Speakers not in any channel are preferred over ones in a different channel but not
+        // active, then whichever is playing in the fewest guilds so songs are spread evenly
+        // between the voice clients.
+        let mut free_speakers = Vec::new();
+        let maybe_in_channel_index = self
+            .find_index(
+                |index, guild_speaker| match guild_speaker.current_channel() {
+                    Some(current_channel_id) if current_channel_id == channel_id => true,
+                    Some(_) if guild_speaker.is_active() => false,
+                    maybe_channel_id => {
+                        free_speakers.push((
+                            maybe_channel_id.is_some(),
+                            guild_speaker.load(),
+                            index,
+                        ));
+                        false
+                    }
+                },
+            )
+            .await;
+        if let Some(index) = maybe_in_channel_index {
+            return Some(self.get_mut(index));
         }
 
-        // Look for a speaker in a different channel but not active
-        let not_active_index = self
-            .guild_speaker_refs
-            .iter()
-            .position(|guild_speaker| !guild_speaker.is_active());
-        if let Some(index) = not_active_index {
-            return Some(&mut self.guild_speaker_refs[index]);
+        // Speakers were unlocked after being looked at, so one could have started playing since
+        free_speakers.sort();
+        for (_, _, index) in free_speakers {
+            if self.guild_speaker_refs[index].is_none() {
+                self.guild_speaker_refs[index] =
+                    Some(self.guild_speaker_handles[index].lock().await);
+            }
+            if !self.get_mut(index).is_active() {
+                return Some(self.get_mut(index));
+            }
+            self.guild_speaker_refs[index] = None;
         }
 
         None
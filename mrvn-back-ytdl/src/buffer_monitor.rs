@@ -0,0 +1,130 @@
+use songbird::input::core::io::MediaSource;
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Reads that wait longer than this mean the buffer ran dry, since a full buffer hands data
+/// over straight away.
+const UNDERRUN_THRESHOLD: Duration = Duration::from_millis(20);
+
+/// Waits long enough to be heard as a gap in the audio.
+const STALL_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// How often playback had to wait for a stream's buffer to fill back up.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BufferStats {
+    pub tracks: u64,
+    pub underruns: u64,
+    pub stalls: u64,
+    pub stalled_time: Duration,
+}
+
+impl BufferStats {
+    fn add(&mut self, other: &BufferStats) {
+        self.tracks += other.tracks;
+        self.underruns += other.underruns;
+        self.stalls += other.stalls;
+        self.stalled_time += other.stalled_time;
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref TOTALS: Mutex<BufferStats> = Mutex::new(BufferStats::default());
+}
+
+/// The buffer stats of every streamed track that's finished since the bot started.
+pub fn buffer_stats() -> BufferStats {
+    *TOTALS.lock().unwrap()
+}
+
+/// The buffer stats of one track, shared between its stream and the speaker playing it.
+#[derive(Clone, Default)]
+pub(crate) struct BufferCounter(Arc<Mutex<BufferStats>>);
+
+impl BufferCounter {
+    pub(crate) fn get(&self) -> BufferStats {
+        *self.0.lock().unwrap()
+    }
+
+    fn record_wait(&self, wait: Duration) {
+        let mut stats = self.0.lock().unwrap();
+        stats.underruns += 1;
+        if wait >= STALL_THRESHOLD {
+            stats.stalls += 1;
+        }
+        stats.stalled_time += wait;
+    }
+}
+
+/// Times each read songbird makes from a stream's buffer, counting the ones that had to wait for
+/// more data to be downloaded.
+pub(crate) struct BufferMonitor {
+    inner: Box<dyn MediaSource>,
+    counter: BufferCounter,
+    /// The buffer starts out empty, so waiting for the first data isn't an underrun.
+    has_started: bool,
+}
+
+impl BufferMonitor {
+    pub(crate) fn new(inner: Box<dyn MediaSource>, counter: BufferCounter) -> Self {
+        BufferMonitor {
+            inner,
+            counter,
+            has_started: false,
+        }
+    }
+}
+
+impl Read for BufferMonitor {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let start = Instant::now();
+        let read = self.inner.read(buf)?;
+        let wait = start.elapsed();
+        if read == 0 {
+            return Ok(0);
+        }
+
+        if self.has_started && wait >= UNDERRUN_THRESHOLD {
+            tracing::trace!("Waited {:?} for the stream buffer to fill", wait);
+            self.counter.record_wait(wait);
+        }
+        self.has_started = true;
+        Ok(read)
+    }
+}
+
+impl Seek for BufferMonitor {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        // Seeking empties the buffer, so the next read is like the first one
+        self.has_started = false;
+        self.inner.seek(pos)
+    }
+}
+
+impl MediaSource for BufferMonitor {
+    fn is_seekable(&self) -> bool {
+        self.inner.is_seekable()
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.inner.byte_len()
+    }
+}
+
+impl Drop for BufferMonitor {
+    fn drop(&mut self) {
+        let stats = BufferStats {
+            tracks: 1,
+            ..self.counter.get()
+        };
+        if stats.underruns > 0 {
+            tracing::info!(
+                "Stream buffer ran dry {} times while playing, with {} stalls and {:.1}s spent waiting in total",
+                stats.underruns,
+                stats.stalls,
+                stats.stalled_time.as_secs_f64()
+            );
+        }
+        TOTALS.lock().unwrap().add(&stats);
+    }
+}
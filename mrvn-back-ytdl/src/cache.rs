@@ -0,0 +1,147 @@
+use futures::StreamExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::AsyncWriteExt;
+
+/// Finished downloads are written under this extension first, then renamed once complete so a
+/// half-written file is never played.
+const PARTIAL_EXTENSION: &str = "part";
+
+/// Songs are cached under their YouTube video ID. IDs are checked to only contain the characters
+/// YouTube uses, so they can't be used to reach outside the cache directory.
+pub(crate) fn cache_key(webpage_url: &str) -> Option<String> {
+    crate::song::get_youtube_video_id(webpage_url).filter(|video_id| {
+        !video_id.is_empty()
+            && video_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+/// Opens a cached song if it's there, marking it as recently used so it's the last to be
+/// evicted.
+pub(crate) async fn open_cached_file(cache_path: &str, key: &str) -> Option<std::fs::File> {
+    let file = tokio::fs::File::open(Path::new(cache_path).join(key))
+        .await
+        .ok()?
+        .into_std()
+        .await;
+    if let Err(why) = file.set_modified(SystemTime::now()) {
+        tracing::warn!("Error while updating cached song {}: {}", key, why);
+    }
+    Some(file)
+}
+
+/// Downloads a song into the cache in the background, evicting the least recently played songs
+/// if the cache grows over `max_bytes`.
+pub(crate) fn spawn_cache_download(
+    cache_path: String,
+    max_bytes: u64,
+    key: String,
+    request_builder: reqwest::RequestBuilder,
+) {
+    tokio::spawn(async move {
+        match download_to_cache(&cache_path, max_bytes, &key, request_builder).await {
+            Ok(true) => {
+                tracing::trace!("Cached song {}", key);
+                if let Err(why) = evict(&cache_path, max_bytes).await {
+                    tracing::warn!("Error while evicting cached songs: {}", why);
+                }
+            }
+            Ok(false) => {}
+            Err(why) => tracing::warn!("Error while caching song {}: {}", key, why),
+        }
+    });
+}
+
+async fn download_to_cache(
+    cache_path: &str,
+    max_bytes: u64,
+    key: &str,
+    request_builder: reqwest::RequestBuilder,
+) -> std::io::Result<bool> {
+    tokio::fs::create_dir_all(cache_path).await?;
+    let file_path = Path::new(cache_path).join(key);
+    let partial_path = file_path.with_extension(PARTIAL_EXTENSION);
+
+    // Creating the partial file fails if another download of the same song is running.
+    let mut file = match tokio::fs::File::options()
+        .write(true)
+        .create_new(true)
+        .open(&partial_path)
+        .await
+    {
+        Ok(file) => file,
+        Err(why) if why.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+        Err(why) => return Err(why),
+    };
+
+    let result = async {
+        let response = request_builder
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(std::io::Error::other)?;
+
+        // Only plain audio files that fit in the cache are kept, not playlists or live streams.
+        let is_media = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+            .is_some_and(|mime_type| {
+                mime_type.starts_with("audio/") || mime_type.starts_with("video/")
+            });
+        let content_length = match response.content_length() {
+            Some(content_length) if is_media && content_length <= max_bytes => content_length,
+            _ => return Ok(false),
+        };
+
+        let mut received_bytes = 0;
+        let mut stream = response.bytes_stream();
+        while let Some(bytes_maybe) = stream.next().await {
+            let bytes = bytes_maybe.map_err(std::io::Error::other)?;
+            received_bytes += bytes.len() as u64;
+            file.write_all(&bytes).await?;
+        }
+        file.flush().await?;
+
+        Ok(received_bytes == content_length)
+    }
+    .await;
+    drop(file);
+
+    match result {
+        Ok(true) => {
+            tokio::fs::rename(&partial_path, &file_path).await?;
+            Ok(true)
+        }
+        _ => {
+            tokio::fs::remove_file(&partial_path).await?;
+            result
+        }
+    }
+}
+
+async fn evict(cache_path: &str, max_bytes: u64) -> std::io::Result<()> {
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = Vec::new();
+    let mut entries = tokio::fs::read_dir(cache_path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        let is_partial = entry.path().extension() == Some(PARTIAL_EXTENSION.as_ref());
+        if metadata.is_file() && !is_partial {
+            files.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+    }
+
+    let mut total_bytes: u64 = files.iter().map(|(_, len, _)| len).sum();
+    files.sort_by_key(|(modified, _, _)| *modified);
+    for (_, len, path) in files {
+        if total_bytes <= max_bytes {
+            break;
+        }
+        tracing::trace!("Evicting cached song {}", path.display());
+        tokio::fs::remove_file(&path).await?;
+        total_bytes -= len;
+    }
+    Ok(())
+}
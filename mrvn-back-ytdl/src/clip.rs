@@ -0,0 +1,29 @@
+use crate::{Error, HTTP_CLIENT};
+use songbird::input::{File, Input};
+use std::path::PathBuf;
+
+/// Loads a short sound clip, either from an HTTP(S) URL or a local file path.
+pub async fn load_clip(source: &str) -> Result<Input, Error> {
+    match url::Url::parse(source) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => download_clip(url).await,
+        _ => Ok(File::new(PathBuf::from(source)).into()),
+    }
+}
+
+/// Clips are short, so they're downloaded completely before playing.
+pub(crate) async fn download_clip(url: url::Url) -> Result<Input, Error> {
+    let clip = HTTP_CLIENT
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(Error::Http)?
+        .bytes()
+        .await
+        .map_err(Error::Http)?;
+    if clip.is_empty() {
+        return Err(Error::NoDataProvided);
+    }
+
+    Ok(Input::from(clip))
+}
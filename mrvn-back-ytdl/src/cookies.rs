@@ -0,0 +1,96 @@
+use std::time::SystemTime;
+
+/// Netscape cookie files mark HTTP-only cookies by prefixing the line with this, which otherwise
+/// looks like a comment.
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// Bits of youtube-dl's errors that mean the media can only be loaded by a logged in account.
+const LOGIN_REQUIRED_ERRORS: &[&str] = &[
+    "Sign in to confirm",
+    "Sign in if you've been granted access",
+    "members-only content",
+    "available to this channel's members",
+    "only available to Music Premium members",
+    "--cookies",
+    "login required",
+];
+
+pub(crate) fn is_login_required_error(message: &str) -> bool {
+    LOGIN_REQUIRED_ERRORS
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+struct Cookie<'s> {
+    domain: &'s str,
+    include_subdomains: bool,
+    path: &'s str,
+    secure: bool,
+    expires_secs: u64,
+    name: &'s str,
+    value: &'s str,
+}
+
+impl<'s> Cookie<'s> {
+    fn parse(line: &'s str) -> Option<Self> {
+        let line = line.strip_prefix(HTTP_ONLY_PREFIX).unwrap_or(line);
+        if line.starts_with('#') {
+            return None;
+        }
+
+        let mut fields = line.trim_end_matches(['\r', '\n']).split('\t');
+        Some(Cookie {
+            domain: fields.next()?,
+            include_subdomains: fields.next()? == "TRUE",
+            path: fields.next()?,
+            secure: fields.next()? == "TRUE",
+            expires_secs: fields.next()?.parse().ok()?,
+            name: fields.next()?,
+            value: fields.next()?,
+        })
+    }
+
+    fn matches(&self, url: &url::Url, now_secs: u64) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let domain = self.domain.trim_start_matches('.');
+        let domain_matches = host == domain
+            || (self.include_subdomains
+                && host
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.')));
+
+        // Session cookies are saved with an expiry of zero.
+        domain_matches
+            && url.path().starts_with(self.path)
+            && (!self.secure || url.scheme() == "https")
+            && (self.expires_secs == 0 || self.expires_secs > now_secs)
+    }
+}
+
+/// Builds a `Cookie` header for a request to `url` from a Netscape-format cookies file, the same
+/// file youtube-dl is given. The file is read each time so changes to it are picked up without a
+/// restart. Returns `None` if no cookies in the file apply.
+pub(crate) async fn cookie_header(cookies_path: &str, url: &url::Url) -> Option<String> {
+    let contents = match tokio::fs::read_to_string(cookies_path).await {
+        Ok(contents) => contents,
+        Err(why) => {
+            tracing::warn!("Error while reading cookies from {}: {}", cookies_path, why);
+            return None;
+        }
+    };
+    let now_secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let header = contents
+        .lines()
+        .filter_map(Cookie::parse)
+        .filter(|cookie| cookie.matches(url, now_secs))
+        .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+        .collect::<Vec<_>>()
+        .join("; ");
+    (!header.is_empty()).then_some(header)
+}
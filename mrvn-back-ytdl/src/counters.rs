@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How often a cache had what was looked up in it.
+#[derive(Default)]
+pub struct CacheCounter {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounter {
+    pub(crate) fn record(&self, is_hit: bool) {
+        let counter = if is_hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn lookups(&self) -> u64 {
+        self.hits() + self.misses.load(Ordering::Relaxed)
+    }
+}
+
+/// Running totals of what the bot has done since it started. These are only kept in memory, so
+/// they start again from zero after a restart.
+pub struct Counters {
+    /// When the bot started, as a Unix timestamp.
+    pub started_time: u64,
+    songs_played: AtomicU64,
+    pub metadata_cache: CacheCounter,
+    pub audio_cache: CacheCounter,
+}
+
+impl Counters {
+    pub(crate) fn record_song_played(&self) {
+        self.songs_played.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn songs_played(&self) -> u64 {
+        self.songs_played.load(Ordering::Relaxed)
+    }
+}
+
+lazy_static::lazy_static! {
+    pub(crate) static ref COUNTERS: Counters = Counters {
+        started_time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        songs_played: AtomicU64::new(0),
+        metadata_cache: CacheCounter::default(),
+        audio_cache: CacheCounter::default(),
+    };
+}
+
+pub fn counters() -> &'static Counters {
+    &COUNTERS
+}
@@ -0,0 +1,411 @@
+use crate::filters::{
+    Filter, GuildFilters, LoudnessFilter, SilenceTrimFilter, FILTER_CHANNELS, MAX_RATE_CHANGE,
+};
+use crate::songbird::PROBE;
+use crate::Error;
+use rubato::{FastFixedIn, PolynomialDegree, Resampler};
+use songbird::input::codecs::CODEC_REGISTRY;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// The sample rate songs are resampled to, which is what Discord plays at.
+pub const OUTPUT_SAMPLE_RATE: u32 = 48000;
+
+/// How many frames the resampler works on at once.
+const RESAMPLER_CHUNK_FRAMES: usize = 1024;
+
+/// Decodes a song itself rather than leaving it to songbird, so the samples can go through the
+/// guild's filters and then be resampled for playback. Produces interleaved stereo `f32` samples
+/// at [`OUTPUT_SAMPLE_RATE`], to be wrapped in songbird's `RawAdapter`.
+pub struct DecodedPcmSource {
+    state: DecodeState,
+    filters: Arc<GuildFilters>,
+    filters_version: Option<u64>,
+    position: PlaybackPosition,
+    chain: Vec<Box<dyn Filter>>,
+    /// Unlike the other filters, these keep their state for the whole song rather than being
+    /// rebuilt when the guild's filters change.
+    trimmer: Option<SilenceTrimFilter>,
+    normalizer: Option<LoudnessFilter>,
+    output: Vec<u8>,
+    output_pos: usize,
+}
+
+/// How far through a song playback has got, in the song's own time. With the speed changed this
+/// is different from how long the song has been playing for.
+#[derive(Clone)]
+pub struct PlaybackPosition(Arc<AtomicU64>);
+
+impl PlaybackPosition {
+    /// Unknown until the song has started being decoded, which isn't done for songs that skip the
+    /// filters.
+    const UNKNOWN: u64 = u64::MAX;
+
+    pub fn new() -> Self {
+        PlaybackPosition(Arc::new(AtomicU64::new(Self::UNKNOWN)))
+    }
+
+    pub fn get(&self) -> Option<Duration> {
+        match self.0.load(Ordering::Relaxed) {
+            Self::UNKNOWN => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+
+    fn set(&self, position: Duration) {
+        self.0.store(position.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Default for PlaybackPosition {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum DecodeState {
+    /// Probing blocks until enough of the song has arrived over the network, so it's left until
+    /// the first read, which songbird does on a blocking thread.
+    Unprobed {
+        source: Box<dyn MediaSource>,
+        hint: Hint,
+    },
+    Decoding(Box<Decoding>),
+    Finished,
+}
+
+struct Decoding {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    resampler: PcmResampler,
+    decoded_frames: u64,
+}
+
+impl DecodedPcmSource {
+    pub fn new(
+        source: Box<dyn MediaSource>,
+        hint: Hint,
+        filters: Arc<GuildFilters>,
+        position: PlaybackPosition,
+        silence_threshold_db: Option<f32>,
+        loudness_target_lufs: Option<f64>,
+    ) -> Self {
+        DecodedPcmSource {
+            state: DecodeState::Unprobed { source, hint },
+            filters,
+            filters_version: None,
+            position,
+            chain: Vec::new(),
+            trimmer: silence_threshold_db.map(SilenceTrimFilter::new),
+            normalizer: loudness_target_lufs.map(LoudnessFilter::new),
+            output: Vec::new(),
+            output_pos: 0,
+        }
+    }
+
+    /// Decodes, filters and resamples the next packet of the song into the output buffer.
+    /// Returns `false` once the song has ended.
+    fn decode_next(&mut self) -> std::io::Result<bool> {
+        if let DecodeState::Unprobed { .. } = self.state {
+            let DecodeState::Unprobed { source, hint } =
+                std::mem::replace(&mut self.state, DecodeState::Finished)
+            else {
+                unreachable!();
+            };
+            self.state = DecodeState::Decoding(Box::new(Decoding::new(source, &hint)?));
+        }
+        let DecodeState::Decoding(decoding) = &mut self.state else {
+            return Ok(false);
+        };
+
+        let pcm = match decoding.next_samples()? {
+            Some(mut samples) => {
+                decoding.decoded_frames += (samples.len() / FILTER_CHANNELS) as u64;
+                self.position.set(Duration::from_secs_f64(
+                    decoding.decoded_frames as f64 / decoding.sample_rate as f64,
+                ));
+
+                let filters_version = self.filters.version();
+                if self.filters_version != Some(filters_version) {
+                    let settings = self.filters.settings();
+                    self.chain = settings.build_chain();
+                    decoding
+                        .resampler
+                        .set_rate(settings.resample_rate())
+                        .map_err(std::io::Error::other)?;
+                    self.filters_version = Some(filters_version);
+                }
+                if let Some(trimmer) = &mut self.trimmer {
+                    trimmer.process(&mut samples, decoding.sample_rate);
+                }
+                if let Some(normalizer) = &mut self.normalizer {
+                    normalizer.process(&mut samples, decoding.sample_rate);
+                }
+                for filter in &mut self.chain {
+                    filter.process(&mut samples, decoding.sample_rate);
+                }
+                decoding.resampler.process(&samples)?
+            }
+            None => {
+                let remaining = decoding.resampler.flush()?;
+                self.state = DecodeState::Finished;
+                remaining
+            }
+        };
+
+        self.output.clear();
+        self.output_pos = 0;
+        self.output
+            .extend(pcm.iter().flat_map(|sample| sample.to_le_bytes()));
+        Ok(!matches!(self.state, DecodeState::Finished) || !self.output.is_empty())
+    }
+}
+
+impl Read for DecodedPcmSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.output_pos >= self.output.len() {
+            if !self.decode_next()? {
+                return Ok(0);
+            }
+        }
+
+        let len = (self.output.len() - self.output_pos).min(buf.len());
+        buf[..len].copy_from_slice(&self.output[self.output_pos..(self.output_pos + len)]);
+        self.output_pos += len;
+        Ok(len)
+    }
+}
+
+impl Seek for DecodedPcmSource {
+    fn seek(&mut self, _pos: SeekFrom) -> std::io::Result<u64> {
+        Err(ErrorKind::Unsupported.into())
+    }
+}
+
+impl MediaSource for DecodedPcmSource {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl Decoding {
+    fn new(source: Box<dyn MediaSource>, hint: &Hint) -> std::io::Result<Self> {
+        let stream = MediaSourceStream::new(source, Default::default());
+        let format = PROBE
+            .format(
+                hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|why| std::io::Error::other(Error::Symphonia(why)))?
+            .format;
+
+        let track = format
+            .default_track()
+            .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .or_else(|| {
+                format
+                    .tracks()
+                    .iter()
+                    .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            })
+            .ok_or_else(|| std::io::Error::other(Error::NoTracks))?;
+        let track_id = track.id;
+        let sample_rate = track.codec_params.sample_rate.unwrap_or(OUTPUT_SAMPLE_RATE);
+        let decoder = CODEC_REGISTRY
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|why| std::io::Error::other(Error::Symphonia(why)))?;
+
+        Ok(Decoding {
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            resampler: PcmResampler::new(sample_rate).map_err(std::io::Error::other)?,
+            decoded_frames: 0,
+        })
+    }
+
+    /// Decodes the next packet of the song as interleaved stereo samples, or returns `None` if
+    /// the song has ended.
+    fn next_samples(&mut self) -> std::io::Result<Option<Vec<f32>>> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(why)) if why.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(None)
+                }
+                Err(SymphoniaError::ResetRequired) => {
+                    self.decoder.reset();
+                    continue;
+                }
+                Err(why) => return Err(std::io::Error::other(Error::Symphonia(why))),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let spec = *decoded.spec();
+                    let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                    buffer.copy_interleaved_ref(decoded);
+                    return Ok(Some(to_stereo(buffer.samples(), spec.channels.count())));
+                }
+                Err(SymphoniaError::DecodeError(why)) => {
+                    tracing::warn!("Skipping packet that couldn't be decoded: {}", why);
+                }
+                Err(SymphoniaError::IoError(why)) if why.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(None)
+                }
+                Err(why) => return Err(std::io::Error::other(Error::Symphonia(why))),
+            }
+        }
+    }
+}
+
+/// Filters always work in stereo, so mono songs are duplicated into both channels and anything
+/// with more channels only keeps the first two.
+fn to_stereo(samples: &[f32], channels: usize) -> Vec<f32> {
+    match channels {
+        0 => Vec::new(),
+        1 => samples
+            .iter()
+            .flat_map(|sample| [*sample, *sample])
+            .collect(),
+        FILTER_CHANNELS => samples.to_vec(),
+        _ => samples
+            .chunks_exact(channels)
+            .flat_map(|frame| [frame[0], frame[1]])
+            .collect(),
+    }
+}
+
+/// Resamples interleaved stereo samples to the output sample rate. The resampler works on
+/// fixed-size chunks, so samples are buffered until there's enough for a chunk.
+struct PcmResampler {
+    sample_rate: u32,
+    /// Songs that are already at the output sample rate don't need resampling, unless their rate
+    /// has been changed.
+    resampler: Option<FastFixedIn<f32>>,
+    pending: [Vec<f32>; FILTER_CHANNELS],
+}
+
+impl PcmResampler {
+    fn new(sample_rate: u32) -> Result<Self, Error> {
+        let mut resampler = PcmResampler {
+            sample_rate,
+            resampler: None,
+            pending: Default::default(),
+        };
+        resampler.set_rate(1.)?;
+        Ok(resampler)
+    }
+
+    /// Changes how much faster than normal the song plays, which also raises its pitch by the
+    /// same amount. Can be changed in the middle of a song.
+    fn set_rate(&mut self, rate: f32) -> Result<(), Error> {
+        let base_ratio = OUTPUT_SAMPLE_RATE as f64 / self.sample_rate as f64;
+        let ratio = base_ratio / rate.clamp(1. / MAX_RATE_CHANGE, MAX_RATE_CHANGE) as f64;
+
+        match &mut self.resampler {
+            Some(resampler) => resampler
+                .set_resample_ratio(ratio, true)
+                .map_err(Error::Rubato),
+            None if ratio == 1. => Ok(()),
+            None => {
+                // A little headroom so rounding doesn't push the limits out of range.
+                let mut resampler = FastFixedIn::new(
+                    base_ratio,
+                    MAX_RATE_CHANGE as f64 * 1.01,
+                    PolynomialDegree::Cubic,
+                    RESAMPLER_CHUNK_FRAMES,
+                    FILTER_CHANNELS,
+                )
+                .map_err(Error::RubatoConstruction)?;
+                resampler
+                    .set_resample_ratio(ratio, false)
+                    .map_err(Error::Rubato)?;
+                self.resampler = Some(resampler);
+                Ok(())
+            }
+        }
+    }
+
+    fn process(&mut self, samples: &[f32]) -> std::io::Result<Vec<f32>> {
+        let Some(resampler) = &mut self.resampler else {
+            return Ok(samples.to_vec());
+        };
+
+        for frame in samples.chunks_exact(FILTER_CHANNELS) {
+            for (pending, sample) in self.pending.iter_mut().zip(frame) {
+                pending.push(*sample);
+            }
+        }
+
+        let mut output = Vec::new();
+        loop {
+            let frames = resampler.input_frames_next();
+            if self.pending[0].len() < frames {
+                break;
+            }
+            let input: Vec<&[f32]> = self
+                .pending
+                .iter()
+                .map(|pending| &pending[..frames])
+                .collect();
+            let resampled = resampler
+                .process(&input, None)
+                .map_err(|why| std::io::Error::other(Error::Rubato(why)))?;
+            interleave_into(&resampled, &mut output);
+            for pending in &mut self.pending {
+                pending.drain(..frames);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Resamples whatever is left over at the end of a song.
+    fn flush(&mut self) -> std::io::Result<Vec<f32>> {
+        let Some(resampler) = &mut self.resampler else {
+            return Ok(Vec::new());
+        };
+        if self.pending[0].is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let resampled = resampler
+            .process_partial(Some(&self.pending), None)
+            .map_err(|why| std::io::Error::other(Error::Rubato(why)))?;
+        for pending in &mut self.pending {
+            pending.clear();
+        }
+        let mut output = Vec::new();
+        interleave_into(&resampled, &mut output);
+        Ok(output)
+    }
+}
+
+fn interleave_into(channels: &[Vec<f32>], output: &mut Vec<f32>) {
+    let frames = channels.iter().map(Vec::len).min().unwrap_or(0);
+    output.reserve(frames * channels.len());
+    for frame in 0..frames {
+        output.extend(channels.iter().map(|channel| channel[frame]));
+    }
+}
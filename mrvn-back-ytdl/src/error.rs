@@ -4,6 +4,8 @@ pub enum Error {
     Runtime(tokio::task::JoinError),
     Parse(serde_json::Error, String),
     Ytdl(String),
+    Innertube(String),
+    LoginRequired(String),
     Http(reqwest::Error),
     SongbirdJoin(songbird::error::JoinError),
     SongbirdControl(songbird::error::ControlError),
@@ -11,8 +13,10 @@ pub enum Error {
     RubatoConstruction(rubato::ResamplerConstructionError),
     Rubato(rubato::ResampleError),
     UnsupportedUrl,
+    NotConnected,
     NoDataProvided,
     NoTracks,
+    NotSeekable,
     ScanTimedOut,
 }
 
@@ -23,6 +27,8 @@ impl std::fmt::Display for Error {
             Error::Runtime(err) => err.fmt(f),
             Error::Parse(err, value) => write!(f, "{}: {}", err, value),
             Error::Ytdl(err) => write!(f, "Could not load media: {}", err),
+            Error::Innertube(err) => write!(f, "Could not load from YouTube: {}", err),
+            Error::LoginRequired(err) => write!(f, "Media needs a logged in account: {}", err),
             Error::Http(err) => err.fmt(f),
             Error::SongbirdJoin(err) => err.fmt(f),
             Error::SongbirdControl(err) => err.fmt(f),
@@ -30,8 +36,10 @@ impl std::fmt::Display for Error {
             Error::RubatoConstruction(err) => err.fmt(f),
             Error::Rubato(err) => err.fmt(f),
             Error::UnsupportedUrl => write!(f, "Unsupported URL"),
+            Error::NotConnected => write!(f, "Not connected to a voice channel"),
             Error::NoDataProvided => write!(f, "No data provided"),
             Error::NoTracks => write!(f, "Media did not have any playable tracks"),
+            Error::NotSeekable => write!(f, "Media can't be seeked"),
             Error::ScanTimedOut => write!(f, "Media scan timed out"),
         }
     }
@@ -0,0 +1,59 @@
+use crate::SongMetadata;
+use serenity::model::prelude::*;
+use tokio::sync::broadcast;
+
+/// How many events a slow subscriber can fall behind by before it starts missing them.
+const PLAYBACK_EVENTS_CAPACITY: usize = 64;
+
+/// Something that changed about what a speaker is playing.
+#[derive(Clone)]
+pub enum PlaybackEvent {
+    Started {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        metadata: Box<SongMetadata>,
+    },
+    Paused {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    },
+    Resumed {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    },
+    /// The speaker is moving to another voice server, and the song will carry on once it has.
+    Reconnecting {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    },
+    Reconnected {
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    },
+    /// The speaker stopped playing, and has nothing else to play.
+    Stopped {
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+    },
+}
+
+/// Sends playback events from every speaker to whoever's subscribed to them.
+#[derive(Clone)]
+pub(crate) struct PlaybackEvents(broadcast::Sender<PlaybackEvent>);
+
+impl PlaybackEvents {
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<PlaybackEvent> {
+        self.0.subscribe()
+    }
+
+    pub(crate) fn send(&self, event: PlaybackEvent) {
+        // Nobody listening is fine, the event just isn't needed
+        let _ = self.0.send(event);
+    }
+}
+
+impl Default for PlaybackEvents {
+    fn default() -> Self {
+        PlaybackEvents(broadcast::channel(PLAYBACK_EVENTS_CAPACITY).0)
+    }
+}
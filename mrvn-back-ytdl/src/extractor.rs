@@ -0,0 +1,78 @@
+use crate::{Error, PlayConfig};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+/// A youtube-dl compatible program that songs are loaded with, like yt-dlp.
+#[derive(Clone, Copy, Debug)]
+pub struct Extractor<'s> {
+    pub name: &'s str,
+    pub args: &'s [String],
+}
+
+/// How often an extractor has loaded something, or failed to, since the bot started.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExtractorStats {
+    pub successes: u64,
+    pub failures: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref STATS: Mutex<HashMap<String, ExtractorStats>> = Mutex::new(HashMap::new());
+}
+
+/// The stats of every extractor that's been used, sorted by name.
+pub fn extractor_stats() -> Vec<(String, ExtractorStats)> {
+    let mut stats: Vec<_> = STATS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, stats)| (name.clone(), *stats))
+        .collect();
+    stats.sort_by(|(a, _), (b, _)| a.cmp(b));
+    stats
+}
+
+fn record(extractor: Extractor<'_>, succeeded: bool) {
+    let mut stats = STATS.lock().unwrap();
+    let entry = stats.entry(extractor.name.to_string()).or_default();
+    if succeeded {
+        entry.successes += 1;
+    } else {
+        entry.failures += 1;
+    }
+}
+
+/// Tries loading `ytdl_url` with each configured extractor in turn, until one of them succeeds.
+/// If they all fail, the last one's error is returned.
+pub(crate) async fn with_extractors<'s, T, F, Fut>(
+    ytdl_url: &str,
+    config: &PlayConfig<'s>,
+    mut load: F,
+) -> Result<T, Error>
+where
+    F: FnMut(Extractor<'s>) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut last_error = Error::UnsupportedUrl;
+    for extractor in config.extractors.iter().copied() {
+        match load(extractor).await {
+            Ok(value) => {
+                tracing::trace!("Loaded {} with {}", ytdl_url, extractor.name);
+                record(extractor, true);
+                return Ok(value);
+            }
+            Err(why) => {
+                tracing::warn!(
+                    "Error while loading {} with {}: {}",
+                    ytdl_url,
+                    extractor.name,
+                    why
+                );
+                record(extractor, false);
+                last_error = why;
+            }
+        }
+    }
+    Err(last_error)
+}
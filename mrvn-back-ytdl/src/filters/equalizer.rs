@@ -0,0 +1,158 @@
+use crate::filters::{Filter, FILTER_CHANNELS};
+use std::f32::consts::PI;
+
+/// The center frequencies of the equalizer's bands, an octave apart.
+pub const EQ_BAND_FREQUENCIES: [u32; EQ_BAND_COUNT] =
+    [31, 62, 125, 250, 500, 1000, 2000, 4000, 8000, 16000];
+
+pub const EQ_BAND_COUNT: usize = 10;
+
+/// How far a band can be boosted or cut, in decibels.
+pub const EQ_MAX_GAIN_DB: f32 = 12.;
+
+/// Gives each band about an octave of width.
+const EQ_BAND_Q: f32 = 1.41;
+
+/// A short name for a band, e.g. `"125 Hz"` or `"2 kHz"`.
+pub fn equalizer_band_name(frequency: u32) -> String {
+    if frequency >= 1000 {
+        format!("{} kHz", frequency / 1000)
+    } else {
+        format!("{} Hz", frequency)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EqualizerPreset {
+    Flat,
+    BassBoost,
+    TrebleBoost,
+    Vocal,
+    Rock,
+    Electronic,
+}
+
+impl EqualizerPreset {
+    pub const ALL: [EqualizerPreset; 6] = [
+        EqualizerPreset::Flat,
+        EqualizerPreset::BassBoost,
+        EqualizerPreset::TrebleBoost,
+        EqualizerPreset::Vocal,
+        EqualizerPreset::Rock,
+        EqualizerPreset::Electronic,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EqualizerPreset::Flat => "flat",
+            EqualizerPreset::BassBoost => "bass_boost",
+            EqualizerPreset::TrebleBoost => "treble_boost",
+            EqualizerPreset::Vocal => "vocal",
+            EqualizerPreset::Rock => "rock",
+            EqualizerPreset::Electronic => "electronic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        EqualizerPreset::ALL
+            .into_iter()
+            .find(|preset| preset.name() == name)
+    }
+
+    /// The gain of each band in decibels, in the same order as [`EQ_BAND_FREQUENCIES`].
+    pub fn gains(self) -> [f32; EQ_BAND_COUNT] {
+        match self {
+            EqualizerPreset::Flat => [0.; EQ_BAND_COUNT],
+            EqualizerPreset::BassBoost => [6., 5., 4., 2., 0., 0., 0., 0., 0., 0.],
+            EqualizerPreset::TrebleBoost => [0., 0., 0., 0., 0., 1., 2., 4., 5., 6.],
+            EqualizerPreset::Vocal => [-3., -3., -1., 1., 3., 4., 3., 1., 0., -1.],
+            EqualizerPreset::Rock => [4., 3., 2., 0., -1., -1., 1., 2., 3., 4.],
+            EqualizerPreset::Electronic => [5., 4., 1., 0., -2., 1., 0., 1., 4., 5.],
+        }
+    }
+}
+
+/// A graphic equalizer, made from a peaking filter for each band that isn't flat.
+pub struct EqualizerFilter {
+    gains: [f32; EQ_BAND_COUNT],
+    sample_rate: Option<u32>,
+    bands: Vec<PeakingFilter>,
+}
+
+impl EqualizerFilter {
+    pub fn new(gains: [f32; EQ_BAND_COUNT]) -> Self {
+        EqualizerFilter {
+            gains,
+            sample_rate: None,
+            bands: Vec::new(),
+        }
+    }
+}
+
+impl Filter for EqualizerFilter {
+    fn process(&mut self, samples: &mut Vec<f32>, sample_rate: u32) {
+        // Coefficients depend on the sample rate, which isn't known until the first block.
+        if self.sample_rate != Some(sample_rate) {
+            self.sample_rate = Some(sample_rate);
+            self.bands = EQ_BAND_FREQUENCIES
+                .iter()
+                .zip(self.gains)
+                .filter(|(frequency, gain)| {
+                    *gain != 0. && (**frequency as f32) < sample_rate as f32 / 2.
+                })
+                .map(|(frequency, gain)| {
+                    PeakingFilter::new(*frequency as f32, gain, sample_rate as f32)
+                })
+                .collect();
+        }
+
+        for frame in samples.chunks_exact_mut(FILTER_CHANNELS) {
+            for band in &mut self.bands {
+                band.process_frame(frame);
+            }
+        }
+        for sample in samples.iter_mut() {
+            *sample = sample.clamp(-1., 1.);
+        }
+    }
+}
+
+/// A biquad peaking filter, using the coefficients from the Audio EQ Cookbook.
+struct PeakingFilter {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    /// The last two inputs and outputs of each channel.
+    history: [[f32; 4]; FILTER_CHANNELS],
+}
+
+impl PeakingFilter {
+    fn new(frequency: f32, gain_db: f32, sample_rate: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.);
+        let w0 = 2. * PI * frequency / sample_rate;
+        let alpha = w0.sin() / (2. * EQ_BAND_Q);
+        let cos_w0 = w0.cos();
+
+        let a0 = 1. + alpha / a;
+        PeakingFilter {
+            b0: (1. + alpha * a) / a0,
+            b1: (-2. * cos_w0) / a0,
+            b2: (1. - alpha * a) / a0,
+            a1: (-2. * cos_w0) / a0,
+            a2: (1. - alpha / a) / a0,
+            history: [[0.; 4]; FILTER_CHANNELS],
+        }
+    }
+
+    fn process_frame(&mut self, frame: &mut [f32]) {
+        for (sample, history) in frame.iter_mut().zip(&mut self.history) {
+            let [x1, x2, y1, y2] = *history;
+            let x0 = *sample;
+            let y0 = self.b0 * x0 + self.b1 * x1 + self.b2 * x2 - self.a1 * y1 - self.a2 * y2;
+            *history = [x0, x1, y0, y1];
+            *sample = y0;
+        }
+    }
+}
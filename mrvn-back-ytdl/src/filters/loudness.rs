@@ -0,0 +1,220 @@
+use crate::filters::{Filter, FILTER_CHANNELS};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+/// Loudness is measured over overlapping windows this long, like EBU R128's momentary loudness.
+const WINDOW_SECS: f64 = 0.4;
+
+/// How often the loudness is measured.
+const BLOCK_SECS: f64 = 0.1;
+
+/// Windows quieter than this are silence and don't count towards a song's loudness.
+const ABSOLUTE_GATE_LUFS: f64 = -70.;
+
+/// The most a song will be made louder or quieter by.
+const MAX_BOOST_DB: f64 = 12.;
+const MAX_CUT_DB: f64 = 20.;
+
+/// How quickly the gain can move towards what the song needs, so it doesn't jump around while
+/// the measurement settles at the start of a song.
+const GAIN_DB_PER_SEC: f64 = 6.;
+
+/// Peaks above this are brought down by the limiter, so boosted songs don't clip.
+const LIMITER_CEILING: f32 = 0.98;
+const LIMITER_RELEASE_SECS: f32 = 0.1;
+
+/// Brings songs to a similar loudness, measured on the fly as described in ITU-R BS.1770. The
+/// loudness of everything played so far is used, rather than just the last few seconds, so the
+/// song's own dynamics are kept. A limiter stops the peaks of boosted songs from clipping.
+pub struct LoudnessFilter {
+    target_lufs: f64,
+    sample_rate: Option<u32>,
+
+    weighting: [KWeighting; FILTER_CHANNELS],
+    block_frames: usize,
+    block_position: usize,
+    block_energy: f64,
+    /// The energy of the blocks making up the current window.
+    window_energies: VecDeque<f64>,
+    /// Total energy and count of every window so far that wasn't silent.
+    total_energy: f64,
+    window_count: u64,
+
+    gain_db: f64,
+    target_gain_db: f64,
+    limiter_envelope: f32,
+    limiter_release: f32,
+}
+
+impl LoudnessFilter {
+    pub fn new(target_lufs: f64) -> Self {
+        LoudnessFilter {
+            target_lufs,
+            sample_rate: None,
+            weighting: Default::default(),
+            block_frames: 0,
+            block_position: 0,
+            block_energy: 0.,
+            window_energies: VecDeque::new(),
+            total_energy: 0.,
+            window_count: 0,
+            gain_db: 0.,
+            target_gain_db: 0.,
+            limiter_envelope: 0.,
+            limiter_release: 0.,
+        }
+    }
+
+    fn reset(&mut self, sample_rate: u32) {
+        *self = LoudnessFilter::new(self.target_lufs);
+        self.sample_rate = Some(sample_rate);
+        self.weighting = [
+            KWeighting::new(sample_rate as f64),
+            KWeighting::new(sample_rate as f64),
+        ];
+        self.block_frames = ((sample_rate as f64 * BLOCK_SECS) as usize).max(1);
+        self.limiter_release = (-1. / (sample_rate as f32 * LIMITER_RELEASE_SECS)).exp();
+    }
+
+    fn end_block(&mut self) {
+        let blocks_per_window = (WINDOW_SECS / BLOCK_SECS).round() as usize;
+        self.window_energies
+            .push_back(self.block_energy / self.block_frames as f64);
+        if self.window_energies.len() > blocks_per_window {
+            self.window_energies.pop_front();
+        }
+        self.block_position = 0;
+        self.block_energy = 0.;
+        if self.window_energies.len() < blocks_per_window {
+            return;
+        }
+
+        let window_energy = self.window_energies.iter().sum::<f64>() / blocks_per_window as f64;
+        if energy_to_lufs(window_energy) < ABSOLUTE_GATE_LUFS {
+            return;
+        }
+        self.total_energy += window_energy;
+        self.window_count += 1;
+
+        let song_lufs = energy_to_lufs(self.total_energy / self.window_count as f64);
+        self.target_gain_db = (self.target_lufs - song_lufs).clamp(-MAX_CUT_DB, MAX_BOOST_DB);
+    }
+}
+
+impl Filter for LoudnessFilter {
+    fn process(&mut self, samples: &mut Vec<f32>, sample_rate: u32) {
+        if self.sample_rate != Some(sample_rate) {
+            self.reset(sample_rate);
+        }
+        let max_gain_step = GAIN_DB_PER_SEC / sample_rate as f64;
+
+        for frame in samples.chunks_exact_mut(FILTER_CHANNELS) {
+            // Measure before changing anything, so the gain doesn't affect the measurement.
+            for (sample, weighting) in frame.iter().zip(&mut self.weighting) {
+                let weighted = weighting.process(*sample as f64);
+                self.block_energy += weighted * weighted;
+            }
+            self.block_position += 1;
+            if self.block_position >= self.block_frames {
+                self.end_block();
+            }
+
+            self.gain_db +=
+                (self.target_gain_db - self.gain_db).clamp(-max_gain_step, max_gain_step);
+            let gain = 10f64.powf(self.gain_db / 20.) as f32;
+            let peak = frame
+                .iter()
+                .map(|sample| (sample * gain).abs())
+                .fold(0., f32::max);
+            self.limiter_envelope = peak.max(self.limiter_envelope * self.limiter_release);
+            let limiter_gain = if self.limiter_envelope > LIMITER_CEILING {
+                LIMITER_CEILING / self.limiter_envelope
+            } else {
+                1.
+            };
+            for sample in frame {
+                *sample *= gain * limiter_gain;
+            }
+        }
+    }
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10. * energy.max(f64::MIN_POSITIVE).log10()
+}
+
+/// The K-weighting curve from BS.1770, roughly matching how loud people perceive different
+/// frequencies to be. It's a high shelf followed by a high-pass filter, with coefficients
+/// derived for any sample rate the same way libebur128 does.
+#[derive(Default)]
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        let shelf = {
+            let frequency = 1681.974450955533;
+            let gain_db = 3.999843853973347;
+            let q = 0.7071752369554196;
+
+            let k = (PI * frequency / sample_rate).tan();
+            let vh = 10f64.powf(gain_db / 20.);
+            let vb = vh.powf(0.4996667741545416);
+            let a0 = 1. + k / q + k * k;
+            Biquad::new(
+                [
+                    (vh + vb * k / q + k * k) / a0,
+                    2. * (k * k - vh) / a0,
+                    (vh - vb * k / q + k * k) / a0,
+                ],
+                [2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0],
+            )
+        };
+        let high_pass = {
+            let frequency = 38.13547087602444;
+            let q = 0.5003270373238773;
+
+            let k = (PI * frequency / sample_rate).tan();
+            let a0 = 1. + k / q + k * k;
+            Biquad::new(
+                [1., -2., 1.],
+                [2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0],
+            )
+        };
+        KWeighting { shelf, high_pass }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(sample))
+    }
+}
+
+#[derive(Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad {
+            b,
+            a,
+            x: [0.; 2],
+            y: [0.; 2],
+        }
+    }
+
+    fn process(&mut self, x0: f64) -> f64 {
+        let y0 = self.b[0] * x0 + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x0, self.x[0]];
+        self.y = [y0, self.y[0]];
+        y0
+    }
+}
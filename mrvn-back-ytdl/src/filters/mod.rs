@@ -0,0 +1,108 @@
+mod equalizer;
+mod loudness;
+mod preset;
+mod silence;
+mod time_stretch;
+mod volume;
+
+pub use self::equalizer::*;
+pub use self::loudness::*;
+pub use self::preset::*;
+pub use self::silence::*;
+pub use self::time_stretch::*;
+pub use self::volume::*;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Filters work on interleaved stereo samples.
+pub const FILTER_CHANNELS: usize = 2;
+
+/// Speed and pitch can each be changed by up to this factor in either direction.
+pub const MAX_RATE_CHANGE: f32 = 2.;
+
+/// An audio effect applied to decoded samples, before they're resampled for playback.
+pub trait Filter: Send + Sync {
+    /// Processes a block of interleaved stereo samples. Filters are allowed to change the number
+    /// of samples in the block.
+    fn process(&mut self, samples: &mut Vec<f32>, sample_rate: u32);
+}
+
+/// The effects applied to songs played in a guild.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterSettings {
+    /// Gain applied to songs, where 1 leaves them unchanged.
+    pub volume: f32,
+    /// Gain of each equalizer band in decibels, in the same order as [`EQ_BAND_FREQUENCIES`].
+    pub equalizer: [f32; EQ_BAND_COUNT],
+    /// How fast songs play, where 1 is normal speed.
+    pub speed: f32,
+    /// Factor the frequencies in songs are multiplied by, where 1 leaves the pitch unchanged.
+    pub pitch: f32,
+    /// The preset the other settings came from, until one of them is changed by hand.
+    pub preset: Option<FilterPreset>,
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        FilterSettings {
+            volume: 1.,
+            equalizer: [0.; EQ_BAND_COUNT],
+            speed: 1.,
+            pitch: 1.,
+            preset: None,
+        }
+    }
+}
+
+impl FilterSettings {
+    /// How much faster than normal the resampler needs to play songs to get the right pitch.
+    pub fn resample_rate(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Builds the chain of filters for these settings, in the order they're applied. Effects that
+    /// wouldn't change anything are left out.
+    pub fn build_chain(&self) -> Vec<Box<dyn Filter>> {
+        let mut chain: Vec<Box<dyn Filter>> = Vec::new();
+        if self.equalizer.iter().any(|gain| *gain != 0.) {
+            chain.push(Box::new(EqualizerFilter::new(self.equalizer)));
+        }
+        if self.volume != 1. {
+            chain.push(Box::new(VolumeFilter::new(self.volume)));
+        }
+        // Pitch is changed by resampling, which also speeds songs up by the same amount, so the
+        // tempo is stretched beforehand to make up the difference.
+        let tempo = self.speed / self.pitch;
+        if tempo != 1. {
+            chain.push(Box::new(TimeStretchFilter::new(tempo)));
+        }
+        chain
+    }
+}
+
+/// A guild's filter settings, shared with the audio thread of every song playing in the guild.
+/// Playing songs pick up changes without needing to be restarted.
+#[derive(Default)]
+pub struct GuildFilters {
+    settings: Mutex<FilterSettings>,
+    version: AtomicU64,
+}
+
+impl GuildFilters {
+    pub fn settings(&self) -> FilterSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut FilterSettings)) {
+        let mut settings = self.settings.lock().unwrap();
+        f(&mut settings);
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    /// Changes every time the settings are updated, so the audio thread can cheaply check if it
+    /// needs to rebuild its filters.
+    pub(crate) fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+}
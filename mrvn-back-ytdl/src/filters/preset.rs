@@ -0,0 +1,54 @@
+use crate::filters::{EqualizerPreset, FilterSettings, EQ_BAND_COUNT};
+
+/// Named combinations of the other filters, for effects people commonly ask for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterPreset {
+    BassBoost,
+    Nightcore,
+    Vaporwave,
+}
+
+impl FilterPreset {
+    pub const ALL: [FilterPreset; 3] = [
+        FilterPreset::BassBoost,
+        FilterPreset::Nightcore,
+        FilterPreset::Vaporwave,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FilterPreset::BassBoost => "bassboost",
+            FilterPreset::Nightcore => "nightcore",
+            FilterPreset::Vaporwave => "vaporwave",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        FilterPreset::ALL
+            .into_iter()
+            .find(|preset| preset.name() == name)
+    }
+
+    /// Sets the equalizer, speed and pitch for this preset. The volume is left alone.
+    pub fn apply(self, settings: &mut FilterSettings) {
+        let (equalizer, speed, pitch) = match self {
+            FilterPreset::BassBoost => (EqualizerPreset::BassBoost.gains(), 1., 1.),
+            // Sped up and pitched up together, like a record played too fast.
+            FilterPreset::Nightcore => ([0.; EQ_BAND_COUNT], 1.25, 1.25),
+            // Slowed down and pitched down, with the highs softened a little.
+            FilterPreset::Vaporwave => ([2., 2., 1., 0., 0., 0., -1., -2., -3., -4.], 0.8, 0.8),
+        };
+        settings.equalizer = equalizer;
+        settings.speed = speed;
+        settings.pitch = pitch;
+        settings.preset = Some(self);
+    }
+
+    /// Turns off the effects presets can change.
+    pub fn clear(settings: &mut FilterSettings) {
+        settings.equalizer = [0.; EQ_BAND_COUNT];
+        settings.speed = 1.;
+        settings.pitch = 1.;
+        settings.preset = None;
+    }
+}
@@ -0,0 +1,52 @@
+use crate::filters::{Filter, FILTER_CHANNELS};
+
+/// Silence longer than this after the song has started is cut down to this length, which keeps
+/// short pauses intact but skips long silent outros.
+const MAX_SILENCE_SECS: f32 = 1.;
+
+/// Skips silence at the start of a song, and cuts any long stretch of silence after that down to
+/// a short pause. Songs can't be known to have ended until they actually do, so a silent outro is
+/// trimmed the same way as a long silence in the middle of a song would be.
+pub struct SilenceTrimFilter {
+    threshold: f32,
+    has_started: bool,
+    silent_frames: usize,
+}
+
+impl SilenceTrimFilter {
+    /// Anything quieter than `threshold_db` (relative to full scale) counts as silence.
+    pub fn new(threshold_db: f32) -> Self {
+        SilenceTrimFilter {
+            threshold: 10f32.powf(threshold_db / 20.),
+            has_started: false,
+            silent_frames: 0,
+        }
+    }
+}
+
+impl Filter for SilenceTrimFilter {
+    fn process(&mut self, samples: &mut Vec<f32>, sample_rate: u32) {
+        let max_silent_frames = (sample_rate as f32 * MAX_SILENCE_SECS) as usize;
+        let mut kept_samples = 0;
+
+        for frame_index in 0..(samples.len() / FILTER_CHANNELS) {
+            let frame_start = frame_index * FILTER_CHANNELS;
+            let frame = &samples[frame_start..(frame_start + FILTER_CHANNELS)];
+            let is_silent = frame.iter().all(|sample| sample.abs() < self.threshold);
+
+            let keep_frame = if is_silent {
+                self.silent_frames += 1;
+                self.has_started && self.silent_frames <= max_silent_frames
+            } else {
+                self.has_started = true;
+                self.silent_frames = 0;
+                true
+            };
+            if keep_frame {
+                samples.copy_within(frame_start..(frame_start + FILTER_CHANNELS), kept_samples);
+                kept_samples += FILTER_CHANNELS;
+            }
+        }
+        samples.truncate(kept_samples);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::filters::{Filter, FILTER_CHANNELS};
+use std::f32::consts::PI;
+
+/// Length of each overlapping window.
+const WINDOW_SECS: f32 = 0.04;
+
+/// How far either side of its nominal position a window can move to line up with the previous
+/// one.
+const SEARCH_SECS: f32 = 0.01;
+
+/// Only every nth sample is compared when lining windows up, which is plenty to find a good match
+/// and much cheaper.
+const SEARCH_STEP: usize = 4;
+
+/// Changes the tempo of a song without changing its pitch, using WSOLA: windows of the input are
+/// overlapped at a fixed spacing in the output, but taken from the input at a spacing scaled by
+/// the tempo. Each window is nudged to where it best continues the one before, which avoids most
+/// of the phasing plain overlap-add would cause.
+pub struct TimeStretchFilter {
+    tempo: f32,
+    sample_rate: Option<u32>,
+    window: Vec<f32>,
+    search_frames: usize,
+
+    /// Input that hasn't been fully used yet, with `input_start` being the index of its first
+    /// frame in the whole song.
+    input: Vec<f32>,
+    input_start: usize,
+    /// Where in the song the next window would be taken from if it didn't need lining up.
+    nominal_frame: f64,
+    /// Where in the song the last window was taken from.
+    last_frame: Option<usize>,
+    /// Overlapping windows are summed here before being output.
+    output: Vec<f32>,
+}
+
+impl TimeStretchFilter {
+    pub fn new(tempo: f32) -> Self {
+        TimeStretchFilter {
+            tempo,
+            sample_rate: None,
+            window: Vec::new(),
+            search_frames: 0,
+            input: Vec::new(),
+            input_start: 0,
+            nominal_frame: 0.,
+            last_frame: None,
+            output: Vec::new(),
+        }
+    }
+
+    fn window_frames(&self) -> usize {
+        self.window.len()
+    }
+
+    fn hop_frames(&self) -> usize {
+        self.window.len() / 2
+    }
+
+    fn input_frames(&self) -> usize {
+        self.input.len() / FILTER_CHANNELS
+    }
+
+    /// Finds the start of the next window, somewhere around its nominal position.
+    fn best_window_start(&self, nominal_frame: usize) -> usize {
+        let Some(last_frame) = self.last_frame else {
+            return nominal_frame;
+        };
+
+        // The window should sound as much as possible like what would have come after the last
+        // window, had it carried on.
+        let target_frame = last_frame + self.hop_frames();
+        let min_frame = nominal_frame
+            .saturating_sub(self.search_frames)
+            .max(self.input_start);
+        let max_frame = nominal_frame + self.search_frames;
+
+        let mut best_frame = nominal_frame;
+        let mut best_correlation = f32::MIN;
+        for candidate_frame in (min_frame..=max_frame).step_by(SEARCH_STEP) {
+            let correlation: f32 = (0..self.hop_frames())
+                .step_by(SEARCH_STEP)
+                .map(|offset| {
+                    self.mono_sample(candidate_frame + offset)
+                        * self.mono_sample(target_frame + offset)
+                })
+                .sum();
+            if correlation > best_correlation {
+                best_correlation = correlation;
+                best_frame = candidate_frame;
+            }
+        }
+        best_frame
+    }
+
+    fn mono_sample(&self, frame: usize) -> f32 {
+        let index = (frame - self.input_start) * FILTER_CHANNELS;
+        self.input[index..(index + FILTER_CHANNELS)].iter().sum()
+    }
+}
+
+impl Filter for TimeStretchFilter {
+    fn process(&mut self, samples: &mut Vec<f32>, sample_rate: u32) {
+        if self.sample_rate != Some(sample_rate) {
+            self.sample_rate = Some(sample_rate);
+            // Periodic Hann windows at 50% overlap sum to exactly 1.
+            let window_frames = ((sample_rate as f32 * WINDOW_SECS) as usize / 2 * 2).max(2);
+            self.window = (0..window_frames)
+                .map(|frame| 0.5 - 0.5 * (2. * PI * frame as f32 / window_frames as f32).cos())
+                .collect();
+            self.search_frames = (sample_rate as f32 * SEARCH_SECS) as usize;
+            self.output = vec![0.; window_frames * FILTER_CHANNELS];
+        }
+
+        self.input.append(samples);
+        let window_frames = self.window_frames();
+        let hop_frames = self.hop_frames();
+
+        loop {
+            let nominal_frame = self.nominal_frame as usize;
+            let input_end = self.input_start + self.input_frames();
+            let needed_end = (nominal_frame + self.search_frames + window_frames)
+                .max(self.last_frame.map_or(0, |frame| frame + window_frames));
+            if input_end < needed_end {
+                break;
+            }
+
+            let start_frame = self.best_window_start(nominal_frame);
+            let input_index = (start_frame - self.input_start) * FILTER_CHANNELS;
+            let input_window =
+                &self.input[input_index..(input_index + window_frames * FILTER_CHANNELS)];
+            for ((output_frame, input_frame), gain) in self
+                .output
+                .chunks_exact_mut(FILTER_CHANNELS)
+                .zip(input_window.chunks_exact(FILTER_CHANNELS))
+                .zip(&self.window)
+            {
+                for (output_sample, input_sample) in output_frame.iter_mut().zip(input_frame) {
+                    *output_sample += input_sample * gain;
+                }
+            }
+
+            // The first half of the summed windows won't be overlapped again, so it's done.
+            samples.extend(self.output.drain(..(hop_frames * FILTER_CHANNELS)));
+            self.output.resize(window_frames * FILTER_CHANNELS, 0.);
+
+            self.last_frame = Some(start_frame);
+            self.nominal_frame += hop_frames as f64 * self.tempo as f64;
+
+            // Drop input that no later window can be taken from.
+            let keep_from = (self.nominal_frame as usize)
+                .saturating_sub(self.search_frames)
+                .min(start_frame + hop_frames);
+            if keep_from > self.input_start {
+                self.input
+                    .drain(..((keep_from - self.input_start) * FILTER_CHANNELS));
+                self.input_start = keep_from;
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use crate::filters::Filter;
+
+/// Scales samples by a constant gain, clipping anything that ends up out of range.
+pub struct VolumeFilter {
+    gain: f32,
+}
+
+impl VolumeFilter {
+    pub fn new(gain: f32) -> Self {
+        VolumeFilter { gain }
+    }
+}
+
+impl Filter for VolumeFilter {
+    fn process(&mut self, samples: &mut Vec<f32>, _sample_rate: u32) {
+        for sample in samples.iter_mut() {
+            *sample = (*sample * self.gain).clamp(-1., 1.);
+        }
+    }
+}
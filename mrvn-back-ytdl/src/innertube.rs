@@ -0,0 +1,319 @@
+use crate::{Error, HTTP_CLIENT};
+use futures::future;
+use serde::Deserialize;
+use serde_json::json;
+
+const PLAYER_URL: &str = "https://www.youtube.com/youtubei/v1/player?prettyPrint=false";
+const SEARCH_URL: &str = "https://www.youtube.com/youtubei/v1/search?prettyPrint=false";
+
+/// The player is asked for videos as the Android VR app, which gets plain audio URLs back
+/// instead of ones with a signature that needs YouTube's JavaScript player to decipher.
+const PLAYER_CLIENT_NAME: &str = "ANDROID_VR";
+const PLAYER_CLIENT_ID: &str = "28";
+const PLAYER_CLIENT_VERSION: &str = "1.60.19";
+const PLAYER_USER_AGENT: &str = "com.google.android.apps.youtube.vr.oculus/1.60.19 (Linux; U; Android 12L; eureka-user Build/SQ3A.220605.009.A1) gzip";
+
+const SEARCH_CLIENT_NAME: &str = "WEB";
+const SEARCH_CLIENT_VERSION: &str = "2.20240726.00.00";
+/// Search filter that only returns videos, not channels or playlists.
+const SEARCH_VIDEOS_PARAMS: &str = "EgIQAQ==";
+
+/// A YouTube video with everything needed to play it.
+pub(crate) struct Video {
+    pub id: String,
+    pub title: String,
+    pub thumbnail_url: Option<String>,
+    pub duration_seconds: Option<f64>,
+    pub author: Option<String>,
+    pub view_count: Option<u64>,
+    pub download_url: String,
+    pub http_headers: Vec<(String, String)>,
+}
+
+enum Request {
+    Video { id: String },
+    Search { query: String, count: usize },
+}
+
+/// Resolves a youtube-dl URL without youtube-dl, if it's a single YouTube video or a YouTube
+/// search. Returns `None` if youtube-dl needs to handle it instead, including if YouTube's API
+/// didn't give us anything we could play.
+pub(crate) async fn load(ytdl_url: &str, no_playlist: bool) -> Option<Vec<Video>> {
+    let request = parse_request(ytdl_url, no_playlist)?;
+    let videos_res = match request {
+        Request::Video { id } => load_video(&id).await.map(|video| vec![video]),
+        Request::Search { query, count } => search(&query, count).await,
+    };
+    match videos_res {
+        Ok(videos) if !videos.is_empty() => Some(videos),
+        Ok(_) => None,
+        Err(why) => {
+            tracing::warn!(
+                "Error while loading {} natively, falling back to youtube-dl: {}",
+                ytdl_url,
+                why
+            );
+            None
+        }
+    }
+}
+
+fn parse_request(ytdl_url: &str, no_playlist: bool) -> Option<Request> {
+    if let Some((prefix, query)) = ytdl_url.split_once(':') {
+        if let Some(count) = prefix.strip_prefix("ytsearch") {
+            let count = match count {
+                "" => 1,
+                count => count.parse().ok()?,
+            };
+            return Some(Request::Search {
+                query: query.to_string(),
+                count,
+            });
+        }
+    }
+
+    // Playlists are left to youtube-dl, unless we only want the video.
+    let url = url::Url::parse(ytdl_url).ok()?;
+    let is_playlist = url.query_pairs().any(|(key, _)| key == "list");
+    if is_playlist && !no_playlist {
+        return None;
+    }
+    let id = crate::song::get_youtube_video_id(ytdl_url)?;
+    Some(Request::Video { id })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlayerResponse {
+    playability_status: PlayabilityStatus,
+    video_details: Option<VideoDetails>,
+    streaming_data: Option<StreamingData>,
+}
+
+#[derive(Deserialize)]
+struct PlayabilityStatus {
+    status: String,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VideoDetails {
+    video_id: String,
+    title: String,
+    length_seconds: Option<String>,
+    #[serde(default)]
+    is_live: bool,
+    thumbnail: Option<Thumbnails>,
+    author: Option<String>,
+    view_count: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Thumbnails {
+    thumbnails: Vec<Thumbnail>,
+}
+
+#[derive(Deserialize)]
+struct Thumbnail {
+    url: String,
+    width: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamingData {
+    #[serde(default)]
+    adaptive_formats: Vec<Format>,
+    hls_manifest_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Format {
+    /// Formats without a URL need their signature deciphered, so they're skipped.
+    url: Option<String>,
+    mime_type: String,
+    #[serde(default)]
+    bitrate: u64,
+    audio_sample_rate: Option<String>,
+}
+
+impl Format {
+    /// Opus audio at 48kHz can be passed straight through to Discord without re-encoding.
+    fn is_passthrough(&self) -> bool {
+        self.mime_type.starts_with("audio/webm")
+            && self.mime_type.contains("opus")
+            && self.audio_sample_rate.as_deref() == Some("48000")
+    }
+}
+
+async fn post(
+    url: &str,
+    user_agent: Option<&str>,
+    body: serde_json::Value,
+) -> Result<Vec<u8>, Error> {
+    let mut request_builder = HTTP_CLIENT
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string());
+    if let Some(user_agent) = user_agent {
+        request_builder = request_builder
+            .header(reqwest::header::USER_AGENT, user_agent)
+            .header("X-YouTube-Client-Name", PLAYER_CLIENT_ID)
+            .header("X-YouTube-Client-Version", PLAYER_CLIENT_VERSION);
+    }
+
+    let response = request_builder
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(Error::Http)?;
+    let bytes = response.bytes().await.map_err(Error::Http)?;
+    Ok(bytes.to_vec())
+}
+
+async fn load_video(id: &str) -> Result<Video, Error> {
+    let body = json!({
+        "context": {
+            "client": {
+                "clientName": PLAYER_CLIENT_NAME,
+                "clientVersion": PLAYER_CLIENT_VERSION,
+                "deviceMake": "Oculus",
+                "deviceModel": "Quest 3",
+                "androidSdkVersion": 32,
+                "osName": "Android",
+                "osVersion": "12L",
+                "hl": "en",
+                "gl": "US",
+            },
+        },
+        "videoId": id,
+        "contentCheckOk": true,
+        "racyCheckOk": true,
+    });
+    let response_bytes = post(PLAYER_URL, Some(PLAYER_USER_AGENT), body).await?;
+    let response: PlayerResponse = serde_json::from_slice(&response_bytes)
+        .map_err(|err| Error::Parse(err, String::from_utf8_lossy(&response_bytes).into_owned()))?;
+
+    if response.playability_status.status != "OK" {
+        return Err(Error::Innertube(
+            response
+                .playability_status
+                .reason
+                .unwrap_or(response.playability_status.status),
+        ));
+    }
+    let (Some(details), Some(streaming_data)) = (response.video_details, response.streaming_data)
+    else {
+        return Err(Error::Innertube("no video details".to_string()));
+    };
+
+    // Live streams don't have separate audio formats, only an HLS playlist.
+    let download_url = if details.is_live {
+        streaming_data.hls_manifest_url
+    } else {
+        streaming_data
+            .adaptive_formats
+            .into_iter()
+            .filter(|format| format.url.is_some() && format.mime_type.starts_with("audio/"))
+            .max_by_key(|format| (format.is_passthrough(), format.bitrate))
+            .and_then(|format| format.url)
+    }
+    .ok_or(Error::NoTracks)?;
+
+    let thumbnail_url = details
+        .thumbnail
+        .and_then(|thumbnails| {
+            thumbnails
+                .thumbnails
+                .into_iter()
+                .max_by_key(|thumbnail| thumbnail.width.unwrap_or(0))
+        })
+        .map(|thumbnail| thumbnail.url);
+    let duration_seconds = details
+        .length_seconds
+        .and_then(|length| length.parse::<f64>().ok())
+        .filter(|length| *length > 0.);
+
+    Ok(Video {
+        id: details.video_id,
+        title: details.title,
+        thumbnail_url,
+        duration_seconds,
+        author: details.author,
+        view_count: details
+            .view_count
+            .and_then(|view_count| view_count.parse().ok()),
+        download_url,
+        http_headers: vec![(
+            reqwest::header::USER_AGENT.to_string(),
+            PLAYER_USER_AGENT.to_string(),
+        )],
+    })
+}
+
+async fn search(query: &str, count: usize) -> Result<Vec<Video>, Error> {
+    let body = json!({
+        "context": {
+            "client": {
+                "clientName": SEARCH_CLIENT_NAME,
+                "clientVersion": SEARCH_CLIENT_VERSION,
+                "hl": "en",
+                "gl": "US",
+            },
+        },
+        "query": query,
+        "params": SEARCH_VIDEOS_PARAMS,
+    });
+    let response_bytes = post(SEARCH_URL, None, body).await?;
+    let response: serde_json::Value = serde_json::from_slice(&response_bytes)
+        .map_err(|err| Error::Parse(err, String::from_utf8_lossy(&response_bytes).into_owned()))?;
+
+    let mut video_ids = Vec::new();
+    find_video_ids(&response, count, &mut video_ids);
+
+    // Results that can't be played natively are left out, like youtube-dl does with results
+    // that fail.
+    let video_results = future::join_all(video_ids.iter().map(|id| load_video(id))).await;
+    Ok(video_results
+        .into_iter()
+        .filter_map(|video_res| match video_res {
+            Ok(video) => Some(video),
+            Err(why) => {
+                tracing::warn!("Error while loading search result: {}", why);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Search results are nested deep in layout renderers that YouTube changes often, so instead of
+/// following a fixed path every `videoRenderer` is collected. Results are always in arrays, so
+/// they stay in order.
+fn find_video_ids(value: &serde_json::Value, count: usize, video_ids: &mut Vec<String>) {
+    if video_ids.len() >= count {
+        return;
+    }
+    match value {
+        serde_json::Value::Object(object) => {
+            if let Some(video_id) = object
+                .get("videoRenderer")
+                .and_then(|renderer| renderer.get("videoId"))
+                .and_then(|video_id| video_id.as_str())
+            {
+                video_ids.push(video_id.to_string());
+                return;
+            }
+            for child in object.values() {
+                find_video_ids(child, count, video_ids);
+            }
+        }
+        serde_json::Value::Array(array) => {
+            for child in array {
+                find_video_ids(child, count, video_ids);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,354 @@
+use roxmltree::{Document, Node};
+use std::fmt::{Display, Formatter};
+use tokio::io;
+
+#[derive(Debug)]
+enum ManifestError {
+    Parse(roxmltree::Error),
+    Live,
+    NoAudio,
+    NoSegments,
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::Parse(err) => write!(f, "failed to parse DASH manifest: {}", err),
+            ManifestError::Live => write!(f, "live DASH streams are not supported"),
+            ManifestError::NoAudio => write!(f, "DASH manifest has no audio representations"),
+            ManifestError::NoSegments => write!(f, "could not find segments in DASH manifest"),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// A piece of a DASH stream. Concatenating every segment in order gives a file that can be
+/// decoded like any other.
+pub struct Segment {
+    pub url: url::Url,
+    /// An inclusive byte range to request from the URL, if only part of it is the segment.
+    pub byte_range: Option<(u64, u64)>,
+}
+
+pub struct Manifest {
+    pub segments: Vec<Segment>,
+    pub mime_type: Option<String>,
+}
+
+pub fn parse_manifest(manifest_url: &url::Url, text: &str) -> io::Result<Manifest> {
+    let document =
+        Document::parse(text).map_err(|err| io::Error::other(ManifestError::Parse(err)))?;
+    let mpd = document.root_element();
+    if mpd.attribute("type") == Some("dynamic") {
+        return Err(io::Error::other(ManifestError::Live));
+    }
+
+    let mpd_base_url = resolve_base_url(manifest_url, mpd);
+    let presentation_secs = mpd
+        .attribute("mediaPresentationDuration")
+        .and_then(parse_duration);
+
+    let mut segments = Vec::new();
+    let mut mime_type = None;
+    let mut has_audio = false;
+    for period in children(mpd, "Period") {
+        let period_secs = period
+            .attribute("duration")
+            .and_then(parse_duration)
+            .or_else(|| {
+                let start_secs = period
+                    .attribute("start")
+                    .and_then(parse_duration)
+                    .unwrap_or(0.);
+                presentation_secs.map(|secs| secs - start_secs)
+            });
+
+        let Some((adaptation_set, representation)) = find_audio_representation(period) else {
+            continue;
+        };
+        has_audio = true;
+        mime_type = mime_type.or_else(|| {
+            representation
+                .attribute("mimeType")
+                .or_else(|| adaptation_set.attribute("mimeType"))
+                .map(str::to_string)
+        });
+
+        let base_url = [period, adaptation_set, representation]
+            .into_iter()
+            .fold(mpd_base_url.clone(), |url, node| {
+                resolve_base_url(&url, node)
+            });
+        let template_nodes = [representation, adaptation_set, period];
+        segments.extend(representation_segments(
+            &base_url,
+            representation,
+            &template_nodes,
+            period_secs,
+        )?);
+    }
+
+    if !has_audio {
+        return Err(io::Error::other(ManifestError::NoAudio));
+    }
+    if segments.is_empty() {
+        return Err(io::Error::other(ManifestError::NoSegments));
+    }
+    Ok(Manifest {
+        segments,
+        mime_type,
+    })
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+fn resolve_base_url(parent_url: &url::Url, node: Node) -> url::Url {
+    child(node, "BaseURL")
+        .and_then(|base_url| base_url.text())
+        .and_then(|text| parent_url.join(text.trim()).ok())
+        .unwrap_or_else(|| parent_url.clone())
+}
+
+fn is_audio(node: Node) -> bool {
+    node.attribute("contentType") == Some("audio")
+        || node
+            .attribute("mimeType")
+            .is_some_and(|mime_type| mime_type.starts_with("audio/"))
+}
+
+/// Picks the highest bandwidth audio representation in a period.
+fn find_audio_representation<'a, 'input>(
+    period: Node<'a, 'input>,
+) -> Option<(Node<'a, 'input>, Node<'a, 'input>)> {
+    children(period, "AdaptationSet")
+        .flat_map(|adaptation_set| {
+            let is_audio_set = is_audio(adaptation_set);
+            children(adaptation_set, "Representation")
+                .filter(move |representation| is_audio_set || is_audio(*representation))
+                .map(move |representation| (adaptation_set, representation))
+        })
+        .max_by_key(|(_, representation)| bandwidth(*representation))
+}
+
+fn bandwidth(representation: Node) -> u64 {
+    representation
+        .attribute("bandwidth")
+        .and_then(|bandwidth| bandwidth.parse().ok())
+        .unwrap_or(0)
+}
+
+fn representation_segments(
+    base_url: &url::Url,
+    representation: Node,
+    template_nodes: &[Node],
+    period_secs: Option<f64>,
+) -> io::Result<Vec<Segment>> {
+    let join = |path: &str| base_url.join(path).map_err(io::Error::other);
+
+    if let Some(segment_list) = child(representation, "SegmentList") {
+        let initialization = child(segment_list, "Initialization").map(|initialization| {
+            Ok(Segment {
+                url: match initialization.attribute("sourceURL") {
+                    Some(source_url) => join(source_url)?,
+                    None => base_url.clone(),
+                },
+                byte_range: initialization.attribute("range").and_then(parse_byte_range),
+            })
+        });
+        let media = children(segment_list, "SegmentURL").map(|segment_url| {
+            Ok(Segment {
+                url: match segment_url.attribute("media") {
+                    Some(media) => join(media)?,
+                    None => base_url.clone(),
+                },
+                byte_range: segment_url
+                    .attribute("mediaRange")
+                    .and_then(parse_byte_range),
+            })
+        });
+        return initialization.into_iter().chain(media).collect();
+    }
+
+    let Some(template) = template_nodes
+        .iter()
+        .find_map(|node| child(*node, "SegmentTemplate"))
+    else {
+        // Without a list or template, the whole representation is a single file.
+        return Ok(vec![Segment {
+            url: base_url.clone(),
+            byte_range: None,
+        }]);
+    };
+
+    let representation_id = representation.attribute("id").unwrap_or_default();
+    let representation_bandwidth = bandwidth(representation);
+    let expand = |template_text: &str, number: u64, time: u64| {
+        join(&expand_template(
+            template_text,
+            representation_id,
+            representation_bandwidth,
+            number,
+            time,
+        ))
+    };
+
+    let mut segments = Vec::new();
+    if let Some(initialization) = template.attribute("initialization") {
+        segments.push(Segment {
+            url: expand(initialization, 0, 0)?,
+            byte_range: None,
+        });
+    }
+    let Some(media) = template.attribute("media") else {
+        return Ok(segments);
+    };
+
+    let timescale: u64 = template
+        .attribute("timescale")
+        .and_then(|timescale| timescale.parse().ok())
+        .unwrap_or(1);
+    let start_number: u64 = template
+        .attribute("startNumber")
+        .and_then(|start_number| start_number.parse().ok())
+        .unwrap_or(1);
+    let period_end = period_secs.map(|secs| (secs * timescale as f64) as u64);
+
+    if let Some(timeline) = child(template, "SegmentTimeline") {
+        let mut number = start_number;
+        let mut time = 0;
+        for s in children(timeline, "S") {
+            time = s
+                .attribute("t")
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(time);
+            let duration: u64 = s.attribute("d").and_then(|d| d.parse().ok()).unwrap_or(0);
+            if duration == 0 {
+                break;
+            }
+
+            // A negative repeat count means the segment repeats until the end of the period.
+            let repeat: i64 = s.attribute("r").and_then(|r| r.parse().ok()).unwrap_or(0);
+            let count = match (repeat, period_end) {
+                (0.., _) => repeat as u64 + 1,
+                (_, Some(period_end)) => period_end.saturating_sub(time).div_ceil(duration),
+                (_, None) => 1,
+            };
+            for _ in 0..count {
+                segments.push(Segment {
+                    url: expand(media, number, time)?,
+                    byte_range: None,
+                });
+                number += 1;
+                time += duration;
+            }
+        }
+        return Ok(segments);
+    }
+
+    let duration: u64 = template
+        .attribute("duration")
+        .and_then(|duration| duration.parse().ok())
+        .ok_or_else(|| io::Error::other(ManifestError::NoSegments))?;
+    let period_end = period_end.ok_or_else(|| io::Error::other(ManifestError::NoSegments))?;
+    let count = period_end.div_ceil(duration.max(1));
+    for index in 0..count {
+        segments.push(Segment {
+            url: expand(media, start_number + index, index * duration)?,
+            byte_range: None,
+        });
+    }
+    Ok(segments)
+}
+
+/// Fills in the `$Identifier$` placeholders in a segment template. Numeric identifiers can have a
+/// printf-style width, like `$Number%05d$`.
+fn expand_template(
+    template: &str,
+    representation_id: &str,
+    bandwidth: u64,
+    number: u64,
+    time: u64,
+) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut parts = template.split('$');
+    if let Some(first_part) = parts.next() {
+        expanded.push_str(first_part);
+    }
+
+    // Placeholders are every other part once split on the dollar signs.
+    let mut is_placeholder = true;
+    for part in parts {
+        if !is_placeholder {
+            expanded.push_str(part);
+            is_placeholder = true;
+            continue;
+        }
+        is_placeholder = false;
+
+        let (identifier, width) = match part.split_once('%') {
+            Some((identifier, format)) => (
+                identifier,
+                format.trim_end_matches('d').parse::<usize>().unwrap_or(0),
+            ),
+            None => (part, 0),
+        };
+        match identifier {
+            "" => expanded.push('$'),
+            "RepresentationID" => expanded.push_str(representation_id),
+            "Number" => expanded.push_str(&format!("{:0>width$}", number, width = width)),
+            "Time" => expanded.push_str(&format!("{:0>width$}", time, width = width)),
+            "Bandwidth" => expanded.push_str(&format!("{:0>width$}", bandwidth, width = width)),
+            _ => {
+                expanded.push('$');
+                expanded.push_str(part);
+                expanded.push('$');
+            }
+        }
+    }
+    expanded
+}
+
+fn parse_byte_range(range: &str) -> Option<(u64, u64)> {
+    let (start, end) = range.split_once('-')?;
+    Some((start.parse().ok()?, end.parse().ok()?))
+}
+
+/// Parses an ISO 8601 duration like `PT1H2M3.5S` into seconds.
+fn parse_duration(duration: &str) -> Option<f64> {
+    let (date, time) = duration
+        .strip_prefix('P')?
+        .split_once('T')
+        .unwrap_or((duration.strip_prefix('P')?, ""));
+
+    let mut secs = 0.;
+    for (part, units) in [
+        (
+            date,
+            &[('Y', 31_536_000.), ('M', 2_592_000.), ('D', 86_400.)],
+        ),
+        (time, &[('H', 3_600.), ('M', 60.), ('S', 1.)]),
+    ] {
+        let mut remaining = part;
+        for (designator, unit_secs) in units {
+            if let Some((value, rest)) = remaining.split_once(*designator) {
+                secs += value.parse::<f64>().ok()? * unit_secs;
+                remaining = rest;
+            }
+        }
+        if !remaining.is_empty() {
+            return None;
+        }
+    }
+    Some(secs)
+}
@@ -0,0 +1,47 @@
+use crate::HTTP_CLIENT;
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt, TryFutureExt};
+use tokio::io;
+
+mod manifest;
+
+pub use self::manifest::{parse_manifest as parse_dash_manifest, Manifest as DashManifest};
+
+pub fn dash_chunks(manifest: DashManifest) -> impl Stream<Item = io::Result<Bytes>> {
+    // Like HLS, segments are requested one ahead of the one that's streaming, and segments that
+    // fail to load are skipped instead of ending playback.
+    stream::iter(manifest.segments)
+        .map(|segment| {
+            let mut builder = HTTP_CLIENT.get(segment.url);
+            if let Some((start, end)) = segment.byte_range {
+                builder =
+                    builder.header(reqwest::header::RANGE, format!("bytes={}-{}", start, end));
+            }
+            builder
+                .send()
+                .and_then(|response| async move { response.error_for_status() })
+        })
+        .buffered(1)
+        .filter_map(|maybe_response| async move {
+            match maybe_response {
+                Ok(response) => Some(response),
+                Err(why) => {
+                    tracing::warn!("Error while loading DASH segment: {}", why);
+                    None
+                }
+            }
+        })
+        .flat_map(|response| {
+            response
+                .bytes_stream()
+                .filter_map(|maybe_chunk| async move {
+                    match maybe_chunk {
+                        Ok(chunk) => Some(Ok(chunk)),
+                        Err(why) => {
+                            tracing::warn!("Error while streaming DASH segment: {}", why);
+                            None
+                        }
+                    }
+                })
+        })
+}
@@ -0,0 +1,129 @@
+use crate::HTTP_CLIENT;
+use bytes::Bytes;
+use m3u8_rs::{AlternativeMediaType, MasterPlaylist, Playlist};
+use std::fmt::{Debug, Display, Formatter};
+use tokio::io;
+
+#[derive(Debug)]
+struct PlaylistParseError;
+
+impl Display for PlaylistParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to parse playlist")
+    }
+}
+
+impl std::error::Error for PlaylistParseError {}
+
+#[derive(Debug)]
+struct NoVariantsError;
+
+impl Display for NoVariantsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "master playlist has no playable variants")
+    }
+}
+
+impl std::error::Error for NoVariantsError {}
+
+/// Codecs that carry video, as they appear at the start of an entry in a variant's `CODECS`.
+const VIDEO_CODEC_PREFIXES: &[&str] = &["avc", "hvc", "hev", "vp8", "vp09", "av01", "dvh", "dva"];
+
+pub struct MediaPlaylistSource {
+    pub url: url::Url,
+    pub initial_playlist: Bytes,
+    pub request_builder: reqwest::RequestBuilder,
+}
+
+/// Loads the media playlist to stream segments from. If the initial playlist is a master
+/// playlist, one of its variants is chosen and that variant's playlist is fetched instead.
+pub async fn resolve_media_playlist(
+    url: url::Url,
+    initial_response: reqwest::Response,
+    request_builder: reqwest::RequestBuilder,
+) -> io::Result<MediaPlaylistSource> {
+    let initial_playlist = initial_response.bytes().await.map_err(io::Error::other)?;
+    let master_playlist = match m3u8_rs::parse_playlist_res(&initial_playlist) {
+        Ok(Playlist::MasterPlaylist(master_playlist)) => master_playlist,
+        Ok(Playlist::MediaPlaylist(_)) => {
+            return Ok(MediaPlaylistSource {
+                url,
+                initial_playlist,
+                request_builder,
+            })
+        }
+        Err(_) => return Err(io::Error::other(PlaylistParseError)),
+    };
+
+    let variant_uri =
+        choose_variant_uri(&master_playlist).ok_or_else(|| io::Error::other(NoVariantsError))?;
+    let variant_url = url.join(variant_uri).map_err(io::Error::other)?;
+    tracing::trace!("Playing HLS variant {}", variant_url);
+
+    // Variants are requested with the same headers youtube-dl gave for the master playlist
+    let headers = request_builder
+        .build()
+        .map_err(io::Error::other)?
+        .headers()
+        .clone();
+    let variant_request_builder = HTTP_CLIENT.get(variant_url.clone()).headers(headers);
+    let variant_playlist = variant_request_builder
+        .try_clone()
+        .unwrap()
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(io::Error::other)?
+        .bytes()
+        .await
+        .map_err(io::Error::other)?;
+
+    Ok(MediaPlaylistSource {
+        url: variant_url,
+        initial_playlist: variant_playlist,
+        request_builder: variant_request_builder,
+    })
+}
+
+/// Prefers the highest bandwidth audio-only variant, then a separate audio rendition, and
+/// finally the lowest bandwidth variant since we'd only be throwing the video away.
+fn choose_variant_uri(master_playlist: &MasterPlaylist) -> Option<&str> {
+    let variants: Vec<_> = master_playlist
+        .variants
+        .iter()
+        .filter(|variant| !variant.is_i_frame && !variant.uri.is_empty())
+        .collect();
+
+    let audio_only_variant = variants
+        .iter()
+        .filter(|variant| variant.codecs.as_deref().is_some_and(is_audio_only))
+        .max_by_key(|variant| variant.bandwidth);
+    if let Some(variant) = audio_only_variant {
+        return Some(&variant.uri);
+    }
+
+    let audio_rendition_uri = master_playlist
+        .alternatives
+        .iter()
+        .filter(|media| media.media_type == AlternativeMediaType::Audio)
+        .filter_map(|media| media.uri.as_deref().map(|uri| (media.default, uri)))
+        .max_by_key(|(is_default, _)| *is_default)
+        .map(|(_, uri)| uri);
+    if audio_rendition_uri.is_some() {
+        return audio_rendition_uri;
+    }
+
+    variants
+        .iter()
+        .min_by_key(|variant| variant.bandwidth)
+        .map(|variant| variant.uri.as_str())
+}
+
+fn is_audio_only(codecs: &str) -> bool {
+    codecs.split(',').all(|codec| {
+        let codec = codec.trim();
+        !VIDEO_CODEC_PREFIXES
+            .iter()
+            .any(|prefix| codec.starts_with(prefix))
+    })
+}
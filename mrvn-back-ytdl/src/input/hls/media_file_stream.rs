@@ -1,7 +1,7 @@
 use crate::HTTP_CLIENT;
 use bytes::Bytes;
 use futures::{FutureExt, Stream, StreamExt, TryStreamExt};
-use m3u8_rs::{Key, KeyMethod};
+use m3u8_rs::{ByteRange, Key, KeyMethod};
 use std::fmt::{Display, Formatter};
 use tokio::io;
 
@@ -16,20 +16,22 @@ impl Display for EncryptionNotSupportedError {
 
 impl std::error::Error for EncryptionNotSupportedError {}
 
+/// Streams the bytes of each segment, along with the sequence number of the segment they're from.
 pub fn media_file_stream(
     base_url: url::Url,
-    segments: impl Stream<Item = io::Result<m3u8_rs::MediaSegment>> + Send + 'static,
-) -> impl Stream<Item = io::Result<Bytes>> {
+    segments: impl Stream<Item = io::Result<(u64, m3u8_rs::MediaSegment)>> + Send + 'static,
+) -> impl Stream<Item = io::Result<(u64, Bytes)>> {
     // This looks like a mess, but roughly we're:
     //  1. Building a request for each incoming segment and sending it.
     //  2. Buffering one request at a time, so we can initiate the next request while the current
     //     one is streaming.
     //  3. Ignore requests that failed. This can happen due to various causes but we should only
-    //     need to halt if the segments stream errors.
+    //     need to halt if the segments stream errors, or if the remote says the segment isn't
+    //     there any more, since the playlist has probably expired.
     //  4. Start streaming chunks from each request, again ignoring errors.
-    // The result is a plain stream of byte chunks.
+    // The result is a stream of byte chunks tagged with their segment's sequence number.
     segments
-        .and_then(move |segment| {
+        .and_then(move |(sequence, segment)| {
             let base_url = base_url.clone();
 
             async move {
@@ -37,42 +39,51 @@ pub fn media_file_stream(
 
                 if let Some(Key { method, .. }) = &segment.key {
                     if *method != KeyMethod::None {
-                        return Err(io::Error::new(
-                            io::ErrorKind::Other,
-                            EncryptionNotSupportedError,
-                        ));
+                        return Err(io::Error::other(EncryptionNotSupportedError));
                     }
                 }
 
-                // todo: support range requests
-                // todo: support relative uri
                 // todo: support encryption
 
-                let absolute_url = base_url
-                    .join(&segment.uri)
-                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
-                let builder = HTTP_CLIENT.get(absolute_url);
-                Ok(builder.send().map(Ok))
+                // Segment URIs are relative to the playlist they came from, unless they're
+                // already absolute
+                let absolute_url = base_url.join(&segment.uri).map_err(io::Error::other)?;
+                let mut builder = HTTP_CLIENT.get(absolute_url);
+
+                // Offsets have already been filled in by the segment stream
+                if let Some(ByteRange { length, offset }) = segment.byte_range {
+                    let start = offset.unwrap_or(0);
+                    let end = start + length.saturating_sub(1);
+                    builder =
+                        builder.header(reqwest::header::RANGE, format!("bytes={}-{}", start, end));
+                }
+
+                Ok(builder
+                    .send()
+                    .map(move |maybe_response| Ok((sequence, maybe_response))))
             }
         })
         .try_buffered(1)
-        .try_filter_map(|maybe_response| async move {
-            match maybe_response {
-                Ok(response) => Ok(Some(response)),
+        .try_filter_map(|(sequence, maybe_response)| async move {
+            match maybe_response.and_then(reqwest::Response::error_for_status) {
+                Ok(response) => Ok(Some((sequence, response))),
+                Err(why) if why.status().is_some_and(|status| status.is_client_error()) => {
+                    Err(io::Error::other(why))
+                }
                 Err(why) => {
-                    log::warn!("Error while loading playlist segment: {}", why);
+                    tracing::warn!("Error while loading playlist segment: {}", why);
                     Ok(None)
                 }
             }
         })
-        .map_ok(|response| {
+        .map_ok(|(sequence, response)| {
             response
                 .bytes_stream()
-                .filter_map(|maybe_chunk| async move {
+                .filter_map(move |maybe_chunk| async move {
                     match maybe_chunk {
-                        Ok(chunk) => Some(Ok(chunk)),
+                        Ok(chunk) => Some(Ok((sequence, chunk))),
                         Err(why) => {
-                            log::warn!("Error while streaming playlist segment: {}", why);
+                            tracing::warn!("Error while streaming playlist segment: {}", why);
                             None
                         }
                     }
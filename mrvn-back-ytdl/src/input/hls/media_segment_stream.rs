@@ -1,4 +1,6 @@
+use crate::input::hls::segment_window::SegmentWindow;
 use async_stream::try_stream;
+use bytes::Bytes;
 use futures::{future, stream, Stream, StreamExt, TryStreamExt};
 use m3u8_rs::parse_media_playlist_res;
 use std::fmt::{Debug, Display, Formatter};
@@ -16,6 +18,27 @@ impl Display for MediaPlaylistParseError {
 
 impl std::error::Error for MediaPlaylistParseError {}
 
+/// Byte ranges without an offset start right after the previous segment's range, as long as it
+/// was in the same file. Offsets are filled in here so segments can be requested on their own.
+fn fill_byte_range_offsets(segments: &mut [m3u8_rs::MediaSegment]) {
+    let mut previous_range_end: Option<(String, u64)> = None;
+    for segment in segments {
+        let Some(byte_range) = &mut segment.byte_range else {
+            previous_range_end = None;
+            continue;
+        };
+
+        let offset = byte_range
+            .offset
+            .unwrap_or_else(|| match &previous_range_end {
+                Some((uri, end)) if *uri == segment.uri => *end,
+                _ => 0,
+            });
+        byte_range.offset = Some(offset);
+        previous_range_end = Some((segment.uri.clone(), offset + byte_range.length));
+    }
+}
+
 struct SegmentData {
     segment: m3u8_rs::MediaSegment,
     sequence: u64,
@@ -23,17 +46,19 @@ struct SegmentData {
 }
 
 fn segment_list_stream(
-    initial_response: reqwest::Response,
+    initial_playlist: Bytes,
     request_builder: reqwest::RequestBuilder,
+    start_sequence: Option<u64>,
+    window: SegmentWindow,
 ) -> impl Stream<Item = io::Result<Vec<SegmentData>>> {
     try_stream! {
-        let mut initial_response = Some(initial_response);
+        let mut initial_playlist = Some(initial_playlist);
         let mut last_seen_sequence = None;
 
         loop {
             let request_instant = Instant::now();
-            let response = match initial_response.take() {
-                Some(response) => response,
+            let response_bytes = match initial_playlist.take() {
+                Some(playlist) => playlist,
                 None => {
                     request_builder
                         .try_clone()
@@ -41,14 +66,16 @@ fn segment_list_stream(
                         .send()
                         .await
                         .and_then(reqwest::Response::error_for_status)
-                        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+                        .map_err(io::Error::other)?
+                        .bytes()
+                        .await
+                        .map_err(io::Error::other)?
                 }
             };
-
-            let response_bytes = response.bytes().await
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
-            let media_playlist = parse_media_playlist_res(&response_bytes)
-                .map_err(|_| io::Error::new(io::ErrorKind::Other, MediaPlaylistParseError))?;
+            let mut media_playlist = parse_media_playlist_res(&response_bytes)
+                .map_err(|_| io::Error::other(MediaPlaylistParseError))?;
+            fill_byte_range_offsets(&mut media_playlist.segments);
+            window.update(media_playlist.media_sequence, &media_playlist.segments);
 
             let playlist_duration_secs: f32 = media_playlist.segments
                 .iter()
@@ -70,14 +97,17 @@ fn segment_list_stream(
 
             // Filter segments:
             //  - If this isn't the first playlist, filter segments we have already seen
-            //  - If this is the first playlist, filter all segments until the first one that ends
-            //    before three target durations from the end of the file
+            //  - If this is the first playlist and we were asked to start from a segment, filter
+            //    the ones before it
+            //  - Otherwise, filter all segments until the first one that ends before three target
+            //    durations from the end of the file
             //    ^ only if the playlist hasn't ended (to support non-live streams)
             let min_end_secs = playlist_duration_secs - media_playlist.target_duration as f32 * 3.;
             let filtered_segments = timed_segments
-                .filter(move |(segment_sequence, segment, segment_start_time)| match last_seen_sequence {
-                    Some(last_seen_sequence) => *segment_sequence > last_seen_sequence,
-                    None => media_playlist.end_list || segment_start_time + segment.duration >= min_end_secs,
+                .filter(move |(segment_sequence, segment, segment_start_time)| match (last_seen_sequence, start_sequence) {
+                    (Some(last_seen_sequence), _) => *segment_sequence > last_seen_sequence,
+                    (None, Some(start_sequence)) => *segment_sequence >= start_sequence,
+                    (None, None) => media_playlist.end_list || segment_start_time + segment.duration >= min_end_secs,
                 });
 
             let segments_with_expiry_time: Vec<_> = filtered_segments
@@ -94,7 +124,7 @@ fn segment_list_stream(
                 (Some(first_segment), Some(last_segment)) => {
                     if let Some(last_seen_sequence) = last_seen_sequence {
                         if last_seen_sequence + 1 < first_segment.sequence {
-                            log::warn!("Discontinuity in HLS stream (sequence {} to {})", last_seen_sequence, first_segment.sequence);
+                            tracing::warn!("Discontinuity in HLS stream (sequence {} to {})", last_seen_sequence, first_segment.sequence);
                         }
                     }
 
@@ -131,11 +161,17 @@ fn segment_list_stream(
     }
 }
 
+/// Streams each segment in a media playlist with its sequence number, refreshing the playlist if
+/// it's live. If `start_sequence` is set, the stream starts from that segment instead of from the
+/// beginning (or near the end, for live playlists). Every playlist that's loaded is recorded in
+/// `window`.
 pub fn segment_stream(
-    initial_response: reqwest::Response,
+    initial_playlist: Bytes,
     request_builder: reqwest::RequestBuilder,
-) -> impl Stream<Item = io::Result<m3u8_rs::MediaSegment>> {
-    segment_list_stream(initial_response, request_builder)
+    start_sequence: Option<u64>,
+    window: SegmentWindow,
+) -> impl Stream<Item = io::Result<(u64, m3u8_rs::MediaSegment)>> {
+    segment_list_stream(initial_playlist, request_builder, start_sequence, window)
         .map(|segments| Ok(future::ready(segments)))
         .try_buffered(1)
         .map_ok(|segments| stream::iter(segments).map(io::Result::Ok))
@@ -143,7 +179,7 @@ pub fn segment_stream(
         .try_filter_map(|segment_data| async move {
             let now = Instant::now();
             if now > segment_data.expiry {
-                log::warn!(
+                tracing::warn!(
                     "Ignoring segment {} since it has expired (-{} secs)",
                     segment_data.sequence,
                     (now - segment_data.expiry).as_secs_f64()
@@ -151,6 +187,6 @@ pub fn segment_stream(
                 return Ok(None);
             }
 
-            Ok(Some(segment_data.segment))
+            Ok(Some((segment_data.sequence, segment_data.segment)))
         })
 }
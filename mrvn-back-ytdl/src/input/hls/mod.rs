@@ -1,16 +1,76 @@
+use crate::input::hls::master_playlist::resolve_media_playlist;
 use crate::input::hls::media_file_stream::media_file_stream;
 use crate::input::hls::media_segment_stream::segment_stream;
+pub use crate::input::hls::segment_window::SegmentWindow;
+use crate::input::{Reconnect, MAX_RECONNECTS};
+use async_stream::try_stream;
 use bytes::Bytes;
 use futures::Stream;
 use tokio::io;
 
+mod master_playlist;
 mod media_file_stream;
 mod media_segment_stream;
+mod segment_window;
 
 pub fn hls_chunks(
     base_url: url::Url,
     initial_response: reqwest::Response,
     request_builder: reqwest::RequestBuilder,
+    reconnect: Reconnect,
+    window: SegmentWindow,
 ) -> impl Stream<Item = io::Result<Bytes>> {
-    media_file_stream(base_url, segment_stream(initial_response, request_builder))
+    try_stream! {
+        let mut source = resolve_media_playlist(base_url, initial_response, request_builder).await?;
+        let mut start_sequence = window.start_sequence();
+        let mut reconnects = 0;
+
+        loop {
+            let chunks = media_file_stream(
+                source.url,
+                segment_stream(
+                    source.initial_playlist,
+                    source.request_builder,
+                    start_sequence,
+                    window.clone(),
+                ),
+            );
+            let mut maybe_error = None;
+            for await chunk in chunks {
+                match chunk {
+                    Ok((sequence, chunk)) => {
+                        window.set_first_played(sequence);
+                        start_sequence = Some(sequence + 1);
+                        yield chunk;
+                    }
+                    Err(why) => {
+                        maybe_error = Some(why);
+                        break;
+                    }
+                }
+            }
+
+            // Playlists stop working when their URL expires, which can happen partway through a
+            // long song. A new one is found and playback continues from the next segment.
+            let Some(why) = maybe_error else {
+                break;
+            };
+            if reconnects >= MAX_RECONNECTS {
+                Err(why)?
+            } else {
+                tracing::warn!("Error while streaming playlist, reconnecting: {}", why);
+            }
+            reconnects += 1;
+
+            let (url, request_builder) = reconnect().await?;
+            let initial_response = request_builder
+                .try_clone()
+                .unwrap()
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .map_err(io::Error::other)?;
+            source = resolve_media_playlist(url, initial_response, request_builder).await?;
+        }
+    }
 }
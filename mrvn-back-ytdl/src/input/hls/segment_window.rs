@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct WindowSegment {
+    sequence: u64,
+    /// When the segment starts, measured from the start of the first playlist that was loaded.
+    start_secs: f64,
+}
+
+#[derive(Default)]
+struct SegmentWindowState {
+    start_sequence: Option<u64>,
+    segments: VecDeque<WindowSegment>,
+    next_start_secs: f64,
+    first_played_secs: Option<f64>,
+}
+
+/// The segments of an HLS playlist that can still be loaded, so playback can go back to an
+/// earlier one. Live streams with a DVR window keep some time behind the live edge available.
+#[derive(Clone, Default)]
+pub struct SegmentWindow(Arc<Mutex<SegmentWindowState>>);
+
+impl SegmentWindow {
+    /// A window for a stream that starts playing from a specific segment, instead of from the
+    /// beginning (or near the live edge).
+    pub(crate) fn from_sequence(sequence: u64) -> Self {
+        SegmentWindow(Arc::new(Mutex::new(SegmentWindowState {
+            start_sequence: Some(sequence),
+            ..SegmentWindowState::default()
+        })))
+    }
+
+    pub(crate) fn start_sequence(&self) -> Option<u64> {
+        self.0.lock().unwrap().start_sequence
+    }
+
+    /// Records the segments in a freshly loaded playlist. Segments that have dropped out of the
+    /// playlist are forgotten, since they can't be loaded any more.
+    pub(crate) fn update(&self, media_sequence: u64, segments: &[m3u8_rs::MediaSegment]) {
+        let mut state = self.0.lock().unwrap();
+        for (index, segment) in segments.iter().enumerate() {
+            let sequence = media_sequence + index as u64;
+            if state
+                .segments
+                .back()
+                .is_some_and(|last| last.sequence >= sequence)
+            {
+                continue;
+            }
+
+            let start_secs = state.next_start_secs;
+            state.segments.push_back(WindowSegment {
+                sequence,
+                start_secs,
+            });
+            state.next_start_secs += segment.duration as f64;
+        }
+        while state
+            .segments
+            .front()
+            .is_some_and(|segment| segment.sequence < media_sequence)
+        {
+            state.segments.pop_front();
+        }
+    }
+
+    pub(crate) fn set_first_played(&self, sequence: u64) {
+        let mut state = self.0.lock().unwrap();
+        if state.first_played_secs.is_some() {
+            return;
+        }
+        state.first_played_secs = state
+            .segments
+            .iter()
+            .find(|segment| segment.sequence == sequence)
+            .map(|segment| segment.start_secs);
+    }
+
+    /// Finds the segment that was playing `amount` before `play_time`, which is measured from
+    /// the first segment that was played. Going back further than the window reaches gives the
+    /// oldest segment that's still available. Returns `None` if nothing has been played yet.
+    pub(crate) fn rewind_sequence(&self, play_time: Duration, amount: Duration) -> Option<u64> {
+        let state = self.0.lock().unwrap();
+        let target_secs = state.first_played_secs? + play_time.as_secs_f64() - amount.as_secs_f64();
+        state
+            .segments
+            .iter()
+            .rev()
+            .find(|segment| segment.start_secs <= target_secs)
+            .or(state.segments.front())
+            .map(|segment| segment.sequence)
+    }
+}
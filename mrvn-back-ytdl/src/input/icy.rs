@@ -0,0 +1,93 @@
+use crate::StreamTitle;
+use async_stream::try_stream;
+use bytes::Bytes;
+use futures::Stream;
+use tokio::io;
+
+/// Internet radio servers interleave metadata into the audio when asked to with this header. The
+/// response's `icy-metaint` header then says how many bytes of audio come between each block.
+pub const ICY_METADATA_HEADER: &str = "Icy-MetaData";
+pub const ICY_METAINT_HEADER: &str = "icy-metaint";
+
+enum IcyState {
+    Audio { remaining: usize },
+    MetadataLength,
+    Metadata { remaining: usize },
+}
+
+/// Strips the metadata blocks out of an Icecast/SHOUTcast stream, leaving plain audio. Each time
+/// a block names a new track, it's written to `stream_title`.
+pub fn icy_chunks(
+    initial_response: reqwest::Response,
+    metadata_interval: usize,
+    stream_title: StreamTitle,
+) -> impl Stream<Item = io::Result<Bytes>> {
+    try_stream! {
+        let mut state = IcyState::Audio { remaining: metadata_interval };
+        let mut metadata = Vec::new();
+
+        for await bytes_maybe in initial_response.bytes_stream() {
+            let bytes = match bytes_maybe {
+                Ok(bytes) => bytes,
+                Err(why) => {
+                    tracing::warn!("Error while receiving radio data: {}", why);
+                    break;
+                }
+            };
+
+            let mut offset = 0;
+            while offset < bytes.len() {
+                state = match state {
+                    IcyState::Audio { remaining } => {
+                        let audio_len = remaining.min(bytes.len() - offset);
+                        yield bytes.slice(offset..(offset + audio_len));
+                        offset += audio_len;
+
+                        match remaining - audio_len {
+                            0 => IcyState::MetadataLength,
+                            remaining => IcyState::Audio { remaining },
+                        }
+                    }
+                    IcyState::MetadataLength => {
+                        // The length byte counts in 16 byte blocks
+                        let metadata_len = bytes[offset] as usize * 16;
+                        offset += 1;
+                        metadata.clear();
+
+                        match metadata_len {
+                            0 => IcyState::Audio { remaining: metadata_interval },
+                            remaining => IcyState::Metadata { remaining },
+                        }
+                    }
+                    IcyState::Metadata { remaining } => {
+                        let metadata_len = remaining.min(bytes.len() - offset);
+                        metadata.extend_from_slice(&bytes[offset..(offset + metadata_len)]);
+                        offset += metadata_len;
+
+                        match remaining - metadata_len {
+                            0 => {
+                                if let Some(title) = parse_stream_title(&metadata) {
+                                    stream_title.set(title);
+                                }
+                                IcyState::Audio { remaining: metadata_interval }
+                            }
+                            remaining => IcyState::Metadata { remaining },
+                        }
+                    }
+                };
+            }
+        }
+    }
+}
+
+/// Metadata blocks look like `StreamTitle='Artist - Track';StreamUrl='';`, padded with zeroes.
+fn parse_stream_title(metadata: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(metadata);
+    let (_, after_key) = text.split_once("StreamTitle='")?;
+    let title = match after_key.find("';") {
+        Some(end) => &after_key[..end],
+        None => after_key.trim_end_matches('\0').trim_end_matches('\''),
+    };
+    let title = title.trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
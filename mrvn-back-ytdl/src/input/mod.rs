@@ -1,5 +1,13 @@
+mod dash;
 mod hls;
+mod icy;
+mod reconnect;
 mod remote_file;
+mod seekable_remote_file;
 
+pub use self::dash::*;
 pub use self::hls::*;
+pub use self::icy::*;
+pub use self::reconnect::*;
 pub use self::remote_file::*;
+pub use self::seekable_remote_file::*;
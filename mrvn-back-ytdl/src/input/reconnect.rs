@@ -0,0 +1,41 @@
+use futures::Future;
+use std::fmt::{Display, Formatter};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io;
+
+/// How many times one stream can find a new URL before giving up, so media that can't be loaded
+/// any more doesn't keep being retried.
+pub const MAX_RECONNECTS: u32 = 3;
+
+pub type ReconnectFuture =
+    Pin<Box<dyn Future<Output = io::Result<(url::Url, reqwest::RequestBuilder)>> + Send + Sync>>;
+
+/// Finds a new URL for the media being streamed, and a request for it, for when the old one
+/// stops working partway through.
+pub type Reconnect = Arc<dyn Fn() -> ReconnectFuture + Send + Sync>;
+
+#[derive(Debug)]
+pub struct MediaChangedError;
+
+impl Display for MediaChangedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "media changed after reconnecting")
+    }
+}
+
+impl std::error::Error for MediaChangedError {}
+
+/// The total length in a range response's `Content-Range` header, e.g. `bytes 100-199/1000`.
+/// Resuming from a byte offset only makes sense if this hasn't changed after reconnecting.
+pub fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .parse()
+        .ok()
+}
@@ -1,3 +1,4 @@
+use crate::input::{content_range_total, MediaChangedError, Reconnect, MAX_RECONNECTS};
 use async_stream::try_stream;
 use bytes::Bytes;
 use futures::Stream;
@@ -6,11 +7,14 @@ use tokio::io;
 pub fn remote_file_chunks(
     initial_response: reqwest::Response,
     request_builder: reqwest::RequestBuilder,
+    reconnect: Reconnect,
 ) -> impl Stream<Item = io::Result<Bytes>> {
     try_stream! {
         let content_length = initial_response.content_length();
+        let mut request_builder = request_builder;
         let mut response = initial_response;
         let mut received_bytes = 0;
+        let mut reconnects = 0;
 
         loop {
             let mut received_this_request = 0;
@@ -18,7 +22,7 @@ pub fn remote_file_chunks(
                 let bytes = match bytes_maybe {
                     Ok(bytes) => bytes,
                     Err(why) => {
-                        log::warn!("Error while receiving data: {}", why);
+                        tracing::warn!("Error while receiving data: {}", why);
                         break;
                     }
                 };
@@ -35,27 +39,53 @@ pub fn remote_file_chunks(
             // We only keep requesting if:
             //  - The initial request had a Content-Length header set, so we know when to stop.
             //  - We haven't received the amount of data we were meant to get.
-            //  - We did not receive an empty response in this request. This ensures we don't
-            //    get into an infinite request loop.
+            //  - We haven't had to find a new URL too many times. This ensures we don't get into
+            //    an infinite request loop.
             let content_length = match content_length {
                 Some(length) => length,
                 None => break,
             };
-            if received_bytes >= content_length || received_this_request == 0 {
+            if received_bytes >= content_length {
                 break;
             }
 
-            response = request_builder
-                .try_clone()
-                .unwrap()
-                .header(
-                    reqwest::header::RANGE,
-                    format!("bytes={}-{}", received_bytes, content_length),
-                )
-                .send()
-                .await
-                .and_then(reqwest::Response::error_for_status)
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            // An empty response means the URL has probably stopped working (YouTube's expire
+            // after a while, for example), so a new one is found instead of asking again.
+            if received_this_request == 0 {
+                if reconnects >= MAX_RECONNECTS {
+                    Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream stopped sending data"))?
+                }
+                reconnects += 1;
+                tracing::warn!("Stream stopped sending data, reconnecting");
+                request_builder = reconnect().await?.1;
+            }
+
+            response = loop {
+                let result = request_builder
+                    .try_clone()
+                    .unwrap()
+                    .header(
+                        reqwest::header::RANGE,
+                        format!("bytes={}-{}", received_bytes, content_length),
+                    )
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status);
+                match result {
+                    Err(why) if reconnects < MAX_RECONNECTS => {
+                        tracing::warn!("Error while resuming stream, reconnecting: {}", why);
+                        reconnects += 1;
+                        request_builder = reconnect().await?.1;
+                    }
+                    result => break result,
+                }
+            }
+            .map_err(io::Error::other)?;
+
+            // The new URL might point to a different file, which can't be resumed partway through
+            if content_range_total(&response).is_some_and(|total| total != content_length) {
+                Err(io::Error::other(MediaChangedError))?
+            }
         }
     }
 }
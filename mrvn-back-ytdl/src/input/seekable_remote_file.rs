@@ -0,0 +1,222 @@
+use crate::input::{
+    content_range_total, MediaChangedError, Reconnect, ReconnectFuture, MAX_RECONNECTS,
+};
+use futures::{ready, Future, TryStreamExt};
+use serenity::async_trait;
+use songbird::input::AsyncMediaSource;
+use std::io::SeekFrom;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use tokio::io::{self, AsyncRead, AsyncSeek, ReadBuf};
+use tokio_util::io::StreamReader;
+
+type ResponseFuture = Pin<Box<dyn Future<Output = reqwest::Result<reqwest::Response>> + Send>>;
+type BodyReader = Pin<Box<dyn AsyncRead + Send>>;
+
+enum BodyState {
+    Streaming(BodyReader),
+    Requesting(ResponseFuture),
+    /// The URL stopped working, so a new one is being found.
+    Reconnecting(ReconnectFuture),
+    /// A new request needs to be made from the current position, after seeking or if the
+    /// remote closed the last one early.
+    Idle,
+}
+
+struct RemoteFileState {
+    request_builder: reqwest::RequestBuilder,
+    reconnect: Reconnect,
+    reconnects: u32,
+    content_length: u64,
+    position: u64,
+    received_this_request: u64,
+    body: BodyState,
+}
+
+impl RemoteFileState {
+    fn can_reconnect(&self) -> bool {
+        self.reconnects < MAX_RECONNECTS
+    }
+
+    fn start_reconnect(&mut self) -> BodyState {
+        self.reconnects += 1;
+        BodyState::Reconnecting((self.reconnect)())
+    }
+}
+
+/// A remote file that can be seeked by making a new request with a Range header. This lets
+/// Symphonia jump around in formats that need it (like MP4 files with their index at the end),
+/// but only works if the server reports the file's length and accepts ranges.
+pub struct SeekableRemoteFile {
+    // Pending requests aren't Sync, so everything is kept behind a mutex that's only ever
+    // accessed through `get_mut`.
+    state: Mutex<RemoteFileState>,
+}
+
+impl SeekableRemoteFile {
+    /// Creates a seekable file if the initial response says the server supports it.
+    pub fn try_new(
+        initial_response: reqwest::Response,
+        request_builder: reqwest::RequestBuilder,
+        reconnect: Reconnect,
+    ) -> Result<Self, reqwest::Response> {
+        let accepts_ranges = initial_response
+            .headers()
+            .get(reqwest::header::ACCEPT_RANGES)
+            .is_some_and(|val| val == "bytes");
+        let content_length = match initial_response.content_length() {
+            Some(content_length) if accepts_ranges => content_length,
+            _ => return Err(initial_response),
+        };
+
+        Ok(SeekableRemoteFile {
+            state: Mutex::new(RemoteFileState {
+                request_builder,
+                reconnect,
+                reconnects: 0,
+                content_length,
+                position: 0,
+                received_this_request: 0,
+                body: BodyState::Streaming(body_reader(initial_response)),
+            }),
+        })
+    }
+}
+
+fn body_reader(response: reqwest::Response) -> BodyReader {
+    Box::pin(StreamReader::new(
+        response.bytes_stream().map_err(io::Error::other),
+    ))
+}
+
+impl AsyncRead for SeekableRemoteFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let state = self.get_mut().state.get_mut().unwrap();
+
+        loop {
+            if state.position >= state.content_length || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let next_body = match &mut state.body {
+                BodyState::Idle => {
+                    state.received_this_request = 0;
+                    let request = state
+                        .request_builder
+                        .try_clone()
+                        .unwrap()
+                        .header(reqwest::header::RANGE, format!("bytes={}-", state.position))
+                        .send();
+                    BodyState::Requesting(Box::pin(request))
+                }
+                BodyState::Requesting(request) => {
+                    let result = ready!(request.as_mut().poll(cx))
+                        .and_then(reqwest::Response::error_for_status);
+                    let response = match result {
+                        Ok(response) => response,
+                        Err(why) if state.can_reconnect() => {
+                            tracing::warn!("Error while requesting data, reconnecting: {}", why);
+                            state.body = state.start_reconnect();
+                            continue;
+                        }
+                        Err(why) => return Poll::Ready(Err(io::Error::other(why))),
+                    };
+                    if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                        return Poll::Ready(Err(io::Error::other("remote ignored range request")));
+                    }
+                    // A new URL might point to a different file, which can't be read from the
+                    // same position
+                    if content_range_total(&response)
+                        .is_some_and(|total| total != state.content_length)
+                    {
+                        return Poll::Ready(Err(io::Error::other(MediaChangedError)));
+                    }
+                    BodyState::Streaming(body_reader(response))
+                }
+                BodyState::Reconnecting(reconnect) => {
+                    let (_, request_builder) = ready!(reconnect.as_mut().poll(cx))?;
+                    state.request_builder = request_builder;
+                    BodyState::Idle
+                }
+                BodyState::Streaming(body) => {
+                    let filled_before = buf.filled().len();
+                    match ready!(body.as_mut().poll_read(cx, buf)) {
+                        Ok(()) => {
+                            let read_len = (buf.filled().len() - filled_before) as u64;
+                            if read_len > 0 {
+                                state.position += read_len;
+                                state.received_this_request += read_len;
+                                return Poll::Ready(Ok(()));
+                            }
+
+                            // The remote closed the request before sending everything. Pick up
+                            // where it left off. If it didn't send anything at all the URL has
+                            // probably stopped working, so a new one is found, up to a limit so
+                            // we don't get stuck requesting forever.
+                            if state.received_this_request > 0 {
+                                BodyState::Idle
+                            } else if state.can_reconnect() {
+                                tracing::warn!("Stream stopped sending data, reconnecting");
+                                state.start_reconnect()
+                            } else {
+                                return Poll::Ready(Err(io::Error::new(
+                                    io::ErrorKind::UnexpectedEof,
+                                    "stream stopped sending data",
+                                )));
+                            }
+                        }
+                        Err(why) => {
+                            tracing::warn!("Error while receiving data: {}", why);
+                            if state.received_this_request > 0 {
+                                BodyState::Idle
+                            } else if state.can_reconnect() {
+                                state.start_reconnect()
+                            } else {
+                                return Poll::Ready(Err(why));
+                            }
+                        }
+                    }
+                }
+            };
+            state.body = next_body;
+        }
+    }
+}
+
+impl AsyncSeek for SeekableRemoteFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let state = self.get_mut().state.get_mut().unwrap();
+        let new_position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => state.content_length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => state.position.checked_add_signed(offset),
+        }
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+
+        if new_position != state.position {
+            state.position = new_position;
+            state.body = BodyState::Idle;
+        }
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.get_mut().state.get_mut().unwrap().position))
+    }
+}
+
+#[async_trait]
+impl AsyncMediaSource for SeekableRemoteFile {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    async fn byte_len(&self) -> Option<u64> {
+        Some(self.state.lock().unwrap().content_length)
+    }
+}
@@ -1,18 +1,45 @@
+#![allow(clippy::result_large_err)]
+
 mod brain;
+mod buffer_monitor;
+mod cache;
+mod clip;
+mod cookies;
+mod counters;
+mod decoded_pcm;
 mod error;
+mod events;
+mod extractor;
+mod filters;
 mod formats;
+mod innertube;
 mod input;
+mod metadata_cache;
+mod playback;
+mod presence;
+mod proxy;
 mod setup;
 mod song;
 mod songbird;
 mod speaker;
+mod tts;
 
 pub use self::brain::*;
+pub use self::buffer_monitor::{buffer_stats, BufferStats};
+pub use self::clip::*;
+pub use self::counters::{counters, CacheCounter, Counters};
+pub use self::decoded_pcm::*;
 pub use self::error::*;
+pub use self::events::PlaybackEvent;
+pub use self::extractor::{extractor_stats, Extractor, ExtractorStats};
+pub use self::filters::*;
+pub use self::playback::{InputState, PlaybackFailure};
+pub use self::presence::*;
+pub use self::proxy::{set_proxy_rules, ProxyRules};
 pub use self::setup::*;
 pub use self::song::*;
 pub use self::speaker::*;
 
 lazy_static::lazy_static! {
-    static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::new();
+    static ref HTTP_CLIENT: reqwest::Client = proxy::build_http_client();
 }
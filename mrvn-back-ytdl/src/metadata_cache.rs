@@ -0,0 +1,99 @@
+use crate::{counters, Song, StreamTitle};
+use dashmap::DashMap;
+use serenity::model::prelude::UserId;
+use std::time::{Duration, SystemTime};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// Download URLs stop working at their expiry time, so cached songs are dropped a little while
+/// before then to leave time for them to actually be played.
+const DOWNLOAD_EXPIRY_MARGIN: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    ytdl_url: String,
+    no_playlist: bool,
+}
+
+struct CachedSongs {
+    songs: Vec<Song>,
+    expiry: Instant,
+}
+
+lazy_static::lazy_static! {
+    static ref METADATA_CACHE: DashMap<CacheKey, CachedSongs> = DashMap::new();
+}
+
+/// Looks up songs youtube-dl recently resolved for a URL. Each song gets a new ID and is
+/// attributed to `user_id`, as if it had just been loaded.
+pub(crate) fn get(ytdl_url: &str, no_playlist: bool, user_id: UserId) -> Option<Vec<Song>> {
+    let key = CacheKey {
+        ytdl_url: ytdl_url.to_string(),
+        no_playlist,
+    };
+    let Some(entry) = METADATA_CACHE.get(&key) else {
+        counters().metadata_cache.record(false);
+        return None;
+    };
+    if entry.expiry <= Instant::now() {
+        drop(entry);
+        METADATA_CACHE.remove(&key);
+        counters().metadata_cache.record(false);
+        return None;
+    }
+    counters().metadata_cache.record(true);
+
+    Some(
+        entry
+            .songs
+            .iter()
+            .map(|song| {
+                let mut song = song.clone();
+                song.metadata.id = Uuid::new_v4();
+                song.metadata.user_id = user_id;
+                song.metadata.stream_title = StreamTitle::default();
+                song
+            })
+            .collect(),
+    )
+}
+
+pub(crate) fn insert(ytdl_url: &str, no_playlist: bool, songs: &[Song], ttl: Duration) {
+    if ttl.is_zero() || songs.is_empty() {
+        return;
+    }
+
+    let now = Instant::now();
+    METADATA_CACHE.retain(|_, cached| cached.expiry > now);
+
+    let now_system = SystemTime::now();
+    let ttl = songs
+        .iter()
+        .filter_map(Song::download_expiry)
+        .map(|expiry| {
+            expiry
+                .duration_since(now_system)
+                .unwrap_or_default()
+                .saturating_sub(DOWNLOAD_EXPIRY_MARGIN)
+        })
+        .fold(ttl, Duration::min);
+    if ttl.is_zero() {
+        return;
+    }
+
+    METADATA_CACHE.insert(
+        CacheKey {
+            ytdl_url: ytdl_url.to_string(),
+            no_playlist,
+        },
+        CachedSongs {
+            songs: songs.to_vec(),
+            expiry: now + ttl,
+        },
+    );
+}
+
+/// Forgets anything cached for a URL, for when its download URL turned out not to work.
+pub(crate) fn invalidate(ytdl_url: &str) {
+    METADATA_CACHE.retain(|key, _| key.ytdl_url != ytdl_url);
+}
@@ -0,0 +1,143 @@
+use crate::buffer_monitor::BufferCounter;
+use crate::input::{MediaChangedError, SegmentWindow};
+use crate::PlaybackPosition;
+use serenity::async_trait;
+use songbird::input::AsyncMediaSource;
+use songbird::tracks::PlayError;
+use std::fmt::{Display, Formatter};
+use std::io::SeekFrom;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{self, AsyncRead, AsyncSeek, ReadBuf};
+
+/// Why a song stopped playing before it finished.
+#[derive(Clone, Debug)]
+pub enum PlaybackFailure {
+    /// The song's audio couldn't be decoded.
+    Decode(String),
+    /// The song's download URL stopped working, and a new one couldn't be found.
+    Expired(String),
+    /// The connection the song was streaming over broke.
+    Network(String),
+}
+
+impl PlaybackFailure {
+    /// Works out why a song's stream failed from the error it returned.
+    fn from_io_error(why: &io::Error) -> Self {
+        let maybe_inner = why.get_ref();
+        let is_expired = maybe_inner.is_some_and(|inner| {
+            // Finding a new URL fails with one of the crate's errors
+            inner.is::<MediaChangedError>()
+                || inner.is::<crate::Error>()
+                || inner
+                    .downcast_ref::<reqwest::Error>()
+                    .and_then(reqwest::Error::status)
+                    .is_some_and(|status| status.is_client_error())
+        });
+        if is_expired {
+            PlaybackFailure::Expired(why.to_string())
+        } else {
+            PlaybackFailure::Network(why.to_string())
+        }
+    }
+
+    pub(crate) fn from_play_error(why: &PlayError) -> Self {
+        match why {
+            PlayError::Create(_) => PlaybackFailure::Network(why.to_string()),
+            _ => PlaybackFailure::Decode(why.to_string()),
+        }
+    }
+}
+
+impl Display for PlaybackFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlaybackFailure::Decode(why) => write!(f, "the audio couldn't be decoded ({})", why),
+            PlaybackFailure::Expired(why) => write!(f, "the link to the audio expired ({})", why),
+            PlaybackFailure::Network(why) => write!(f, "the connection was lost ({})", why),
+        }
+    }
+}
+
+/// What a song's input shares with the speaker playing it.
+#[derive(Clone, Default)]
+pub struct InputState {
+    pub(crate) position: PlaybackPosition,
+    pub(crate) window: SegmentWindow,
+    pub(crate) buffer: BufferCounter,
+    failure: Arc<Mutex<Option<PlaybackFailure>>>,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// State for an input that starts from a specific HLS segment.
+    pub(crate) fn from_sequence(sequence: u64) -> Self {
+        InputState {
+            window: SegmentWindow::from_sequence(sequence),
+            ..Self::default()
+        }
+    }
+
+    /// The first thing that went wrong while streaming, if anything did.
+    pub(crate) fn failure(&self) -> Option<PlaybackFailure> {
+        self.failure.lock().unwrap().clone()
+    }
+
+    fn record_failure(&self, failure: PlaybackFailure) {
+        self.failure.lock().unwrap().get_or_insert(failure);
+    }
+}
+
+/// Remembers the first error a song's stream returns. songbird reads streams on its own and only
+/// sees that they've ended, so this is how the reason gets back to the speaker.
+pub(crate) struct FailureRecorder {
+    inner: Box<dyn AsyncMediaSource>,
+    state: InputState,
+}
+
+impl FailureRecorder {
+    pub(crate) fn new(inner: Box<dyn AsyncMediaSource>, state: InputState) -> Self {
+        FailureRecorder { inner, state }
+    }
+}
+
+impl AsyncRead for FailureRecorder {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Err(why)) = &result {
+            this.state
+                .record_failure(PlaybackFailure::from_io_error(why));
+        }
+        result
+    }
+}
+
+impl AsyncSeek for FailureRecorder {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.get_mut().inner).start_seek(position)
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.get_mut().inner).poll_complete(cx)
+    }
+}
+
+#[async_trait]
+impl AsyncMediaSource for FailureRecorder {
+    fn is_seekable(&self) -> bool {
+        self.inner.is_seekable()
+    }
+
+    async fn byte_len(&self) -> Option<u64> {
+        self.inner.byte_len().await
+    }
+}
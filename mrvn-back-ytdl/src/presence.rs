@@ -0,0 +1,54 @@
+use serenity::gateway::{ActivityData, ShardMessenger};
+use serenity::model::prelude::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Keeps a voice bot's presence showing what it's playing. A bot can play in several guilds at
+/// once, so the most recently started song is shown until nothing is playing anywhere. Bots with
+/// more than one shard show the same song on all of them.
+#[derive(Default)]
+pub struct SpeakerPresence {
+    state: Mutex<PresenceState>,
+}
+
+#[derive(Default)]
+struct PresenceState {
+    shards: HashMap<ShardId, ShardMessenger>,
+    playing: Vec<(GuildId, String)>,
+}
+
+impl PresenceState {
+    fn update(&self) {
+        let activity = self
+            .playing
+            .last()
+            .map(|(_, title)| ActivityData::listening(title));
+        for shard in self.shards.values() {
+            shard.set_activity(activity.clone());
+        }
+    }
+}
+
+impl SpeakerPresence {
+    pub fn set_shard(&self, shard_id: ShardId, shard: ShardMessenger) {
+        let mut state = self.state.lock().unwrap();
+        state.shards.insert(shard_id, shard);
+        state.update();
+    }
+
+    pub(crate) fn set_playing(&self, guild_id: GuildId, maybe_title: Option<&str>) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .playing
+            .retain(|(playing_guild_id, _)| *playing_guild_id != guild_id);
+        if let Some(title) = maybe_title {
+            state.playing.push((guild_id, title.to_string()));
+        }
+        state.update();
+    }
+
+    /// How many guilds the speaker is playing in.
+    pub fn playing_count(&self) -> usize {
+        self.state.lock().unwrap().playing.len()
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::OnceLock;
+
+static PROXY_RULES: OnceLock<ProxyRules> = OnceLock::new();
+
+/// Which proxies requests go through, for both youtube-dl and streaming media. HTTP and SOCKS
+/// proxies can be used, e.g. `socks5://localhost:1080`.
+#[derive(Debug)]
+pub struct ProxyRules {
+    default: Option<url::Url>,
+    hosts: Vec<(String, url::Url)>,
+}
+
+impl ProxyRules {
+    /// Requests to a host containing one of the patterns in `hosts` go through its proxy, and any
+    /// other requests go through `default` if it's set. The longest matching pattern wins.
+    pub fn new(
+        default: Option<&str>,
+        hosts: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<ProxyRules, url::ParseError> {
+        let default = default.map(url::Url::parse).transpose()?;
+        let mut hosts = hosts
+            .into_iter()
+            .map(|(pattern, proxy)| Ok((pattern, url::Url::parse(&proxy)?)))
+            .collect::<Result<Vec<_>, url::ParseError>>()?;
+        hosts.sort_by_key(|(pattern, _)| std::cmp::Reverse(pattern.len()));
+        Ok(ProxyRules { default, hosts })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.default.is_none() && self.hosts.is_empty()
+    }
+
+    fn proxy_for_host(&self, maybe_host: Option<&str>) -> Option<&url::Url> {
+        maybe_host
+            .and_then(|host| {
+                self.hosts
+                    .iter()
+                    .find(|(pattern, _)| host.contains(pattern.as_str()))
+            })
+            .map(|(_, proxy)| proxy)
+            .or(self.default.as_ref())
+    }
+}
+
+/// Sets the proxies used for every request. This needs to be called before anything is loaded,
+/// and only the first call has any effect.
+pub fn set_proxy_rules(rules: ProxyRules) {
+    if PROXY_RULES.set(rules).is_err() {
+        tracing::warn!("Proxy rules were set more than once, ignoring the new ones");
+    }
+}
+
+/// The proxy to use for a URL. Things that aren't URLs, like youtube-dl searches, use the default
+/// proxy.
+pub(crate) fn proxy_for_url(url: &str) -> Option<&'static url::Url> {
+    let rules = PROXY_RULES.get()?;
+    let maybe_url = url::Url::parse(url).ok();
+    rules.proxy_for_host(maybe_url.as_ref().and_then(|url| url.host_str()))
+}
+
+pub(crate) fn build_http_client() -> reqwest::Client {
+    let mut builder = reqwest::Client::builder();
+
+    // Adding a proxy stops reqwest from using the system's proxy settings, so this is only done
+    // when proxies have actually been configured.
+    if let Some(rules) = PROXY_RULES.get().filter(|rules| !rules.is_empty()) {
+        builder = builder.proxy(reqwest::Proxy::custom(|url| {
+            rules.proxy_for_host(url.host_str()).cloned()
+        }));
+    }
+    builder.build().expect("Unable to create HTTP client")
+}
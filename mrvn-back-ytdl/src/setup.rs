@@ -1,8 +1,12 @@
-use crate::PlayConfig;
+use crate::{proxy, Extractor};
 use std::fmt::{Debug, Display, Formatter};
-use std::io::{Error, ErrorKind, Result};
-use std::process::ExitStatus;
+use std::io::{Error, Result};
+use std::process::{ExitStatus, Stdio};
 use tokio::process::Command;
+use tokio::sync::Mutex;
+
+/// Where youtube-dl downloads its updates from, used to pick a proxy for updating.
+const YTDL_UPDATE_URL: &str = "https://github.com/";
 
 #[derive(Debug)]
 pub struct StatusCodeError(ExitStatus);
@@ -15,8 +19,39 @@ impl Display for StatusCodeError {
 
 impl std::error::Error for StatusCodeError {}
 
-pub async fn get_ytdl_version(config: &PlayConfig<'_>) -> Result<String> {
-    let ytdl = Command::new(config.ytdl_name)
+#[derive(Debug)]
+pub struct UpdateFailedError {
+    status: ExitStatus,
+    message: String,
+}
+
+impl Display for UpdateFailedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "status code {}: {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for UpdateFailedError {}
+
+/// youtube-dl's version before and after it was updated.
+#[derive(Debug)]
+pub struct YtdlUpdate {
+    pub old_version: String,
+    pub new_version: String,
+}
+
+impl YtdlUpdate {
+    pub fn is_updated(&self) -> bool {
+        self.old_version != self.new_version
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref UPDATE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+pub async fn get_ytdl_version(extractor: Extractor<'_>) -> Result<String> {
+    let ytdl = Command::new(extractor.name)
         .arg("--version")
         .output()
         .await?;
@@ -28,9 +63,49 @@ pub async fn get_ytdl_version(config: &PlayConfig<'_>) -> Result<String> {
                 version_raw.truncate(version_raw.trim_end().len());
                 Ok(version_raw)
             }
-            Err(err) => Err(Error::new(ErrorKind::Other, err)),
+            Err(err) => Err(Error::other(err)),
         }
     } else {
-        Err(Error::new(ErrorKind::Other, StatusCodeError(ytdl.status)))
+        Err(Error::other(StatusCodeError(ytdl.status)))
+    }
+}
+
+/// Runs an extractor's self-update, then checks it still works by asking for its version again.
+/// Only one update runs at a time, so updating on a timer and by command can't overlap.
+pub async fn update_ytdl(extractor: Extractor<'_>) -> Result<YtdlUpdate> {
+    let _update_guard = UPDATE_LOCK.lock().await;
+    let old_version = get_ytdl_version(extractor).await?;
+
+    let mut command = Command::new(extractor.name);
+    command.arg("--update");
+    if let Some(proxy) = proxy::proxy_for_url(YTDL_UPDATE_URL) {
+        command.args(["--proxy", proxy.as_str()]);
+    }
+    let update = command.stdin(Stdio::null()).output().await?;
+    if !update.status.success() {
+        // youtube-dl explains why it couldn't update in the last line it prints, e.g. if it was
+        // installed with a package manager.
+        let message = last_line(&update.stderr)
+            .or_else(|| last_line(&update.stdout))
+            .unwrap_or_default();
+        return Err(Error::other(UpdateFailedError {
+            status: update.status,
+            message,
+        }));
     }
+
+    let new_version = get_ytdl_version(extractor).await?;
+    Ok(YtdlUpdate {
+        old_version,
+        new_version,
+    })
+}
+
+fn last_line(output: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
 }
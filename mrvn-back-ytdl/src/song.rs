@@ -1,36 +1,155 @@
-use crate::input::{hls_chunks, remote_file_chunks};
-use crate::{Error, HTTP_CLIENT};
-use futures::{future, TryStreamExt};
+use crate::buffer_monitor::BufferMonitor;
+use crate::cache::{cache_key, open_cached_file, spawn_cache_download};
+use crate::cookies::{cookie_header, is_login_required_error};
+use crate::extractor::{with_extractors, Extractor};
+use crate::input::{
+    dash_chunks, hls_chunks, icy_chunks, parse_dash_manifest, remote_file_chunks, Reconnect,
+    SeekableRemoteFile, ICY_METADATA_HEADER, ICY_METAINT_HEADER,
+};
+use crate::playback::FailureRecorder;
+use crate::songbird::PROBE;
+use crate::{
+    counters, DecodedPcmSource, Error, GuildFilters, InputState, PlaybackPosition, FILTER_CHANNELS,
+    HTTP_CLIENT, OUTPUT_SAMPLE_RATE,
+};
+use crate::{innertube, metadata_cache, proxy};
+use async_stream::try_stream;
+use futures::{future, Stream, TryStreamExt};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serenity::async_trait;
 use serenity::model::prelude::UserId;
 use songbird::input::core::io::MediaSource;
-use songbird::input::{AsyncAdapterStream, AsyncMediaSource, AudioStream, Input, LiveInput};
+use songbird::input::{
+    AsyncAdapterStream, AsyncMediaSource, AudioStream, Input, LiveInput, RawAdapter,
+};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::process::Stdio;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+use symphonia::core::io::MediaSourceStream;
 use symphonia::core::probe::Hint;
-use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncSeek, BufReader, ReadBuf};
-use tokio::process::Command as TokioCommand;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncSeek, BufReader, Lines, ReadBuf};
+use tokio::process::{ChildStderr, Command as TokioCommand};
 use tokio_util::io::StreamReader;
 use uuid::Uuid;
 
+/// Queries starting with this play a file from the local library instead of going through
+/// youtube-dl, e.g. `local:albums/song.mp3`.
+pub const LOCAL_SONG_PREFIX: &str = "local:";
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Song {
     pub metadata: SongMetadata,
     download_url: String,
     http_headers: Vec<(String, String)>,
 }
 
+#[derive(Default)]
 pub struct PlayConfig<'s> {
     pub search_prefix: &'s str,
+    pub search_results_prefix: &'s str,
     pub host_blocklist: &'s [String],
-    pub ytdl_name: &'s str,
-    pub ytdl_args: &'s [String],
+    /// The programs songs are loaded with. If one fails, the next is tried.
+    pub extractors: Vec<Extractor<'s>>,
+    /// A Netscape-format cookies file that's given to youtube-dl and sent with requests for the
+    /// media it finds, so songs that need an account can be played.
+    pub cookies_path: Option<&'s str>,
     pub buffer_capacity_kb: usize,
+    /// The directory `local:` songs are played from. Local songs can't be played if this isn't
+    /// set.
+    pub local_library_path: Option<&'s str>,
+    /// The directory YouTube songs are cached in after they're first played, if set.
+    pub audio_cache_path: Option<&'s str>,
+    /// How big the audio cache can get before the least recently played songs are removed.
+    pub audio_cache_max_bytes: u64,
+    /// How long youtube-dl's results are reused for before asking it again. Zero turns this off.
+    pub metadata_cache_ttl: Duration,
+    /// The most songs that are loaded from a single playlist. Zero means there's no limit.
+    pub max_playlist_size: usize,
+    /// Whether YouTube videos and searches are loaded with YouTube's API directly instead of
+    /// through youtube-dl. Anything else still goes through youtube-dl.
+    pub native_youtube: bool,
+    /// Whether songs are decoded and sent through the guild's audio filters. This stops songbird
+    /// from passing Opus audio straight through, so it costs more CPU.
+    pub audio_filters: bool,
+    /// Silence quieter than this many decibels is trimmed from songs going through the audio
+    /// filters, if set.
+    pub silence_threshold_db: Option<f32>,
+    /// The loudness in LUFS that songs going through the audio filters are normalized to, if set.
+    pub loudness_target_lufs: Option<f64>,
+    /// A text-to-speech endpoint used to announce each song before it plays, if set.
+    pub tts_url: Option<&'s str>,
+    pub tts_announcement: &'s str,
+}
+
+/// The parts of `PlayConfig` that `Song::fetch_one` needs, kept so a stream can find its song's
+/// download URL again after `Song::get_input` has returned.
+#[derive(Clone)]
+struct RefetchConfig {
+    extractors: Vec<(String, Vec<String>)>,
+    cookies_path: Option<String>,
+    metadata_cache_ttl: Duration,
+    native_youtube: bool,
+}
+
+impl RefetchConfig {
+    fn new(config: &PlayConfig<'_>) -> Self {
+        RefetchConfig {
+            extractors: config
+                .extractors
+                .iter()
+                .map(|extractor| (extractor.name.to_string(), extractor.args.to_vec()))
+                .collect(),
+            cookies_path: config.cookies_path.map(str::to_string),
+            metadata_cache_ttl: config.metadata_cache_ttl,
+            native_youtube: config.native_youtube,
+        }
+    }
+
+    fn play_config(&self) -> PlayConfig<'_> {
+        PlayConfig {
+            extractors: self
+                .extractors
+                .iter()
+                .map(|(name, args)| Extractor { name, args })
+                .collect(),
+            cookies_path: self.cookies_path.as_deref(),
+            metadata_cache_ttl: self.metadata_cache_ttl,
+            native_youtube: self.native_youtube,
+            ..PlayConfig::default()
+        }
+    }
+}
+
+/// Songs that were found by `Song::load_streaming`, and a stream of the ones youtube-dl is still
+/// looking for.
+pub struct LoadedSongs {
+    pub songs: Vec<Song>,
+    pub remaining: Option<RemainingSongs>,
+    /// How many songs the playlist has in total, if it's known. This ignores
+    /// `PlayConfig::max_playlist_size`.
+    pub total_count: Option<usize>,
 }
 
+impl LoadedSongs {
+    /// Songs that were all loaded up front, like from the metadata cache, so there's nothing left
+    /// to stream and the total is how many there are.
+    fn complete(songs: Vec<Song>) -> Self {
+        LoadedSongs {
+            total_count: Some(songs.len()),
+            songs,
+            remaining: None,
+        }
+    }
+}
+
+pub type RemainingSongs = Pin<Box<dyn Stream<Item = Result<Song, Error>> + Send>>;
+
 #[derive(serde::Deserialize)]
 struct YtdlOutput {
     pub title: String,
@@ -42,25 +161,204 @@ struct YtdlOutput {
     pub thumbnail: Option<String>,
     pub http_headers: HashMap<String, String>,
     pub duration: Option<f64>,
+    pub n_entries: Option<usize>,
+    pub chapters: Option<Vec<YtdlChapter>>,
+    pub extractor_key: Option<String>,
+    pub artist: Option<String>,
+    pub uploader: Option<String>,
+    pub channel: Option<String>,
+    pub view_count: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+struct YtdlChapter {
+    pub start_time: f64,
+    pub title: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct YtdlFlatOutput {
+    pub id: String,
+}
+
+pub(crate) fn get_youtube_video_id(webpage_url: &str) -> Option<String> {
+    let url = url::Url::parse(webpage_url).ok()?;
+    let host = url.host_str()?;
+    if host == "youtu.be" {
+        let video_id = url.path().trim_start_matches('/');
+        return (!video_id.is_empty()).then(|| video_id.to_string());
+    }
+    if host == "youtube.com" || host.ends_with(".youtube.com") {
+        return url
+            .query_pairs()
+            .find(|(key, _)| key == "v")
+            .map(|(_, value)| value.into_owned());
+    }
+    None
+}
+
+/// Turns a song's URL into a form that's the same for every link to the song, so songs can be
+/// compared no matter how they were linked. YouTube links come down to the video ID.
+pub fn canonical_url(url: &str) -> String {
+    if let Some(video_id) = get_youtube_video_id(url) {
+        return format!("youtube:{}", video_id);
+    }
+    let Ok(parsed) = url::Url::parse(url) else {
+        return url.to_string();
+    };
+
+    let host = parsed.host_str().unwrap_or_default();
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let path = parsed.path().trim_end_matches('/');
+    match parsed.query() {
+        Some(query) => format!("{}{}?{}", host, path, query),
+        None => format!("{}{}", host, path),
+    }
+}
+
+/// Turns a term from `/play` into something youtube-dl understands. Search terms get the search
+/// prefix added, and links to blocked hosts are rejected.
+fn get_ytdl_url<'t>(term: &'t str, config: &PlayConfig<'_>) -> Result<Cow<'t, str>, Error> {
+    match url::Url::parse(term) {
+        Ok(url) => {
+            if let Some(host_str) = url.host_str() {
+                // Ensure the resolved host isn't in the blocklist
+                if config
+                    .host_blocklist
+                    .iter()
+                    .any(|domain| host_str.contains(domain))
+                {
+                    return Err(Error::UnsupportedUrl);
+                }
+            }
+
+            Ok(Cow::Borrowed(term))
+        }
+        Err(_) => Ok(Cow::Owned(format!("{}:{}", config.search_prefix, &term))),
+    }
+}
+
+/// Creates a youtube-dl command for loading a URL, with the extractor's arguments and the
+/// configured cookies and proxy.
+fn ytdl_command(extractor: Extractor<'_>, ytdl_url: &str, config: &PlayConfig<'_>) -> TokioCommand {
+    let mut command = TokioCommand::new(extractor.name);
+    command.args(extractor.args);
+    if let Some(cookies_path) = config.cookies_path {
+        command.args(["--cookies", cookies_path]);
+    }
+    if let Some(proxy) = proxy::proxy_for_url(ytdl_url) {
+        command.args(["--proxy", proxy.as_str()]);
+    }
+    command
+}
+
+/// Turns an error youtube-dl printed into an `Error`, picking out ones that need logging in.
+fn ytdl_error(message: &str) -> Error {
+    if is_login_required_error(message) {
+        Error::LoginRequired(message.to_string())
+    } else {
+        Error::Ytdl(message.to_string())
+    }
+}
+
+/// Starts youtube-dl resolving a URL. Each line it outputs describes one song.
+fn spawn_ytdl_lines(
+    extractor: Extractor<'_>,
+    ytdl_url: &str,
+    config: &PlayConfig<'_>,
+) -> Result<Lines<BufReader<ChildStderr>>, Error> {
+    let mut command = ytdl_command(extractor, ytdl_url, config);
+    if config.max_playlist_size > 0 {
+        command.args(["--playlist-end", &config.max_playlist_size.to_string()]);
+    }
+    let mut ytdl = command
+        .args([
+            "--dump-json",
+            "--ignore-config",
+            "--no-warnings",
+            ytdl_url,
+            "-o",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(Error::Io)?;
+    Ok(BufReader::new(ytdl.stderr.take().unwrap()).lines())
 }
 
 fn parse_ytdl_line(line: &str, user_id: UserId) -> Result<Song, Error> {
+    parse_ytdl_output(line).map(|value| song_from_ytdl_output(value, user_id))
+}
+
+fn parse_ytdl_output(line: &str) -> Result<YtdlOutput, Error> {
     let trimmed_line = line.trim();
     if let Some(error) = trimmed_line.strip_prefix("ERROR: ") {
-        return Err(Error::Ytdl(error.to_string()));
+        return Err(ytdl_error(error));
     }
 
-    let value: YtdlOutput = serde_json::from_str(trimmed_line)
-        .map_err(|err| Error::Parse(err, trimmed_line.to_string()))?;
+    serde_json::from_str(trimmed_line).map_err(|err| Error::Parse(err, trimmed_line.to_string()))
+}
+
+fn song_from_innertube_video(video: innertube::Video, user_id: UserId) -> Song {
+    Song {
+        metadata: SongMetadata {
+            id: Uuid::new_v4(),
+            title: video.title,
+            url: format!("https://www.youtube.com/watch?v={}", video.id),
+            thumbnail_url: video.thumbnail_url,
+            duration_seconds: video.duration_seconds,
+            user_id,
+            stream_title: StreamTitle::default(),
+            chapters: Vec::new(),
+            author: video.author,
+            // The same name youtube-dl gives YouTube, so songs look the same however they loaded.
+            source: Some("Youtube".to_string()),
+            view_count: video.view_count,
+        },
+        download_url: video.download_url,
+        http_headers: video.http_headers,
+    }
+}
+
+/// Loads songs with YouTube's API if that's turned on and the URL is something it can load.
+async fn load_native(
+    ytdl_url: &str,
+    no_playlist: bool,
+    user_id: UserId,
+    config: &PlayConfig<'_>,
+) -> Option<Vec<Song>> {
+    if !config.native_youtube {
+        return None;
+    }
+    let videos = innertube::load(ytdl_url, no_playlist).await?;
+    let songs: Vec<_> = videos
+        .into_iter()
+        .map(|video| song_from_innertube_video(video, user_id))
+        .collect();
+    metadata_cache::insert(ytdl_url, no_playlist, &songs, config.metadata_cache_ttl);
+    Some(songs)
+}
 
+fn song_from_ytdl_output(value: YtdlOutput, user_id: UserId) -> Song {
     // Twitch stream extractor puts the stream title as the description for some reason
     let title = match &value.extractor as &str {
         "twitch:stream" => value.description,
         _ => value.fulltitle,
     };
     let title = title.unwrap_or(value.title);
+    let chapters = value
+        .chapters
+        .unwrap_or_default()
+        .into_iter()
+        .map(|chapter| Chapter {
+            title: chapter.title.unwrap_or_default(),
+            start_seconds: chapter.start_time,
+        })
+        .collect();
 
-    Ok(Song {
+    Song {
         metadata: SongMetadata {
             id: Uuid::new_v4(),
             title,
@@ -72,6 +370,11 @@ fn parse_ytdl_line(line: &str, user_id: UserId) -> Result<Song, Error> {
                 value.duration
             },
             user_id,
+            stream_title: StreamTitle::default(),
+            chapters,
+            author: value.artist.or(value.uploader).or(value.channel),
+            source: value.extractor_key,
+            view_count: value.view_count,
         },
         download_url: value.url.to_string(),
         http_headers: value
@@ -79,7 +382,7 @@ fn parse_ytdl_line(line: &str, user_id: UserId) -> Result<Song, Error> {
             .iter()
             .map(|(key, value)| (key.to_string(), value.to_string()))
             .collect(),
-    })
+    }
 }
 
 impl Song {
@@ -88,46 +391,198 @@ impl Song {
         user_id: UserId,
         config: &PlayConfig<'_>,
     ) -> Result<Vec<Song>, Error> {
-        let ytdl_url = match url::Url::parse(term) {
-            Ok(url) => {
-                if let Some(host_str) = url.host_str() {
-                    // Ensure the resolved host isn't in the blocklist
-                    if config
-                        .host_blocklist
-                        .iter()
-                        .any(|domain| host_str.contains(domain))
-                    {
-                        return Err(Error::UnsupportedUrl);
-                    }
-                }
+        if let Some(path) = term.strip_prefix(LOCAL_SONG_PREFIX) {
+            return Song::load_local(path.trim(), user_id, config).await;
+        }
+
+        let ytdl_url = get_ytdl_url(term, config)?;
+        Song::load_ytdl_url(&ytdl_url, user_id, config).await
+    }
+
+    /// Like `load`, but returns as soon as the first song has been found. Any more songs (from a
+    /// playlist, for example) are sent through `LoadedSongs::remaining` as youtube-dl finds them.
+    pub async fn load_streaming(
+        term: &str,
+        user_id: UserId,
+        config: &PlayConfig<'_>,
+    ) -> Result<LoadedSongs, Error> {
+        if let Some(path) = term.strip_prefix(LOCAL_SONG_PREFIX) {
+            return Ok(LoadedSongs::complete(
+                Song::load_local(path.trim(), user_id, config).await?,
+            ));
+        }
 
-                Cow::Borrowed(term)
+        let ytdl_url = get_ytdl_url(term, config)?.into_owned();
+        if let Some(songs) = metadata_cache::get(&ytdl_url, false, user_id) {
+            return Ok(LoadedSongs::complete(songs));
+        }
+        if let Some(songs) = load_native(&ytdl_url, false, user_id, config).await {
+            return Ok(LoadedSongs::complete(songs));
+        }
+
+        // Only the first song can be retried with another extractor, since the rest are streamed
+        // after this returns.
+        let (maybe_first_output, mut lines) = with_extractors(&ytdl_url, config, |extractor| {
+            let ytdl_url = &ytdl_url;
+            async move {
+                let mut lines = spawn_ytdl_lines(extractor, ytdl_url, config)?;
+                let maybe_first_output = match lines.next_line().await.map_err(Error::Io)? {
+                    Some(line) => Some(parse_ytdl_output(&line)?),
+                    None => None,
+                };
+                Ok((maybe_first_output, lines))
+            }
+        })
+        .await?;
+        let first_output = match maybe_first_output {
+            Some(first_output) => first_output,
+            None => {
+                return Ok(LoadedSongs {
+                    songs: Vec::new(),
+                    remaining: None,
+                    total_count: None,
+                })
+            }
+        };
+        let total_count = first_output.n_entries;
+        let first_song = song_from_ytdl_output(first_output, user_id);
+
+        let metadata_cache_ttl = config.metadata_cache_ttl;
+        let mut all_songs = vec![first_song.clone()];
+        let remaining = try_stream! {
+            while let Some(line) = lines.next_line().await.map_err(Error::Io)? {
+                let song = parse_ytdl_line(&line, user_id)?;
+                all_songs.push(song.clone());
+                yield song;
             }
-            Err(_) => Cow::Owned(format!("{}:{}", config.search_prefix, &term)),
+            metadata_cache::insert(&ytdl_url, false, &all_songs, metadata_cache_ttl);
         };
 
-        let mut ytdl = TokioCommand::new(config.ytdl_name)
-            .args(config.ytdl_args)
-            .args([
-                "--dump-json",
-                "--ignore-config",
-                "--no-warnings",
-                ytdl_url.as_ref(),
-                "-o",
-                "-",
-            ])
-            .stdin(Stdio::null())
-            .stderr(Stdio::piped())
-            .stdout(Stdio::null())
-            .spawn()
-            .map_err(Error::Io)?;
-        let mut lines = BufReader::new(ytdl.stderr.take().unwrap()).lines();
-
-        let mut songs = Vec::new();
-        while let Some(line) = lines.next_line().await.map_err(Error::Io)? {
-            songs.push(parse_ytdl_line(&line, user_id)?);
+        Ok(LoadedSongs {
+            songs: vec![first_song],
+            remaining: Some(Box::pin(remaining)),
+            total_count,
+        })
+    }
+
+    /// Creates a song that streams a file straight from a URL, like a Discord attachment, without
+    /// asking youtube-dl about it. The format is guessed from the file name.
+    pub fn from_remote_file(url: &str, file_name: &str, user_id: UserId) -> Song {
+        let title = Path::new(file_name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| file_name.to_string());
+
+        Song {
+            metadata: SongMetadata {
+                id: Uuid::new_v4(),
+                title,
+                url: url.to_string(),
+                thumbnail_url: None,
+                duration_seconds: None,
+                user_id,
+                stream_title: StreamTitle::default(),
+                chapters: Vec::new(),
+                author: None,
+                source: None,
+                view_count: None,
+            },
+            download_url: url.to_string(),
+            http_headers: Vec::new(),
         }
+    }
 
+    /// Copies a song so it can be queued again by a user, without resolving it again. The copy is
+    /// a separate song, so it gets its own ID.
+    pub fn replay(&self, user_id: UserId) -> Song {
+        Song {
+            metadata: SongMetadata {
+                id: Uuid::new_v4(),
+                user_id,
+                stream_title: StreamTitle::default(),
+                ..self.metadata.clone()
+            },
+            download_url: self.download_url.clone(),
+            http_headers: self.http_headers.clone(),
+        }
+    }
+
+    pub async fn search(
+        term: &str,
+        count: usize,
+        user_id: UserId,
+        config: &PlayConfig<'_>,
+    ) -> Result<Vec<Song>, Error> {
+        let ytdl_url = format!("{}{}:{}", config.search_results_prefix, count, term);
+        Song::load_ytdl_url(&ytdl_url, user_id, config).await
+    }
+
+    async fn load_local(
+        path: &str,
+        user_id: UserId,
+        config: &PlayConfig<'_>,
+    ) -> Result<Vec<Song>, Error> {
+        let library_path = config.local_library_path.ok_or(Error::UnsupportedUrl)?;
+        let Some(file_path) = resolve_local_path(library_path, path).await? else {
+            return Ok(Vec::new());
+        };
+
+        let duration_seconds = tokio::task::spawn_blocking({
+            let file_path = file_path.clone();
+            move || probe_local_duration(&file_path)
+        })
+        .await
+        .map_err(Error::Runtime)?;
+        let title = file_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+        let download_url = url::Url::from_file_path(&file_path)
+            .map_err(|_| Error::UnsupportedUrl)?
+            .to_string();
+
+        Ok(vec![Song {
+            metadata: SongMetadata {
+                id: Uuid::new_v4(),
+                title,
+                url: format!("{}{}", LOCAL_SONG_PREFIX, path),
+                thumbnail_url: None,
+                duration_seconds,
+                user_id,
+                stream_title: StreamTitle::default(),
+                chapters: Vec::new(),
+                author: None,
+                source: None,
+                view_count: None,
+            },
+            download_url,
+            http_headers: Vec::new(),
+        }])
+    }
+
+    async fn load_ytdl_url(
+        ytdl_url: &str,
+        user_id: UserId,
+        config: &PlayConfig<'_>,
+    ) -> Result<Vec<Song>, Error> {
+        if let Some(songs) = metadata_cache::get(ytdl_url, false, user_id) {
+            return Ok(songs);
+        }
+        if let Some(songs) = load_native(ytdl_url, false, user_id, config).await {
+            return Ok(songs);
+        }
+
+        let songs = with_extractors(ytdl_url, config, |extractor| async move {
+            let mut lines = spawn_ytdl_lines(extractor, ytdl_url, config)?;
+            let mut songs = Vec::new();
+            while let Some(line) = lines.next_line().await.map_err(Error::Io)? {
+                songs.push(parse_ytdl_line(&line, user_id)?);
+            }
+            Ok(songs)
+        })
+        .await?;
+
+        metadata_cache::insert(ytdl_url, false, &songs, config.metadata_cache_ttl);
         Ok(songs)
     }
 
@@ -136,61 +591,246 @@ impl Song {
         user_id: UserId,
         config: &PlayConfig<'_>,
     ) -> Result<Song, Error> {
-        let mut ytdl = TokioCommand::new(config.ytdl_name)
-            .args(config.ytdl_args)
-            .args([
-                "--dump-json",
-                "--ignore-config",
-                "--no-warnings",
-                "--no-playlist",
-                webpage_url,
-                "-o",
-                "-",
-            ])
-            .stdin(Stdio::null())
-            .stderr(Stdio::piped())
-            .stdout(Stdio::null())
-            .spawn()
-            .map_err(Error::Io)?;
-        let first_line = BufReader::new(ytdl.stderr.take().unwrap())
-            .lines()
-            .next_line()
+        if let Some(path) = webpage_url.strip_prefix(LOCAL_SONG_PREFIX) {
+            return Song::load_local(path.trim(), user_id, config)
+                .await?
+                .pop()
+                .ok_or(Error::UnsupportedUrl);
+        }
+        if let Some(song) =
+            metadata_cache::get(webpage_url, true, user_id).and_then(|mut songs| songs.pop())
+        {
+            return Ok(song);
+        }
+        if let Some(song) = load_native(webpage_url, true, user_id, config)
             .await
-            .map_err(Error::Io)?
-            .ok_or(Error::UnsupportedUrl)?;
+            .and_then(|mut songs| songs.pop())
+        {
+            return Ok(song);
+        }
 
-        parse_ytdl_line(&first_line, user_id)
+        let song = with_extractors(webpage_url, config, |extractor| async move {
+            let mut ytdl = ytdl_command(extractor, webpage_url, config)
+                .args([
+                    "--dump-json",
+                    "--ignore-config",
+                    "--no-warnings",
+                    "--no-playlist",
+                    webpage_url,
+                    "-o",
+                    "-",
+                ])
+                .stdin(Stdio::null())
+                .stderr(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .map_err(Error::Io)?;
+            let first_line = BufReader::new(ytdl.stderr.take().unwrap())
+                .lines()
+                .next_line()
+                .await
+                .map_err(Error::Io)?
+                .ok_or(Error::UnsupportedUrl)?;
+            parse_ytdl_line(&first_line, user_id)
+        })
+        .await?;
+        metadata_cache::insert(
+            webpage_url,
+            true,
+            std::slice::from_ref(&song),
+            config.metadata_cache_ttl,
+        );
+        Ok(song)
+    }
+
+    /// Finds a song related to the one at a URL, using the mix YouTube generates for it. Songs
+    /// with URLs in `exclude_urls` are skipped, so the same few songs aren't played over and over.
+    /// Returns `None` if the URL isn't a YouTube video or nothing related could be found.
+    pub async fn fetch_related(
+        webpage_url: &str,
+        exclude_urls: &[String],
+        user_id: UserId,
+        config: &PlayConfig<'_>,
+    ) -> Result<Option<Song>, Error> {
+        let Some(video_id) = get_youtube_video_id(webpage_url) else {
+            return Ok(None);
+        };
+        let mix_url = format!(
+            "https://www.youtube.com/watch?v={}&list=RD{}",
+            video_id, video_id
+        );
+
+        let maybe_related_url = with_extractors(&mix_url, config, |extractor| {
+            let (mix_url, video_id) = (&mix_url, &video_id);
+            async move {
+                let mut ytdl = ytdl_command(extractor, mix_url, config)
+                    .args([
+                        "--dump-json",
+                        "--ignore-config",
+                        "--no-warnings",
+                        "--flat-playlist",
+                        "--yes-playlist",
+                        mix_url,
+                        "-o",
+                        "-",
+                    ])
+                    .stdin(Stdio::null())
+                    .stderr(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .spawn()
+                    .map_err(Error::Io)?;
+                let mut lines = BufReader::new(ytdl.stderr.take().unwrap()).lines();
+
+                let mut maybe_related_url = None;
+                while let Some(line) = lines.next_line().await.map_err(Error::Io)? {
+                    let trimmed_line = line.trim();
+                    if let Some(error) = trimmed_line.strip_prefix("ERROR: ") {
+                        return Err(ytdl_error(error));
+                    }
+                    if maybe_related_url.is_some() {
+                        continue;
+                    }
+
+                    let value: YtdlFlatOutput = serde_json::from_str(trimmed_line)
+                        .map_err(|err| Error::Parse(err, trimmed_line.to_string()))?;
+                    let already_played = value.id == *video_id
+                        || exclude_urls.iter().any(|exclude_url| {
+                            get_youtube_video_id(exclude_url) == Some(value.id.clone())
+                        });
+                    if !already_played {
+                        maybe_related_url =
+                            Some(format!("https://www.youtube.com/watch?v={}", value.id));
+                    }
+                }
+                Ok(maybe_related_url)
+            }
+        })
+        .await?;
+
+        match maybe_related_url {
+            Some(related_url) => Song::fetch_one(&related_url, user_id, config)
+                .await
+                .map(Some),
+            None => Ok(None),
+        }
     }
 
     pub async fn get_input(
         &self,
         config: &PlayConfig<'_>,
+        filters: &Arc<GuildFilters>,
+        state: &InputState,
     ) -> Result<songbird::input::Input, Error> {
         // The cached download URL might have become invalid since fetching it. We assume it's fine
         // but fetch a new one from youtube-dl if playback fails.
-        match self.get_input_no_retry(config).await {
+        match self.get_input_no_retry(config, filters, state).await {
             Ok(input) => Ok(input),
             Err(why) => {
-                log::error!(
+                tracing::error!(
                     "Error opening stream to play {}: {}",
                     &self.metadata.url,
                     why
                 );
+                metadata_cache::invalidate(&self.metadata.url);
                 let refetch_song =
                     Song::fetch_one(&self.metadata.url, self.metadata.user_id, config).await?;
-                refetch_song.get_input_no_retry(config).await
+                refetch_song
+                    .get_input_no_retry(config, filters, state)
+                    .await
             }
         }
     }
 
+    /// When the download URL stops working, if it says. YouTube's URLs have this in an `expire`
+    /// query parameter.
+    pub(crate) fn download_expiry(&self) -> Option<SystemTime> {
+        let url = url::Url::parse(&self.download_url).ok()?;
+        let expire_secs = url
+            .query_pairs()
+            .find(|(key, _)| key == "expire")
+            .and_then(|(_, value)| value.parse().ok())?;
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(expire_secs))
+    }
+
     async fn get_input_no_retry(
         &self,
         config: &PlayConfig<'_>,
+        filters: &Arc<GuildFilters>,
+        state: &InputState,
     ) -> Result<songbird::input::Input, Error> {
         let parsed_download_url =
             url::Url::parse(&self.download_url).map_err(|_| Error::UnsupportedUrl)?;
+        let maybe_filters = config
+            .audio_filters
+            .then(|| (filters.clone(), state.position.clone()));
+
+        // Only songs from the local library can be read from disk, in case youtube-dl ever hands
+        // back a file URL.
+        if self.metadata.url.starts_with(LOCAL_SONG_PREFIX) {
+            let file_path = parsed_download_url
+                .to_file_path()
+                .map_err(|_| Error::UnsupportedUrl)?;
+            let file = tokio::fs::File::open(&file_path)
+                .await
+                .map_err(Error::Io)?
+                .into_std()
+                .await;
+
+            let mut hint = Hint::new();
+            if let Some(extension) = file_path.extension().and_then(|ext| ext.to_str()) {
+                hint.with_extension(extension);
+            }
+            return Ok(create_input(config, Box::new(file), hint, maybe_filters));
+        }
+
+        let maybe_cache = config.audio_cache_path.zip(cache_key(&self.metadata.url));
+        if let Some((cache_path, key)) = &maybe_cache {
+            let maybe_file = open_cached_file(cache_path, key).await;
+            counters().audio_cache.record(maybe_file.is_some());
+            if let Some(file) = maybe_file {
+                tracing::trace!("Playing {} from the audio cache", self.metadata.url);
+                return Ok(create_input(
+                    config,
+                    Box::new(file),
+                    Hint::new(),
+                    maybe_filters,
+                ));
+            }
+        }
 
         // Start streaming data from the remote
+        let request_builder = self.request_builder(config, &parsed_download_url).await;
+
+        // Live streams never finish downloading, so there's no point trying to cache them
+        if let Some((cache_path, key)) = maybe_cache {
+            if self.metadata.duration_seconds.is_some() {
+                spawn_cache_download(
+                    cache_path.to_string(),
+                    config.audio_cache_max_bytes,
+                    key,
+                    request_builder.try_clone().unwrap(),
+                );
+            }
+        }
+        create_source(
+            config,
+            parsed_download_url,
+            request_builder,
+            self.reconnect(config),
+            &self.metadata.stream_title,
+            state,
+            maybe_filters,
+        )
+        .await
+    }
+
+    /// Builds a request for the song's media, with the headers youtube-dl asked for and any
+    /// cookies that apply.
+    async fn request_builder(
+        &self,
+        config: &PlayConfig<'_>,
+        parsed_download_url: &url::Url,
+    ) -> reqwest::RequestBuilder {
         let mut headers = reqwest::header::HeaderMap::new();
         for (key, value) in &self.http_headers {
             headers.insert(
@@ -198,13 +838,53 @@ impl Song {
                 value.parse().unwrap(),
             );
         }
+        // youtube-dl sometimes includes the cookies it used in the song's headers already
+        if let Some(cookies_path) = config.cookies_path {
+            if !headers.contains_key(reqwest::header::COOKIE) {
+                let maybe_cookie = cookie_header(cookies_path, parsed_download_url).await;
+                if let Some(cookie) = maybe_cookie.and_then(|cookie| cookie.parse().ok()) {
+                    headers.insert(reqwest::header::COOKIE, cookie);
+                }
+            }
+        }
 
-        let request_builder = HTTP_CLIENT.get(&self.download_url).headers(headers);
-        create_source(config, parsed_download_url, request_builder).await
+        // Radio streams only include the current track's title if asked for it
+        HTTP_CLIENT
+            .get(&self.download_url)
+            .headers(headers)
+            .header(ICY_METADATA_HEADER, "1")
+    }
+
+    /// Lets a stream find the song's download URL again if it stops working partway through,
+    /// like when a YouTube URL expires during a long song.
+    fn reconnect(&self, config: &PlayConfig<'_>) -> Reconnect {
+        let webpage_url = self.metadata.url.clone();
+        let user_id = self.metadata.user_id;
+        let refetch_config = RefetchConfig::new(config);
+
+        Arc::new(move || {
+            let webpage_url = webpage_url.clone();
+            let refetch_config = refetch_config.clone();
+            // Loading the song isn't `Sync` but the streams waiting on it need to be, so it's
+            // done on its own task.
+            let handle = tokio::spawn(async move {
+                tracing::info!("Finding a new download URL for {}", webpage_url);
+                metadata_cache::invalidate(&webpage_url);
+                let config = refetch_config.play_config();
+                let song = Song::fetch_one(&webpage_url, user_id, &config)
+                    .await
+                    .map_err(std::io::Error::other)?;
+                let parsed_download_url = url::Url::parse(&song.download_url)
+                    .map_err(|_| std::io::Error::other(Error::UnsupportedUrl))?;
+                let request_builder = song.request_builder(&config, &parsed_download_url).await;
+                Ok((parsed_download_url, request_builder))
+            });
+            Box::pin(async move { handle.await.map_err(std::io::Error::other)? })
+        })
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SongMetadata {
     pub id: Uuid,
     pub title: String,
@@ -212,12 +892,99 @@ pub struct SongMetadata {
     pub thumbnail_url: Option<String>,
     pub duration_seconds: Option<f64>,
     pub user_id: UserId,
+    pub stream_title: StreamTitle,
+    /// The song's chapters in order, if it has any.
+    pub chapters: Vec<Chapter>,
+    /// The song's artist, or whoever uploaded it if that isn't known.
+    #[serde(default)]
+    pub author: Option<String>,
+    /// The name youtube-dl gives the site the song is from, like `Youtube` or `Soundcloud`.
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub view_count: Option<u64>,
+}
+
+/// A named part of a song, like a chapter in a YouTube video.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    pub start_seconds: f64,
+}
+
+/// The track currently playing on an internet radio stream, which changes as the stream plays.
+/// Clones of a song's metadata all share the same title.
+#[derive(Clone, Default)]
+pub struct StreamTitle(Arc<std::sync::Mutex<Option<String>>>);
+
+impl StreamTitle {
+    pub fn get(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub(crate) fn set(&self, title: String) {
+        *self.0.lock().unwrap() = Some(title);
+    }
+}
+
+// Only the title at the time is kept, since a song that's been serialized isn't playing anymore.
+impl Serialize for StreamTitle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for StreamTitle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let title = Option::<String>::deserialize(deserializer)?;
+        Ok(StreamTitle(Arc::new(std::sync::Mutex::new(title))))
+    }
+}
+
+/// Finds a file in the local library, making sure the path can't point outside of it (through
+/// `..` or a symlink, for example). Returns `None` if there's no such file.
+async fn resolve_local_path(library_path: &str, path: &str) -> Result<Option<PathBuf>, Error> {
+    let library_path = tokio::fs::canonicalize(library_path)
+        .await
+        .map_err(Error::Io)?;
+    let joined_path = library_path.join(path.trim_start_matches('/'));
+    let file_path = match tokio::fs::canonicalize(&joined_path).await {
+        Ok(file_path) => file_path,
+        Err(why) if why.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(why) => return Err(Error::Io(why)),
+    };
+    if !file_path.starts_with(&library_path) {
+        return Err(Error::UnsupportedUrl);
+    }
+
+    let metadata = tokio::fs::metadata(&file_path).await.map_err(Error::Io)?;
+    Ok(metadata.is_file().then_some(file_path))
+}
+
+fn probe_local_duration(file_path: &Path) -> Option<f64> {
+    let file = std::fs::File::open(file_path).ok()?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = file_path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = PROBE
+        .format(&hint, stream, &Default::default(), &Default::default())
+        .ok()?;
+    let params = &probed.format.default_track()?.codec_params;
+    let time = params.time_base?.calc_time(params.n_frames?);
+    Some(time.seconds as f64 + time.frac)
 }
 
 async fn create_source(
     config: &PlayConfig<'_>,
     request_url: url::Url,
     request_builder: reqwest::RequestBuilder,
+    reconnect: Reconnect,
+    stream_title: &StreamTitle,
+    state: &InputState,
+    maybe_filters: Option<(Arc<GuildFilters>, PlaybackPosition)>,
 ) -> Result<Input, Error> {
     let buffer_capacity_bytes = config.buffer_capacity_kb * 1024;
 
@@ -231,7 +998,7 @@ async fn create_source(
 
     let maybe_extension = request_url
         .path_segments()
-        .and_then(|segments| segments.last())
+        .and_then(|mut segments| segments.next_back())
         .and_then(|segment| segment.rfind('.').map(|idx| (segment, idx)))
         .map(|(segment, idx)| &segment[(idx + 1)..]);
 
@@ -244,10 +1011,21 @@ async fn create_source(
         || maybe_extension == Some("m3u")
         || maybe_mime_type == Some("application/vnd.apple.mpegurl")
         || maybe_mime_type == Some("audio/mpegurl");
+    let maybe_metadata_interval = initial_response
+        .headers()
+        .get(ICY_METAINT_HEADER)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.parse::<usize>().ok())
+        .filter(|interval| *interval > 0);
+
+    let is_dash_stream =
+        maybe_extension == Some("mpd") || maybe_mime_type == Some("application/dash+xml");
 
     let mut hint = Hint::new();
 
-    if is_mpeg_stream {
+    if is_dash_stream {
+        // The format comes from the representation chosen in the manifest
+    } else if is_mpeg_stream {
         // todo: use hint of file linked in m3u8
         // m3u8 stream will probably contain MPEG-TS files
         hint.with_extension("ts");
@@ -258,27 +1036,84 @@ async fn create_source(
     }
 
     // Start streaming chunks from the remote
-    let adapter_stream = if is_mpeg_stream {
-        let stream = hls_chunks(request_url, initial_response, request_builder);
+    let source: Box<dyn AsyncMediaSource> = if is_dash_stream {
+        let manifest_text = initial_response.text().await.map_err(Error::Http)?;
+        let manifest = parse_dash_manifest(&request_url, &manifest_text).map_err(Error::Io)?;
+        if let Some(mime_type) = &manifest.mime_type {
+            hint.mime_type(mime_type);
+        }
+
+        let stream = dash_chunks(manifest);
         let reader = StreamReader::new(stream.try_filter(|chunk| future::ready(!chunk.is_empty())));
-        AsyncAdapterStream::new(
-            Box::new(AsyncReader::new(Box::pin(reader))),
-            buffer_capacity_bytes,
-        )
-    } else {
-        let stream = remote_file_chunks(initial_response, request_builder);
+        Box::new(AsyncReader::new(Box::pin(reader)))
+    } else if let Some(metadata_interval) = maybe_metadata_interval {
+        let stream = icy_chunks(initial_response, metadata_interval, stream_title.clone());
         let reader = StreamReader::new(stream.try_filter(|chunk| future::ready(!chunk.is_empty())));
-        AsyncAdapterStream::new(
-            Box::new(AsyncReader::new(Box::pin(reader))),
-            buffer_capacity_bytes,
-        )
+        Box::new(AsyncReader::new(Box::pin(reader)))
+    } else if is_mpeg_stream {
+        let stream = hls_chunks(
+            request_url,
+            initial_response,
+            request_builder,
+            reconnect,
+            state.window.clone(),
+        );
+        let reader = StreamReader::new(stream.try_filter(|chunk| future::ready(!chunk.is_empty())));
+        Box::new(AsyncReader::new(Box::pin(reader)))
+    } else {
+        match SeekableRemoteFile::try_new(
+            initial_response,
+            request_builder.try_clone().unwrap(),
+            reconnect.clone(),
+        ) {
+            Ok(file) => Box::new(file),
+            Err(initial_response) => {
+                let stream = remote_file_chunks(initial_response, request_builder, reconnect);
+                let reader =
+                    StreamReader::new(stream.try_filter(|chunk| future::ready(!chunk.is_empty())));
+                Box::new(AsyncReader::new(Box::pin(reader)))
+            }
+        }
     };
+    let adapter_stream = AsyncAdapterStream::new(
+        Box::new(FailureRecorder::new(source, state.clone())),
+        buffer_capacity_bytes,
+    );
+
+    Ok(create_input(
+        config,
+        Box::new(BufferMonitor::new(
+            Box::new(adapter_stream),
+            state.buffer.clone(),
+        )),
+        hint,
+        maybe_filters,
+    ))
+}
+
+fn create_input(
+    config: &PlayConfig<'_>,
+    source: Box<dyn MediaSource>,
+    hint: Hint,
+    maybe_filters: Option<(Arc<GuildFilters>, PlaybackPosition)>,
+) -> Input {
+    if let Some((filters, position)) = maybe_filters {
+        let decoded_source = DecodedPcmSource::new(
+            source,
+            hint,
+            filters,
+            position,
+            config.silence_threshold_db,
+            config.loudness_target_lufs,
+        );
+        return RawAdapter::new(decoded_source, OUTPUT_SAMPLE_RATE, FILTER_CHANNELS as u32).into();
+    }
 
     let audio_stream = AudioStream {
-        input: Box::new(adapter_stream) as Box<dyn MediaSource>,
+        input: source,
         hint: Some(hint),
     };
-    Ok(Input::Live(LiveInput::Raw(audio_stream), None))
+    Input::Live(LiveInput::Raw(audio_stream), None)
 }
 
 struct AsyncReader<T> {
@@ -1,5 +1,6 @@
 use crate::formats::MpegTsReader;
 use lazy_static::lazy_static;
+use songbird::input::codecs::RawReader;
 use songbird::{Config, Songbird};
 use std::ops::Deref;
 use std::sync::Arc;
@@ -7,10 +8,11 @@ use symphonia::core::probe::Probe;
 use symphonia::default::register_enabled_formats;
 
 lazy_static! {
-    static ref PROBE: Probe = {
+    pub(crate) static ref PROBE: Probe = {
         let mut probe = Probe::default();
         register_enabled_formats(&mut probe);
         probe.register_all::<MpegTsReader>();
+        probe.register_all::<RawReader>();
         probe
     };
 }
@@ -1,11 +1,17 @@
+use crate::events::PlaybackEvents;
 use crate::songbird::songbird;
-use crate::{Brain, PlayConfig, Song, SongMetadata};
+use crate::{
+    counters, Brain, BufferStats, GuildFilters, InputState, PlayConfig, PlaybackEvent,
+    PlaybackFailure, Song, SongMetadata, SpeakerPresence, HTTP_CLIENT,
+};
 use dashmap::DashMap;
 use serenity::client::ClientBuilder;
 use serenity::{model::prelude::*, prelude::*};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::MutexGuard;
+use uuid::Uuid;
 
 pub struct SpeakerKey;
 
@@ -13,16 +19,35 @@ impl TypeMapKey for SpeakerKey {
     type Value = Arc<Speaker>;
 }
 
+/// Filter settings for each guild, shared between all speakers.
+pub(crate) type GuildFiltersMap = Arc<DashMap<GuildId, Arc<GuildFilters>>>;
+
 pub struct Speaker {
+    token: Arc<str>,
+    presence: Arc<SpeakerPresence>,
+    filters: GuildFiltersMap,
+    events: PlaybackEvents,
     songbird: Arc<songbird::Songbird>,
     guilds: DashMap<GuildId, Arc<Mutex<GuildSpeaker>>>,
+    /// Set once the speaker's voice client has stopped, so nothing tries to play with it.
+    is_stopped: Arc<AtomicBool>,
 }
 
 impl Speaker {
-    fn new(songbird: Arc<songbird::Songbird>) -> Self {
+    fn new(
+        token: Arc<str>,
+        filters: GuildFiltersMap,
+        events: PlaybackEvents,
+        songbird: Arc<songbird::Songbird>,
+    ) -> Self {
         Speaker {
+            token,
+            presence: Arc::new(SpeakerPresence::default()),
+            filters,
+            events,
             songbird,
             guilds: DashMap::new(),
+            is_stopped: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -35,12 +60,65 @@ impl Speaker {
         let current_call = self.songbird.get(guild_id);
         GuildSpeakerHandle {
             guild_id,
+            token: self.token.clone(),
+            presence: self.presence.clone(),
+            filters: self.filters.clone(),
+            events: self.events.clone(),
+            is_stopped: self.is_stopped.clone(),
             songbird: self.songbird.clone(),
             guild_speaker,
             current_call,
         }
     }
 
+    /// Takes the speaker out of use after its voice client stops. The rest of the bot keeps
+    /// running with the other speakers.
+    pub fn set_stopped(&self) {
+        self.is_stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.is_stopped.load(Ordering::Relaxed)
+    }
+
+    pub fn presence(&self) -> &SpeakerPresence {
+        &self.presence
+    }
+
+    /// Called when Discord tells the speaker which voice server to use for a guild. If it's
+    /// different to the one the speaker is connected to, the song carries on once songbird has
+    /// moved over to it, and it's shown as reconnecting until then.
+    pub async fn voice_server_changed(&self, guild_id: GuildId, endpoint: Option<&str>) {
+        let Some(guild_speaker) = self.guilds.get(&guild_id).map(|guild| guild.clone()) else {
+            return;
+        };
+        let mut guild_speaker = guild_speaker.lock().await;
+        if guild_speaker.playing_state.is_none()
+            || guild_speaker.is_reconnecting
+            || guild_speaker.connected_endpoint.is_none()
+            || guild_speaker.connected_endpoint.as_deref() == endpoint
+        {
+            return;
+        }
+
+        tracing::debug!("Voice server has changed, waiting to reconnect");
+        guild_speaker.is_reconnecting = true;
+        let maybe_channel_id = match self.songbird.get(guild_id) {
+            Some(call) => call
+                .lock()
+                .await
+                .current_channel()
+                .map(|id| ChannelId::new(id.0.get())),
+            None => None,
+        };
+        if let Some(channel_id) = maybe_channel_id {
+            self.events.send(PlaybackEvent::Reconnecting {
+                guild_id,
+                channel_id,
+            });
+        }
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = GuildSpeakerHandle> + '_ {
         self.guilds.iter().map(move |guild| {
             let guild_id = *guild.key();
@@ -48,6 +126,11 @@ impl Speaker {
             let current_call = self.songbird.get(guild_id);
             GuildSpeakerHandle {
                 guild_id,
+                token: self.token.clone(),
+                presence: self.presence.clone(),
+                filters: self.filters.clone(),
+                events: self.events.clone(),
+                is_stopped: self.is_stopped.clone(),
                 songbird: self.songbird.clone(),
                 guild_speaker,
                 current_call,
@@ -57,29 +140,90 @@ impl Speaker {
 }
 
 pub trait SpeakerInit {
-    fn register_speaker(self, brain: &mut Brain) -> Self;
+    fn register_speaker(self, brain: &Brain) -> Self;
 }
 
 impl SpeakerInit for ClientBuilder {
-    fn register_speaker(self, brain: &mut Brain) -> Self {
+    fn register_speaker(self, brain: &Brain) -> Self {
         let songbird = songbird();
-        let speaker = Arc::new(Speaker::new(songbird.clone()));
-        brain.speakers.push(speaker.clone());
+        let token = Arc::from(self.get_token());
+        let speaker = Arc::new(Speaker::new(
+            token,
+            brain.filters.clone(),
+            brain.events.clone(),
+            songbird.clone(),
+        ));
+        brain.add_speaker(speaker.clone());
 
         self.voice_manager_arc(songbird)
             .type_map_insert::<SpeakerKey>(speaker)
     }
 }
 
+type BoxedEndedHandler = Box<dyn FnOnce(GuildSpeakerEndedHandle) + Send>;
+type EndedHandlerData = Arc<Mutex<Option<(BoxedEndedHandler, GuildSpeakerEndedBuilder)>>>;
+
 struct GuildPlayingState {
-    metadata: SongMetadata,
+    song: Song,
     track: songbird::tracks::TrackHandle,
+    ended_data: EndedHandlerData,
     is_paused: bool,
+    /// The position in here is only known when the song goes through the audio filters.
+    state: InputState,
+    /// Seeking a track that can't seek ends it, so this is checked first.
+    is_seekable: bool,
+}
+
+/// A song's input, opened ahead of time so the song can start as soon as the one before it ends.
+/// Opening the input starts buffering the song in the background.
+pub struct PrefetchedInput {
+    song_id: Uuid,
+    /// Inputs aren't `Sync`, which the speaker's state needs to be, but the input is only ever
+    /// taken out once so the lock costs nothing.
+    input: std::sync::Mutex<songbird::input::Input>,
+    state: InputState,
+}
+
+impl PrefetchedInput {
+    pub async fn load(
+        song: &Song,
+        config: &PlayConfig<'_>,
+        filters: &Arc<GuildFilters>,
+    ) -> Result<Self, crate::Error> {
+        let state = InputState::new();
+        let input = song.get_input(config, filters, &state).await?;
+        Ok(PrefetchedInput {
+            song_id: song.metadata.id,
+            input: std::sync::Mutex::new(input),
+            state,
+        })
+    }
+
+    pub fn song_id(&self) -> Uuid {
+        self.song_id
+    }
 }
 
 struct GuildSpeaker {
     last_ended_time: Option<Instant>,
     playing_state: Option<GuildPlayingState>,
+    /// The song expected to play next. It's thrown away if a different song ends up playing.
+    prefetched: Option<PrefetchedInput>,
+    /// Sound clips play on their own track, mixed over whatever song is playing.
+    clip_track: Option<songbird::tracks::TrackHandle>,
+    /// The song that was playing when the voice connection dropped out, so it can carry on once
+    /// the speaker has reconnected.
+    interrupted: Option<InterruptedSong>,
+    /// The voice server the speaker last connected to. Discord moves calls to another server when
+    /// the channel's region changes, and songbird reconnects to it on its own.
+    connected_endpoint: Option<String>,
+    is_reconnecting: bool,
+}
+
+struct InterruptedSong {
+    channel_id: ChannelId,
+    song: Song,
+    play_time: Option<Duration>,
 }
 
 impl GuildSpeaker {
@@ -87,12 +231,22 @@ impl GuildSpeaker {
         GuildSpeaker {
             last_ended_time: None,
             playing_state: None,
+            prefetched: None,
+            clip_track: None,
+            interrupted: None,
+            connected_endpoint: None,
+            is_reconnecting: false,
         }
     }
 }
 
 pub struct GuildSpeakerHandle {
     guild_id: GuildId,
+    token: Arc<str>,
+    presence: Arc<SpeakerPresence>,
+    filters: GuildFiltersMap,
+    events: PlaybackEvents,
+    is_stopped: Arc<AtomicBool>,
     songbird: Arc<songbird::Songbird>,
     guild_speaker: Arc<Mutex<GuildSpeaker>>,
     current_call: Option<Arc<Mutex<songbird::Call>>>,
@@ -102,6 +256,11 @@ impl GuildSpeakerHandle {
     pub async fn lock(&self) -> GuildSpeakerRef<'_> {
         GuildSpeakerRef {
             guild_id: self.guild_id,
+            token: self.token.clone(),
+            presence: self.presence.clone(),
+            filters: self.filters.clone(),
+            events: self.events.clone(),
+            is_stopped: self.is_stopped.clone(),
             songbird: self.songbird.clone(),
             guild_speaker_ref: self.guild_speaker.clone(),
             guild_speaker: self.guild_speaker.lock().await,
@@ -115,6 +274,11 @@ impl GuildSpeakerHandle {
 
 pub struct GuildSpeakerRef<'handle> {
     guild_id: GuildId,
+    token: Arc<str>,
+    presence: Arc<SpeakerPresence>,
+    filters: GuildFiltersMap,
+    events: PlaybackEvents,
+    is_stopped: Arc<AtomicBool>,
     songbird: Arc<songbird::Songbird>,
     guild_speaker_ref: Arc<Mutex<GuildSpeaker>>,
     guild_speaker: MutexGuard<'handle, GuildSpeaker>,
@@ -140,6 +304,17 @@ impl<'handle> GuildSpeakerRef<'handle> {
         self.guild_speaker.playing_state.is_some()
     }
 
+    /// How many guilds the speaker is playing in, counting every guild and not just this one.
+    pub fn load(&self) -> usize {
+        self.presence.playing_count()
+    }
+
+    /// Whether the speaker is moving to another voice server. The song keeps its place while it
+    /// does.
+    pub fn is_reconnecting(&self) -> bool {
+        self.guild_speaker.is_reconnecting
+    }
+
     pub fn is_paused(&self) -> bool {
         match &self.guild_speaker.playing_state {
             Some(state) => state.is_paused,
@@ -151,11 +326,58 @@ impl<'handle> GuildSpeakerRef<'handle> {
         self.guild_speaker
             .playing_state
             .as_ref()
-            .map(|state| state.metadata.clone())
+            .map(|state| state.song.metadata.clone())
     }
 
+    pub fn active_song(&self) -> Option<Song> {
+        self.guild_speaker
+            .playing_state
+            .as_ref()
+            .map(|state| state.song.clone())
+    }
+
+    /// Sets the status shown on the voice channel the speaker is connected to. An empty status
+    /// clears it.
+    pub async fn set_voice_status(&self, status: &str) -> Result<(), crate::Error> {
+        let Some(channel_id) = self.current_channel() else {
+            return Ok(());
+        };
+
+        let body = serde_json::to_vec(&serde_json::json!({ "status": status }))
+            .expect("Voice status should always serialize");
+        HTTP_CLIENT
+            .put(format!(
+                "https://discord.com/api/v10/channels/{}/voice-status",
+                channel_id.get()
+            ))
+            .header(reqwest::header::AUTHORIZATION, &*self.token)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(crate::Error::Http)?;
+        Ok(())
+    }
+
+    /// The audio filters applied to songs in this speaker's guild.
+    pub fn filters(&self) -> Arc<GuildFilters> {
+        self.filters.entry(self.guild_id).or_default().clone()
+    }
+
+    /// How often the current song has had to wait for its stream to download more audio.
+    pub fn active_buffer_stats(&self) -> Option<BufferStats> {
+        let playing_state = self.guild_speaker.playing_state.as_ref()?;
+        Some(playing_state.state.buffer.get())
+    }
+
+    /// How far through the current song playback is. This is in the song's own time, so it takes
+    /// any speed changes into account.
     pub async fn active_play_time(&self) -> Option<Duration> {
         let playing_state = self.guild_speaker.playing_state.as_ref()?;
+        if let Some(position) = playing_state.state.position.get() {
+            return Some(position);
+        }
         let track_state = playing_state.track.get_info().await.ok()?;
         Some(track_state.position)
     }
@@ -167,11 +389,38 @@ impl<'handle> GuildSpeakerRef<'handle> {
         config: &PlayConfig<'_>,
         ended_handler: Ended,
     ) -> Result<(), crate::Error> {
-        let input = song.get_input(config).await?;
+        let (input, state) = match self.guild_speaker.prefetched.take() {
+            Some(prefetched) if prefetched.song_id == song.metadata.id => {
+                tracing::trace!("Playing prefetched input for \"{}\"", song.metadata.title);
+                let input = prefetched.input.into_inner().unwrap();
+                (input, prefetched.state)
+            }
+            _ => {
+                let state = InputState::new();
+                let input = song.get_input(config, &self.filters(), &state).await?;
+                (input, state)
+            }
+        };
+        let is_seekable = is_seekable(&input);
+        let maybe_announcement = match config.tts_url {
+            Some(tts_url) => {
+                let text = config
+                    .tts_announcement
+                    .replace("{song_title}", &song.metadata.title);
+                match crate::tts::synthesize(tts_url, &text).await {
+                    Ok(announcement) => Some(announcement),
+                    Err(why) => {
+                        tracing::warn!("Error while synthesizing announcement: {}", why);
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
 
         let track_handle = match &mut self.current_call {
             Some(call) if call.current_channel() == Some(channel_id.into()) => {
-                call.play_only_input(input)
+                play_announced(call, &self.guild_speaker_ref, input, maybe_announcement)
             }
             _ => {
                 // Ensure we don't deadlock by having a current_call lock
@@ -193,43 +442,197 @@ impl<'handle> GuildSpeakerRef<'handle> {
                         return Err(crate::Error::SongbirdJoin(why));
                     }
                 }
+                self.guild_speaker.connected_endpoint =
+                    call.current_connection().map(|info| info.endpoint.clone());
+                self.guild_speaker.is_reconnecting = false;
                 call.remove_all_global_events();
                 call.add_global_event(
                     songbird::Event::Core(songbird::CoreEvent::DriverDisconnect),
                     GuildSpeakerDisconnectedEventHandler {
+                        guild_id: self.guild_id,
+                        songbird: self.songbird.clone(),
                         guild_speaker: self.guild_speaker_ref.clone(),
                     },
                 );
-                call.play_only_input(input)
+                for event in [
+                    songbird::CoreEvent::DriverConnect,
+                    songbird::CoreEvent::DriverReconnect,
+                ] {
+                    call.add_global_event(
+                        songbird::Event::Core(event),
+                        GuildSpeakerConnectedEventHandler {
+                            guild_id: self.guild_id,
+                            events: self.events.clone(),
+                            guild_speaker: self.guild_speaker_ref.clone(),
+                        },
+                    );
+                }
+                play_announced(
+                    &mut call,
+                    &self.guild_speaker_ref,
+                    input,
+                    maybe_announcement,
+                )
             }
         };
 
+        let ended_data: EndedHandlerData = Arc::new(Mutex::new(Some((
+            Box::new(move |ended_handle| ended_handler.on_ended(ended_handle)),
+            GuildSpeakerEndedBuilder {
+                guild_id: self.guild_id,
+                token: self.token.clone(),
+                presence: self.presence.clone(),
+                filters: self.filters.clone(),
+                events: self.events.clone(),
+                is_stopped: self.is_stopped.clone(),
+                songbird: self.songbird.clone(),
+                guild_speaker: self.guild_speaker_ref.clone(),
+            },
+        ))));
         track_handle
             .add_event(
                 songbird::Event::Track(songbird::TrackEvent::End),
                 GuildSpeakerEndedEventHandler {
-                    data: Mutex::new(Some((
-                        ended_handler,
-                        GuildSpeakerEndedBuilder {
-                            guild_id: self.guild_id,
-                            songbird: self.songbird.clone(),
-                            guild_speaker: self.guild_speaker_ref.clone(),
-                        },
-                    ))),
+                    data: ended_data.clone(),
                 },
             )
             .map_err(crate::Error::SongbirdControl)?;
+        self.presence
+            .set_playing(self.guild_id, Some(&song.metadata.title));
+        self.events.send(PlaybackEvent::Started {
+            guild_id: self.guild_id,
+            channel_id,
+            metadata: Box::new(song.metadata.clone()),
+        });
+        counters().record_song_played();
+        self.guild_speaker.interrupted = None;
         self.guild_speaker.playing_state = Some(GuildPlayingState {
-            metadata: song.metadata,
+            song,
             track: track_handle,
+            ended_data,
             is_paused: false,
+            state,
+            is_seekable,
         });
 
         Ok(())
     }
 
+    pub async fn restart(&mut self, config: &PlayConfig<'_>) -> Result<(), crate::Error> {
+        self.reopen(config, InputState::new()).await
+    }
+
+    /// Goes back in the current song. Songs that can be seeked jump straight there. HLS streams,
+    /// including live ones with a DVR window, are opened again from an earlier segment, going back
+    /// as far as the playlist allows.
+    pub async fn rewind(
+        &mut self,
+        amount: Duration,
+        config: &PlayConfig<'_>,
+    ) -> Result<(), crate::Error> {
+        let play_time = self.active_play_time().await.unwrap_or_default();
+        let Some(playing_state) = &self.guild_speaker.playing_state else {
+            return Ok(());
+        };
+        if playing_state.is_seekable {
+            return self.seek(play_time.saturating_sub(amount)).await;
+        }
+
+        let Some(sequence) = playing_state
+            .state
+            .window
+            .rewind_sequence(play_time, amount)
+        else {
+            return Err(crate::Error::NotSeekable);
+        };
+        tracing::trace!("Rewinding to segment {}", sequence);
+        self.reopen(config, InputState::from_sequence(sequence))
+            .await
+    }
+
+    async fn reopen(
+        &mut self,
+        config: &PlayConfig<'_>,
+        state: InputState,
+    ) -> Result<(), crate::Error> {
+        let filters = self.filters();
+        let (Some(playing_state), Some(call)) = (
+            &mut self.guild_speaker.playing_state,
+            &mut self.current_call,
+        ) else {
+            return Ok(());
+        };
+
+        // Live inputs can't be seeked, so restarting or rewinding means opening the song again.
+        let input = playing_state
+            .song
+            .get_input(config, &filters, &state)
+            .await?;
+        let is_seekable = is_seekable(&input);
+
+        // Move the ended handler over to the new track, so stopping the old track doesn't cause
+        // the next song to play.
+        let ended_data = Arc::new(Mutex::new(playing_state.ended_data.lock().await.take()));
+        let track_handle = call.play_only_input(input);
+        track_handle
+            .add_event(
+                songbird::Event::Track(songbird::TrackEvent::End),
+                GuildSpeakerEndedEventHandler {
+                    data: ended_data.clone(),
+                },
+            )
+            .map_err(crate::Error::SongbirdControl)?;
+
+        playing_state.track = track_handle;
+        playing_state.ended_data = ended_data;
+        playing_state.is_paused = false;
+        playing_state.state = state;
+        playing_state.is_seekable = is_seekable;
+
+        Ok(())
+    }
+
+    /// Jumps to a time in the current song. Only songs that are streamed from a file can be
+    /// seeked, not live streams or songs going through the audio filters.
+    pub async fn seek(&mut self, time: Duration) -> Result<(), crate::Error> {
+        let Some(playing_state) = &mut self.guild_speaker.playing_state else {
+            return Ok(());
+        };
+        if !playing_state.is_seekable {
+            return Err(crate::Error::NotSeekable);
+        }
+
+        playing_state
+            .track
+            .seek_async(time)
+            .await
+            .map_err(crate::Error::SongbirdControl)?;
+        Ok(())
+    }
+
+    pub fn prefetched_song_id(&self) -> Option<Uuid> {
+        self.guild_speaker
+            .prefetched
+            .as_ref()
+            .map(PrefetchedInput::song_id)
+    }
+
+    pub fn set_prefetched(&mut self, prefetched: Option<PrefetchedInput>) {
+        self.guild_speaker.prefetched = prefetched;
+    }
+
     pub fn unlock(&mut self) {
+        self.presence.set_playing(self.guild_id, None);
+        if self.guild_speaker.playing_state.is_some() {
+            self.events.send(PlaybackEvent::Stopped {
+                guild_id: self.guild_id,
+                channel_id: self.current_channel(),
+            });
+        }
         self.guild_speaker.playing_state = None;
+        self.guild_speaker.prefetched = None;
+        self.guild_speaker.interrupted = None;
+        self.guild_speaker.is_reconnecting = false;
         self.guild_speaker.last_ended_time = Some(Instant::now());
     }
 
@@ -250,6 +653,10 @@ impl<'handle> GuildSpeakerRef<'handle> {
                 .pause()
                 .map_err(crate::Error::SongbirdControl)?;
             playing_state.is_paused = true;
+            self.send_channel_event(|guild_id, channel_id| PlaybackEvent::Paused {
+                guild_id,
+                channel_id,
+            });
         }
         Ok(())
     }
@@ -261,7 +668,62 @@ impl<'handle> GuildSpeakerRef<'handle> {
                 .play()
                 .map_err(crate::Error::SongbirdControl)?;
             playing_state.is_paused = false;
+            self.send_channel_event(|guild_id, channel_id| PlaybackEvent::Resumed {
+                guild_id,
+                channel_id,
+            });
+        }
+        Ok(())
+    }
+
+    fn send_channel_event(&self, event: impl FnOnce(GuildId, ChannelId) -> PlaybackEvent) {
+        if let Some(channel_id) = self.current_channel() {
+            self.events.send(event(self.guild_id, channel_id));
         }
+    }
+
+    /// Plays a sound clip over the current song, without affecting it. Only one clip plays at a
+    /// time, so any clip that's still playing is stopped.
+    pub fn play_clip(&mut self, input: songbird::input::Input) -> Result<(), crate::Error> {
+        let Some(call) = &mut self.current_call else {
+            return Err(crate::Error::NotConnected);
+        };
+
+        if let Some(clip_track) = self.guild_speaker.clip_track.take() {
+            // The previous clip may well have finished already, so failing here is expected.
+            let _ = clip_track.stop();
+        }
+        self.guild_speaker.clip_track = Some(call.play_input(input));
+        Ok(())
+    }
+
+    /// Turns the current song down to silence over `duration`. The song keeps playing silently
+    /// afterwards, so it should be stopped once this is done.
+    pub async fn fade_out(&self, duration: Duration) {
+        const FADE_STEPS: u32 = 20;
+
+        let Some(playing_state) = &self.guild_speaker.playing_state else {
+            return;
+        };
+        for step in (0..FADE_STEPS).rev() {
+            let volume = step as f32 / FADE_STEPS as f32;
+            if let Err(why) = playing_state.track.set_volume(volume) {
+                tracing::debug!("Unable to fade out song: {}", why);
+                return;
+            }
+            tokio::time::sleep(duration / FADE_STEPS).await;
+        }
+    }
+
+    /// Moves the speaker to another voice channel in the same guild, without interrupting what
+    /// it's playing.
+    pub async fn move_to(&mut self, channel_id: ChannelId) -> Result<(), crate::Error> {
+        // Joining locks the call, so it can't be held here
+        self.current_call = None;
+        self.songbird
+            .join(self.guild_id, channel_id)
+            .await
+            .map_err(crate::Error::SongbirdJoin)?;
         Ok(())
     }
 
@@ -273,19 +735,101 @@ impl<'handle> GuildSpeakerRef<'handle> {
     }
 }
 
+struct GuildSpeakerConnectedEventHandler {
+    guild_id: GuildId,
+    events: PlaybackEvents,
+    guild_speaker: Arc<Mutex<GuildSpeaker>>,
+}
+
+#[serenity::async_trait]
+impl songbird::events::EventHandler for GuildSpeakerConnectedEventHandler {
+    async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        let (songbird::EventContext::DriverConnect(data)
+        | songbird::EventContext::DriverReconnect(data)) = ctx
+        else {
+            return None;
+        };
+
+        let mut guild_speaker = self.guild_speaker.lock().await;
+        guild_speaker.connected_endpoint = Some(data.server.to_string());
+        if guild_speaker.is_reconnecting {
+            tracing::debug!("Reconnected to new voice server");
+            guild_speaker.is_reconnecting = false;
+            if let Some(channel_id) = data.channel_id {
+                self.events.send(PlaybackEvent::Reconnected {
+                    guild_id: self.guild_id,
+                    channel_id: ChannelId::new(channel_id.0.get()),
+                });
+            }
+        }
+        None
+    }
+}
+
 struct GuildSpeakerDisconnectedEventHandler {
+    guild_id: GuildId,
+    songbird: Arc<songbird::Songbird>,
     guild_speaker: Arc<Mutex<GuildSpeaker>>,
 }
 
+impl GuildSpeakerDisconnectedEventHandler {
+    /// Whether the call still has somewhere to be connected to. When the call moves to another
+    /// voice server, the old connection closes afterwards without saying why.
+    async fn is_call_connected(&self) -> bool {
+        match self.songbird.get(self.guild_id) {
+            Some(call) => call.lock().await.current_connection().is_some(),
+            None => false,
+        }
+    }
+}
+
 #[serenity::async_trait]
 impl songbird::events::EventHandler for GuildSpeakerDisconnectedEventHandler {
-    async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
-        log::debug!("Disconnected from call, stopping current song");
+    async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        if let songbird::EventContext::DriverDisconnect(data) = ctx {
+            if data.reason.is_none() && self.is_call_connected().await {
+                tracing::debug!("Old voice connection closed after moving voice server");
+                return None;
+            }
+        }
+
+        tracing::debug!("Disconnected from call, stopping current song");
         let mut guild_speaker_ref = self.guild_speaker.lock().await;
-        if let Some(playing_state) = &mut guild_speaker_ref.playing_state {
+        let guild_speaker = &mut *guild_speaker_ref;
+        if let Some(playing_state) = &guild_speaker.playing_state {
+            // Connections that failed, instead of the bot leaving or being kicked, are worth
+            // reconnecting to so the song can carry on.
+            let maybe_dropped_channel_id = match ctx {
+                songbird::EventContext::DriverDisconnect(data) => match data.reason {
+                    None
+                    | Some(songbird::events::context_data::DisconnectReason::WsClosed(Some(
+                        songbird::model::CloseCode::Disconnected,
+                    ))) => None,
+                    Some(_) => data.channel_id,
+                },
+                _ => None,
+            };
+            if let Some(channel_id) = maybe_dropped_channel_id {
+                let play_time = match playing_state.state.position.get() {
+                    Some(position) => Some(position),
+                    None => playing_state
+                        .track
+                        .get_info()
+                        .await
+                        .ok()
+                        .map(|info| info.position),
+                };
+                guild_speaker.interrupted = Some(InterruptedSong {
+                    channel_id: ChannelId::new(channel_id.0.get()),
+                    song: playing_state.song.clone(),
+                    play_time,
+                });
+            }
+        }
+        if let Some(playing_state) = &mut guild_speaker.playing_state {
             let res = playing_state.track.stop();
             if let Err(why) = res {
-                log::warn!("Error while stopping song: {}", why);
+                tracing::warn!("Error while stopping song: {}", why);
             }
         }
 
@@ -293,17 +837,91 @@ impl songbird::events::EventHandler for GuildSpeakerDisconnectedEventHandler {
     }
 }
 
-struct GuildSpeakerEndedEventHandler<Ended: EndedHandler> {
-    data: Mutex<Option<(Ended, GuildSpeakerEndedBuilder)>>,
+fn is_seekable(input: &songbird::input::Input) -> bool {
+    match input {
+        songbird::input::Input::Live(songbird::input::LiveInput::Raw(stream), _) => {
+            stream.input.is_seekable()
+        }
+        _ => false,
+    }
+}
+
+/// Plays an input, after a spoken announcement if there is one. The input's track starts paused,
+/// and is resumed once the announcement has finished.
+fn play_announced(
+    call: &mut songbird::Call,
+    guild_speaker: &Arc<Mutex<GuildSpeaker>>,
+    input: songbird::input::Input,
+    maybe_announcement: Option<songbird::input::Input>,
+) -> songbird::tracks::TrackHandle {
+    let Some(announcement) = maybe_announcement else {
+        return call.play_only_input(input);
+    };
+
+    let announcement_handle = call.play_only_input(announcement);
+    let track_handle = call.play(songbird::tracks::Track::new(input).pause());
+    let add_event_res = announcement_handle.add_event(
+        songbird::Event::Track(songbird::TrackEvent::End),
+        AnnouncementEndedEventHandler {
+            guild_speaker: guild_speaker.clone(),
+            track: track_handle.clone(),
+        },
+    );
+    if let Err(why) = add_event_res {
+        tracing::warn!("Error while waiting for announcement: {}", why);
+        let _ = announcement_handle.stop();
+        let _ = track_handle.play();
+    }
+    track_handle
+}
+
+struct AnnouncementEndedEventHandler {
+    guild_speaker: Arc<Mutex<GuildSpeaker>>,
+    track: songbird::tracks::TrackHandle,
 }
 
 #[serenity::async_trait]
-impl<Ended: EndedHandler> songbird::events::EventHandler for GuildSpeakerEndedEventHandler<Ended> {
+impl songbird::events::EventHandler for AnnouncementEndedEventHandler {
     async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        // Only start the song if it's still the one playing, and nobody has paused it while the
+        // announcement was playing.
+        let guild_speaker_ref = self.guild_speaker.lock().await;
+        if let Some(playing_state) = &guild_speaker_ref.playing_state {
+            if playing_state.track.uuid() == self.track.uuid() && !playing_state.is_paused {
+                if let Err(why) = self.track.play() {
+                    tracing::warn!("Error while starting song after announcement: {}", why);
+                }
+            }
+        }
+
+        Some(songbird::Event::Cancel)
+    }
+}
+
+struct GuildSpeakerEndedEventHandler {
+    data: EndedHandlerData,
+}
+
+#[serenity::async_trait]
+impl songbird::events::EventHandler for GuildSpeakerEndedEventHandler {
+    async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+        // songbird only knows about errors it ran into itself, like the audio not decoding
+        let maybe_play_failure = match ctx {
+            songbird::EventContext::Track(tracks) => {
+                tracks.iter().find_map(|(state, _)| match &state.playing {
+                    songbird::tracks::PlayMode::Errored(why) => {
+                        Some(PlaybackFailure::from_play_error(why))
+                    }
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+
         let mut data_ref = self.data.lock().await;
         let data = data_ref.take();
         if let Some((ended_handler, builder)) = data {
-            ended_handler.on_ended(builder.build());
+            ended_handler(builder.build(maybe_play_failure));
         }
 
         Some(songbird::Event::Cancel)
@@ -316,15 +934,26 @@ pub trait EndedHandler: Send + 'static {
 
 struct GuildSpeakerEndedBuilder {
     guild_id: GuildId,
+    token: Arc<str>,
+    presence: Arc<SpeakerPresence>,
+    filters: GuildFiltersMap,
+    events: PlaybackEvents,
+    is_stopped: Arc<AtomicBool>,
     songbird: Arc<songbird::Songbird>,
     guild_speaker: Arc<Mutex<GuildSpeaker>>,
 }
 
 impl GuildSpeakerEndedBuilder {
-    fn build(self) -> GuildSpeakerEndedHandle {
+    fn build(self, maybe_play_failure: Option<PlaybackFailure>) -> GuildSpeakerEndedHandle {
         GuildSpeakerEndedHandle {
+            maybe_play_failure,
             guild_speaker_handle: GuildSpeakerHandle {
                 guild_id: self.guild_id,
+                token: self.token.clone(),
+                presence: self.presence.clone(),
+                filters: self.filters.clone(),
+                events: self.events.clone(),
+                is_stopped: self.is_stopped.clone(),
                 songbird: self.songbird.clone(),
                 guild_speaker: self.guild_speaker.clone(),
                 current_call: self.songbird.get(self.guild_id),
@@ -335,6 +964,7 @@ impl GuildSpeakerEndedBuilder {
 
 pub struct GuildSpeakerEndedHandle {
     guild_speaker_handle: GuildSpeakerHandle,
+    maybe_play_failure: Option<PlaybackFailure>,
 }
 
 impl GuildSpeakerEndedHandle {
@@ -342,11 +972,30 @@ impl GuildSpeakerEndedHandle {
         self.guild_speaker_handle.guild_id
     }
 
+    /// Whether the speaker that played the song has been stopped since, in which case nothing else
+    /// should be played with it.
+    pub fn is_speaker_stopped(&self) -> bool {
+        self.guild_speaker_handle.is_stopped.load(Ordering::Relaxed)
+    }
+
     pub async fn lock(&self) -> (GuildSpeakerEndedState, GuildSpeakerEndedRef<'_>) {
         let guild_speaker_ref = self.guild_speaker_handle.lock().await;
+        // Errors from the stream itself say more than whatever songbird saw afterwards
+        let failure = guild_speaker_ref
+            .guild_speaker
+            .playing_state
+            .as_ref()
+            .and_then(|playing_state| playing_state.state.failure())
+            .or_else(|| self.maybe_play_failure.clone());
         let ended_state = GuildSpeakerEndedState {
             channel_id: guild_speaker_ref.current_channel(),
             ended_metadata: guild_speaker_ref.active_metadata(),
+            failure,
+            interrupted_channel_id: guild_speaker_ref
+                .guild_speaker
+                .interrupted
+                .as_ref()
+                .map(|interrupted| interrupted.channel_id),
         };
         (ended_state, GuildSpeakerEndedRef { guild_speaker_ref })
     }
@@ -355,6 +1004,11 @@ impl GuildSpeakerEndedHandle {
 pub struct GuildSpeakerEndedState {
     pub channel_id: Option<ChannelId>,
     pub ended_metadata: Option<SongMetadata>,
+    /// Why the song ended early, if it failed.
+    pub failure: Option<PlaybackFailure>,
+    /// The channel the speaker was playing in, if the song ended because the voice connection
+    /// dropped out. The song can carry on with [`GuildSpeakerEndedRef::resume`].
+    pub interrupted_channel_id: Option<ChannelId>,
 }
 
 #[must_use]
@@ -384,9 +1038,50 @@ impl<'handle> GuildSpeakerEndedRef<'handle> {
         }
     }
 
+    /// Joins the channel again after the voice connection dropped out, and carries on playing the
+    /// song from where it was. The song is opened again, so its stream is looked up again if the
+    /// old one has expired.
+    pub async fn resume<Ended: EndedHandler>(
+        mut self,
+        config: &PlayConfig<'_>,
+        ended_handler: Ended,
+    ) -> Result<GuildSpeakerRef<'handle>, (GuildSpeakerEndedRef<'handle>, crate::Error)> {
+        let Some(interrupted) = self.guild_speaker_ref.guild_speaker.interrupted.take() else {
+            return Ok(self.stop());
+        };
+
+        let play_res = self
+            .guild_speaker_ref
+            .play(
+                interrupted.channel_id,
+                interrupted.song.clone(),
+                config,
+                ended_handler,
+            )
+            .await;
+        if let Err(why) = play_res {
+            // Kept around for the next attempt
+            self.guild_speaker_ref.guild_speaker.interrupted = Some(interrupted);
+            return Err((self, why));
+        }
+
+        // Songs that can't be seeked start again from the beginning
+        let is_seekable = self
+            .guild_speaker_ref
+            .guild_speaker
+            .playing_state
+            .as_ref()
+            .is_some_and(|playing_state| playing_state.is_seekable);
+        if let (true, Some(play_time)) = (is_seekable, interrupted.play_time) {
+            if let Err(why) = self.guild_speaker_ref.seek(play_time).await {
+                tracing::warn!("Error while seeking resumed song: {}", why);
+            }
+        }
+        Ok(self.guild_speaker_ref)
+    }
+
     pub fn stop(mut self) -> GuildSpeakerRef<'handle> {
-        self.guild_speaker_ref.guild_speaker.playing_state = None;
-        self.guild_speaker_ref.guild_speaker.last_ended_time = Some(Instant::now());
+        self.guild_speaker_ref.unlock();
         self.guild_speaker_ref
     }
 }
@@ -0,0 +1,13 @@
+use crate::clip::download_clip;
+use crate::Error;
+use songbird::input::Input;
+
+/// Synthesizes a short spoken clip using a text-to-speech HTTP endpoint. `{text}` in the URL
+/// template is replaced with the text to speak, and the endpoint should respond with an audio
+/// file.
+pub(crate) async fn synthesize(url_template: &str, text: &str) -> Result<Input, Error> {
+    let encoded_text: String = url::form_urlencoded::byte_serialize(text.as_bytes()).collect();
+    let url = url::Url::parse(&url_template.replace("{text}", &encoded_text))
+        .map_err(|_| Error::UnsupportedUrl)?;
+    download_clip(url).await
+}
@@ -1,4 +1,3 @@
-use crate::config::Config;
 use crate::frontend::Frontend;
 use futures::future;
 use mrvn_back_ytdl::GuildSpeakerHandle;
@@ -8,7 +7,7 @@ use std::time::Duration;
 async fn check_cleanup_for_speaker(
     guild_speaker_handle: GuildSpeakerHandle,
     cache: Arc<serenity::cache::Cache>,
-    config: Arc<Config>,
+    frontend: Arc<Frontend>,
 ) {
     let mut guild_speaker = guild_speaker_handle.lock().await;
 
@@ -30,6 +29,10 @@ async fn check_cleanup_for_speaker(
     };
 
     // Ignore the speaker if not enough time has passed since last playback
+    let config = frontend.guild_config(guild_speaker.guild_id()).await;
+    if config.always_on {
+        return;
+    }
     if last_ended_time.elapsed().as_secs() < config.disconnect_min_inactive_secs {
         return;
     }
@@ -54,19 +57,18 @@ async fn check_cleanup_for_speaker(
 
     // We've passed the conditions, disconnect
     match guild_speaker.disconnect().await {
-        Ok(_) => log::debug!("Disconnected speaker due to inactivity"),
-        Err(why) => log::error!("Error when disconnecting speaker: {}", why),
+        Ok(_) => tracing::debug!("Disconnected speaker due to inactivity"),
+        Err(why) => tracing::error!("Error when disconnecting speaker: {}", why),
     }
 }
 
 async fn check_cleanup(frontend: Arc<Frontend>, cache: Arc<serenity::cache::Cache>) {
-    let futures = frontend
-        .backend_brain
-        .speakers
+    let speakers = frontend.backend_brain.speakers();
+    let futures = speakers
         .iter()
         .flat_map(|speaker| speaker.iter())
         .map(|guild_speaker_handle| {
-            check_cleanup_for_speaker(guild_speaker_handle, cache.clone(), frontend.config.clone())
+            check_cleanup_for_speaker(guild_speaker_handle, cache.clone(), frontend.clone())
         });
 
     future::join_all(futures).await;
@@ -74,7 +76,7 @@ async fn check_cleanup(frontend: Arc<Frontend>, cache: Arc<serenity::cache::Cach
 
 pub async fn cleanup_loop(frontend: Arc<Frontend>, cache: Arc<serenity::cache::Cache>) -> ! {
     let mut interval = tokio::time::interval(Duration::from_secs(
-        frontend.config.disconnect_check_interval_secs,
+        frontend.config().disconnect_check_interval_secs,
     ));
     loop {
         interval.tick().await;
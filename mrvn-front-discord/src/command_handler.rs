@@ -1,4 +1,5 @@
 use crate::frontend::Frontend;
+use serenity::gateway::ShardStageUpdateEvent;
 use serenity::{model::prelude::*, prelude::*};
 use std::sync::Arc;
 
@@ -14,13 +15,47 @@ impl CommandHandler {
 
 #[serenity::async_trait]
 impl EventHandler for CommandHandler {
-    async fn ready(&self, _ctx: Context, ready: Ready) {
-        log::info!("Command client is connected as {}", ready.user.name);
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        let shard = ready.shard.unwrap_or(ShardInfo {
+            id: ctx.shard_id,
+            total: 1,
+        });
+        tracing::info!(
+            "Command client shard {} of {} is connected as {}",
+            shard.id,
+            shard.total,
+            ready.user.name
+        );
+        // Any shard's context will do, since it's only used to reach the cache and HTTP client.
+        self.frontend.set_command_context(ctx);
+    }
+
+    async fn shard_stage_update(&self, _ctx: Context, event: ShardStageUpdateEvent) {
+        tracing::debug!("Command client shard {} is {}", event.shard_id, event.new);
+    }
+
+    async fn voice_state_update(&self, _ctx: Context, old: Option<VoiceState>, new: VoiceState) {
+        let old_channel_id = old.and_then(|old| old.channel_id);
+        self.frontend
+            .handle_voice_state_update(old_channel_id, &new)
+            .await;
+    }
+
+    async fn message(&self, ctx: Context, new_message: Message) {
+        self.frontend.handle_message(&ctx, &new_message).await;
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::Command(command) = interaction {
-            self.frontend.handle_command(&ctx, &command).await;
+        match interaction {
+            Interaction::Command(command) => self.frontend.handle_command(&ctx, &command).await,
+            Interaction::Modal(modal) => self.frontend.handle_modal(&ctx, &modal).await,
+            Interaction::Autocomplete(command) => {
+                self.frontend.handle_autocomplete(&ctx, &command).await
+            }
+            Interaction::Component(component) => {
+                self.frontend.handle_component(&ctx, &component).await
+            }
+            _ => {}
         }
     }
 }
@@ -1,22 +1,86 @@
+use crate::config::Config;
+use crate::guild_settings::SettingKey;
+use mrvn_back_ytdl::{
+    equalizer_band_name, EqualizerPreset, FilterPreset, EQ_BAND_FREQUENCIES, EQ_MAX_GAIN_DB,
+    MAX_RATE_CHANGE,
+};
 use serenity::all::{CreateCommand, CreateCommandOption};
 use serenity::model::prelude::*;
 
-pub async fn register_commands(
-    http: impl AsRef<serenity::http::Http>,
-    guild_id: Option<GuildId>,
-) -> serenity::Result<()> {
-    let http_ref = http.as_ref();
+fn setting_name_option() -> CreateCommandOption {
+    SettingKey::ALL.into_iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "name", "The setting to change.")
+            .required(true),
+        |option, key| option.add_string_choice(key.name(), key.name()),
+    )
+}
+
+/// Discord only allows this many choices for an option.
+pub const MAX_OPTION_CHOICES: usize = 25;
+
+/// Choice names longer than this are rejected by Discord.
+pub const MAX_CHOICE_NAME_LENGTH: usize = 100;
+
+/// An option for picking one of the songs in your queue, counting from 1. Choices are filled in
+/// from the queue as it's typed.
+fn queue_position_option(description: &str) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::Integer, "position", description)
+        .min_int_value(1)
+        .set_autocomplete(true)
+        .required(true)
+}
 
+/// The loudest `/volume` can go. Any louder and most songs just clip.
+const MAX_VOLUME_PERCENT: u64 = 200;
+
+/// The choice for `/filter` that turns the current preset off.
+pub const FILTER_OFF_NAME: &str = "off";
+
+/// How far `/pitch` can go in either direction, which is an octave.
+pub const MAX_PITCH_SEMITONES: i64 = 12;
+
+fn sound_effect_name_option(config: &Config) -> CreateCommandOption {
+    let mut names: Vec<_> = config.sound_effects.keys().collect();
+    names.sort();
+    names.into_iter().take(MAX_OPTION_CHOICES).fold(
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "name",
+            "The sound effect to play.",
+        )
+        .required(true),
+        |option, name| option.add_string_choice(name, name),
+    )
+}
+
+/// Every command the bot has. These are registered with Discord, and also used to read prefix
+/// commands.
+pub fn create_commands(config: &Config) -> Vec<CreateCommand> {
     let commands = vec![
         CreateCommand::new("play")
             .description("Add a song to your queue.")
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "term",
+                "A search term or song link.",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Attachment,
+                "file",
+                "An audio file to play instead.",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "choose",
+                "Choose which songs to queue if it's a playlist.",
+            )),
+        CreateCommand::new(PLAY_MANY_COMMAND_NAME)
+            .description("Paste a list of links or search terms to add to your queue."),
+        CreateCommand::new("search")
+            .description("Search for a song and choose which result to queue.")
             .add_option(
-                CreateCommandOption::new(
-                    CommandOptionType::String,
-                    "term",
-                    "A search term or song link.",
-                )
-                .required(true),
+                CreateCommandOption::new(CommandOptionType::String, "term", "A search term.")
+                    .required(true),
             ),
         CreateCommand::new("resume").description("Resume a paused song."),
         CreateCommand::new("replace")
@@ -29,23 +93,626 @@ pub async fn register_commands(
                 )
                 .required(true),
             ),
+        CreateCommand::new("wrong").description("Remove the song you queued most recently."),
+        CreateCommand::new("remove")
+            .description("Remove a song from your queue.")
+            .add_option(queue_position_option("The song to remove.")),
+        CreateCommand::new("jump")
+            .description("Move a song to the front of your queue.")
+            .add_option(queue_position_option("The song to play next.")),
+        CreateCommand::new("undo").description("Undo the last change you made to your queue."),
         CreateCommand::new("pause").description("Pause the current song."),
         CreateCommand::new("skip").description("Vote to skip the current song."),
         CreateCommand::new("stop").description("Vote to skip the current song and stop playback."),
+        CreateCommand::new("votepause").description("Vote to pause the current song."),
+        CreateCommand::new("restart").description("Play the current song from the beginning."),
+        CreateCommand::new("chapters").description("View the chapters of the current song."),
+        CreateCommand::new("chapter")
+            .description("Jump to a chapter of the current song.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "number",
+                    "The chapter's number in /chapters.",
+                )
+                .min_int_value(1)
+                .required(true),
+            ),
+        CreateCommand::new("rewind")
+            .description("Go back in the current song, or in a live stream that allows it.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "time",
+                    "How far to go back, like 30s, 2m or 1:30.",
+                )
+                .required(true),
+            ),
+        CreateCommand::new("previous")
+            .description("Play the last song that finished in your channel again."),
+        CreateCommand::new("replay")
+            .description("Add the song playing in your channel to your queue again."),
+        CreateCommand::new("grab").description("Send the current song to your direct messages."),
+        CreateCommand::new("history")
+            .description("View the songs recently played in your channel."),
+        CreateCommand::new("sfx")
+            .description("Play a sound effect over the current song.")
+            .add_option(sound_effect_name_option(config)),
+        CreateCommand::new("volume")
+            .description("Change the volume of songs in this server.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "percent",
+                    "The volume as a percentage of the original.",
+                )
+                .min_int_value(0)
+                .max_int_value(MAX_VOLUME_PERCENT)
+                .required(true),
+            ),
+        CreateCommand::new("speed")
+            .description("Change how fast songs play in this server, without changing their pitch.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Number,
+                    "multiplier",
+                    "How many times faster than normal to play songs, e.g. 1.25.",
+                )
+                .min_number_value(1. / MAX_RATE_CHANGE as f64)
+                .max_number_value(MAX_RATE_CHANGE as f64)
+                .required(true),
+            ),
+        CreateCommand::new("pitch")
+            .description("Change the pitch of songs in this server, without changing their speed.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "semitones",
+                    "How many semitones to raise or lower songs by, up to 12. 0 is the original pitch.",
+                )
+                // Serenity can't set a negative minimum here, so the range is enforced when the
+                // command is handled instead.
+                .required(true),
+            ),
+        CreateCommand::new("filter")
+            .description("Apply a preset audio effect to songs in this server.")
+            .add_option(
+                FilterPreset::ALL
+                    .into_iter()
+                    .map(FilterPreset::name)
+                    .chain([FILTER_OFF_NAME])
+                    .fold(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "name",
+                            "The effect to apply, or off to go back to normal.",
+                        )
+                        .required(true),
+                        |option, name| option.add_string_choice(name, name),
+                    ),
+            ),
+        CreateCommand::new("eq")
+            .description("Change the equalizer for songs in this server.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "set",
+                    "Boost or cut a frequency band.",
+                )
+                .add_sub_option(
+                    EQ_BAND_FREQUENCIES.into_iter().fold(
+                        CreateCommandOption::new(
+                            CommandOptionType::Integer,
+                            "band",
+                            "The band to change.",
+                        )
+                        .required(true),
+                        |option, frequency| {
+                            option.add_int_choice(equalizer_band_name(frequency), frequency as i32)
+                        },
+                    ),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Number,
+                        "gain",
+                        "The gain in decibels. 0 leaves the band unchanged.",
+                    )
+                    .min_number_value(-EQ_MAX_GAIN_DB as f64)
+                    .max_number_value(EQ_MAX_GAIN_DB as f64)
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "preset",
+                    "Switch to a preset. The flat preset turns the equalizer off.",
+                )
+                .add_sub_option(
+                    EqualizerPreset::ALL.into_iter().fold(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "name",
+                            "The preset to use.",
+                        )
+                        .required(true),
+                        |option, preset| option.add_string_choice(preset.name(), preset.name()),
+                    ),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "view",
+                "View the gain of each band.",
+            )),
+        CreateCommand::new("language")
+            .description("Change the language of messages in this server.")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "name",
+                "The language to use. Leave empty to use the default.",
+            )),
+        CreateCommand::new("settings")
+            .description("View or change the bot's settings in this server.")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "view",
+                "View the current settings.",
+            ))
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Change a setting.")
+                    .add_sub_option(setting_name_option())
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "value",
+                            "The new value of the setting.",
+                        )
+                        .required(true),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "reset",
+                    "Change a setting back to the default.",
+                )
+                .add_sub_option(setting_name_option()),
+            ),
+        CreateCommand::new("dj")
+            .description("Manage the roles that can control playback in this server.")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "add",
+                    "Let a role control playback.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Role, "role", "The role to add.")
+                        .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "remove",
+                    "Stop a role from controlling playback.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Role,
+                        "role",
+                        "The role to remove.",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "list",
+                "View the roles that can control playback.",
+            )),
+        CreateCommand::new("priority")
+            .description("Manage the roles whose songs are played first in this server.")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "set",
+                    "Set the queue priority for a role.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Role,
+                        "role",
+                        "The role to change.",
+                    )
+                    .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "priority",
+                        "Higher priorities are played first. 0 removes the priority.",
+                    )
+                    .min_int_value(0)
+                    .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "list",
+                "View the roles that have a queue priority.",
+            )),
+        CreateCommand::new("botban")
+            .description("Manage the users that can't use the bot in this server.")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "add",
+                    "Stop a user from using the bot.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::User, "user", "The user to ban.")
+                        .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "remove",
+                    "Let a banned user use the bot again.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::User, "user", "The user to unban.")
+                        .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "list",
+                "View the users that can't use the bot.",
+            )),
+        CreateCommand::new("clearuser")
+            .description("Remove every song a user has queued.")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::User,
+                    "user",
+                    "The user whose songs to remove.",
+                )
+                .required(true),
+            ),
+        CreateCommand::new(COMMANDS_COMMAND_NAME)
+            .description("Turn commands on or off in this server.")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "disable",
+                    "Stop a command from being used.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "The command to turn off.",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "enable",
+                    "Let a command be used again.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "The command to turn back on.",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "list",
+                "View the commands that have been turned off.",
+            )),
+        CreateCommand::new("block")
+            .description("Manage the songs that can't be played in this server.")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "add",
+                    "Stop matching songs from being played.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "entry",
+                        "A URL, video ID or title keyword to block.",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "remove",
+                    "Allow matching songs to be played again.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "entry",
+                        "The entry to remove.",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "list",
+                "View the blocked entries.",
+            )),
+        CreateCommand::new("playlist")
+            .description("Manage saved playlists.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "save",
+                    "Save your playing and queued songs as a playlist.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "The name of the playlist.",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "load",
+                    "Add a saved playlist to your queue.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "The name of the playlist.",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "list",
+                "View the saved playlists.",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "delete",
+                    "Delete a saved playlist.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "The name of the playlist.",
+                    )
+                    .required(true),
+                ),
+            ),
+        CreateCommand::new("fav")
+            .description("Manage your favorite songs.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "add",
+                    "Save the song that's playing as a favorite.",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "name",
+                    "The name to save it as, instead of the song's title.",
+                )),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "list",
+                "View your favorite songs.",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "play",
+                    "Add a favorite song to your queue.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "The name of the favorite.",
+                    )
+                    .required(true),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "remove",
+                    "Remove a favorite song.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "name",
+                        "The name of the favorite.",
+                    )
+                    .required(true),
+                ),
+            ),
+        CreateCommand::new("queue")
+            .description("Save or load queued songs as a file.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "export",
+                    "Get your playing and queued songs as a file.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "scope",
+                        "Whose songs to export.",
+                    )
+                    .add_string_choice("Mine", "mine")
+                    .add_string_choice("Everyone in my channel", "channel"),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "format",
+                        "The kind of file to export.",
+                    )
+                    .add_string_choice("JSON", "json")
+                    .add_string_choice("M3U", "m3u"),
+                ),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "import",
+                    "Add the songs in an exported queue to your queue.",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Attachment,
+                        "file",
+                        "A JSON or M3U file from /queue export.",
+                    )
+                    .required(true),
+                ),
+            ),
+        CreateCommand::new("admin")
+            .description("Manage the bot itself.")
+            .default_member_permissions(Permissions::ADMINISTRATOR)
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "update-ytdl",
+                "Update youtube-dl to the latest version.",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "buffers",
+                "See how often songs have had to wait for more audio to download.",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "reload",
+                "Reload the config file without restarting.",
+            )),
         CreateCommand::new("nowplaying")
-            .description("View the current playing song and its progress."),
+            .description("View the current playing song and its progress.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Channel,
+                    "channel",
+                    "The voice channel to check, instead of the one you're in.",
+                )
+                .channel_types(vec![ChannelType::Voice, ChannelType::Stage]),
+            ),
+        CreateCommand::new("stats").description("See what the bot has been up to."),
+        CreateCommand::new("Queue this").kind(CommandType::Message),
     ];
 
+    // Nothing works outside of servers, so commands aren't offered in DMs. Permissions from the
+    // config replace the defaults above.
+    commands
+        .into_iter()
+        .map(|command| {
+            let command = command.dm_permission(false);
+            match command_name(&command)
+                .and_then(|name| config.command_permissions.get(&name).copied())
+            {
+                Some(permissions) => command.default_member_permissions(permissions),
+                None => command,
+            }
+        })
+        .collect()
+}
+
+/// The command that opens a modal to paste a list of songs into.
+pub const PLAY_MANY_COMMAND_NAME: &str = "playmany";
+
+/// The text input in the `/playmany` modal, which is read as an option with this name.
+pub const PLAY_MANY_SONGS_NAME: &str = "songs";
+
+/// The command that turns other commands on and off, which can't be turned off itself.
+pub const COMMANDS_COMMAND_NAME: &str = "commands";
+
+pub fn command_name(command: &CreateCommand) -> Option<String> {
+    let value = serde_json::to_value(command).ok()?;
+    Some(value.get("name")?.as_str()?.to_string())
+}
+
+/// Whether a command with this name exists and can be turned off in a guild.
+pub fn can_disable_command(config: &Config, name: &str) -> bool {
+    name != COMMANDS_COMMAND_NAME
+        && create_commands(config)
+            .iter()
+            .any(|command| command_name(command).as_deref() == Some(name))
+}
+
+pub async fn register_commands(
+    http: impl AsRef<serenity::http::Http>,
+    guild_id: Option<GuildId>,
+    config: &Config,
+    disabled_commands: &[String],
+) -> serenity::Result<()> {
+    let http_ref = http.as_ref();
+
     match guild_id {
         Some(guild_id) => {
             Command::set_global_commands(http_ref, Vec::new()).await?;
-            guild_id.set_commands(http_ref, commands).await?;
+            register_guild_commands(http_ref, guild_id, config, disabled_commands).await?;
         }
         None => {
-            log::trace!("Registering global application commands");
-            Command::set_global_commands(http_ref, commands).await?;
+            tracing::trace!("Registering global application commands");
+            Command::set_global_commands(http_ref, create_commands(config)).await?;
         }
     }
 
     Ok(())
 }
+
+/// Registers the commands for one guild, leaving out any that have been turned off there.
+pub async fn register_guild_commands(
+    http: impl AsRef<serenity::http::Http>,
+    guild_id: GuildId,
+    config: &Config,
+    disabled_commands: &[String],
+) -> serenity::Result<()> {
+    let commands: Vec<_> = create_commands(config)
+        .into_iter()
+        .filter(|command| {
+            command_name(command).is_none_or(|name| !disabled_commands.contains(&name))
+        })
+        .collect();
+    guild_id.set_commands(http, commands).await?;
+    Ok(())
+}
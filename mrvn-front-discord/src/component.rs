@@ -0,0 +1,74 @@
+use uuid::Uuid;
+
+/// Actions attached to message components, encoded into each component's custom ID so they can
+/// be recovered when the component is interacted with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComponentAction {
+    SearchSelect(Uuid),
+    PlaylistSelect(Uuid),
+    QueuePlaylist(Uuid),
+    QueueDuplicate(Uuid),
+    Pause,
+    Resume,
+    Skip,
+    Stop,
+}
+
+impl ComponentAction {
+    /// The command that does the same thing as this action.
+    pub fn command_name(self) -> &'static str {
+        match self {
+            ComponentAction::SearchSelect(_) => "search",
+            ComponentAction::PlaylistSelect(_) => "play",
+            ComponentAction::QueuePlaylist(_) => "play",
+            ComponentAction::QueueDuplicate(_) => "play",
+            ComponentAction::Pause => "pause",
+            ComponentAction::Resume => "resume",
+            ComponentAction::Skip => "skip",
+            ComponentAction::Stop => "stop",
+        }
+    }
+
+    pub fn to_custom_id(self) -> String {
+        match self {
+            ComponentAction::SearchSelect(search_id) => format!("search:{}", search_id),
+            ComponentAction::PlaylistSelect(choices_id) => format!("playlist:{}", choices_id),
+            ComponentAction::QueuePlaylist(confirm_id) => {
+                format!("queue_playlist:{}", confirm_id)
+            }
+            ComponentAction::QueueDuplicate(duplicate_id) => {
+                format!("duplicate:{}", duplicate_id)
+            }
+            ComponentAction::Pause => "playback:pause".to_string(),
+            ComponentAction::Resume => "playback:resume".to_string(),
+            ComponentAction::Skip => "playback:skip".to_string(),
+            ComponentAction::Stop => "playback:stop".to_string(),
+        }
+    }
+
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let (action, argument) = custom_id.split_once(':')?;
+        match action {
+            "search" => Uuid::parse_str(argument)
+                .ok()
+                .map(ComponentAction::SearchSelect),
+            "playlist" => Uuid::parse_str(argument)
+                .ok()
+                .map(ComponentAction::PlaylistSelect),
+            "queue_playlist" => Uuid::parse_str(argument)
+                .ok()
+                .map(ComponentAction::QueuePlaylist),
+            "duplicate" => Uuid::parse_str(argument)
+                .ok()
+                .map(ComponentAction::QueueDuplicate),
+            "playback" => match argument {
+                "pause" => Some(ComponentAction::Pause),
+                "resume" => Some(ComponentAction::Resume),
+                "skip" => Some(ComponentAction::Skip),
+                "stop" => Some(ComponentAction::Stop),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
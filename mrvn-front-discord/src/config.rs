@@ -1,31 +1,58 @@
-use mrvn_back_ytdl::PlayConfig;
+use mrvn_back_ytdl::{Extractor, PlayConfig};
+use mrvn_model::{
+    AppModelConfig, GuildSettings, SchedulingStrategy, StorageBackend, VoteThreshold,
+};
 use serde::de::Error;
 use serde::Deserialize;
+use serenity::model::id::{ChannelId, UserId};
+use serenity::model::permissions::Permissions;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct CommandBot {
+    #[serde(default)]
     pub token: String,
+    /// A file to read the token from instead, like a Docker or Kubernetes secret.
+    pub token_file: Option<PathBuf>,
+    #[serde(deserialize_with = "from_number_or_string")]
     pub application_id: u64,
+    #[serde(default, deserialize_with = "from_optional_number_or_string")]
     pub guild_id: Option<u64>,
+    /// How many gateway shards to connect with. Discord's recommended count is used if not set.
+    pub shard_count: Option<u32>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct VoiceBot {
+    #[serde(default)]
     pub token: String,
+    pub token_file: Option<PathBuf>,
+    #[serde(deserialize_with = "from_number_or_string")]
     pub application_id: u64,
+    pub shard_count: Option<u32>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
-pub struct YtdlConfig {
+pub struct ExtractorConfig {
     pub name: String,
     pub args: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
-pub struct SecretHighfive {
-    pub image_url: String,
-    pub timezone: String,
+pub struct YtdlConfig {
+    pub extractors: Vec<ExtractorConfig>,
+    pub update_interval_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HttpConfig {
+    pub address: Option<String>,
+    pub public_url: Option<String>,
+    pub client_secret: Option<String>,
+    /// Tokens that can be used with the API. It's turned off if there aren't any.
+    pub api_tokens: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -37,38 +64,159 @@ pub struct Config {
     #[serde(deserialize_with = "from_hex")]
     pub error_embed_color: u32,
 
-    pub skip_votes_required: usize,
-    pub stop_votes_required: usize,
+    pub skip_votes_required: VoteThreshold,
+    pub stop_votes_required: VoteThreshold,
+    pub pause_votes_required: VoteThreshold,
+    pub history_length: usize,
+    pub max_user_queue_length: usize,
+    pub scheduling_strategy: SchedulingStrategy,
 
     pub disconnect_min_inactive_secs: u64,
     pub disconnect_check_interval_secs: u64,
     pub only_disconnect_when_alone: bool,
+    pub autoplay: bool,
+    pub always_on: bool,
+    pub idle_playlist: Option<String>,
+    pub follow_mode: bool,
     pub progress_min_update_secs: f64,
     pub progress_max_update_secs: f64,
+    pub ephemeral_responses: bool,
+    pub voice_channel_status: bool,
+    pub channel_topic: bool,
+    pub topic_channel: Option<ChannelId>,
+    pub topic_min_update_secs: f64,
+    pub announcements_channel: Option<ChannelId>,
+    pub session_threads: bool,
+    pub tts_announcements: bool,
+    pub tts_url: Option<String>,
+    pub audio_filters: bool,
+    pub loudness_normalization: bool,
+    pub loudness_target_lufs: f64,
+    pub trim_silence: bool,
+    pub silence_threshold_db: f32,
+    pub ephemeral_command_overrides: HashMap<String, bool>,
+    /// Lets commands be typed in messages starting with this, like `!play`, as well as used as
+    /// slash commands.
+    pub command_prefix: Option<String>,
+    pub command_cooldown_secs: HashMap<String, f64>,
+    /// The permissions members need to see and use each command, replacing the defaults.
+    #[serde(deserialize_with = "from_permission_names")]
+    pub command_permissions: HashMap<String, Permissions>,
+    pub sound_effects: HashMap<String, String>,
 
     pub buffer_capacity_kb: usize,
+    pub prefetch_songs: bool,
+    pub local_library_path: Option<String>,
+    pub audio_cache_path: Option<String>,
+    pub audio_cache_max_mb: u64,
+    pub metadata_cache_secs: u64,
+    pub max_playlist_size: usize,
+    pub playlist_confirm_size: usize,
+    pub native_youtube: bool,
+
+    pub data_path: String,
+    pub storage: StorageBackend,
+    pub redis_url: Option<String>,
+    pub http: HttpConfig,
+    pub sentry_dsn: Option<String>,
 
     pub search_prefix: String,
+    pub search_results_prefix: String,
+    pub search_results_count: usize,
     pub host_blocklist: Vec<String>,
     pub ytdl: YtdlConfig,
+    pub cookies_path: Option<String>,
+    pub proxy: Option<String>,
+    pub host_proxies: HashMap<String, String>,
 
+    /// Users that can manage the bot itself with commands like `/admin update-ytdl`, which affect
+    /// every server it's in.
+    pub bot_owners: Vec<UserId>,
     pub command_bot: CommandBot,
     pub voice_bots: Vec<VoiceBot>,
     pub messages: HashMap<String, String>,
+    pub locales: HashMap<String, HashMap<String, String>>,
 }
 
 impl Config {
-    pub fn get_raw_message<'s>(&'s self, message_key: &'s str) -> &'s str {
-        match self.messages.get(message_key) {
+    /// Reads the config from a file. It's parsed as TOML or YAML if the file has one of their
+    /// extensions, and as JSON otherwise. Parse errors say where in the file they are.
+    pub fn load(path: &Path) -> Result<Config, crate::error::Error> {
+        let config_error = |why: &dyn std::fmt::Display| {
+            crate::error::Error::Config(format!("{}: {}", path.display(), why))
+        };
+        let text = std::fs::read_to_string(path).map_err(|why| config_error(&why))?;
+        let mut value: serde_json::Value =
+            match path.extension().and_then(|extension| extension.to_str()) {
+                Some("toml") => toml::from_str(&text).map_err(|why| config_error(&why))?,
+                Some("yaml" | "yml") => {
+                    serde_norway::from_str(&text).map_err(|why| config_error(&why))?
+                }
+                _ => serde_json::from_str(&text).map_err(|why| config_error(&why))?,
+            };
+
+        // Environment variables are filled in after parsing, so a value with quotes or newlines in
+        // it can't change the structure of the file.
+        interpolate_env(&mut value).map_err(|why| config_error(&why))?;
+        let mut config: Config =
+            serde_path_to_error::deserialize(value).map_err(|why| config_error(&why))?;
+
+        config.command_bot.token = read_token(
+            "command_bot",
+            &config.command_bot.token,
+            config.command_bot.token_file.as_deref(),
+        )
+        .map_err(|why| config_error(&why))?;
+        for (index, voice_bot) in config.voice_bots.iter_mut().enumerate() {
+            voice_bot.token = read_token(
+                &format!("voice_bots[{}]", index),
+                &voice_bot.token,
+                voice_bot.token_file.as_deref(),
+            )
+            .map_err(|why| config_error(&why))?;
+        }
+        Ok(config)
+    }
+
+    pub fn get_raw_message<'s>(&'s self, locale: Option<&str>, message_key: &'s str) -> &'s str {
+        // Locales only need to include the messages they change, anything else falls back to the
+        // default messages.
+        let maybe_localized = locale
+            .and_then(|locale| self.locales.get(locale))
+            .and_then(|messages| messages.get(message_key));
+        match maybe_localized.or_else(|| self.messages.get(message_key)) {
             Some(template) => template,
             None => {
-                log::warn!("Message string {} was not included in config", message_key);
+                tracing::warn!("Message string {} was not included in config", message_key);
                 message_key
             }
         }
     }
 
-    pub fn format_time(&self, seconds: f64, minutes_width: usize) -> (String, usize) {
+    pub fn is_bot_owner(&self, user_id: UserId) -> bool {
+        self.bot_owners.contains(&user_id)
+    }
+
+    pub fn is_ephemeral_command(&self, command_name: &str) -> bool {
+        self.ephemeral_command_overrides
+            .get(command_name)
+            .copied()
+            .unwrap_or(self.ephemeral_responses)
+    }
+
+    pub fn get_command_cooldown(&self, command_name: &str) -> Option<Duration> {
+        self.command_cooldown_secs
+            .get(command_name)
+            .filter(|secs| **secs > 0.)
+            .map(|secs| Duration::from_secs_f64(*secs))
+    }
+
+    pub fn format_time(
+        &self,
+        locale: Option<&str>,
+        seconds: f64,
+        minutes_width: usize,
+    ) -> (String, usize) {
         let minutes = (seconds / 60.).floor();
         let seconds = (seconds % 60.).floor();
 
@@ -77,6 +225,7 @@ impl Config {
 
         (
             self.get_message(
+                locale,
                 "time",
                 &[("minutes", &minutes_string), ("seconds", &seconds_string)],
             ),
@@ -84,36 +233,278 @@ impl Config {
         )
     }
 
-    pub fn get_message(&self, message_key: &str, substitutions: &[(&str, &str)]) -> String {
-        let message_template = self.get_raw_message(message_key);
+    pub fn has_locale(&self, locale: &str) -> bool {
+        self.locales.contains_key(locale)
+    }
+
+    pub fn get_message(
+        &self,
+        locale: Option<&str>,
+        message_key: &str,
+        substitutions: &[(&str, &str)],
+    ) -> String {
+        let message_template = self.get_raw_message(locale, message_key);
+        crate::template::render(message_template, substitutions)
+    }
+
+    /// Creates a copy of the config with a guild's overridden settings applied.
+    pub fn with_guild_settings(&self, settings: &GuildSettings) -> Config {
+        let mut config = self.clone();
+        if let Some(value) = settings.skip_votes_required {
+            config.skip_votes_required = value;
+        }
+        if let Some(value) = settings.stop_votes_required {
+            config.stop_votes_required = value;
+        }
+        if let Some(value) = settings.pause_votes_required {
+            config.pause_votes_required = value;
+        }
+        if let Some(value) = settings.disconnect_min_inactive_secs {
+            config.disconnect_min_inactive_secs = value;
+        }
+        if let Some(value) = settings.only_disconnect_when_alone {
+            config.only_disconnect_when_alone = value;
+        }
+        if let Some(value) = settings.action_embed_color {
+            config.action_embed_color = value;
+        }
+        if let Some(value) = settings.response_embed_color {
+            config.response_embed_color = value;
+        }
+        if let Some(value) = settings.error_embed_color {
+            config.error_embed_color = value;
+        }
+        if let Some(value) = &settings.search_prefix {
+            config.search_prefix = value.clone();
+        }
+        if let Some(value) = settings.scheduling_strategy {
+            config.scheduling_strategy = value;
+        }
+        if let Some(value) = settings.autoplay {
+            config.autoplay = value;
+        }
+        if let Some(value) = settings.always_on {
+            config.always_on = value;
+        }
+        if let Some(value) = &settings.idle_playlist {
+            config.idle_playlist = Some(value.clone());
+        }
+        if let Some(value) = settings.follow_mode {
+            config.follow_mode = value;
+        }
+        if let Some(value) = settings.channel_topic {
+            config.channel_topic = value;
+        }
+        if let Some(value) = settings.topic_channel {
+            config.topic_channel = Some(value);
+        }
+        if let Some(value) = settings.announcements_channel {
+            config.announcements_channel = Some(value);
+        }
+        if let Some(value) = settings.session_threads {
+            config.session_threads = value;
+        }
+        if let Some(value) = settings.tts_announcements {
+            config.tts_announcements = value;
+        }
+        if let Some(value) = settings.loudness_normalization {
+            config.loudness_normalization = value;
+        }
+        if let Some(value) = settings.trim_silence {
+            config.trim_silence = value;
+        }
+        config
+    }
 
-        lazy_static::lazy_static! {
-            static ref SUBSTITUTE_REGEX: regex::Regex = regex::Regex::new(r"\{(\w+)\}").unwrap();
+    pub fn get_model_config(&self) -> AppModelConfig {
+        AppModelConfig {
+            skip_votes_required: self.skip_votes_required,
+            stop_votes_required: self.stop_votes_required,
+            pause_votes_required: self.pause_votes_required,
+            history_length: self.history_length,
+            max_user_queue_length: self.max_user_queue_length,
+            scheduling_strategy: self.scheduling_strategy,
         }
+    }
 
-        SUBSTITUTE_REGEX
-            .replace_all(message_template, |caps: &regex::Captures| {
-                let substitute_name = &caps[1];
-                substitutions
-                    .iter()
-                    .find(|(key, _)| *key == substitute_name)
-                    .map(|(_, value)| *value)
-                    .unwrap_or("")
+    pub fn get_extractors(&self) -> Vec<Extractor<'_>> {
+        self.ytdl
+            .extractors
+            .iter()
+            .map(|extractor| Extractor {
+                name: &extractor.name,
+                args: &extractor.args,
             })
-            .into_owned()
+            .collect()
     }
 
-    pub fn get_play_config(&self) -> PlayConfig {
+    pub fn get_play_config(&self) -> PlayConfig<'_> {
         PlayConfig {
             search_prefix: &self.search_prefix,
+            search_results_prefix: &self.search_results_prefix,
             host_blocklist: &self.host_blocklist,
-            ytdl_name: &self.ytdl.name,
-            ytdl_args: &self.ytdl.args,
+            extractors: self.get_extractors(),
+            cookies_path: self.cookies_path.as_deref(),
             buffer_capacity_kb: self.buffer_capacity_kb,
+            local_library_path: self.local_library_path.as_deref(),
+            audio_cache_path: self.audio_cache_path.as_deref(),
+            audio_cache_max_bytes: self.audio_cache_max_mb * 1024 * 1024,
+            metadata_cache_ttl: Duration::from_secs(self.metadata_cache_secs),
+            max_playlist_size: self.max_playlist_size,
+            native_youtube: self.native_youtube,
+            audio_filters: self.audio_filters,
+            silence_threshold_db: self.trim_silence.then_some(self.silence_threshold_db),
+            loudness_target_lufs: self
+                .loudness_normalization
+                .then_some(self.loudness_target_lufs),
+            tts_url: self.tts_url.as_deref().filter(|_| self.tts_announcements),
+            tts_announcement: self.get_raw_message(None, "tts.playing"),
         }
     }
 }
 
+/// Gets a bot's token from its `token`, or reads it from its `token_file`. Files often end with a
+/// newline, so whitespace around the token is ignored.
+fn read_token(
+    bot_name: &str,
+    token: &str,
+    maybe_token_file: Option<&Path>,
+) -> Result<String, String> {
+    match (token.is_empty(), maybe_token_file) {
+        (true, Some(token_file)) => {
+            let file_token = std::fs::read_to_string(token_file).map_err(|why| {
+                format!(
+                    "{} token_file {} couldn't be read: {}",
+                    bot_name,
+                    token_file.display(),
+                    why
+                )
+            })?;
+            let file_token = file_token.trim();
+            if file_token.is_empty() {
+                return Err(format!(
+                    "{} token_file {} is empty",
+                    bot_name,
+                    token_file.display()
+                ));
+            }
+            Ok(file_token.to_string())
+        }
+        (false, Some(_)) => Err(format!(
+            "{} has both a token and a token_file, only one can be used",
+            bot_name
+        )),
+        (false, None) => Ok(token.to_string()),
+        (true, None) => Err(format!("{} needs a token or a token_file", bot_name)),
+    }
+}
+
+/// Replaces each `${NAME}` in the config's strings with the environment variable `NAME`, so secrets
+/// like bot tokens don't have to be kept in the file. `$${` is left in as `${`.
+fn interpolate_env(value: &mut serde_json::Value) -> Result<(), String> {
+    match value {
+        serde_json::Value::String(text) => *text = interpolate_env_string(text)?,
+        serde_json::Value::Array(values) => {
+            for value in values {
+                interpolate_env(value)?;
+            }
+        }
+        serde_json::Value::Object(values) => {
+            for value in values.values_mut() {
+                interpolate_env(value)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn interpolate_env_string(text: &str) -> Result<String, String> {
+    let mut interpolated = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        if let Some(before) = rest[..start].strip_suffix('$') {
+            interpolated.push_str(before);
+            interpolated.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        let Some(length) = rest[start + 2..].find('}') else {
+            return Err("a `${` is never closed".to_string());
+        };
+        let name = &rest[start + 2..start + 2 + length];
+        let value = std::env::var(name)
+            .map_err(|_| format!("the environment variable `{}` isn't set", name))?;
+        interpolated.push_str(&rest[..start]);
+        interpolated.push_str(&value);
+        rest = &rest[start + 2 + length + 1..];
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+/// Reads permissions given by name, like `["MANAGE_GUILD"]`. An empty list would mean only
+/// administrators could use the command, which is more likely a mistake than intended.
+fn from_permission_names<'de, D>(deserializer: D) -> Result<HashMap<String, Permissions>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let names: HashMap<String, Vec<String>> = Deserialize::deserialize(deserializer)?;
+    names
+        .into_iter()
+        .map(|(command_name, permission_names)| {
+            if permission_names.is_empty() {
+                return Err(D::Error::custom(format!(
+                    "no permissions given for command {}",
+                    command_name
+                )));
+            }
+            let permissions =
+                permission_names
+                    .iter()
+                    .try_fold(
+                        Permissions::empty(),
+                        |permissions, name| match Permissions::from_name(name) {
+                            Some(permission) => Ok(permissions | permission),
+                            None => Err(D::Error::custom(format!("unknown permission {}", name))),
+                        },
+                    )?;
+            Ok((command_name, permissions))
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(u64),
+    String(String),
+}
+
+/// Reads a number that can also be written as a string, so IDs can be filled in from environment
+/// variables like `"${APPLICATION_ID}"`.
+fn from_number_or_string<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(number) => Ok(number),
+        NumberOrString::String(s) => s.trim().parse().map_err(D::Error::custom),
+    }
+}
+
+fn from_optional_number_or_string<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<NumberOrString>::deserialize(deserializer)? {
+        Some(NumberOrString::Number(number)) => Ok(Some(number)),
+        Some(NumberOrString::String(s)) => s.trim().parse().map(Some).map_err(D::Error::custom),
+        None => Ok(None),
+    }
+}
+
 fn from_hex<'de, D>(deserializer: D) -> Result<u32, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -2,6 +2,9 @@
 pub enum Error {
     Serenity(serenity::Error),
     Backend(mrvn_back_ytdl::Error),
+    Storage(mrvn_model::StorageError),
+    ModelLock(mrvn_model::LockError),
+    Config(String),
 
     UnknownCommand(String),
     NoGuild,
@@ -13,6 +16,9 @@ impl std::fmt::Display for Error {
         match self {
             Error::Serenity(err) => err.fmt(f),
             Error::Backend(err) => err.fmt(f),
+            Error::Storage(err) => err.fmt(f),
+            Error::ModelLock(err) => write!(f, "Unable to lock guild model: {}", err),
+            Error::Config(why) => write!(f, "Invalid config: {}", why),
             Error::UnknownCommand(command) => write!(f, "Received unknown command {}", command),
             Error::NoGuild => write!(f, "Command was not invoked from a guild"),
             Error::ModelPlayingSpeakerNotDesync => write!(
@@ -0,0 +1,205 @@
+use crate::frontend::{get_subcommand, Frontend};
+use crate::invocation::CommandInvocation;
+use crate::message::{BufferStatsMessageEntry, Message, ResponseMessage};
+use crate::queued_song::QueuedSong;
+use mrvn_back_ytdl::{buffer_stats, counters, update_ytdl};
+use mrvn_model::GuildModelHandle;
+use serenity::{
+    model::prelude::{GuildId, UserId},
+    prelude::*,
+};
+use std::sync::Arc;
+
+impl Frontend {
+    /// Commands for the bot's owners and anyone checking on how the bot is doing.
+    pub(super) async fn dispatch_admin_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        command: &CommandInvocation<'_>,
+        guild_id: GuildId,
+        _guild_model_handle: &GuildModelHandle<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let user_id = command.user_id;
+        match command.name.as_str() {
+            "admin" => {
+                let (subcommand_name, _) = get_subcommand(command).unwrap_or_default();
+                match subcommand_name {
+                    "update-ytdl" => {
+                        tracing::debug!("Received admin update-ytdl");
+                        self.handle_admin_update_ytdl_command(user_id).await
+                    }
+                    "buffers" => {
+                        tracing::debug!("Received admin buffers");
+                        self.handle_admin_buffers_command(guild_id).await
+                    }
+                    "reload" => {
+                        tracing::debug!("Received admin reload");
+                        self.handle_admin_reload_command(user_id).await
+                    }
+                    subcommand_name => Err(crate::error::Error::UnknownCommand(format!(
+                        "admin {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            "stats" => {
+                tracing::debug!("Received stats");
+                self.handle_stats_command(ctx).await
+            }
+            command_name => Err(crate::error::Error::UnknownCommand(
+                command_name.to_string(),
+            )),
+        }
+    }
+
+    async fn handle_admin_update_ytdl_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        // The command is registered in every guild, but updating affects all of them, so being a
+        // guild admin isn't enough.
+        if !self.config().is_bot_owner(user_id) {
+            tracing::trace!("User isn't one of the bot's owners, not updating");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotBotOwnerError,
+                delegate: None,
+            }]);
+        }
+
+        let mut messages = Vec::new();
+        for extractor in self.config().get_extractors() {
+            let name = extractor.name.to_string();
+            let message = match update_ytdl(extractor).await {
+                Ok(update) if update.is_updated() => {
+                    tracing::info!(
+                        "Updated {} from version {} to {}",
+                        name,
+                        update.old_version,
+                        update.new_version
+                    );
+                    ResponseMessage::YtdlUpdated {
+                        name,
+                        old_version: update.old_version,
+                        new_version: update.new_version,
+                    }
+                }
+                Ok(update) => ResponseMessage::YtdlUpToDate {
+                    name,
+                    version: update.new_version,
+                },
+                Err(why) => {
+                    tracing::error!("Error while updating {}: {}", name, why);
+                    ResponseMessage::YtdlUpdateFailedError {
+                        name,
+                        reason: why.to_string(),
+                    }
+                }
+            };
+            messages.push(Message::Response {
+                message,
+                delegate: None,
+            });
+        }
+        Ok(messages)
+    }
+
+    async fn handle_admin_reload_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        // Like updating youtube-dl, the config is shared by every guild.
+        if !self.config().is_bot_owner(user_id) {
+            tracing::trace!("User isn't one of the bot's owners, not reloading");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotBotOwnerError,
+                delegate: None,
+            }]);
+        }
+
+        let message = match self.reload_config().await {
+            Ok(()) => ResponseMessage::ConfigReloaded,
+            Err(why) => {
+                tracing::error!("Error while reloading config: {}", why);
+                ResponseMessage::ConfigReloadFailedError {
+                    reason: why.to_string(),
+                }
+            }
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_admin_buffers_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+
+        let entries = guild_speakers_ref
+            .lock_all()
+            .await
+            .filter_map(|guild_speaker| {
+                let active_metadata = guild_speaker.active_metadata()?;
+                let stats = guild_speaker.active_buffer_stats()?;
+                Some(BufferStatsMessageEntry {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                    voice_channel_id: guild_speaker.current_channel()?,
+                    underruns: stats.underruns,
+                    stalls: stats.stalls,
+                    stalled_seconds: stats.stalled_time.as_secs_f64(),
+                })
+            })
+            .collect();
+
+        let totals = buffer_stats();
+        Ok(vec![Message::Response {
+            message: ResponseMessage::BufferStats {
+                capacity_kb: self.config().buffer_capacity_kb,
+                tracks: totals.tracks,
+                underruns: totals.underruns,
+                stalls: totals.stalls,
+                stalled_seconds: totals.stalled_time.as_secs_f64(),
+                entries,
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_stats_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let guild_speaker_handles: Vec<_> = self
+            .backend_brain
+            .speakers()
+            .iter()
+            .flat_map(|speaker| speaker.iter())
+            .collect();
+        let mut active_speakers = 0;
+        for guild_speaker_handle in &guild_speaker_handles {
+            if guild_speaker_handle.lock().await.is_active() {
+                active_speakers += 1;
+            }
+        }
+
+        let counters = counters();
+        Ok(vec![Message::Response {
+            message: ResponseMessage::Stats {
+                started_time: counters.started_time,
+                guild_count: ctx.cache.guild_count(),
+                songs_played: counters.songs_played(),
+                active_speakers,
+                total_speakers: self.backend_brain.speakers().len(),
+                metadata_cache_hits: counters.metadata_cache.hits(),
+                metadata_cache_lookups: counters.metadata_cache.lookups(),
+                audio_cache_hits: counters.audio_cache.hits(),
+                audio_cache_lookups: counters.audio_cache.lookups(),
+            },
+            delegate: None,
+        }])
+    }
+}
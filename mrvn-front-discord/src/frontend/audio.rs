@@ -0,0 +1,350 @@
+use crate::commands::{FILTER_OFF_NAME, MAX_PITCH_SEMITONES};
+use crate::frontend::{get_string_option, get_subcommand, get_user_voice_channel, Frontend};
+use crate::invocation::CommandInvocation;
+use crate::message::{Message, ResponseMessage};
+use crate::queued_song::QueuedSong;
+use mrvn_back_ytdl::{
+    load_clip, EqualizerPreset, FilterPreset, EQ_BAND_FREQUENCIES, EQ_MAX_GAIN_DB, MAX_RATE_CHANGE,
+};
+use mrvn_model::GuildModelHandle;
+use serenity::{
+    model::prelude::{GuildId, UserId},
+    prelude::*,
+};
+use std::sync::Arc;
+
+impl Frontend {
+    /// Commands that change how the guild's songs sound, and play sound effects over them.
+    pub(super) async fn dispatch_audio_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        command: &CommandInvocation<'_>,
+        guild_id: GuildId,
+        _guild_model_handle: &GuildModelHandle<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let user_id = command.user_id;
+        match command.name.as_str() {
+            "sfx" => {
+                let name = get_string_option(&command.options, "name").unwrap_or_default();
+                tracing::debug!("Received sfx \"{}\"", name);
+                self.handle_sfx_command(ctx, user_id, guild_id, name).await
+            }
+            "volume" => {
+                let percent = command
+                    .options
+                    .iter()
+                    .find(|option| option.name == "percent")
+                    .and_then(|option| option.value.as_i64())
+                    .and_then(|percent| u32::try_from(percent).ok())
+                    .unwrap_or(100);
+                tracing::debug!("Received volume {}", percent);
+                self.handle_volume_command(guild_id, percent).await
+            }
+            "speed" => {
+                let speed = command
+                    .options
+                    .iter()
+                    .find(|option| option.name == "multiplier")
+                    .and_then(|option| option.value.as_f64())
+                    .unwrap_or(1.);
+                tracing::debug!("Received speed {}", speed);
+                self.handle_speed_command(guild_id, speed as f32).await
+            }
+            "pitch" => {
+                let semitones = command
+                    .options
+                    .iter()
+                    .find(|option| option.name == "semitones")
+                    .and_then(|option| option.value.as_i64())
+                    .unwrap_or(0)
+                    .clamp(-MAX_PITCH_SEMITONES, MAX_PITCH_SEMITONES)
+                    as i32;
+                tracing::debug!("Received pitch {}", semitones);
+                self.handle_pitch_command(guild_id, semitones).await
+            }
+            "filter" => {
+                let name = get_string_option(&command.options, "name").unwrap_or_default();
+                tracing::debug!("Received filter {}", name);
+                self.handle_filter_command(guild_id, name).await
+            }
+            "eq" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                let maybe_frequency = options
+                    .iter()
+                    .find(|option| option.name == "band")
+                    .and_then(|option| option.value.as_i64())
+                    .and_then(|frequency| u32::try_from(frequency).ok());
+                let maybe_gain = options
+                    .iter()
+                    .find(|option| option.name == "gain")
+                    .and_then(|option| option.value.as_f64());
+                let maybe_preset =
+                    get_string_option(options, "name").and_then(EqualizerPreset::from_name);
+                match (subcommand_name, maybe_frequency, maybe_gain, maybe_preset) {
+                    ("set", Some(frequency), Some(gain), _) => {
+                        tracing::debug!("Received eq set {} {}", frequency, gain);
+                        self.handle_eq_set_command(guild_id, frequency, gain as f32)
+                            .await
+                    }
+                    ("preset", _, _, Some(preset)) => {
+                        tracing::debug!("Received eq preset {}", preset.name());
+                        self.handle_eq_preset_command(guild_id, preset).await
+                    }
+                    ("view", _, _, _) => {
+                        tracing::debug!("Received eq view");
+                        self.handle_eq_view_command(guild_id).await
+                    }
+                    (subcommand_name, _, _, _) => Err(crate::error::Error::UnknownCommand(
+                        format!("eq {}", subcommand_name),
+                    )),
+                }
+            }
+            command_name => Err(crate::error::Error::UnknownCommand(
+                command_name.to_string(),
+            )),
+        }
+    }
+
+    async fn handle_sfx_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let config = self.config();
+        let Some(source) = config.sound_effects.get(name) else {
+            let mut sound_effects: Vec<_> = config.sound_effects.keys().cloned().collect();
+            sound_effects.sort();
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::UnknownSoundEffectError {
+                    name: name.to_string(),
+                    sound_effects,
+                },
+                delegate: None,
+            }]);
+        };
+
+        // Load the clip before locking the speakers, so playback isn't held up while it downloads.
+        let input = load_clip(source)
+            .await
+            .map_err(crate::error::Error::Backend)?;
+
+        // Sound effects play over whatever the bot in the channel is doing, so there needs to be
+        // one there already.
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        let Some(guild_speaker) = guild_speakers_ref.find_in_channel(channel_id).await else {
+            tracing::trace!(
+                "No speakers are in the user's voice channel, not playing sound effect"
+            );
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        };
+
+        guild_speaker
+            .play_clip(input)
+            .map_err(crate::error::Error::Backend)?;
+        Ok(vec![Message::Response {
+            message: ResponseMessage::SoundEffectPlayed {
+                name: name.to_string(),
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_volume_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        percent: u32,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if !self.config().audio_filters {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::AudioFiltersDisabledError,
+                delegate: None,
+            }]);
+        }
+
+        // Songs that are already playing pick up the new volume straight away.
+        self.backend_brain
+            .guild_filters(guild_id)
+            .update(|settings| settings.volume = percent as f32 / 100.);
+        Ok(vec![Message::Response {
+            message: ResponseMessage::VolumeSet { percent },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_speed_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        speed: f32,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if !self.config().audio_filters {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::AudioFiltersDisabledError,
+                delegate: None,
+            }]);
+        }
+
+        let speed = speed.clamp(1. / MAX_RATE_CHANGE, MAX_RATE_CHANGE);
+        self.backend_brain
+            .guild_filters(guild_id)
+            .update(|settings| {
+                settings.speed = speed;
+                settings.preset = None;
+            });
+        Ok(vec![Message::Response {
+            message: ResponseMessage::SpeedSet { speed },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_pitch_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        semitones: i32,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if !self.config().audio_filters {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::AudioFiltersDisabledError,
+                delegate: None,
+            }]);
+        }
+
+        self.backend_brain
+            .guild_filters(guild_id)
+            .update(|settings| {
+                settings.pitch = 2f32.powf(semitones as f32 / 12.);
+                settings.preset = None;
+            });
+        Ok(vec![Message::Response {
+            message: ResponseMessage::PitchSet { semitones },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_filter_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if !self.config().audio_filters {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::AudioFiltersDisabledError,
+                delegate: None,
+            }]);
+        }
+
+        let filters = self.backend_brain.guild_filters(guild_id);
+        let message = match FilterPreset::from_name(name) {
+            Some(preset) => {
+                filters.update(|settings| preset.apply(settings));
+                ResponseMessage::FilterPresetSet {
+                    name: preset.name().to_string(),
+                }
+            }
+            None if name == FILTER_OFF_NAME => {
+                filters.update(FilterPreset::clear);
+                ResponseMessage::FilterPresetCleared
+            }
+            None => {
+                return Err(crate::error::Error::UnknownCommand(format!(
+                    "filter {}",
+                    name
+                )))
+            }
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_eq_set_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        frequency: u32,
+        gain: f32,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if !self.config().audio_filters {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::AudioFiltersDisabledError,
+                delegate: None,
+            }]);
+        }
+        let Some(band_index) = EQ_BAND_FREQUENCIES
+            .iter()
+            .position(|band_frequency| *band_frequency == frequency)
+        else {
+            return Err(crate::error::Error::UnknownCommand(format!(
+                "eq set {}",
+                frequency
+            )));
+        };
+
+        let gain = gain.clamp(-EQ_MAX_GAIN_DB, EQ_MAX_GAIN_DB);
+        self.backend_brain
+            .guild_filters(guild_id)
+            .update(|settings| {
+                settings.equalizer[band_index] = gain;
+                settings.preset = None;
+            });
+        Ok(vec![Message::Response {
+            message: ResponseMessage::EqualizerBandSet { frequency, gain },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_eq_preset_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        preset: EqualizerPreset,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if !self.config().audio_filters {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::AudioFiltersDisabledError,
+                delegate: None,
+            }]);
+        }
+
+        self.backend_brain
+            .guild_filters(guild_id)
+            .update(|settings| {
+                settings.equalizer = preset.gains();
+                settings.preset = None;
+            });
+        Ok(vec![Message::Response {
+            message: ResponseMessage::EqualizerPresetSet {
+                name: preset.name().to_string(),
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_eq_view_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let settings = self.backend_brain.guild_filters(guild_id).settings();
+        let gains = EQ_BAND_FREQUENCIES
+            .into_iter()
+            .zip(settings.equalizer)
+            .collect();
+        Ok(vec![Message::Response {
+            message: ResponseMessage::Equalizer { gains },
+            delegate: None,
+        }])
+    }
+}
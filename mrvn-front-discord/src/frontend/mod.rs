@@ -0,0 +1,1965 @@
+use crate::commands::{
+    MAX_CHOICE_NAME_LENGTH, MAX_OPTION_CHOICES, PLAY_MANY_COMMAND_NAME, PLAY_MANY_SONGS_NAME,
+};
+use crate::component::ComponentAction;
+use crate::config::Config;
+use crate::idle_songs::IdleSongProvider;
+use crate::interaction::InteractionRef;
+use crate::invocation::{CommandInvocation, InvocationSource};
+use crate::message::{
+    send_messages, ActionMessage, Message, ResponseMessage, SendMessageDestination,
+};
+use crate::permissions::{get_member_priority, MemberPermission};
+use crate::playing_message::{build_playing_message, SkipVotesChanged};
+use crate::prefix_command::{parse_prefix_command, PrefixCommandError};
+use crate::queued_song::QueuedSong;
+use crate::rate_limit::RateLimiter;
+use crate::topic_message::ChannelTopics;
+use crate::voice_bots::VoiceBots;
+use futures::prelude::*;
+use mrvn_back_ytdl::{
+    set_proxy_rules, Brain, EndedHandler, GuildSpeakerEndedHandle, GuildSpeakerEndedRef,
+    GuildSpeakerRef, PrefetchedInput, ProxyRules, RemainingSongs, Song, SongMetadata,
+};
+use mrvn_model::{
+    AppModel, FavoriteStore, GuildModelGuard, GuildModelHandle, HistoryEntry, HistoryStore,
+    PlaylistStore, SettingsStore, VoteCount, VoteType,
+};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, ComponentInteraction,
+    ComponentInteractionDataKind, CreateActionRow, CreateAutocompleteResponse, CreateEmbed,
+    CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    CreateModal, EditInteractionResponse, EditMessage, InputTextStyle, ModalInteraction, Timestamp,
+};
+use serenity::model::id::{ChannelId, MessageId};
+use serenity::{
+    model::prelude::{GuildId, Member, UserId},
+    prelude::*,
+};
+use std::collections::HashMap;
+use std::ops::DerefMut;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::Instrument;
+use uuid::Uuid;
+
+const SEND_WORKING_TIMEOUT_MS: u64 = 50;
+const PLAYLIST_LOAD_CONCURRENCY: usize = 4;
+const SEARCH_EXPIRY: Duration = Duration::from_secs(15 * 60);
+/// Select menus can't have more options than this, so only the start of a longer playlist can be
+/// chosen from.
+const MAX_PLAYLIST_CHOICES: usize = 25;
+const SKIP_VOTES_CHANNEL_CAPACITY: usize = 16;
+const SHUTDOWN_FADE_DURATION: Duration = Duration::from_secs(2);
+/// Exported queues are only a URL and title for each song, so anything bigger than this isn't one.
+const MAX_QUEUE_FILE_SIZE: u32 = 1024 * 1024;
+/// How many times to try getting back into a channel after the voice connection drops out, and
+/// how long to wait before the first try. The wait doubles after each failed try.
+const RECONNECT_ATTEMPTS: u32 = 5;
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(1);
+
+mod admin;
+mod audio;
+mod playback;
+mod playlist;
+mod queue;
+mod settings;
+
+enum HandleCommandError {
+    CreateError(crate::error::Error),
+    EditError(crate::error::Error),
+}
+
+enum QueuedSongsMetadata {
+    Single(mrvn_back_ytdl::SongMetadata),
+    Multiple(usize),
+}
+
+/// What `/play` does when it finds a playlist.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlaylistMode {
+    /// Queue every song, asking first if there are more than `playlist_confirm_size`.
+    QueueAll,
+    /// Let the user choose which songs to queue.
+    Choose,
+    /// Queue every song without asking, because the user already said to.
+    Confirmed,
+}
+
+/// Songs from a search or playlist, kept until the user chooses which to queue.
+struct PendingSearch {
+    user_id: UserId,
+    created_time: Instant,
+    songs: Vec<Song>,
+}
+
+/// A song that wasn't queued because it's already queued or playing, kept until the user decides
+/// whether to queue it anyway.
+struct PendingDuplicate {
+    user_id: UserId,
+    created_time: Instant,
+    song: Song,
+}
+
+/// A playlist that's too big to queue without asking, kept until the user confirms it. Only the
+/// term is kept, so nothing is left loading while waiting.
+struct PendingPlaylist {
+    user_id: UserId,
+    created_time: Instant,
+    term: String,
+}
+
+pub struct Frontend {
+    /// The config can be reloaded while the bot is running, so it's read again for each use.
+    config: std::sync::RwLock<Arc<Config>>,
+    config_path: PathBuf,
+    pub backend_brain: Brain,
+    pub model: AppModel<QueuedSong>,
+    pub playlists: PlaylistStore,
+    pub settings: SettingsStore,
+    pub favorites: FavoriteStore,
+    pub history: HistoryStore,
+    pub voice_bots: VoiceBots,
+    guild_configs: std::sync::Mutex<HashMap<GuildId, Arc<Config>>>,
+    pending_searches: Mutex<HashMap<Uuid, PendingSearch>>,
+    pending_duplicates: Mutex<HashMap<Uuid, PendingDuplicate>>,
+    pending_playlists: Mutex<HashMap<Uuid, PendingPlaylist>>,
+    rate_limiter: RateLimiter,
+    idle_songs: IdleSongProvider,
+    pub channel_topics: ChannelTopics,
+    pub skip_votes_sender: broadcast::Sender<SkipVotesChanged>,
+    /// Sent once when the bot starts shutting down, so playing messages can say so.
+    pub shutdown_sender: broadcast::Sender<()>,
+    is_shutting_down: AtomicBool,
+    /// The command bot's context, once it's connected. Things that don't come from Discord, like
+    /// the HTTP API, use it to play songs and send messages.
+    command_context: std::sync::RwLock<Option<Context>>,
+}
+
+impl Frontend {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        config: Arc<Config>,
+        config_path: PathBuf,
+        backend_brain: Brain,
+        model: AppModel<QueuedSong>,
+        playlists: PlaylistStore,
+        settings: SettingsStore,
+        favorites: FavoriteStore,
+        history: HistoryStore,
+    ) -> Frontend {
+        Frontend {
+            config: std::sync::RwLock::new(config),
+            config_path,
+            backend_brain,
+            model,
+            playlists,
+            settings,
+            favorites,
+            history,
+            voice_bots: VoiceBots::default(),
+            guild_configs: std::sync::Mutex::new(HashMap::new()),
+            pending_searches: Mutex::new(HashMap::new()),
+            pending_duplicates: Mutex::new(HashMap::new()),
+            pending_playlists: Mutex::new(HashMap::new()),
+            rate_limiter: RateLimiter::default(),
+            idle_songs: IdleSongProvider::default(),
+            channel_topics: ChannelTopics::default(),
+            skip_votes_sender: broadcast::channel(SKIP_VOTES_CHANNEL_CAPACITY).0,
+            shutdown_sender: broadcast::channel(1).0,
+            is_shutting_down: AtomicBool::new(false),
+            command_context: std::sync::RwLock::new(None),
+        }
+    }
+
+    pub fn set_command_context(&self, ctx: Context) {
+        *self.command_context.write().unwrap() = Some(ctx);
+    }
+
+    pub fn command_context(&self) -> Option<Context> {
+        self.command_context.read().unwrap().clone()
+    }
+
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.is_shutting_down.load(Ordering::Relaxed)
+    }
+
+    /// Stops everything that's playing before the bot exits. Songs fade out, playing messages say
+    /// the bot is restarting, and the speakers leave their voice channels. Nothing else is played
+    /// after this starts.
+    pub async fn shut_down(&self) {
+        self.is_shutting_down.store(true, Ordering::Relaxed);
+        let _ = self.shutdown_sender.send(());
+
+        let guild_speaker_handles: Vec<_> = self
+            .backend_brain
+            .speakers()
+            .iter()
+            .flat_map(|speaker| speaker.iter())
+            .collect();
+        future::join_all(guild_speaker_handles.iter().map(|handle| async move {
+            let mut guild_speaker = handle.lock().await;
+            let Some(channel_id) = guild_speaker.current_channel() else {
+                return;
+            };
+            let guild_id = guild_speaker.guild_id();
+            guild_speaker.fade_out(SHUTDOWN_FADE_DURATION).await;
+            if let Err(why) = guild_speaker.stop() {
+                tracing::error!("Error while stopping song: {}", why);
+            }
+            guild_speaker.unlock();
+            if let Err(why) = guild_speaker.disconnect().await {
+                tracing::error!("Error while leaving voice channel: {}", why);
+            }
+            drop(guild_speaker);
+
+            // Nothing is playing here anymore, which matters if the model is shared with another
+            // instance that takes over.
+            let guild_model_handle = self.model.get(guild_id);
+            match guild_model_handle.lock().await {
+                Ok(mut guild_model) => guild_model.set_channel_not_playing(channel_id),
+                Err(why) => tracing::error!("Error while stopping channel: {}", why),
+            }
+        }))
+        .await;
+    }
+
+    /// Reads the config file again and starts using it. Message templates, colors, blocklists,
+    /// timeouts and the like change straight away, but bot tokens, the voice bots, `data_path` and
+    /// the HTTP server need a restart.
+    pub async fn reload_config(&self) -> Result<(), crate::error::Error> {
+        let config = Config::load(&self.config_path)?;
+        let proxy_rules = ProxyRules::new(config.proxy.as_deref(), config.host_proxies.clone())
+            .map_err(|why| crate::error::Error::Config(why.to_string()))?;
+        set_proxy_rules(proxy_rules);
+        self.model.set_config(config.get_model_config());
+        *self.config.write().unwrap() = Arc::new(config);
+        self.guild_configs.lock().unwrap().clear();
+
+        // Guilds that have already started using the model need to be told about the change
+        for guild_id in self.model.guild_ids() {
+            let model_config = self.guild_config(guild_id).await.get_model_config();
+            self.model.set_guild_config(guild_id, model_config);
+            match self.model.get(guild_id).lock().await {
+                Ok(mut guild_model) => guild_model.set_config(model_config),
+                Err(why) => tracing::error!("Error while updating guild model config: {}", why),
+            }
+        }
+
+        tracing::info!("Reloaded config from {}", self.config_path.display());
+        Ok(())
+    }
+
+    /// Gets the config for a guild, with any settings the guild has overridden applied.
+    pub async fn guild_config(&self, guild_id: GuildId) -> Arc<Config> {
+        let maybe_cached = self.guild_configs.lock().unwrap().get(&guild_id).cloned();
+        if let Some(config) = maybe_cached {
+            return config;
+        }
+
+        let config = self
+            .settings
+            .read(|settings| match settings.get(guild_id) {
+                Some(guild_settings) => Arc::new(self.config().with_guild_settings(guild_settings)),
+                None => self.config(),
+            })
+            .await;
+        self.guild_configs
+            .lock()
+            .unwrap()
+            .insert(guild_id, config.clone());
+        config
+    }
+
+    async fn member_permission(
+        &self,
+        guild_id: GuildId,
+        maybe_member: Option<&Member>,
+    ) -> MemberPermission {
+        self.settings
+            .read(|settings| match settings.get(guild_id) {
+                Some(guild_settings) => MemberPermission::get(guild_settings, maybe_member),
+                None => MemberPermission::Unrestricted,
+            })
+            .await
+    }
+
+    async fn member_priority(&self, guild_id: GuildId, maybe_member: Option<&Member>) -> u32 {
+        self.settings
+            .read(|settings| match settings.get(guild_id) {
+                Some(guild_settings) => get_member_priority(guild_settings, maybe_member),
+                None => 0,
+            })
+            .await
+    }
+
+    async fn is_user_banned(&self, guild_id: GuildId, user_id: UserId) -> bool {
+        self.settings
+            .read(|settings| {
+                settings
+                    .get(guild_id)
+                    .map(|guild_settings| guild_settings.banned_users.contains(&user_id))
+                    .unwrap_or(false)
+            })
+            .await
+    }
+
+    pub async fn disabled_commands(&self, guild_id: GuildId) -> Vec<String> {
+        self.settings
+            .read(|settings| {
+                settings
+                    .get(guild_id)
+                    .map(|guild_settings| guild_settings.disabled_commands.clone())
+                    .unwrap_or_default()
+            })
+            .await
+    }
+
+    pub async fn guild_locale(&self, guild_id: GuildId) -> Option<String> {
+        self.settings
+            .read(|settings| {
+                settings
+                    .get(guild_id)
+                    .and_then(|guild_settings| guild_settings.locale.clone())
+            })
+            .await
+    }
+
+    /// With follow mode on, moves the speaker playing someone's song to whichever voice channel
+    /// they move to.
+    pub async fn handle_voice_state_update(
+        self: &Arc<Self>,
+        old_channel_id: Option<ChannelId>,
+        new: &serenity::model::voice::VoiceState,
+    ) {
+        let (Some(guild_id), Some(from_channel_id), Some(to_channel_id)) =
+            (new.guild_id, old_channel_id, new.channel_id)
+        else {
+            return;
+        };
+        if from_channel_id == to_channel_id
+            || self.is_shutting_down()
+            || !self.guild_config(guild_id).await.follow_mode
+        {
+            return;
+        }
+
+        let guild_model_handle = self.model.get(guild_id);
+        let mut guild_model = match guild_model_handle.lock().await {
+            Ok(guild_model) => guild_model,
+            Err(why) => {
+                tracing::error!("Error while following user: {}", why);
+                return;
+            }
+        };
+        if guild_model.get_channel_playing_user(from_channel_id) != Some(new.user_id) {
+            return;
+        }
+
+        let guild_speakers = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers.lock();
+        if guild_speakers_ref
+            .find_active_in_channel(to_channel_id)
+            .await
+            .is_some()
+        {
+            return;
+        }
+        let Some((guild_speaker, _)) = guild_speakers_ref
+            .find_active_in_channel(from_channel_id)
+            .await
+        else {
+            return;
+        };
+        if !guild_model.move_channel(from_channel_id, to_channel_id) {
+            return;
+        }
+
+        // Joining the new channel can take a while, so the guild is unlocked first. If it fails,
+        // the speaker is released before the guild is locked again to move the channel back.
+        drop(guild_model);
+        if let Err(why) = guild_speaker.move_to(to_channel_id).await {
+            tracing::error!(
+                "Error while following user to another voice channel: {}",
+                why
+            );
+            drop(guild_speakers_ref);
+            match guild_model_handle.lock().await {
+                Ok(mut guild_model) => {
+                    guild_model.move_channel(to_channel_id, from_channel_id);
+                }
+                Err(why) => tracing::error!("Error while moving channel back: {}", why),
+            }
+            return;
+        }
+        tracing::debug!("Followed user to another voice channel");
+    }
+
+    pub async fn handle_command(self: &Arc<Self>, ctx: &Context, command: &CommandInteraction) {
+        let interaction = InteractionRef::Command(command);
+        let span = interaction_span(interaction, &command.data.name);
+        if command.data.name == PLAY_MANY_COMMAND_NAME {
+            self.show_play_many_modal(ctx, command)
+                .instrument(span)
+                .await;
+            return;
+        }
+        self.handle_interaction(ctx, interaction)
+            .instrument(span)
+            .await;
+    }
+
+    pub async fn handle_modal(self: &Arc<Self>, ctx: &Context, modal: &ModalInteraction) {
+        let interaction = InteractionRef::Modal(modal);
+        self.handle_interaction(ctx, interaction)
+            .instrument(interaction_span(interaction, &modal.data.custom_id))
+            .await;
+    }
+
+    /// Opens a modal to paste songs into. Submitting it runs `/playmany` with what was pasted.
+    /// Modals have to be the first response to a command, so this can't wait on anything slow.
+    async fn show_play_many_modal(&self, ctx: &Context, command: &CommandInteraction) {
+        let (guild_config, locale) = self.config_and_locale(command.guild_id).await;
+        let input = CreateInputText::new(
+            InputTextStyle::Paragraph,
+            guild_config.get_raw_message(locale.as_deref(), "modal.play_many_songs"),
+            PLAY_MANY_SONGS_NAME,
+        );
+        let modal = CreateModal::new(
+            PLAY_MANY_COMMAND_NAME,
+            guild_config.get_raw_message(locale.as_deref(), "modal.play_many_title"),
+        )
+        .components(vec![CreateActionRow::InputText(input)]);
+        if let Err(why) = command
+            .create_response(ctx, CreateInteractionResponse::Modal(modal))
+            .await
+        {
+            tracing::error!("Error while opening modal: {}", why);
+        }
+    }
+
+    /// Suggests choices while an option is being typed. Nothing here waits on the guild's model,
+    /// since Discord only gives a few seconds to respond.
+    pub async fn handle_autocomplete(
+        self: &Arc<Self>,
+        ctx: &Context,
+        command: &CommandInteraction,
+    ) {
+        let choices = self.queue_position_choices(command);
+        if let Err(why) = command
+            .create_response(ctx, CreateInteractionResponse::Autocomplete(choices))
+            .await
+        {
+            tracing::error!("Error while sending autocomplete choices: {}", why);
+        }
+    }
+
+    /// The songs in the user's queue that match what they've typed, by position or title.
+    fn queue_position_choices(&self, command: &CommandInteraction) -> CreateAutocompleteResponse {
+        let response = CreateAutocompleteResponse::new();
+        let (Some(guild_id), Some(option)) = (command.guild_id, command.data.autocomplete()) else {
+            return response;
+        };
+        if option.name != "position" {
+            return response;
+        }
+        let Some(guild_model) = self.model.get(guild_id).try_lock_local() else {
+            tracing::trace!("Guild model is in use, not suggesting any choices");
+            return response;
+        };
+
+        let typed = option.value.trim().to_lowercase();
+        guild_model
+            .user_entries(command.user.id)
+            .enumerate()
+            .map(|(index, queued_song)| (index + 1, &queued_song.song.metadata.title))
+            .filter(|(position, title)| {
+                position.to_string().starts_with(&typed) || title.to_lowercase().contains(&typed)
+            })
+            .take(MAX_OPTION_CHOICES)
+            .fold(response, |response, (position, title)| {
+                let name: String = format!("{}. {}", position, title)
+                    .chars()
+                    .take(MAX_CHOICE_NAME_LENGTH)
+                    .collect();
+                response.add_int_choice(name, position as i64)
+            })
+    }
+
+    pub async fn handle_component(
+        self: &Arc<Self>,
+        ctx: &Context,
+        component: &ComponentInteraction,
+    ) {
+        let interaction = InteractionRef::Component(component);
+        self.handle_interaction(ctx, interaction)
+            .instrument(interaction_span(interaction, &component.data.custom_id))
+            .await;
+    }
+
+    /// Runs a command typed in a message with the command prefix, if prefix commands are turned
+    /// on. Messages that aren't commands are ignored.
+    pub async fn handle_message(
+        self: &Arc<Self>,
+        ctx: &Context,
+        message: &serenity::model::channel::Message,
+    ) {
+        let Some(guild_id) = message.guild_id else {
+            return;
+        };
+        let Some(prefix) = self.config().command_prefix.clone() else {
+            return;
+        };
+        if message.author.bot || prefix.is_empty() {
+            return;
+        }
+        let Some(text) = message.content.strip_prefix(prefix.as_str()) else {
+            return;
+        };
+
+        let span = tracing::info_span!(
+            "prefix_command",
+            guild_id = guild_id.get(),
+            channel_id = message.channel_id.get(),
+            user_id = message.author.id.get(),
+        );
+        self.handle_prefix_command(ctx, message, guild_id, text)
+            .instrument(span)
+            .await;
+    }
+
+    async fn handle_prefix_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        message: &serenity::model::channel::Message,
+        guild_id: GuildId,
+        text: &str,
+    ) {
+        let messages_res = match parse_prefix_command(&self.config(), text, &message.attachments) {
+            Ok(prefix_command) => {
+                // Discord only sends members with their permissions for interactions, so they're
+                // worked out here instead.
+                let maybe_member = match guild_id.member(ctx, message.author.id).await {
+                    Ok(mut member) => {
+                        member.permissions = ctx
+                            .cache
+                            .guild(guild_id)
+                            .map(|guild| guild.member_permissions(&member));
+                        Some(member)
+                    }
+                    Err(why) => {
+                        tracing::error!("Error while getting member: {}", why);
+                        None
+                    }
+                };
+                let has_permissions = prefix_command.required_permissions.is_empty()
+                    || maybe_member
+                        .as_ref()
+                        .and_then(|member| member.permissions)
+                        .is_some_and(|permissions| {
+                            permissions.contains(prefix_command.required_permissions)
+                        });
+
+                if has_permissions {
+                    let command = CommandInvocation {
+                        name: prefix_command.name,
+                        options: prefix_command.options,
+                        user_id: message.author.id,
+                        member: maybe_member,
+                        source: InvocationSource::Message(message),
+                    };
+                    tracing::debug!("Received prefix command {}", command.name);
+                    self.handle_guild_invocation(ctx, &command, guild_id, message.channel_id)
+                        .await
+                } else {
+                    tracing::trace!("User doesn't have permission to use this command");
+                    Ok(vec![Message::Response {
+                        message: ResponseMessage::MissingPermissionsError,
+                        delegate: None,
+                    }])
+                }
+            }
+            Err(PrefixCommandError::UnknownCommand) => {
+                tracing::trace!("Message isn't a known command, ignoring it");
+                return;
+            }
+            Err(PrefixCommandError::InvalidOptions { command_name }) => {
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::InvalidPrefixCommandError { command_name },
+                    delegate: None,
+                }])
+            }
+        };
+
+        let messages = match messages_res {
+            Ok(messages) => messages,
+            Err(why) => {
+                tracing::error!("Error while handling command: {}", why);
+                let error_embed = self.unknown_error_embed(Some(guild_id)).await;
+                if let Err(why) = message
+                    .channel_id
+                    .send_message(ctx, CreateMessage::new().embed(error_embed))
+                    .await
+                {
+                    tracing::error!("Error while sending error response: {}", why);
+                }
+                return;
+            }
+        };
+
+        let guild_model_handle = self.model.get(guild_id);
+        let guild_config = self.guild_config(guild_id).await;
+        let locale = self.guild_locale(guild_id).await;
+        let send_res = send_messages(
+            &guild_config,
+            locale.as_deref(),
+            ctx,
+            SendMessageDestination::Channel(message.channel_id),
+            &guild_model_handle,
+            messages,
+        )
+        .await;
+        if let Err(why) = send_res {
+            tracing::error!("Error while sending response: {}", why);
+        }
+
+        self.clone().prefetch_next_songs(ctx.clone(), guild_id);
+    }
+
+    async fn config_and_locale(
+        &self,
+        maybe_guild_id: Option<GuildId>,
+    ) -> (Arc<Config>, Option<String>) {
+        match maybe_guild_id {
+            Some(guild_id) => (
+                self.guild_config(guild_id).await,
+                self.guild_locale(guild_id).await,
+            ),
+            None => (self.config(), None),
+        }
+    }
+
+    async fn unknown_error_embed(&self, maybe_guild_id: Option<GuildId>) -> CreateEmbed {
+        let (guild_config, locale) = self.config_and_locale(maybe_guild_id).await;
+        CreateEmbed::new()
+            .description(guild_config.get_raw_message(locale.as_deref(), "action.unknown_error"))
+            .color(guild_config.response_embed_color)
+    }
+
+    async fn handle_interaction(self: &Arc<Self>, ctx: &Context, interaction: InteractionRef<'_>) {
+        let Err(why) = self.handle_interaction_fallable(ctx, interaction).await else {
+            return;
+        };
+        let error_embed = self.unknown_error_embed(interaction.guild_id()).await;
+
+        let send_error_res = match why {
+            HandleCommandError::CreateError(why) => {
+                tracing::error!("Error while handling command: {}", why);
+                interaction
+                    .create_response(
+                        ctx,
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new().embed(error_embed),
+                        ),
+                    )
+                    .await
+            }
+            HandleCommandError::EditError(why) => {
+                tracing::error!("Error while handling command: {}", why);
+                interaction
+                    .edit_response(ctx, EditInteractionResponse::new().embed(error_embed))
+                    .await
+                    .map(|_| ())
+            }
+        };
+
+        if let Err(why) = send_error_res {
+            tracing::error!("Error while sending error response: {}", why);
+        }
+    }
+
+    async fn handle_interaction_fallable(
+        self: &Arc<Self>,
+        ctx: &Context,
+        interaction: InteractionRef<'_>,
+    ) -> Result<(), HandleCommandError> {
+        let guild_id = interaction
+            .guild_id()
+            .ok_or(HandleCommandError::CreateError(
+                crate::error::Error::NoGuild,
+            ))?;
+        let message_channel_id = interaction.channel_id();
+
+        // This signal is used to cancel sending a "loading..." message when we finish executing
+        // the command.
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let send_deferred_message_future = async {
+            let show_deferred_message = futures::select!(
+                _ = rx.fuse() => false,
+                _ = tokio::time::sleep(Duration::from_millis(SEND_WORKING_TIMEOUT_MS)).fuse() => true,
+            );
+            if show_deferred_message {
+                if let Err(why) = interaction
+                    .create_response(
+                        ctx,
+                        CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+                    )
+                    .await
+                {
+                    tracing::error!("Error while sending deferred message: {}", why);
+                }
+            }
+        };
+
+        let send_future = async {
+            let guild_model_handle = self.model.get(guild_id);
+            let messages_res = match interaction {
+                InteractionRef::Command(command) => {
+                    let command = CommandInvocation::from_interaction(command);
+                    self.handle_guild_invocation(ctx, &command, guild_id, message_channel_id)
+                        .await
+                }
+                InteractionRef::Modal(modal) => {
+                    let command = CommandInvocation::from_modal(modal);
+                    self.handle_guild_invocation(ctx, &command, guild_id, message_channel_id)
+                        .await
+                }
+                InteractionRef::Component(component) => {
+                    let is_allowed = self
+                        .begin_guild_request(
+                            guild_id,
+                            &guild_model_handle,
+                            message_channel_id,
+                            interaction.user_id(),
+                            interaction.member(),
+                        )
+                        .await;
+                    match is_allowed {
+                        Ok(true) => {
+                            self.handle_guild_component(
+                                ctx,
+                                component,
+                                guild_id,
+                                &guild_model_handle,
+                            )
+                            .await
+                        }
+                        Ok(false) => Ok(vec![banned_user_message()]),
+                        Err(why) => Err(why),
+                    }
+                }
+            };
+
+            // If the timeout has finished, rx will be closed so this send call will return an
+            // error. We can use this to know that a response has been created, and we need to edit
+            // it from now on.
+            let has_sent_deferred = tx.send(()).is_err();
+            let messages = messages_res.map_err(if has_sent_deferred {
+                HandleCommandError::EditError
+            } else {
+                HandleCommandError::CreateError
+            })?;
+
+            let guild_config = self.guild_config(guild_id).await;
+            let locale = self.guild_locale(guild_id).await;
+            let send_res = send_messages(
+                &guild_config,
+                locale.as_deref(),
+                ctx,
+                SendMessageDestination::Interaction {
+                    interaction,
+                    is_edit: has_sent_deferred,
+                    is_ephemeral: match interaction {
+                        InteractionRef::Command(command) => {
+                            self.config().is_ephemeral_command(&command.data.name)
+                        }
+                        InteractionRef::Component(_) => self.config().ephemeral_responses,
+                        InteractionRef::Modal(modal) => {
+                            self.config().is_ephemeral_command(&modal.data.custom_id)
+                        }
+                    },
+                },
+                &guild_model_handle,
+                messages,
+            )
+            .await;
+            if let Err(why) = send_res {
+                tracing::error!("Error while sending response: {}", why);
+            }
+
+            // Most commands can change what's queued, so the song that plays next might have too.
+            self.clone().prefetch_next_songs(ctx.clone(), guild_id);
+
+            Ok(())
+        };
+
+        let (send_res, _) = futures::join!(send_future, send_deferred_message_future);
+        send_res
+    }
+
+    /// Records where and by whom the bot is being used. Returns false if the user is banned from
+    /// using the bot, in which case nothing should be run for them and the guild is left as it was.
+    async fn begin_guild_request(
+        &self,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        message_channel_id: ChannelId,
+        user_id: UserId,
+        maybe_member: Option<&Member>,
+    ) -> Result<bool, crate::error::Error> {
+        if self.is_user_banned(guild_id, user_id).await {
+            tracing::trace!("User is banned from using the bot, not running command");
+            return Ok(false);
+        }
+
+        // Keep the user's queue priority up to date in case their roles have changed.
+        let priority = self.member_priority(guild_id, maybe_member).await;
+        let mut guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        guild_model.set_message_channel(Some(message_channel_id));
+        guild_model.set_user_priority(user_id, priority);
+        Ok(true)
+    }
+
+    /// Runs a command, regardless of whether it came from a slash command or a prefix command.
+    async fn handle_guild_invocation(
+        self: &Arc<Self>,
+        ctx: &Context,
+        command: &CommandInvocation<'_>,
+        guild_id: GuildId,
+        message_channel_id: ChannelId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        // The guild is only locked while the model is being changed, so commands that wait on
+        // youtube-dl or Discord don't hold up everything else in the guild.
+        let guild_model_handle = self.model.get(guild_id);
+        let is_allowed = self
+            .begin_guild_request(
+                guild_id,
+                &guild_model_handle,
+                message_channel_id,
+                command.user_id,
+                command.member.as_ref(),
+            )
+            .await?;
+        if !is_allowed {
+            return Ok(vec![banned_user_message()]);
+        }
+        self.handle_guild_command(ctx, command, guild_id, &guild_model_handle)
+            .await
+    }
+
+    async fn handle_guild_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        command: &CommandInvocation<'_>,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let user_id = command.user_id;
+        if self
+            .disabled_commands(guild_id)
+            .await
+            .contains(&command.name)
+        {
+            tracing::trace!("Command is disabled in this guild, not running it");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::CommandDisabledError {
+                    command_name: command.name.clone(),
+                },
+                delegate: None,
+            }]);
+        }
+        let permission = self
+            .member_permission(guild_id, command.member.as_ref())
+            .await;
+        if !permission.can_use_command(&command.name) {
+            tracing::trace!("User doesn't have a DJ role, not running command");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::DjOnlyError,
+                delegate: None,
+            }]);
+        }
+        if let Some(cooldown) = self.config().get_command_cooldown(&command.name) {
+            if let Err(wait_time) = self.rate_limiter.check(user_id, &command.name, cooldown) {
+                tracing::trace!("User is on cooldown for this command, not running it");
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::CooldownError {
+                        command_name: command.name.clone(),
+                        wait_secs: wait_time.as_secs_f64().ceil() as u64,
+                    },
+                    delegate: None,
+                }]);
+            }
+        }
+
+        match command.name.as_str() {
+            "play" | "playmany" | "search" | "replace" | "remove" | "jump" | "wrong" | "undo"
+            | "queue" | "Queue this" => {
+                self.dispatch_queue_command(ctx, command, guild_id, guild_model_handle)
+                    .await
+            }
+            "resume" | "pause" | "skip" | "stop" | "votepause" | "restart" | "chapters"
+            | "chapter" | "rewind" | "previous" | "replay" | "grab" | "history" | "nowplaying" => {
+                self.dispatch_playback_command(
+                    ctx,
+                    command,
+                    guild_id,
+                    guild_model_handle,
+                    permission,
+                )
+                .await
+            }
+            "sfx" | "volume" | "speed" | "pitch" | "filter" | "eq" => {
+                self.dispatch_audio_command(ctx, command, guild_id, guild_model_handle)
+                    .await
+            }
+            "playlist" | "fav" => {
+                self.dispatch_playlist_command(ctx, command, guild_id, guild_model_handle)
+                    .await
+            }
+            "language" | "settings" | "dj" | "priority" | "botban" | "clearuser" | "commands"
+            | "block" => {
+                self.dispatch_settings_command(ctx, command, guild_id, guild_model_handle)
+                    .await
+            }
+            "admin" | "stats" => {
+                self.dispatch_admin_command(ctx, command, guild_id, guild_model_handle)
+                    .await
+            }
+            command_name => Err(crate::error::Error::UnknownCommand(
+                command_name.to_string(),
+            )),
+        }
+    }
+
+    async fn handle_guild_component(
+        self: &Arc<Self>,
+        ctx: &Context,
+        component: &ComponentInteraction,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let user_id = component.user.id;
+        let Some(action) = ComponentAction::parse(&component.data.custom_id) else {
+            return Err(crate::error::Error::UnknownCommand(
+                component.data.custom_id.clone(),
+            ));
+        };
+        let permission = self
+            .member_permission(guild_id, component.member.as_ref())
+            .await;
+        if !permission.can_use_command(action.command_name()) {
+            tracing::trace!("User doesn't have a DJ role, not running action");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::DjOnlyError,
+                delegate: None,
+            }]);
+        }
+
+        match action {
+            ComponentAction::SearchSelect(search_id) => {
+                let maybe_index = match &component.data.kind {
+                    ComponentInteractionDataKind::StringSelect { values } => {
+                        values.first().and_then(|value| value.parse::<usize>().ok())
+                    }
+                    _ => None,
+                };
+                tracing::debug!("Received search select {:?}", maybe_index);
+                self.handle_search_select(
+                    ctx,
+                    component,
+                    user_id,
+                    guild_id,
+                    guild_model_handle,
+                    search_id,
+                    maybe_index,
+                )
+                .await
+            }
+            ComponentAction::PlaylistSelect(choices_id) => {
+                let indices = match &component.data.kind {
+                    ComponentInteractionDataKind::StringSelect { values } => values
+                        .iter()
+                        .filter_map(|value| value.parse::<usize>().ok())
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                tracing::debug!("Received playlist select {:?}", indices);
+                self.handle_playlist_select(
+                    ctx,
+                    component,
+                    user_id,
+                    guild_id,
+                    guild_model_handle,
+                    choices_id,
+                    indices,
+                )
+                .await
+            }
+            ComponentAction::QueuePlaylist(confirm_id) => {
+                tracing::debug!("Received queue playlist button");
+                self.handle_playlist_confirm(
+                    ctx,
+                    component,
+                    user_id,
+                    guild_id,
+                    guild_model_handle,
+                    confirm_id,
+                )
+                .await
+            }
+            ComponentAction::QueueDuplicate(duplicate_id) => {
+                tracing::debug!("Received queue duplicate button");
+                self.handle_duplicate_confirm(
+                    ctx,
+                    component,
+                    user_id,
+                    guild_id,
+                    guild_model_handle,
+                    duplicate_id,
+                )
+                .await
+            }
+            ComponentAction::Pause => {
+                tracing::debug!("Received pause button");
+                self.handle_pause_command(ctx, user_id, guild_id).await
+            }
+            ComponentAction::Resume => {
+                tracing::debug!("Received resume button");
+                self.handle_unpause_command(
+                    ctx,
+                    user_id,
+                    guild_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?,
+                )
+                .await
+            }
+            ComponentAction::Skip => {
+                tracing::debug!("Received skip button");
+                self.handle_skip_command(
+                    ctx,
+                    user_id,
+                    guild_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                    permission.can_force_skip(),
+                )
+                .await
+            }
+            ComponentAction::Stop => {
+                tracing::debug!("Received stop button");
+                self.handle_stop_command(
+                    ctx,
+                    user_id,
+                    guild_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                    permission.can_force_skip(),
+                )
+                .await
+            }
+        }
+    }
+
+    /// Rejoins the channel and carries on with the song if playback ended because the voice
+    /// connection dropped out. Returns true if it's playing again, or false if playback should
+    /// move on as if the song had finished.
+    async fn resume_interrupted_playback(
+        self: &Arc<Self>,
+        ctx: &Context,
+        started_channel_id: ChannelId,
+        ended_handle: &GuildSpeakerEndedHandle,
+    ) -> bool {
+        // Checked first, so songs that finished normally don't wait before the next one plays
+        if ended_handle.lock().await.0.interrupted_channel_id.is_none() {
+            return false;
+        }
+
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        for attempt in 1..=RECONNECT_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+            if self.is_shutting_down() || ended_handle.is_speaker_stopped() {
+                return false;
+            }
+
+            let (state, speaker_ended_ref) = ended_handle.lock().await;
+            let (Some(channel_id), Some(metadata)) =
+                (state.interrupted_channel_id, &state.ended_metadata)
+            else {
+                return false;
+            };
+            tracing::info!(
+                "Voice connection dropped out, reconnecting (attempt {} of {})",
+                attempt,
+                RECONNECT_ATTEMPTS
+            );
+
+            let span = playback_span(ended_handle.guild_id(), channel_id, metadata);
+            let play_config = self.guild_config(ended_handle.guild_id()).await;
+            let resume_res = speaker_ended_ref
+                .resume(
+                    &play_config.get_play_config(),
+                    EndedDelegate {
+                        frontend: self.clone(),
+                        ctx: ctx.clone(),
+                        started_channel_id,
+                        span: span.clone(),
+                    },
+                )
+                .instrument(span)
+                .await;
+            match resume_res {
+                Ok(_) => return true,
+                Err((_, why)) => {
+                    tracing::warn!("Unable to resume playback after reconnecting: {}", why)
+                }
+            }
+        }
+
+        tracing::error!(
+            "Unable to reconnect after {} attempts, moving on",
+            RECONNECT_ATTEMPTS
+        );
+        false
+    }
+
+    async fn handle_playback_ended(
+        self: Arc<Self>,
+        ctx: Context,
+        started_channel_id: ChannelId,
+        ended_handle: GuildSpeakerEndedHandle,
+    ) {
+        if self.is_shutting_down() {
+            tracing::trace!("Playback has ended while shutting down, not playing anything else");
+            return;
+        }
+        if self
+            .resume_interrupted_playback(&ctx, started_channel_id, &ended_handle)
+            .await
+        {
+            return;
+        }
+        tracing::trace!("Playback has ended, preparing to play the next available song");
+
+        let guild_model_handle = self.model.get(ended_handle.guild_id());
+        let mut guild_model = match guild_model_handle.lock().await {
+            Ok(guild_model) => guild_model,
+            Err(why) => {
+                // Without the model there's no telling what should play next
+                tracing::error!("Error while continuing playback: {}", why);
+                ended_handle.lock().await.1.stop();
+                return;
+            }
+        };
+        let guild_config = self.guild_config(ended_handle.guild_id()).await;
+
+        // Without an announcements channel, messages go wherever the last command came from.
+        let maybe_message_channel = guild_config
+            .announcements_channel
+            .or(guild_model.message_channel());
+        let locale = self.guild_locale(ended_handle.guild_id()).await;
+
+        let (state, speaker_ended_ref) = ended_handle.lock().await;
+        let maybe_history_entries = state.ended_metadata.as_ref().map(|ended_metadata| {
+            guild_model.push_history_entry(
+                started_channel_id,
+                HistoryEntry {
+                    user_id: ended_metadata.user_id,
+                    title: ended_metadata.title.clone(),
+                    url: ended_metadata.url.clone(),
+                    ended_time: Timestamp::now(),
+                },
+            );
+            guild_model
+                .history_entries(started_channel_id)
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+
+        // Say why the song stopped early, before moving on to the next one
+        let maybe_failure_message = match (&state.failure, &state.ended_metadata) {
+            (Some(failure), Some(ended_metadata)) => {
+                tracing::error!("Playback of {} failed: {}", ended_metadata.url, failure);
+                Some(Message::Action {
+                    message: ActionMessage::PlaybackFailedError {
+                        song_title: ended_metadata.title.clone(),
+                        song_url: ended_metadata.url.clone(),
+                        reason: failure.to_string(),
+                    },
+                    voice_channel: started_channel_id,
+                    delegate: None,
+                })
+            }
+            _ => None,
+        };
+
+        // A speaker that's been stopped can't play anything else, so the channel is stopped the same
+        // way as if it had been disconnected.
+        let maybe_channel_id = state
+            .channel_id
+            .filter(|_| !ended_handle.is_speaker_stopped());
+        let messages = match maybe_channel_id {
+            Some(channel_id) => {
+                self.continue_channel_playback(
+                    &ctx,
+                    guild_model,
+                    started_channel_id,
+                    channel_id,
+                    state
+                        .ended_metadata
+                        .as_ref()
+                        .map(|ended_metadata| ended_metadata.url.as_str()),
+                    &ended_handle,
+                    speaker_ended_ref,
+                )
+                .await
+            }
+            None => {
+                // The speaker that played a song is no longer in a voice channel. Interpret
+                // this as a forced stop command, instead of just trying to play the next song.
+                guild_model.set_channel_stopped(started_channel_id);
+                drop(guild_model);
+                speaker_ended_ref.stop();
+                match state.ended_metadata {
+                    Some(active_metadata) => Ok(vec![Message::Action {
+                        message: ActionMessage::Stopped {
+                            song_title: active_metadata.title.clone(),
+                            song_url: active_metadata.url.clone(),
+                            user_id: active_metadata.user_id,
+                        },
+                        voice_channel: started_channel_id,
+                        delegate: None,
+                    }]),
+                    None => Ok(Vec::new()),
+                }
+            }
+        };
+        let messages = messages.map(|messages| {
+            maybe_failure_message
+                .into_iter()
+                .chain(messages)
+                .collect::<Vec<_>>()
+        });
+
+        let destination = |message_channel| {
+            if guild_config.session_threads {
+                SendMessageDestination::SessionThread {
+                    channel_id: message_channel,
+                    voice_channel_id: started_channel_id,
+                }
+            } else {
+                SendMessageDestination::Channel(message_channel)
+            }
+        };
+        let is_session_finished = match &messages {
+            Ok(messages) => messages.iter().any(Message::is_finished),
+            Err(_) => false,
+        };
+
+        // Messages are sent and history is saved without the guild locked, so other commands
+        // aren't held up by Discord or storage
+        if let Some(history_entries) = maybe_history_entries {
+            let save_result = self
+                .history
+                .save_channel(ended_handle.guild_id(), started_channel_id, history_entries)
+                .await;
+            if let Err(why) = save_result {
+                tracing::error!("Error while saving history: {}", why);
+            }
+        }
+
+        let send_result = match (messages, maybe_message_channel) {
+            (Ok(messages), Some(message_channel)) => {
+                send_messages(
+                    &guild_config,
+                    locale.as_deref(),
+                    &ctx,
+                    destination(message_channel),
+                    &guild_model_handle,
+                    messages,
+                )
+                .await
+            }
+            (Err(why), Some(message_channel)) => {
+                tracing::error!("Error while continuing playback: {}", why);
+                send_messages(
+                    &guild_config,
+                    locale.as_deref(),
+                    &ctx,
+                    destination(message_channel),
+                    &guild_model_handle,
+                    vec![Message::Action {
+                        message: ActionMessage::UnknownError,
+                        voice_channel: started_channel_id,
+                        delegate: None,
+                    }],
+                )
+                .await
+            }
+            (Err(why), _) => Err(why),
+            (_, None) => Ok(()),
+        };
+
+        // The next song played in the channel will start a new session, with its own thread.
+        if is_session_finished {
+            match guild_model_handle.lock().await {
+                Ok(mut guild_model) => guild_model.set_session_thread(started_channel_id, None),
+                Err(why) => tracing::error!("Error while finishing session: {}", why),
+            }
+        } else {
+            self.clone()
+                .prefetch_next_songs(ctx.clone(), ended_handle.guild_id());
+        }
+
+        if let Err(why) = send_result {
+            tracing::error!("Error while continuing playback: {}", why);
+        }
+    }
+
+    /// Plays whatever comes next in a channel after a song has ended. The guild is only locked
+    /// while choosing what to play, and is unlocked while the song is opened or related songs are
+    /// looked up, so other commands aren't held up meanwhile.
+    #[allow(clippy::too_many_arguments)]
+    async fn continue_channel_playback<'handle>(
+        self: &Arc<Self>,
+        ctx: &Context,
+        mut guild_model: GuildModelGuard<QueuedSong>,
+        started_channel_id: ChannelId,
+        current_channel_id: ChannelId,
+        maybe_ended_url: Option<&str>,
+        ended_handle: &'handle GuildSpeakerEndedHandle,
+        mut speaker_ended_ref: GuildSpeakerEndedRef<'handle>,
+    ) -> Result<Vec<Message>, crate::error::Error> {
+        let guild_id = guild_model.guild_id();
+
+        // If the speaker has moved channels, simply indicate the original channel as stopped and
+        // do not play anything in the new channel. This ensures we follow the behavior of not
+        // playing songs until the user instructs the bot to. Speakers that moved in follow mode
+        // brought the channel's state along with them, so they carry on in the new channel.
+        let has_followed = guild_model
+            .get_channel_playing_user(started_channel_id)
+            .is_none()
+            && guild_model
+                .get_channel_playing_user(current_channel_id)
+                .is_some();
+        if started_channel_id != current_channel_id && !has_followed {
+            tracing::trace!("Speaker has switched channel, not playing any more songs.");
+            guild_model.set_channel_stopped(started_channel_id);
+            speaker_ended_ref.stop();
+            return Ok(Vec::new());
+        }
+
+        // Playing a song can fail - keep trying to play until we succeed or run out of songs
+        loop {
+            // Don't play anything more if the channel was stopped.
+            if guild_model.is_channel_stopped(current_channel_id) {
+                tracing::trace!("Channel has been stopped, not playing any more songs.");
+                speaker_ended_ref.stop();
+                return Ok(Vec::new());
+            }
+
+            let Some(next_song) =
+                guild_model.next_channel_entry_finished(&ctx.cache, current_channel_id)
+            else {
+                break;
+            };
+            let skip_votes =
+                guild_model.skip_vote_count(&ctx.cache, VoteType::Skip, current_channel_id);
+            drop(guild_model);
+
+            let next_metadata = next_song.song.metadata.clone();
+            let span = playback_span(guild_id, current_channel_id, &next_metadata);
+            span.in_scope(|| tracing::trace!("Playing \"{}\" to speaker", next_metadata.title));
+
+            let play_res = speaker_ended_ref
+                .play(
+                    next_song.song,
+                    &self.guild_config(guild_id).await.get_play_config(),
+                    EndedDelegate {
+                        frontend: self.clone(),
+                        ctx: ctx.clone(),
+                        started_channel_id: current_channel_id,
+                        span: span.clone(),
+                    },
+                )
+                .instrument(span)
+                .await;
+
+            self.clone().update_queued_message(
+                ctx.clone(),
+                guild_id,
+                current_channel_id,
+                next_song.queue_message_id,
+                next_metadata.clone(),
+            );
+
+            match play_res {
+                Ok(guild_speaker) => {
+                    return Ok(vec![
+                        build_playing_message(
+                            self.clone(),
+                            &guild_speaker,
+                            false,
+                            current_channel_id,
+                            next_metadata,
+                            skip_votes,
+                        )
+                        .await,
+                    ])
+                }
+                Err((failed_ref, why)) => {
+                    tracing::error!("Error while continuing playback: {}", why);
+
+                    // The speaker is released before the guild is locked again, since the guild is
+                    // always locked first.
+                    drop(failed_ref);
+                    guild_model = self
+                        .model
+                        .get(guild_id)
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?;
+                    let (state, new_ref) = ended_handle.lock().await;
+                    speaker_ended_ref = new_ref;
+
+                    // The speaker could have been used to play somewhere else meanwhile
+                    if state.channel_id != Some(current_channel_id)
+                        || ended_handle.is_speaker_stopped()
+                    {
+                        tracing::trace!(
+                            "Speaker has been taken elsewhere, not playing any more songs."
+                        );
+                        guild_model.set_channel_stopped(current_channel_id);
+                        return Ok(Vec::new());
+                    }
+                }
+            }
+        }
+
+        let guild_config = self.guild_config(guild_id).await;
+        let bot_user_id = ctx.cache.current_user().id;
+
+        // Songs that weren't queued by anyone are attributed to the bot itself. The channel is
+        // marked as playing before the guild is unlocked, so nothing else starts playing in it
+        // while a song is found.
+        let maybe_autoplay_url = maybe_ended_url.filter(|_| guild_config.autoplay);
+        let is_playing_unqueued = maybe_autoplay_url.is_some() || guild_config.always_on;
+        let played_urls: Vec<_> = guild_model
+            .history_entries(current_channel_id)
+            .map(|entry| entry.url.clone())
+            .collect();
+        let skip_votes =
+            guild_model.skip_vote_count(&ctx.cache, VoteType::Skip, current_channel_id);
+        if is_playing_unqueued {
+            guild_model.set_channel_playing(current_channel_id, bot_user_id);
+        }
+        drop(guild_model);
+
+        // With autoplay on, a song related to the one that just finished is played next.
+        if let Some(ended_url) = maybe_autoplay_url {
+            let related_res = Song::fetch_related(
+                ended_url,
+                &played_urls,
+                bot_user_id,
+                &guild_config.get_play_config(),
+            )
+            .await;
+            match related_res {
+                Ok(Some(related_song)) => {
+                    tracing::trace!(
+                        "No songs are queued, autoplaying related song \"{}\"",
+                        related_song.metadata.title
+                    );
+                    match self
+                        .play_unqueued_song(
+                            ctx,
+                            guild_id,
+                            current_channel_id,
+                            speaker_ended_ref,
+                            related_song,
+                            &guild_config,
+                            skip_votes,
+                        )
+                        .await
+                    {
+                        Ok(messages) => return Ok(messages),
+                        Err(new_ref) => speaker_ended_ref = new_ref,
+                    }
+                }
+                Ok(None) => tracing::trace!("No related songs were found to autoplay"),
+                Err(why) => tracing::error!("Error while finding a related song: {}", why),
+            }
+        }
+
+        // In 24/7 mode the guild's idle playlist is played instead of stopping.
+        if guild_config.always_on {
+            let maybe_idle_song = self
+                .idle_songs
+                .next_song(&self.playlists, guild_id, &guild_config, bot_user_id)
+                .await;
+            if let Some(idle_song) = maybe_idle_song {
+                tracing::trace!(
+                    "No songs are queued, playing \"{}\" from the idle playlist",
+                    idle_song.metadata.title
+                );
+                match self
+                    .play_unqueued_song(
+                        ctx,
+                        guild_id,
+                        current_channel_id,
+                        speaker_ended_ref,
+                        idle_song,
+                        &guild_config,
+                        skip_votes,
+                    )
+                    .await
+                {
+                    Ok(messages) => return Ok(messages),
+                    Err(new_ref) => speaker_ended_ref = new_ref,
+                }
+            }
+        }
+
+        tracing::trace!("No songs are available to play in the channel, nothing will be played");
+        speaker_ended_ref.stop();
+        if is_playing_unqueued {
+            // Unless something else has happened in the channel since it was marked as playing
+            let mut guild_model = self
+                .model
+                .get(guild_id)
+                .lock()
+                .await
+                .map_err(crate::error::Error::ModelLock)?;
+            if guild_model.get_channel_playing_user(current_channel_id) == Some(bot_user_id) {
+                guild_model.set_channel_not_playing(current_channel_id);
+            }
+        }
+        Ok(vec![Message::Action {
+            message: ActionMessage::Finished,
+            voice_channel: current_channel_id,
+            delegate: None,
+        }])
+    }
+
+    /// Plays a song that wasn't queued by anyone, like one picked by autoplay or 24/7 mode. The
+    /// channel needs to have already been marked as playing it.
+    #[allow(clippy::too_many_arguments)]
+    async fn play_unqueued_song<'handle>(
+        self: &Arc<Self>,
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        speaker_ended_ref: GuildSpeakerEndedRef<'handle>,
+        song: Song,
+        guild_config: &Config,
+        skip_votes: VoteCount,
+    ) -> Result<Vec<Message>, GuildSpeakerEndedRef<'handle>> {
+        let metadata = song.metadata.clone();
+        let span = playback_span(guild_id, channel_id, &metadata);
+        let play_res = speaker_ended_ref
+            .play(
+                song,
+                &guild_config.get_play_config(),
+                EndedDelegate {
+                    frontend: self.clone(),
+                    ctx: ctx.clone(),
+                    started_channel_id: channel_id,
+                    span: span.clone(),
+                },
+            )
+            .instrument(span)
+            .await;
+
+        match play_res {
+            Ok(guild_speaker) => Ok(vec![
+                build_playing_message(
+                    self.clone(),
+                    &guild_speaker,
+                    false,
+                    channel_id,
+                    metadata,
+                    skip_votes,
+                )
+                .await,
+            ]),
+            Err((new_ref, why)) => {
+                tracing::error!("Error while playing unqueued song: {}", why);
+                Err(new_ref)
+            }
+        }
+    }
+
+    /// Plays a song that was taken from the queues. Opening the song's stream and joining the
+    /// channel can take a while, so the guild is unlocked first. The channel is already marked as
+    /// playing, so nothing else will try to play in it meanwhile.
+    ///
+    /// The guild is always locked before its speakers, so it can't be locked again until the
+    /// speaker has been released. If playing fails, the caller needs to release the speaker and
+    /// then mark the channel as stopped.
+    async fn play_to_speaker(
+        self: &Arc<Self>,
+        ctx: &Context,
+        guild_model: GuildModelGuard<QueuedSong>,
+        guild_speaker: &mut GuildSpeakerRef<'_>,
+        channel_id: ChannelId,
+        queued_song: QueuedSong,
+    ) -> Result<(), crate::error::Error> {
+        let guild_id = guild_model.guild_id();
+        drop(guild_model);
+
+        let metadata = queued_song.song.metadata.clone();
+        let span = playback_span(guild_id, channel_id, &metadata);
+        span.in_scope(|| tracing::trace!("Playing \"{}\" to speaker", metadata.title));
+
+        let guild_config = self.guild_config(guild_id).await;
+        let play_res = guild_speaker
+            .play(
+                channel_id,
+                queued_song.song,
+                &guild_config.get_play_config(),
+                EndedDelegate {
+                    frontend: self.clone(),
+                    ctx: ctx.clone(),
+                    started_channel_id: channel_id,
+                    span: span.clone(),
+                },
+            )
+            .instrument(span)
+            .await;
+
+        self.clone().update_queued_message(
+            ctx.clone(),
+            guild_id,
+            channel_id,
+            queued_song.queue_message_id,
+            metadata,
+        );
+
+        play_res.map_err(crate::error::Error::Backend)
+    }
+
+    /// Marks a channel as stopped after a song couldn't be played in it. The speaker that tried to
+    /// play it needs to have been released first.
+    async fn set_play_failed(&self, guild_id: GuildId, channel_id: ChannelId) {
+        match self.model.get(guild_id).lock().await {
+            Ok(mut guild_model) => guild_model.set_channel_stopped(channel_id),
+            Err(why) => tracing::error!("Error while stopping channel: {}", why),
+        }
+    }
+
+    fn update_queued_message(
+        self: Arc<Self>,
+        ctx: Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        queue_message_id: Option<(ChannelId, MessageId)>,
+        metadata: SongMetadata,
+    ) {
+        if let Some((queue_channel_id, queue_message_id)) = queue_message_id {
+            let new_message = ActionMessage::Played {
+                song_title: metadata.title,
+                song_url: metadata.url,
+            };
+
+            tokio::task::spawn(async move {
+                let guild_config = self.guild_config(guild_id).await;
+                let locale = self.guild_locale(guild_id).await;
+                let maybe_err = queue_channel_id
+                    .edit_message(
+                        ctx,
+                        queue_message_id,
+                        EditMessage::new()
+                            .embed(new_message.create_embed(
+                                &guild_config,
+                                locale.as_deref(),
+                                channel_id,
+                            ))
+                            .components(
+                                new_message.create_components(&guild_config, locale.as_deref()),
+                            ),
+                    )
+                    .await;
+
+                if let Err(why) = maybe_err {
+                    tracing::error!("Error while updating queue message: {}", why);
+                }
+            });
+        }
+    }
+
+    /// Starts loading the song that will play next in each channel of a guild, so it can start
+    /// straight away when the current one ends. This is called whenever the queues might have
+    /// changed, and only does anything if the next song is different from what's been loaded.
+    fn prefetch_next_songs(self: Arc<Self>, ctx: Context, guild_id: GuildId) {
+        if !self.config().prefetch_songs {
+            return;
+        }
+
+        tokio::task::spawn(async move {
+            let guild_config = self.guild_config(guild_id).await;
+            let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+
+            let to_prefetch = {
+                let guild_model_handle = self.model.get(guild_id);
+                let guild_model = match guild_model_handle.lock().await {
+                    Ok(guild_model) => guild_model,
+                    Err(why) => {
+                        tracing::warn!("Error while prefetching songs: {}", why);
+                        return;
+                    }
+                };
+                let mut guild_speakers_ref = guild_speakers_handle.lock();
+
+                let mut to_prefetch = Vec::new();
+                for guild_speaker in guild_speakers_ref.lock_all().await {
+                    let Some(channel_id) = guild_speaker
+                        .current_channel()
+                        .filter(|_| guild_speaker.is_active())
+                    else {
+                        continue;
+                    };
+
+                    let maybe_next_song = guild_model
+                        .peek_channel_entry(&ctx.cache, channel_id)
+                        .map(|entry| &entry.song);
+                    match maybe_next_song {
+                        Some(next_song)
+                            if guild_speaker.prefetched_song_id()
+                                != Some(next_song.metadata.id) =>
+                        {
+                            to_prefetch.push((channel_id, next_song.clone()));
+                        }
+                        Some(_) => {}
+                        None => guild_speaker.set_prefetched(None),
+                    }
+                }
+                to_prefetch
+            };
+
+            // Loading happens without anything locked, since it waits on the network.
+            let filters = self.backend_brain.guild_filters(guild_id);
+            for (channel_id, song) in to_prefetch {
+                tracing::trace!("Prefetching \"{}\"", song.metadata.title);
+                let prefetched =
+                    match PrefetchedInput::load(&song, &guild_config.get_play_config(), &filters)
+                        .await
+                    {
+                        Ok(prefetched) => prefetched,
+                        Err(why) => {
+                            tracing::warn!("Error while prefetching song: {}", why);
+                            continue;
+                        }
+                    };
+
+                let mut guild_speakers_ref = guild_speakers_handle.lock();
+                if let Some((guild_speaker, _)) =
+                    guild_speakers_ref.find_active_in_channel(channel_id).await
+                {
+                    guild_speaker.set_prefetched(Some(prefetched));
+                }
+            }
+        });
+    }
+}
+
+/// A span for everything done while handling one command or button press, so logs from many
+/// guilds at once can be told apart.
+fn interaction_span(interaction: InteractionRef<'_>, name: &str) -> tracing::Span {
+    tracing::info_span!(
+        "interaction",
+        name,
+        guild_id = interaction.guild_id().map(|guild_id| guild_id.get()),
+        channel_id = interaction.channel_id().get(),
+        user_id = interaction.user_id().get(),
+    )
+}
+
+/// A span for one song playing, from when it starts until whatever plays after it has been chosen.
+fn playback_span(
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    metadata: &SongMetadata,
+) -> tracing::Span {
+    tracing::info_span!(
+        "playback",
+        guild_id = guild_id.get(),
+        channel_id = channel_id.get(),
+        song_id = %metadata.id,
+    )
+}
+
+struct EndedDelegate {
+    frontend: Arc<Frontend>,
+    ctx: Context,
+    started_channel_id: ChannelId,
+    span: tracing::Span,
+}
+
+impl EndedHandler for EndedDelegate {
+    fn on_ended(self, ended_handle: GuildSpeakerEndedHandle) {
+        tokio::task::spawn(
+            self.frontend
+                .handle_playback_ended(self.ctx, self.started_channel_id, ended_handle)
+                .instrument(self.span),
+        );
+    }
+}
+
+/// Resolves each line as a song link or search term, one after another. Songs from playlists are
+/// streamed as they're found, and lines that can't be resolved are skipped.
+fn load_lines(lines: Vec<String>, user_id: UserId, guild_config: Arc<Config>) -> RemainingSongs {
+    stream::iter(lines)
+        .then(move |line| {
+            let guild_config = guild_config.clone();
+            async move {
+                match Song::load_streaming(&line, user_id, &guild_config.get_play_config()).await {
+                    Ok(loaded) => {
+                        let songs = stream::iter(loaded.songs.into_iter().map(Ok));
+                        match loaded.remaining {
+                            Some(remaining) => songs.chain(remaining).boxed(),
+                            None => songs.boxed(),
+                        }
+                    }
+                    Err(why) => {
+                        tracing::warn!("Error while resolving line \"{}\": {}", line, why);
+                        stream::empty().boxed()
+                    }
+                }
+            }
+        })
+        .flatten()
+        .filter(|song_res| {
+            if let Err(why) = song_res {
+                tracing::warn!("Error while resolving the rest of a playlist: {}", why);
+            }
+            future::ready(song_res.is_ok())
+        })
+        .boxed()
+}
+
+/// Finds the links in a message's content and embeds, in the order they appear.
+fn extract_message_urls(message: &serenity::model::channel::Message) -> Vec<String> {
+    lazy_static::lazy_static! {
+        static ref URL_REGEX: regex::Regex =
+            regex::Regex::new(r"https?://[^\s<>()\[\]]+").unwrap();
+    }
+
+    let embed_urls = message
+        .embeds
+        .iter()
+        .filter_map(|embed| embed.url.as_deref());
+    let mut urls: Vec<String> = Vec::new();
+    for url in URL_REGEX
+        .find_iter(&message.content)
+        .map(|url_match| url_match.as_str())
+        .chain(embed_urls)
+    {
+        if !urls.iter().any(|existing_url| existing_url == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
+}
+
+/// Reads a length of time like `90`, `2m`, `1m30s` or `1:30`. Plain numbers are seconds.
+fn parse_duration(value: &str) -> Option<Duration> {
+    lazy_static::lazy_static! {
+        static ref UNITS_REGEX: regex::Regex =
+            regex::Regex::new(r"^(?:(\d+)h)?\s*(?:(\d+)m)?\s*(?:(\d+)s)?$").unwrap();
+    }
+
+    let value = value.trim();
+    if value.contains(':') {
+        let secs = value.split(':').try_fold(0u64, |total, part| {
+            total.checked_mul(60)?.checked_add(part.parse().ok()?)
+        })?;
+        return Some(Duration::from_secs(secs));
+    }
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let captures = UNITS_REGEX.captures(value)?;
+    let mut secs = 0u64;
+    let mut has_unit = false;
+    for (index, unit_secs) in [(1, 60 * 60), (2, 60), (3, 1)] {
+        if let Some(unit_match) = captures.get(index) {
+            let count: u64 = unit_match.as_str().parse().ok()?;
+            secs = secs.checked_add(count.checked_mul(unit_secs)?)?;
+            has_unit = true;
+        }
+    }
+    has_unit.then(|| Duration::from_secs(secs))
+}
+
+fn banned_user_message() -> Message {
+    Message::Response {
+        message: ResponseMessage::BannedUserError,
+        delegate: None,
+    }
+}
+
+fn get_subcommand<'command>(
+    command: &'command CommandInvocation<'_>,
+) -> Option<(&'command str, &'command [CommandDataOption])> {
+    let option = command.options.first()?;
+    match &option.value {
+        CommandDataOptionValue::SubCommand(options) => Some((&option.name, options)),
+        _ => None,
+    }
+}
+
+/// Reads a position in the user's queue, counting from 1. Anything missing or negative is read
+/// as 0, which is never a valid position.
+fn get_position_option(command: &CommandInvocation<'_>) -> usize {
+    command
+        .options
+        .iter()
+        .find(|option| option.name == "position")
+        .and_then(|option| option.value.as_i64())
+        .and_then(|position| usize::try_from(position).ok())
+        .unwrap_or_default()
+}
+
+fn get_string_option<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.value.as_str())
+}
+
+fn get_bool_option(options: &[CommandDataOption], name: &str) -> Option<bool> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.value.as_bool())
+}
+
+fn get_user_voice_channel(
+    cache: &serenity::cache::Cache,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Option<ChannelId> {
+    let guild = cache.guild(guild_id)?;
+    let voice_state = guild.voice_states.get(&user_id)?;
+    voice_state.channel_id
+}
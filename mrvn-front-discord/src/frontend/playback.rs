@@ -0,0 +1,1108 @@
+use crate::frontend::{
+    get_string_option, get_user_voice_channel, parse_duration, Frontend, QueuedSongsMetadata,
+};
+use crate::invocation::CommandInvocation;
+use crate::message::{
+    send_direct_message, ActionMessage, ChapterMessageEntry, HistoryMessageEntry, Message,
+    ResponseMessage,
+};
+use crate::permissions::MemberPermission;
+use crate::playing_message::{build_playing_message, SkipVotesChanged};
+use crate::queued_song::QueuedSong;
+use mrvn_back_ytdl::Song;
+use mrvn_model::{GuildModel, GuildModelGuard, GuildModelHandle, NextEntry, VoteStatus, VoteType};
+use serenity::model::id::ChannelId;
+use serenity::{
+    model::prelude::{GuildId, UserId},
+    prelude::*,
+};
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::time::Duration;
+
+impl Frontend {
+    /// Commands that control the song playing in the user's channel.
+    pub(super) async fn dispatch_playback_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        command: &CommandInvocation<'_>,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        permission: MemberPermission,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let user_id = command.user_id;
+        match command.name.as_str() {
+            "resume" => {
+                tracing::debug!("Received resume");
+                self.handle_unpause_command(
+                    ctx,
+                    user_id,
+                    guild_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?,
+                )
+                .await
+            }
+            "pause" => {
+                tracing::debug!("Received pause");
+                self.handle_pause_command(ctx, user_id, guild_id).await
+            }
+            "skip" => {
+                tracing::debug!("Received skip");
+                self.handle_skip_command(
+                    ctx,
+                    user_id,
+                    guild_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                    permission.can_force_skip(),
+                )
+                .await
+            }
+            "stop" => {
+                tracing::debug!("Received stop");
+                self.handle_stop_command(
+                    ctx,
+                    user_id,
+                    guild_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                    permission.can_force_skip(),
+                )
+                .await
+            }
+            "votepause" => {
+                tracing::debug!("Received vote pause");
+                self.handle_vote_pause_command(
+                    ctx,
+                    user_id,
+                    guild_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                    permission.can_force_skip(),
+                )
+                .await
+            }
+            "restart" => {
+                tracing::debug!("Received restart");
+                self.handle_restart_command(ctx, user_id, guild_id).await
+            }
+            "chapters" => {
+                tracing::debug!("Received chapters");
+                self.handle_chapters_command(ctx, user_id, guild_id).await
+            }
+            "chapter" => {
+                let number = command
+                    .options
+                    .iter()
+                    .find(|option| option.name == "number")
+                    .and_then(|option| option.value.as_i64())
+                    .and_then(|number| usize::try_from(number).ok())
+                    .unwrap_or(1);
+                tracing::debug!("Received chapter {}", number);
+                self.handle_chapter_command(ctx, user_id, guild_id, number)
+                    .await
+            }
+            "rewind" => {
+                let time = get_string_option(&command.options, "time").unwrap_or_default();
+                tracing::debug!("Received rewind {}", time);
+                self.handle_rewind_command(ctx, user_id, guild_id, time)
+                    .await
+            }
+            "previous" => {
+                tracing::debug!("Received previous");
+                self.handle_previous_command(ctx, user_id, guild_id, guild_model_handle)
+                    .await
+            }
+            "replay" => {
+                tracing::debug!("Received replay");
+                self.handle_replay_command(ctx, user_id, guild_id, guild_model_handle)
+                    .await
+            }
+            "grab" => {
+                tracing::debug!("Received grab");
+                self.handle_grab_command(ctx, user_id, guild_id).await
+            }
+            "history" => {
+                tracing::debug!("Received history");
+                self.handle_history_command(
+                    ctx,
+                    user_id,
+                    guild_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                )
+            }
+            "nowplaying" => {
+                let maybe_channel_id = command
+                    .options
+                    .iter()
+                    .find(|option| option.name == "channel")
+                    .and_then(|option| option.value.as_channel_id());
+                tracing::debug!("Received nowplaying");
+                self.handle_nowplaying_command(
+                    ctx,
+                    user_id,
+                    guild_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                    maybe_channel_id,
+                )
+                .await
+            }
+            command_name => Err(crate::error::Error::UnknownCommand(
+                command_name.to_string(),
+            )),
+        }
+    }
+
+    pub(super) async fn handle_unpause_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        mut guild_model: GuildModelGuard<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        // See if there's currently a speaker in this channel to unpause.
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        if let Some((guild_speaker, active_metadata)) =
+            guild_speakers_ref.find_active_in_channel(channel_id).await
+        {
+            return if guild_speaker.is_paused() {
+                tracing::trace!(
+                    "Found a paused speaker in the user's voice channel, starting playback"
+                );
+                guild_speaker
+                    .unpause()
+                    .map_err(crate::error::Error::Backend)?;
+                Ok(vec![
+                    build_playing_message(
+                        self.clone(),
+                        guild_speaker,
+                        false,
+                        channel_id,
+                        active_metadata,
+                        guild_model.skip_vote_count(&ctx.cache, VoteType::Skip, channel_id),
+                    )
+                    .await,
+                ])
+            } else {
+                tracing::trace!(
+                    "Found an unpaused speaker in the user's voice channel, playback will continue"
+                );
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::AlreadyPlayingError {
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            };
+        };
+
+        // Otherwise, try starting to play in this channel.
+        let guild_speaker = match guild_speakers_ref.find_to_play_in_channel(channel_id).await {
+            Some(speaker) => speaker,
+            None => {
+                tracing::trace!(
+                    "No speakers are available to handle playback, nothing will be played"
+                );
+                return Ok(vec![Message::Action {
+                    message: ActionMessage::NoSpeakersError,
+                    voice_channel: channel_id,
+                    delegate: None,
+                }]);
+            }
+        };
+        let next_song = match guild_model.next_channel_entry(&ctx.cache, channel_id) {
+            NextEntry::Entry(song) => song,
+            NextEntry::AlreadyPlaying | NextEntry::NoneAvailable => {
+                tracing::trace!(
+                    "No songs are available to play back in the channel, nothing will be played"
+                );
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::NothingIsQueuedError {
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }]);
+            }
+        };
+
+        let next_metadata = next_song.song.metadata.clone();
+        let skip_votes = guild_model.skip_vote_count(&ctx.cache, VoteType::Skip, channel_id);
+        let play_res = self
+            .play_to_speaker(ctx, guild_model, guild_speaker, channel_id, next_song)
+            .await;
+        if let Err(why) = play_res {
+            drop(guild_speakers_ref);
+            self.set_play_failed(guild_id, channel_id).await;
+            return Err(why);
+        }
+
+        Ok(vec![
+            build_playing_message(
+                self.clone(),
+                guild_speaker,
+                false,
+                channel_id,
+                next_metadata,
+                skip_votes,
+            )
+            .await,
+        ])
+    }
+
+    async fn handle_restart_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        match guild_speakers_ref.find_active_in_channel(channel_id).await {
+            Some((guild_speaker, active_metadata)) => {
+                tracing::trace!("Found a speaker in the user's voice channel, restarting playback");
+                guild_speaker
+                    .restart(&self.guild_config(guild_id).await.get_play_config())
+                    .await
+                    .map_err(crate::error::Error::Backend)?;
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::Restarted {
+                        song_title: active_metadata.title,
+                        song_url: active_metadata.url,
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            }
+            None => {
+                tracing::trace!(
+                    "No speakers are in the user's voice channel, playback will not change"
+                );
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::NothingIsPlayingError {
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            }
+        }
+    }
+
+    async fn handle_chapters_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        let Some((_, active_metadata)) =
+            guild_speakers_ref.find_active_in_channel(channel_id).await
+        else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        };
+
+        if active_metadata.chapters.is_empty() {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoChaptersError {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                },
+                delegate: None,
+            }]);
+        }
+
+        let chapters = active_metadata
+            .chapters
+            .into_iter()
+            .map(|chapter| ChapterMessageEntry {
+                title: chapter.title,
+                start_seconds: chapter.start_seconds,
+            })
+            .collect();
+        Ok(vec![Message::Response {
+            message: ResponseMessage::Chapters {
+                song_title: active_metadata.title,
+                song_url: active_metadata.url,
+                chapters,
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_chapter_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        number: usize,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        let Some((guild_speaker, active_metadata)) =
+            guild_speakers_ref.find_active_in_channel(channel_id).await
+        else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        };
+
+        if active_metadata.chapters.is_empty() {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoChaptersError {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                },
+                delegate: None,
+            }]);
+        }
+        let Some(chapter) = number
+            .checked_sub(1)
+            .and_then(|index| active_metadata.chapters.get(index))
+        else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::UnknownChapterError {
+                    position: number,
+                    count: active_metadata.chapters.len(),
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                },
+                delegate: None,
+            }]);
+        };
+
+        tracing::trace!(
+            "Jumping to chapter \"{}\" at {}s",
+            chapter.title,
+            chapter.start_seconds
+        );
+        match guild_speaker
+            .seek(Duration::from_secs_f64(chapter.start_seconds.max(0.)))
+            .await
+        {
+            Ok(()) => Ok(vec![Message::Response {
+                message: ResponseMessage::ChapterJumped {
+                    chapter_title: chapter.title.clone(),
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]),
+            Err(mrvn_back_ytdl::Error::NotSeekable) => Ok(vec![Message::Response {
+                message: ResponseMessage::ChapterNotSeekableError {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                },
+                delegate: None,
+            }]),
+            Err(why) => Err(crate::error::Error::Backend(why)),
+        }
+    }
+
+    async fn handle_rewind_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        time: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(amount) = parse_duration(time) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::InvalidDurationError {
+                    value: time.to_string(),
+                },
+                delegate: None,
+            }]);
+        };
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        let Some((guild_speaker, active_metadata)) =
+            guild_speakers_ref.find_active_in_channel(channel_id).await
+        else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        };
+
+        tracing::trace!("Rewinding {}s", amount.as_secs_f64());
+        let guild_config = self.guild_config(guild_id).await;
+        match guild_speaker
+            .rewind(amount, &guild_config.get_play_config())
+            .await
+        {
+            Ok(()) => Ok(vec![Message::Response {
+                message: ResponseMessage::Rewound {
+                    amount_seconds: amount.as_secs_f64(),
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]),
+            Err(mrvn_back_ytdl::Error::NotSeekable) => Ok(vec![Message::Response {
+                message: ResponseMessage::RewindNotSeekableError {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                },
+                delegate: None,
+            }]),
+            Err(why) => Err(crate::error::Error::Backend(why)),
+        }
+    }
+
+    async fn handle_previous_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let maybe_history_entry = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?
+            .last_history_entry(channel_id)
+            .cloned();
+        let Some(history_entry) = maybe_history_entry else {
+            tracing::trace!("Nothing has finished playing in the user's voice channel");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoPreviousSongError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        };
+
+        // The download URL of the previous song has probably expired, so resolve it again.
+        let guild_config = self.guild_config(guild_id).await;
+        let play_config = guild_config.get_play_config();
+        let song = match Song::fetch_one(&history_entry.url, user_id, &play_config).await {
+            Ok(song) => song,
+            Err(mrvn_back_ytdl::Error::UnsupportedUrl) => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::NoMatchingSongsError,
+                    delegate: None,
+                }]);
+            }
+            Err(mrvn_back_ytdl::Error::LoginRequired(why)) => {
+                tracing::debug!("Song needs a logged in account: {}", why);
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::LoginRequiredError,
+                    delegate: None,
+                }]);
+            }
+            Err(why) => return Err(crate::error::Error::Backend(why)),
+        };
+
+        tracing::trace!(
+            "Resolved previous song as {} (\"{}\")",
+            song.metadata.url,
+            song.metadata.title
+        );
+        let metadata = QueuedSongsMetadata::Single(song.metadata.clone());
+        let mut guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        guild_model.push_entry_front(
+            user_id,
+            QueuedSong {
+                song,
+                queue_message_id: None,
+            },
+        );
+
+        self.play_queued_songs(ctx, user_id, guild_id, guild_model, metadata)
+            .await
+    }
+
+    async fn handle_replay_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let maybe_song = {
+            let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+            let mut guild_speakers_ref = guild_speakers_handle.lock();
+            match guild_speakers_ref.find_active_in_channel(channel_id).await {
+                Some((guild_speaker, _)) => guild_speaker.active_song(),
+                None => None,
+            }
+        };
+        let Some(song) = maybe_song else {
+            tracing::trace!("No speakers are playing in the user's voice channel");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        };
+
+        // The song is already resolved, so it's queued straight away instead of searching again.
+        let song = song.replay(user_id);
+        tracing::trace!(
+            "Replaying {} (\"{}\")",
+            song.metadata.url,
+            song.metadata.title
+        );
+        let metadata = QueuedSongsMetadata::Single(song.metadata.clone());
+        let guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        self.queue_songs(ctx, user_id, guild_id, guild_model, vec![song], metadata)
+            .await
+    }
+
+    async fn handle_grab_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        let Some((guild_speaker, active_metadata)) =
+            guild_speakers_ref.find_active_in_channel(channel_id).await
+        else {
+            tracing::trace!("No speakers are in the user's voice channel, nothing will be sent");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        };
+        let play_time = guild_speaker.active_play_time().await;
+
+        let guild_config = self.guild_config(guild_id).await;
+        let locale = self.guild_locale(guild_id).await;
+        let send_res = send_direct_message(
+            &guild_config,
+            locale.as_deref(),
+            ctx,
+            user_id,
+            ResponseMessage::Grab {
+                song_title: active_metadata.title.clone(),
+                song_url: active_metadata.url.clone(),
+                voice_channel_id: channel_id,
+                thumbnail_url: active_metadata.thumbnail_url,
+                time_seconds: play_time.map(|time| time.as_secs_f64()).unwrap_or(0.),
+                duration_seconds: active_metadata.duration_seconds,
+            },
+        )
+        .await;
+
+        match send_res {
+            Ok(()) => Ok(vec![Message::Response {
+                message: ResponseMessage::Grabbed {
+                    song_title: active_metadata.title,
+                    song_url: active_metadata.url,
+                },
+                delegate: None,
+            }]),
+            Err(why) => {
+                tracing::warn!("Error while sending direct message: {}", why);
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::GrabFailedError,
+                    delegate: None,
+                }])
+            }
+        }
+    }
+
+    fn handle_history_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let entries: Vec<_> = guild_model
+            .history_entries(channel_id)
+            .map(|entry| HistoryMessageEntry {
+                song_title: entry.title.clone(),
+                song_url: entry.url.clone(),
+                user_id: entry.user_id,
+                ended_time: entry.ended_time.unix_timestamp(),
+            })
+            .collect();
+
+        if entries.is_empty() {
+            tracing::trace!("Nothing has finished playing in the user's voice channel");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoPreviousSongError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        }
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::History {
+                voice_channel_id: channel_id,
+                entries,
+            },
+            delegate: None,
+        }])
+    }
+
+    pub(super) async fn handle_pause_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        match guild_speakers_ref.find_active_in_channel(channel_id).await {
+            Some((guild_speaker, active_metadata)) => {
+                if guild_speaker.is_paused() {
+                    tracing::trace!("Found a paused speaker in the user's voice channel, playback will remain paused");
+                    Ok(vec![Message::Response {
+                        message: ResponseMessage::NothingIsPlayingError {
+                            voice_channel_id: channel_id,
+                        },
+                        delegate: None,
+                    }])
+                } else {
+                    tracing::trace!("Found an unpaused speaker in the user's voice channel, playback will be paused");
+                    guild_speaker
+                        .pause()
+                        .map_err(crate::error::Error::Backend)?;
+                    Ok(vec![Message::Action {
+                        message: ActionMessage::Paused {
+                            song_title: active_metadata.title.clone(),
+                            song_url: active_metadata.url.clone(),
+                            user_id: active_metadata.user_id,
+                        },
+                        voice_channel: channel_id,
+                        delegate: None,
+                    }])
+                }
+            }
+            _ => {
+                tracing::trace!(
+                    "No speakers are in the user's voice channel, playback will not change"
+                );
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::NothingIsPlayingError {
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            }
+        }
+    }
+
+    async fn handle_vote_pause_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        can_force: bool,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        let Some((guild_speaker, active_metadata)) =
+            guild_speakers_ref.find_active_in_channel(channel_id).await
+        else {
+            tracing::trace!("No speakers are in the user's voice channel, not voting to pause");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        };
+        // Votes aren't counted while it's already paused, so they don't pause it again later
+        if guild_speaker.is_paused() {
+            tracing::trace!("Speaker in the user's voice channel is already paused");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingIsPlayingError {
+                    voice_channel_id: channel_id,
+                },
+                delegate: None,
+            }]);
+        }
+
+        let pause_status =
+            guild_model.vote_for_skip(&ctx.cache, VoteType::Pause, channel_id, user_id, can_force);
+        match pause_status {
+            VoteStatus::Success => {
+                tracing::trace!("Pause vote passed, pausing playback");
+                guild_speaker
+                    .pause()
+                    .map_err(crate::error::Error::Backend)?;
+                Ok(vec![Message::Action {
+                    message: ActionMessage::Paused {
+                        song_title: active_metadata.title,
+                        song_url: active_metadata.url,
+                        user_id: active_metadata.user_id,
+                    },
+                    voice_channel: channel_id,
+                    delegate: None,
+                }])
+            }
+            VoteStatus::AlreadyVoted => {
+                tracing::trace!("User attempting to pause has already voted, not pausing playback");
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::PauseAlreadyVotedError {
+                        song_title: active_metadata.title,
+                        song_url: active_metadata.url,
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            }
+            VoteStatus::NeedsMoreVotes(count) => {
+                tracing::trace!(
+                    "Pause vote has been counted but more are needed, not pausing playback"
+                );
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::PauseMoreVotesNeeded {
+                        song_title: active_metadata.title,
+                        song_url: active_metadata.url,
+                        voice_channel_id: channel_id,
+                        count,
+                    },
+                    delegate: None,
+                }])
+            }
+            VoteStatus::NothingPlaying => Err(crate::error::Error::ModelPlayingSpeakerNotDesync),
+        }
+    }
+
+    pub(super) async fn handle_skip_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        can_force: bool,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let skip_status =
+            guild_model.vote_for_skip(&ctx.cache, VoteType::Skip, channel_id, user_id, can_force);
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        let maybe_guild_speaker = guild_speakers_ref.find_active_in_channel(channel_id).await;
+
+        match (skip_status, maybe_guild_speaker) {
+            (VoteStatus::Success, Some((guild_speaker, active_metadata))) => {
+                tracing::trace!("Skip command passed preconditions, stopping current playback");
+                guild_speaker.stop().map_err(crate::error::Error::Backend)?;
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::Skipped {
+                        song_title: active_metadata.title,
+                        song_url: active_metadata.url,
+                        voice_channel_id: channel_id,
+                        user_id: active_metadata.user_id,
+                    },
+                    delegate: None,
+                }])
+            }
+            (VoteStatus::AlreadyVoted, Some((_, active_metadata))) => {
+                tracing::trace!("User attempting to skip has already voted, not stopping playback");
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::SkipAlreadyVotedError {
+                        song_title: active_metadata.title,
+                        song_url: active_metadata.url,
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            }
+            (VoteStatus::NeedsMoreVotes(count), Some((_, active_metadata))) => {
+                tracing::trace!(
+                    "Skip vote has been counted but more are needed, not stopping playback"
+                );
+                // Nothing needs to happen if no playing messages are listening for the count.
+                let _ = self.skip_votes_sender.send(SkipVotesChanged {
+                    guild_id,
+                    channel_id,
+                    skip_votes: guild_model.skip_vote_count(&ctx.cache, VoteType::Skip, channel_id),
+                });
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::SkipMoreVotesNeeded {
+                        song_title: active_metadata.title,
+                        song_url: active_metadata.url,
+                        voice_channel_id: channel_id,
+                        count,
+                    },
+                    delegate: None,
+                }])
+            }
+            (VoteStatus::NothingPlaying, _) => {
+                tracing::trace!(
+                    "Nothing is playing in the user's voice channel, not stopping playback"
+                );
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::NothingIsPlayingError {
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            }
+            (_, None) => Err(crate::error::Error::ModelPlayingSpeakerNotDesync),
+        }
+    }
+
+    pub(super) async fn handle_stop_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        can_force: bool,
+    ) -> Result<Vec<Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let stop_status =
+            guild_model.vote_for_skip(&ctx.cache, VoteType::Stop, channel_id, user_id, can_force);
+        match stop_status {
+            VoteStatus::Success => {
+                let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+                let mut guild_speakers_ref = guild_speakers_handle.lock();
+                let maybe_guild_speaker =
+                    guild_speakers_ref.find_active_in_channel(channel_id).await;
+                match maybe_guild_speaker {
+                    Some((guild_speaker, active_metadata)) => {
+                        tracing::trace!("Stop command passed preconditions, stopping playback");
+                        guild_model.set_channel_stopped(channel_id);
+                        guild_model.set_session_thread(channel_id, None);
+                        guild_speaker.stop().map_err(crate::error::Error::Backend)?;
+                        Ok(vec![Message::Action {
+                            message: ActionMessage::Stopped {
+                                song_title: active_metadata.title.clone(),
+                                song_url: active_metadata.url.clone(),
+                                user_id: active_metadata.user_id,
+                            },
+                            voice_channel: channel_id,
+                            delegate: None,
+                        }])
+                    }
+                    None => Err(crate::error::Error::ModelPlayingSpeakerNotDesync),
+                }
+            }
+            VoteStatus::AlreadyVoted => {
+                tracing::trace!("User attempting to stop has already voted, not stopping playback");
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::StopAlreadyVotedError {
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            }
+            VoteStatus::NeedsMoreVotes(count) => {
+                tracing::trace!(
+                    "Stop vote has been counted but more are needed, not stopping playback"
+                );
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::StopMoreVotesNeeded {
+                        voice_channel_id: channel_id,
+                        count,
+                    },
+                    delegate: None,
+                }])
+            }
+            VoteStatus::NothingPlaying => {
+                tracing::trace!(
+                    "Nothing is playing in the user's voice channel, not stopping playback"
+                );
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::NothingIsPlayingError {
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            }
+        }
+    }
+
+    async fn handle_nowplaying_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        maybe_channel_id: Option<ChannelId>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        // Anyone can check on a channel they aren't in, so it doesn't need to be their own
+        let maybe_channel_id =
+            maybe_channel_id.or_else(|| get_user_voice_channel(&ctx.cache, guild_id, user_id));
+        let Some(channel_id) = maybe_channel_id else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+
+        match guild_speakers_ref.find_active_in_channel(channel_id).await {
+            Some((guild_speaker, active_metadata)) => {
+                if guild_speaker.is_paused() {
+                    Ok(vec![Message::Action {
+                        message: ActionMessage::Paused {
+                            song_title: active_metadata.title,
+                            song_url: active_metadata.url,
+                            user_id: active_metadata.user_id,
+                        },
+                        voice_channel: channel_id,
+                        delegate: None,
+                    }])
+                } else {
+                    Ok(vec![
+                        build_playing_message(
+                            self.clone(),
+                            guild_speaker,
+                            false,
+                            channel_id,
+                            active_metadata,
+                            guild_model.skip_vote_count(&ctx.cache, VoteType::Skip, channel_id),
+                        )
+                        .await,
+                    ])
+                }
+            }
+            None => {
+                tracing::trace!(
+                    "No speakers are in the user's voice channel, no metadata will be shown"
+                );
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::NothingIsPlayingError {
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }])
+            }
+        }
+    }
+}
@@ -0,0 +1,459 @@
+use crate::frontend::{
+    get_string_option, get_subcommand, get_user_voice_channel, Frontend, QueuedSongsMetadata,
+    PLAYLIST_LOAD_CONCURRENCY,
+};
+use crate::invocation::CommandInvocation;
+use crate::message::{FavoriteMessageEntry, Message, PlaylistMessageEntry, ResponseMessage};
+use crate::queued_song::QueuedSong;
+use futures::prelude::*;
+use mrvn_back_ytdl::Song;
+use mrvn_model::{
+    DeletePlaylistStatus, GuildModel, GuildModelHandle, Playlist, PlaylistEntry, SavePlaylistStatus,
+};
+use serenity::{
+    model::prelude::{GuildId, UserId},
+    prelude::*,
+};
+use std::ops::DerefMut;
+use std::sync::Arc;
+
+impl Frontend {
+    /// Commands for the guild's saved playlists and each user's favorites.
+    pub(super) async fn dispatch_playlist_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        command: &CommandInvocation<'_>,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let user_id = command.user_id;
+        match command.name.as_str() {
+            "playlist" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                let name = get_string_option(options, "name")
+                    .unwrap_or_default()
+                    .trim();
+                match subcommand_name {
+                    "save" => {
+                        tracing::debug!("Received playlist save \"{}\"", name);
+                        self.handle_playlist_save_command(
+                            ctx,
+                            user_id,
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            name,
+                        )
+                        .await
+                    }
+                    "load" => {
+                        tracing::debug!("Received playlist load \"{}\"", name);
+                        self.handle_playlist_load_command(
+                            ctx,
+                            user_id,
+                            guild_id,
+                            guild_model_handle,
+                            name,
+                        )
+                        .await
+                    }
+                    "list" => {
+                        tracing::debug!("Received playlist list");
+                        self.handle_playlist_list_command(guild_id).await
+                    }
+                    "delete" => {
+                        tracing::debug!("Received playlist delete \"{}\"", name);
+                        self.handle_playlist_delete_command(user_id, guild_id, name)
+                            .await
+                    }
+                    subcommand_name => Err(crate::error::Error::UnknownCommand(format!(
+                        "playlist {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            "fav" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                let name = get_string_option(options, "name")
+                    .unwrap_or_default()
+                    .trim();
+                match subcommand_name {
+                    "add" => {
+                        tracing::debug!("Received favorite add \"{}\"", name);
+                        self.handle_fav_add_command(ctx, user_id, guild_id, name)
+                            .await
+                    }
+                    "list" => {
+                        tracing::debug!("Received favorite list");
+                        self.handle_fav_list_command(user_id).await
+                    }
+                    "play" => {
+                        tracing::debug!("Received favorite play \"{}\"", name);
+                        self.handle_fav_play_command(
+                            ctx,
+                            user_id,
+                            guild_id,
+                            guild_model_handle,
+                            name,
+                        )
+                        .await
+                    }
+                    "remove" => {
+                        tracing::debug!("Received favorite remove \"{}\"", name);
+                        self.handle_fav_remove_command(user_id, name).await
+                    }
+                    subcommand_name => Err(crate::error::Error::UnknownCommand(format!(
+                        "fav {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            command_name => Err(crate::error::Error::UnknownCommand(
+                command_name.to_string(),
+            )),
+        }
+    }
+
+    async fn handle_playlist_save_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        // The saved playlist starts with the song that's playing, if it was added by this user.
+        let mut entries = Vec::new();
+        if let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) {
+            let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+            let mut guild_speakers_ref = guild_speakers_handle.lock();
+            if let Some((_, active_metadata)) =
+                guild_speakers_ref.find_active_in_channel(channel_id).await
+            {
+                if active_metadata.user_id == user_id {
+                    entries.push(PlaylistEntry {
+                        title: active_metadata.title,
+                        url: active_metadata.url,
+                    });
+                }
+            }
+        }
+        entries.extend(
+            guild_model
+                .user_entries(user_id)
+                .map(|queued_song| PlaylistEntry {
+                    title: queued_song.song.metadata.title.clone(),
+                    url: queued_song.song.metadata.url.clone(),
+                }),
+        );
+
+        if entries.is_empty() {
+            tracing::trace!("User has nothing playing or queued, no playlist will be saved");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingToSaveError,
+                delegate: None,
+            }]);
+        }
+
+        let count = entries.len();
+        let save_status = self
+            .playlists
+            .save(guild_id, name, Playlist { user_id, entries })
+            .await
+            .map_err(crate::error::Error::Storage)?;
+
+        match save_status {
+            SavePlaylistStatus::Created | SavePlaylistStatus::Replaced => {
+                Ok(vec![Message::Response {
+                    message: ResponseMessage::PlaylistSaved {
+                        name: name.to_string(),
+                        count,
+                    },
+                    delegate: None,
+                }])
+            }
+            SavePlaylistStatus::NotOwner(owner_id) => Ok(vec![Message::Response {
+                message: ResponseMessage::PlaylistNotOwnerError {
+                    name: name.to_string(),
+                    user_id: owner_id,
+                },
+                delegate: None,
+            }]),
+        }
+    }
+
+    async fn handle_playlist_load_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let maybe_playlist = self
+            .playlists
+            .read(|playlists| playlists.get(guild_id, name).cloned())
+            .await;
+        let Some(playlist) = maybe_playlist else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::PlaylistNotFoundError {
+                    name: name.to_string(),
+                },
+                delegate: None,
+            }]);
+        };
+
+        // Download URLs expire, so every entry needs to be resolved again. Entries that can't be
+        // resolved anymore are skipped.
+        let guild_config = self.guild_config(guild_id).await;
+        let play_config = guild_config.get_play_config();
+        let fetch_futures: Vec<_> = playlist
+            .entries
+            .iter()
+            .map(|entry| Song::fetch_one(&entry.url, user_id, &play_config))
+            .collect();
+        let song_results: Vec<_> = stream::iter(fetch_futures)
+            .buffered(PLAYLIST_LOAD_CONCURRENCY)
+            .collect()
+            .await;
+        let songs: Vec<_> = song_results
+            .into_iter()
+            .filter_map(|song_res| match song_res {
+                Ok(song) => Some(song),
+                Err(why) => {
+                    tracing::warn!("Error while resolving playlist entry: {}", why);
+                    None
+                }
+            })
+            .collect();
+
+        let songs = match self.remove_blocked_songs(guild_id, songs).await {
+            Ok(songs) => songs,
+            Err(message) => return Ok(vec![message]),
+        };
+
+        let metadata = match songs.len() {
+            0 => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::NoMatchingSongsError,
+                    delegate: None,
+                }]);
+            }
+            1 => QueuedSongsMetadata::Single(songs[0].metadata.clone()),
+            count => QueuedSongsMetadata::Multiple(count),
+        };
+        tracing::trace!(
+            "Resolved {} of {} songs in playlist",
+            songs.len(),
+            playlist.entries.len()
+        );
+
+        let guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        self.queue_songs(ctx, user_id, guild_id, guild_model, songs, metadata)
+            .await
+    }
+
+    async fn handle_playlist_list_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let playlists: Vec<_> = self
+            .playlists
+            .read(|playlists| {
+                playlists
+                    .iter(guild_id)
+                    .map(|(name, playlist)| PlaylistMessageEntry {
+                        name: name.clone(),
+                        count: playlist.entries.len(),
+                        user_id: playlist.user_id,
+                    })
+                    .collect()
+            })
+            .await;
+
+        if playlists.is_empty() {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoPlaylistsError,
+                delegate: None,
+            }]);
+        }
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::PlaylistList { playlists },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_playlist_delete_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+        guild_id: GuildId,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let delete_status = self
+            .playlists
+            .delete(guild_id, name, user_id)
+            .await
+            .map_err(crate::error::Error::Storage)?;
+
+        let message = match delete_status {
+            DeletePlaylistStatus::Deleted => ResponseMessage::PlaylistDeleted {
+                name: name.to_string(),
+            },
+            DeletePlaylistStatus::NotFound => ResponseMessage::PlaylistNotFoundError {
+                name: name.to_string(),
+            },
+            DeletePlaylistStatus::NotOwner(owner_id) => ResponseMessage::PlaylistNotOwnerError {
+                name: name.to_string(),
+                user_id: owner_id,
+            },
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_fav_add_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        };
+
+        let active_metadata = {
+            let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+            let mut guild_speakers_ref = guild_speakers_handle.lock();
+            match guild_speakers_ref.find_active_in_channel(channel_id).await {
+                Some((_, active_metadata)) => active_metadata,
+                None => {
+                    return Ok(vec![Message::Response {
+                        message: ResponseMessage::NothingIsPlayingError {
+                            voice_channel_id: channel_id,
+                        },
+                        delegate: None,
+                    }]);
+                }
+            }
+        };
+
+        // Favorites are named after the song unless the user picks a name.
+        let name = match name {
+            "" => active_metadata.title.clone(),
+            name => name.to_string(),
+        };
+        let entry = PlaylistEntry {
+            title: active_metadata.title.clone(),
+            url: active_metadata.url.clone(),
+        };
+        self.favorites
+            .add(user_id, &name, entry)
+            .await
+            .map_err(crate::error::Error::Storage)?;
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::FavoriteAdded {
+                name,
+                song_title: active_metadata.title,
+                song_url: active_metadata.url,
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_fav_list_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let favorites: Vec<_> = self
+            .favorites
+            .read(|favorites| {
+                favorites
+                    .iter(user_id)
+                    .map(|(name, entry)| FavoriteMessageEntry {
+                        name: name.clone(),
+                        song_title: entry.title.clone(),
+                        song_url: entry.url.clone(),
+                    })
+                    .collect()
+            })
+            .await;
+
+        if favorites.is_empty() {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoFavoritesError,
+                delegate: None,
+            }]);
+        }
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::FavoriteList { favorites },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_fav_play_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let maybe_entry = self
+            .favorites
+            .read(|favorites| favorites.get(user_id, name).cloned())
+            .await;
+        let Some(entry) = maybe_entry else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::FavoriteNotFoundError {
+                    name: name.to_string(),
+                },
+                delegate: None,
+            }]);
+        };
+
+        // The download URL will have expired, so the song is resolved again the same way as a link.
+        self.handle_queue_urls_command(ctx, user_id, guild_id, guild_model_handle, &[entry.url])
+            .await
+    }
+
+    async fn handle_fav_remove_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let is_removed = self
+            .favorites
+            .remove(user_id, name)
+            .await
+            .map_err(crate::error::Error::Storage)?;
+
+        let message = if is_removed {
+            ResponseMessage::FavoriteRemoved {
+                name: name.to_string(),
+            }
+        } else {
+            ResponseMessage::FavoriteNotFoundError {
+                name: name.to_string(),
+            }
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+}
@@ -0,0 +1,1775 @@
+use crate::commands::PLAY_MANY_SONGS_NAME;
+use crate::frontend::{
+    extract_message_urls, get_bool_option, get_position_option, get_string_option, get_subcommand,
+    get_user_voice_channel, load_lines, Frontend, PendingDuplicate, PendingPlaylist, PendingSearch,
+    PlaylistMode, QueuedSongsMetadata, MAX_PLAYLIST_CHOICES, MAX_QUEUE_FILE_SIZE,
+    PLAYLIST_LOAD_CONCURRENCY, SEARCH_EXPIRY,
+};
+use crate::invocation::CommandInvocation;
+use crate::message::{
+    send_messages, Message, ResponseMessage, SearchMessageEntry, SendMessageDestination,
+    SongDetails,
+};
+use crate::playing_message::build_playing_message;
+use crate::playlist_progress::PlaylistProgress;
+use crate::queue_file::QueueFileFormat;
+use crate::queued_message::build_queued_message;
+use crate::queued_song::QueuedSong;
+use futures::prelude::*;
+use mrvn_back_ytdl::{canonical_url, LoadedSongs, RemainingSongs, Song};
+use mrvn_model::{
+    GuildModel, GuildModelGuard, GuildModelHandle, NextEntry, PlaylistEntry, PushStatus,
+    ReplaceStatus, UndoStatus, VoteType,
+};
+use serenity::all::{Attachment, CommandDataOptionValue, ComponentInteraction, EditMessage};
+use serenity::{
+    model::prelude::{GuildId, UserId},
+    prelude::*,
+};
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+
+impl Frontend {
+    /// Commands that add songs to queues, or change what's in them.
+    pub(super) async fn dispatch_queue_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        command: &CommandInvocation<'_>,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let user_id = command.user_id;
+        match command.name.as_str() {
+            "play" => {
+                let maybe_attachment =
+                    command
+                        .options
+                        .iter()
+                        .find_map(|option| match option.value {
+                            CommandDataOptionValue::Attachment(attachment_id) => {
+                                command.attachment(attachment_id)
+                            }
+                            _ => None,
+                        });
+                match maybe_attachment {
+                    Some(attachment) => {
+                        tracing::debug!(
+                            "Received play with attachment \"{}\"",
+                            attachment.filename
+                        );
+                        self.handle_queue_attachment_command(
+                            ctx,
+                            user_id,
+                            guild_id,
+                            guild_model_handle,
+                            attachment,
+                        )
+                        .await
+                    }
+                    None => {
+                        let term = get_string_option(&command.options, "term").unwrap_or_default();
+                        let mode = match get_bool_option(&command.options, "choose") {
+                            Some(true) => PlaylistMode::Choose,
+                            _ => PlaylistMode::QueueAll,
+                        };
+                        tracing::debug!("Received play \"{}\" ({:?})", term, mode);
+                        self.handle_queue_play_command(
+                            ctx,
+                            user_id,
+                            guild_id,
+                            guild_model_handle,
+                            term,
+                            mode,
+                        )
+                        .await
+                    }
+                }
+            }
+            "playmany" => {
+                let songs =
+                    get_string_option(&command.options, PLAY_MANY_SONGS_NAME).unwrap_or_default();
+                tracing::debug!("Received playmany with {} lines", songs.lines().count());
+                self.handle_play_many_command(ctx, user_id, guild_id, guild_model_handle, songs)
+                    .await
+            }
+            "search" => {
+                let term = get_string_option(&command.options, "term").unwrap_or_default();
+                tracing::debug!("Received search \"{}\"", term);
+                self.handle_search_command(user_id, guild_id, term).await
+            }
+            "replace" => {
+                let term = command
+                    .options
+                    .first()
+                    .and_then(|option| option.value.as_str())
+                    .unwrap_or_default();
+
+                tracing::debug!("Received replace \"{}\"", term);
+                self.handle_replace_command(ctx, user_id, guild_id, guild_model_handle, term)
+                    .await
+            }
+            "remove" => {
+                let position = get_position_option(command);
+                tracing::debug!("Received remove {}", position);
+                self.handle_remove_command(
+                    user_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                    position,
+                )
+                .await
+            }
+            "jump" => {
+                let position = get_position_option(command);
+                tracing::debug!("Received jump {}", position);
+                self.handle_jump_command(
+                    user_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                    position,
+                )
+                .await
+            }
+            "wrong" => {
+                tracing::debug!("Received wrong");
+                self.handle_wrong_command(
+                    user_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                )
+                .await
+            }
+            "undo" => {
+                tracing::debug!("Received undo");
+                self.handle_undo_command(
+                    user_id,
+                    guild_model_handle
+                        .lock()
+                        .await
+                        .map_err(crate::error::Error::ModelLock)?
+                        .deref_mut(),
+                )
+                .await
+            }
+            "queue" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                match subcommand_name {
+                    "export" => {
+                        let is_channel = get_string_option(options, "scope") == Some("channel");
+                        let format = get_string_option(options, "format")
+                            .and_then(QueueFileFormat::parse)
+                            .unwrap_or(QueueFileFormat::Json);
+                        tracing::debug!("Received queue export as {:?}", format);
+                        self.handle_queue_export_command(
+                            ctx,
+                            user_id,
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            is_channel,
+                            format,
+                        )
+                        .await
+                    }
+                    "import" => {
+                        let maybe_attachment =
+                            options.iter().find_map(|option| match option.value {
+                                CommandDataOptionValue::Attachment(attachment_id) => {
+                                    command.attachment(attachment_id)
+                                }
+                                _ => None,
+                            });
+                        match maybe_attachment {
+                            Some(attachment) => {
+                                tracing::debug!(
+                                    "Received queue import of \"{}\"",
+                                    attachment.filename
+                                );
+                                self.handle_queue_import_command(
+                                    ctx,
+                                    user_id,
+                                    guild_id,
+                                    guild_model_handle,
+                                    attachment,
+                                )
+                                .await
+                            }
+                            None => Ok(vec![Message::Response {
+                                message: ResponseMessage::InvalidQueueFileError,
+                                delegate: None,
+                            }]),
+                        }
+                    }
+                    subcommand_name => Err(crate::error::Error::UnknownCommand(format!(
+                        "queue {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            "Queue this" => {
+                let urls = command
+                    .target_message()
+                    .map(extract_message_urls)
+                    .unwrap_or_default();
+                tracing::debug!("Received queue this with {} links", urls.len());
+                self.handle_queue_urls_command(ctx, user_id, guild_id, guild_model_handle, &urls)
+                    .await
+            }
+            command_name => Err(crate::error::Error::UnknownCommand(
+                command_name.to_string(),
+            )),
+        }
+    }
+
+    /// Queues a song for a user without a command, the same way `/play` would. Messages about
+    /// songs starting go to the announcements channel, or wherever the last command came from,
+    /// and responses to the user are returned instead.
+    pub async fn queue_for_user(
+        self: &Arc<Self>,
+        ctx: &Context,
+        guild_id: GuildId,
+        user_id: UserId,
+        term: &str,
+    ) -> Result<Vec<ResponseMessage>, crate::error::Error> {
+        let guild_model_handle = self.model.get(guild_id);
+
+        if self.is_user_banned(guild_id, user_id).await {
+            tracing::trace!("User is banned from using the bot, not queueing song");
+            return Ok(vec![ResponseMessage::BannedUserError]);
+        }
+        let messages = self
+            .handle_queue_play_command(
+                ctx,
+                user_id,
+                guild_id,
+                &guild_model_handle,
+                term,
+                PlaylistMode::Confirmed,
+            )
+            .await?;
+
+        let (actions, responses): (Vec<_>, Vec<_>) =
+            messages.into_iter().partition(Message::is_action);
+        let guild_config = self.guild_config(guild_id).await;
+        let maybe_message_channel = match guild_config.announcements_channel {
+            Some(announcements_channel) => Some(announcements_channel),
+            None => guild_model_handle
+                .lock()
+                .await
+                .map_err(crate::error::Error::ModelLock)?
+                .message_channel(),
+        };
+        if let (Some(message_channel), false) = (maybe_message_channel, actions.is_empty()) {
+            let locale = self.guild_locale(guild_id).await;
+            let send_res = send_messages(
+                &guild_config,
+                locale.as_deref(),
+                ctx,
+                SendMessageDestination::Channel(message_channel),
+                &guild_model_handle,
+                actions,
+            )
+            .await;
+            if let Err(why) = send_res {
+                tracing::error!("Error while sending messages: {}", why);
+            }
+        }
+
+        self.clone().prefetch_next_songs(ctx.clone(), guild_id);
+        Ok(responses
+            .into_iter()
+            .filter_map(|message| match message {
+                Message::Response { message, .. } => Some(message),
+                Message::Action { .. } => None,
+            })
+            .collect())
+    }
+
+    async fn handle_queue_play_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        term: &str,
+        mode: PlaylistMode,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let guild_config = self.guild_config(guild_id).await;
+        let play_config = guild_config.get_play_config();
+
+        let loaded = match Song::load_streaming(term, user_id, &play_config).await {
+            Ok(data) => data,
+            Err(mrvn_back_ytdl::Error::UnsupportedUrl) => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::UnsupportedSiteError,
+                    delegate: None,
+                }]);
+            }
+            Err(mrvn_back_ytdl::Error::LoginRequired(why)) => {
+                tracing::debug!("Song needs a logged in account: {}", why);
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::LoginRequiredError,
+                    delegate: None,
+                }]);
+            }
+            Err(why) => return Err(crate::error::Error::Backend(why)),
+        };
+
+        // Single songs are queued straight away even if the user wanted to choose.
+        if mode == PlaylistMode::Choose && (loaded.songs.len() > 1 || loaded.remaining.is_some()) {
+            return self
+                .handle_playlist_choices(user_id, guild_id, loaded)
+                .await;
+        }
+
+        // Pasting a channel's link by accident shouldn't flood the queue, so big playlists are
+        // only queued once the user confirms it. Playlists that don't say how many songs they
+        // have can't be checked.
+        if mode == PlaylistMode::QueueAll && guild_config.playlist_confirm_size != 0 {
+            let maybe_song_count =
+                loaded
+                    .total_count
+                    .map(|total_count| match guild_config.max_playlist_size {
+                        0 => total_count,
+                        max_count => total_count.min(max_count),
+                    });
+            if let Some(song_count) = maybe_song_count
+                .filter(|&song_count| song_count > guild_config.playlist_confirm_size)
+            {
+                tracing::trace!("Playlist has {} songs, asking to confirm", song_count);
+                return Ok(vec![
+                    self.add_pending_playlist(user_id, term, song_count).await,
+                ]);
+            }
+        }
+
+        let (mut messages, queued_count) = match self
+            .remove_blocked_songs(guild_id, loaded.songs)
+            .await
+        {
+            Ok(songs) if songs.is_empty() => (
+                vec![Message::Response {
+                    message: ResponseMessage::NoMatchingSongsError,
+                    delegate: None,
+                }],
+                0,
+            ),
+            Ok(mut songs) => {
+                // Playlists are expected to have songs that are already queued, so only single
+                // songs are checked.
+                if songs.len() == 1
+                    && loaded.remaining.is_none()
+                    && self
+                        .is_duplicate_song(ctx, user_id, guild_id, guild_model_handle, &songs[0])
+                        .await
+                {
+                    tracing::trace!("Song is already queued or playing, asking to confirm");
+                    return Ok(vec![
+                        self.add_pending_duplicate(user_id, songs.remove(0)).await,
+                    ]);
+                }
+
+                let metadata = if songs.len() == 1 {
+                    let song_metadata = &songs[0].metadata;
+                    tracing::trace!(
+                        "Resolved song query as {} (\"{}\")",
+                        song_metadata.url,
+                        song_metadata.title
+                    );
+                    QueuedSongsMetadata::Single(song_metadata.clone())
+                } else {
+                    tracing::trace!("Resolved song query as {} songs", songs.len());
+                    QueuedSongsMetadata::Multiple(songs.len())
+                };
+
+                let queued_count = songs.len();
+                let guild_model = guild_model_handle
+                    .lock()
+                    .await
+                    .map_err(crate::error::Error::ModelLock)?;
+                let messages = self
+                    .queue_songs(ctx, user_id, guild_id, guild_model, songs, metadata)
+                    .await?;
+                (messages, queued_count)
+            }
+            Err(message) => (vec![message], 0),
+        };
+
+        // The rest of a playlist is queued in the background, once this command has released the
+        // guild so the first song can start playing. Playlists say how many songs they have, so
+        // that's shown in a message that's kept up to date until they're all queued.
+        if let Some(remaining) = loaded.remaining {
+            let maybe_progress = match loaded.total_count {
+                Some(total_count) => {
+                    let (progress, progress_message) = PlaylistProgress::new(
+                        ctx.clone(),
+                        guild_config.clone(),
+                        self.guild_locale(guild_id).await,
+                        queued_count,
+                        total_count,
+                        guild_config.max_playlist_size,
+                    );
+                    messages.push(progress_message);
+                    Some(progress)
+                }
+                None => None,
+            };
+            self.clone().queue_remaining_songs(
+                ctx.clone(),
+                user_id,
+                guild_id,
+                remaining,
+                maybe_progress,
+            );
+        }
+
+        Ok(messages)
+    }
+
+    async fn handle_playlist_choices(
+        &self,
+        user_id: UserId,
+        guild_id: GuildId,
+        loaded: LoadedSongs,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        // One song past the limit is loaded, to tell whether the playlist is longer than the menu.
+        let mut songs = loaded.songs;
+        if let Some(remaining) = loaded.remaining {
+            let remaining_songs: Vec<_> = remaining
+                .take((MAX_PLAYLIST_CHOICES + 1).saturating_sub(songs.len()))
+                .take_while(|song_res| future::ready(song_res.is_ok()))
+                .filter_map(|song_res| future::ready(song_res.ok()))
+                .collect()
+                .await;
+            songs.extend(remaining_songs);
+        }
+        let is_truncated = songs.len() > MAX_PLAYLIST_CHOICES;
+        songs.truncate(MAX_PLAYLIST_CHOICES);
+        tracing::trace!(
+            "Loaded {} playlist songs to choose from (truncated: {})",
+            songs.len(),
+            is_truncated
+        );
+
+        let songs = match self.remove_blocked_songs(guild_id, songs).await {
+            Ok(songs) if songs.is_empty() => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::NoMatchingSongsError,
+                    delegate: None,
+                }]);
+            }
+            Ok(songs) => songs,
+            Err(message) => return Ok(vec![message]),
+        };
+
+        let choices = songs
+            .iter()
+            .map(|song| SearchMessageEntry {
+                song_title: song.metadata.title.clone(),
+                song_url: song.metadata.url.clone(),
+                duration_seconds: song.metadata.duration_seconds,
+            })
+            .collect();
+        let choices_id = self.add_pending_search(user_id, songs).await;
+        Ok(vec![Message::Response {
+            message: ResponseMessage::PlaylistChoices {
+                choices_id,
+                choices,
+                is_truncated,
+            },
+            delegate: None,
+        }])
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn handle_playlist_select(
+        self: &Arc<Self>,
+        ctx: &Context,
+        component: &ComponentInteraction,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        choices_id: Uuid,
+        indices: Vec<usize>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let songs = match self
+            .take_pending_search(ctx, component, user_id, choices_id)
+            .await
+        {
+            Ok(Some(songs)) => songs,
+            Ok(None) => {
+                tracing::trace!("Playlist is no longer available, nothing will be queued");
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::PlaylistChoiceExpiredError,
+                    delegate: None,
+                }]);
+            }
+            Err(owner_id) => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::PlaylistChoiceNotOwnerError { user_id: owner_id },
+                    delegate: None,
+                }]);
+            }
+        };
+
+        let songs: Vec<_> = songs
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| indices.contains(index))
+            .map(|(_, song)| song)
+            .collect();
+        let metadata = match songs.as_slice() {
+            [] => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::NoMatchingSongsError,
+                    delegate: None,
+                }]);
+            }
+            [song] => QueuedSongsMetadata::Single(song.metadata.clone()),
+            songs => QueuedSongsMetadata::Multiple(songs.len()),
+        };
+        tracing::trace!("Chose {} songs from playlist", songs.len());
+
+        let guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        self.queue_songs(ctx, user_id, guild_id, guild_model, songs, metadata)
+            .await
+    }
+
+    /// Checks whether a song is playing in the user's voice channel, or queued by the user or anyone
+    /// else listening there.
+    async fn is_duplicate_song(
+        &self,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        song: &Song,
+    ) -> bool {
+        let song_url = canonical_url(&song.metadata.url);
+        let maybe_channel_id = get_user_voice_channel(&ctx.cache, guild_id, user_id);
+        if let Some(channel_id) = maybe_channel_id {
+            let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+            let mut guild_speakers_ref = guild_speakers_handle.lock();
+            if let Some((_, active_metadata)) =
+                guild_speakers_ref.find_active_in_channel(channel_id).await
+            {
+                if canonical_url(&active_metadata.url) == song_url {
+                    return true;
+                }
+            }
+        }
+
+        // The song is queued as usual, which reports the error if the model still can't be locked
+        let Ok(guild_model) = guild_model_handle.lock().await else {
+            return false;
+        };
+        let is_queued = guild_model
+            .queued_entries()
+            .filter(|(queue_user_id, _)| {
+                *queue_user_id == user_id
+                    || (maybe_channel_id.is_some()
+                        && get_user_voice_channel(&ctx.cache, guild_id, *queue_user_id)
+                            == maybe_channel_id)
+            })
+            .any(|(_, queued_song)| canonical_url(&queued_song.song.metadata.url) == song_url);
+        is_queued
+    }
+
+    /// Keeps a duplicate song around until the user chooses to queue it anyway. Interactions can't
+    /// be responded to after a while, the same as with searches.
+    async fn add_pending_duplicate(&self, user_id: UserId, song: Song) -> crate::message::Message {
+        let duplicate_id = Uuid::new_v4();
+        let message = ResponseMessage::DuplicateSong {
+            duplicate_id,
+            song_title: song.metadata.title.clone(),
+            song_url: song.metadata.url.clone(),
+        };
+
+        let mut pending_duplicates = self.pending_duplicates.lock().await;
+        pending_duplicates.retain(|_, duplicate| duplicate.created_time.elapsed() < SEARCH_EXPIRY);
+        pending_duplicates.insert(
+            duplicate_id,
+            PendingDuplicate {
+                user_id,
+                created_time: Instant::now(),
+                song,
+            },
+        );
+
+        Message::Response {
+            message,
+            delegate: None,
+        }
+    }
+
+    pub(super) async fn handle_duplicate_confirm(
+        self: &Arc<Self>,
+        ctx: &Context,
+        component: &ComponentInteraction,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        duplicate_id: Uuid,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let maybe_song = {
+            let mut pending_duplicates = self.pending_duplicates.lock().await;
+            match pending_duplicates.get(&duplicate_id) {
+                Some(duplicate) if duplicate.user_id != user_id => {
+                    return Ok(vec![Message::Response {
+                        message: ResponseMessage::DuplicateNotOwnerError {
+                            user_id: duplicate.user_id,
+                        },
+                        delegate: None,
+                    }]);
+                }
+                Some(_) => pending_duplicates
+                    .remove(&duplicate_id)
+                    .map(|duplicate| duplicate.song),
+                None => None,
+            }
+        };
+
+        // The song can only be queued once, so remove the button from the message.
+        let edit_res = component
+            .message
+            .channel_id
+            .edit_message(
+                ctx,
+                component.message.id,
+                EditMessage::new().components(Vec::new()),
+            )
+            .await;
+        if let Err(why) = edit_res {
+            tracing::error!("Error while removing duplicate button: {}", why);
+        }
+
+        let Some(song) = maybe_song else {
+            tracing::trace!("Duplicate song is no longer available, nothing will be queued");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::DuplicateExpiredError,
+                delegate: None,
+            }]);
+        };
+
+        tracing::trace!(
+            "Queueing duplicate song {} (\"{}\") anyway",
+            song.metadata.url,
+            song.metadata.title
+        );
+        let metadata = QueuedSongsMetadata::Single(song.metadata.clone());
+        let guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        self.queue_songs(ctx, user_id, guild_id, guild_model, vec![song], metadata)
+            .await
+    }
+
+    async fn add_pending_playlist(
+        &self,
+        user_id: UserId,
+        term: &str,
+        song_count: usize,
+    ) -> crate::message::Message {
+        let confirm_id = Uuid::new_v4();
+        let mut pending_playlists = self.pending_playlists.lock().await;
+        pending_playlists.retain(|_, playlist| playlist.created_time.elapsed() < SEARCH_EXPIRY);
+        pending_playlists.insert(
+            confirm_id,
+            PendingPlaylist {
+                user_id,
+                created_time: Instant::now(),
+                term: term.to_string(),
+            },
+        );
+
+        Message::Response {
+            message: ResponseMessage::LargePlaylist {
+                confirm_id,
+                song_count,
+            },
+            delegate: None,
+        }
+    }
+
+    pub(super) async fn handle_playlist_confirm(
+        self: &Arc<Self>,
+        ctx: &Context,
+        component: &ComponentInteraction,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        confirm_id: Uuid,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let maybe_term = {
+            let mut pending_playlists = self.pending_playlists.lock().await;
+            match pending_playlists.get(&confirm_id) {
+                Some(playlist) if playlist.user_id != user_id => {
+                    return Ok(vec![Message::Response {
+                        message: ResponseMessage::PlaylistChoiceNotOwnerError {
+                            user_id: playlist.user_id,
+                        },
+                        delegate: None,
+                    }]);
+                }
+                Some(_) => pending_playlists
+                    .remove(&confirm_id)
+                    .map(|playlist| playlist.term),
+                None => None,
+            }
+        };
+
+        // The playlist can only be queued once, so remove the button from the message.
+        let edit_res = component
+            .message
+            .channel_id
+            .edit_message(
+                ctx,
+                component.message.id,
+                EditMessage::new().components(Vec::new()),
+            )
+            .await;
+        if let Err(why) = edit_res {
+            tracing::error!("Error while removing playlist button: {}", why);
+        }
+
+        let Some(term) = maybe_term else {
+            tracing::trace!("Playlist is no longer available, nothing will be queued");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::PlaylistChoiceExpiredError,
+                delegate: None,
+            }]);
+        };
+
+        tracing::trace!("Queueing playlist \"{}\" after confirming", term);
+        self.handle_queue_play_command(
+            ctx,
+            user_id,
+            guild_id,
+            guild_model_handle,
+            &term,
+            PlaylistMode::Confirmed,
+        )
+        .await
+    }
+
+    /// Queues songs from a playlist as youtube-dl finds them, after the first has already been
+    /// queued by the command.
+    fn queue_remaining_songs(
+        self: Arc<Self>,
+        ctx: Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        mut remaining: RemainingSongs,
+        mut maybe_progress: Option<PlaylistProgress>,
+    ) {
+        tokio::task::spawn(async move {
+            let mut queued_count = 0;
+            let mut maybe_failed_message = None;
+            while let Some(song_res) = remaining.next().await {
+                let song = match song_res {
+                    Ok(song) => song,
+                    Err(why) => {
+                        tracing::warn!("Error while resolving the rest of a playlist: {}", why);
+                        break;
+                    }
+                };
+                let Ok(songs) = self.remove_blocked_songs(guild_id, vec![song]).await else {
+                    continue;
+                };
+                let song_metadata = songs[0].metadata.clone();
+
+                let push_status = {
+                    let guild_model_handle = self.model.get(guild_id);
+                    let mut guild_model = match guild_model_handle.lock().await {
+                        Ok(guild_model) => guild_model,
+                        Err(why) => {
+                            tracing::error!("Error while queueing the rest of a playlist: {}", why);
+                            break;
+                        }
+                    };
+                    guild_model.push_more_entries(
+                        user_id,
+                        songs.into_iter().map(|song| QueuedSong {
+                            song,
+                            queue_message_id: None,
+                        }),
+                    )
+                };
+                if let PushStatus::QueueFull {
+                    queued_count,
+                    max_count,
+                } = push_status
+                {
+                    tracing::trace!(
+                        "User's queue is full, the rest of the playlist will not be queued"
+                    );
+                    maybe_failed_message = Some(ResponseMessage::QueueFullError {
+                        queued_count,
+                        max_count,
+                    });
+                    break;
+                }
+
+                // The song after the one that's playing is now known, so it can start loading.
+                queued_count += 1;
+                if queued_count == 1 {
+                    self.clone().prefetch_next_songs(ctx.clone(), guild_id);
+                }
+                if let Some(progress) = &mut maybe_progress {
+                    progress.song_queued(&song_metadata).await;
+                }
+            }
+            tracing::trace!("Queued {} more songs from the playlist", queued_count);
+
+            match (maybe_progress, maybe_failed_message) {
+                (Some(progress), Some(message)) => progress.fail(message).await,
+                (Some(progress), None) => progress.finish().await,
+                (None, _) => {}
+            }
+        });
+    }
+
+    async fn handle_play_many_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        songs_text: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let lines: Vec<_> = songs_text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        let line_count = lines.len();
+        if line_count == 0 {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoLinksInMessageError,
+                delegate: None,
+            }]);
+        }
+
+        // Only the first song is waited for, so it can start playing while the rest are found.
+        let mut songs = load_lines(lines, user_id, self.guild_config(guild_id).await);
+        let Some(Ok(first_song)) = songs.next().await else {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoMatchingSongsError,
+                delegate: None,
+            }]);
+        };
+        let mut messages = match self.remove_blocked_songs(guild_id, vec![first_song]).await {
+            Ok(songs) => {
+                let metadata = QueuedSongsMetadata::Single(songs[0].metadata.clone());
+                let guild_model = guild_model_handle
+                    .lock()
+                    .await
+                    .map_err(crate::error::Error::ModelLock)?;
+                self.queue_songs(ctx, user_id, guild_id, guild_model, songs, metadata)
+                    .await?
+            }
+            Err(message) => vec![message],
+        };
+
+        if line_count > 1 {
+            messages.push(Message::Response {
+                message: ResponseMessage::QueueingRemainingLines {
+                    line_count: line_count - 1,
+                },
+                delegate: None,
+            });
+        }
+        self.clone()
+            .queue_remaining_songs(ctx.clone(), user_id, guild_id, songs, None);
+        Ok(messages)
+    }
+
+    pub(super) async fn handle_queue_urls_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        urls: &[String],
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if urls.is_empty() {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoLinksInMessageError,
+                delegate: None,
+            }]);
+        }
+
+        // Messages can contain links to anything, so links that can't be played are skipped
+        // instead of failing the whole command.
+        let guild_config = self.guild_config(guild_id).await;
+        let play_config = guild_config.get_play_config();
+        let load_futures: Vec<_> = urls
+            .iter()
+            .map(|url| Song::load(url, user_id, &play_config))
+            .collect();
+        let song_results: Vec<_> = stream::iter(load_futures)
+            .buffered(PLAYLIST_LOAD_CONCURRENCY)
+            .collect()
+            .await;
+        let songs: Vec<_> = song_results
+            .into_iter()
+            .flat_map(|songs_res| match songs_res {
+                Ok(songs) => songs,
+                Err(why) => {
+                    tracing::warn!("Error while resolving message link: {}", why);
+                    Vec::new()
+                }
+            })
+            .collect();
+
+        let songs = match self.remove_blocked_songs(guild_id, songs).await {
+            Ok(songs) => songs,
+            Err(message) => return Ok(vec![message]),
+        };
+
+        let metadata = match songs.len() {
+            0 => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::NoMatchingSongsError,
+                    delegate: None,
+                }]);
+            }
+            1 => QueuedSongsMetadata::Single(songs[0].metadata.clone()),
+            count => QueuedSongsMetadata::Multiple(count),
+        };
+        tracing::trace!(
+            "Resolved {} songs from {} message links",
+            songs.len(),
+            urls.len()
+        );
+
+        let guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        self.queue_songs(ctx, user_id, guild_id, guild_model, songs, metadata)
+            .await
+    }
+
+    async fn handle_queue_attachment_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        attachment: &Attachment,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let is_media = attachment
+            .content_type
+            .as_deref()
+            .is_some_and(|content_type| {
+                content_type.starts_with("audio/") || content_type.starts_with("video/")
+            });
+        if !is_media {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::UnsupportedAttachmentError,
+                delegate: None,
+            }]);
+        }
+
+        let song = Song::from_remote_file(&attachment.url, &attachment.filename, user_id);
+        let songs = match self.remove_blocked_songs(guild_id, vec![song]).await {
+            Ok(songs) => songs,
+            Err(message) => return Ok(vec![message]),
+        };
+        let metadata = QueuedSongsMetadata::Single(songs[0].metadata.clone());
+
+        let guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        self.queue_songs(ctx, user_id, guild_id, guild_model, songs, metadata)
+            .await
+    }
+
+    /// Adds songs to the user's queue, starting playback in their channel if needed.
+    pub(super) async fn queue_songs(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        mut guild_model: GuildModelGuard<QueuedSong>,
+        songs: Vec<Song>,
+        metadata: QueuedSongsMetadata,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let push_status = guild_model.push_entries(
+            user_id,
+            songs.into_iter().map(|song| QueuedSong {
+                song,
+                queue_message_id: None,
+            }),
+        );
+        if let PushStatus::QueueFull {
+            queued_count,
+            max_count,
+        } = push_status
+        {
+            tracing::trace!("User's queue is full, songs will not be queued");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::QueueFullError {
+                    queued_count,
+                    max_count,
+                },
+                delegate: None,
+            }]);
+        }
+
+        self.play_queued_songs(ctx, user_id, guild_id, guild_model, metadata)
+            .await
+    }
+
+    pub(super) async fn play_queued_songs(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        mut guild_model: GuildModelGuard<QueuedSong>,
+        metadata: QueuedSongsMetadata,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        // From this point on the user needs to be in a channel, otherwise the songs will only stay
+        // queued.
+        let Some(channel_id) = get_user_voice_channel(&ctx.cache, guild_id, user_id) else {
+            tracing::trace!("User is not in any voice channel, song will remain queued");
+            return match metadata {
+                QueuedSongsMetadata::Single(song_metadata) => Ok(vec![build_queued_message(
+                    self.clone(),
+                    guild_id,
+                    user_id,
+                    song_metadata.id,
+                    ResponseMessage::Queued {
+                        details: SongDetails::new(&song_metadata),
+                        song_title: song_metadata.title,
+                        song_url: song_metadata.url,
+                    },
+                )]),
+                QueuedSongsMetadata::Multiple(count) => Ok(vec![Message::Response {
+                    message: ResponseMessage::QueuedMultiple { count },
+                    delegate: None,
+                }]),
+            };
+        };
+
+        // Find a speaker that will be able to play in this channel. We do this before checking if
+        // we actually need to play anything so the song can stay in the queue if a speaker isn't
+        // found.
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        let guild_speaker = match guild_speakers_ref.find_to_play_in_channel(channel_id).await {
+            Some(speaker) => speaker,
+            None => {
+                tracing::trace!(
+                    "No speakers are available to handle playback, song will remain queued"
+                );
+                return match metadata {
+                    QueuedSongsMetadata::Single(song_metadata) => Ok(vec![build_queued_message(
+                        self.clone(),
+                        guild_id,
+                        user_id,
+                        song_metadata.id,
+                        ResponseMessage::QueuedNoSpeakers {
+                            details: SongDetails::new(&song_metadata),
+                            song_title: song_metadata.title,
+                            song_url: song_metadata.url,
+                        },
+                    )]),
+                    QueuedSongsMetadata::Multiple(count) => Ok(vec![Message::Response {
+                        message: ResponseMessage::QueuedMultipleNoSpeakers { count },
+                        delegate: None,
+                    }]),
+                };
+            }
+        };
+
+        // Play a song if the model indicates one isn't playing.
+        let next_song = match guild_model.next_channel_entry(&ctx.cache, channel_id) {
+            NextEntry::Entry(song) => song,
+            NextEntry::AlreadyPlaying | NextEntry::NoneAvailable => {
+                tracing::trace!("Channel is already playing, song will remain queued");
+                return match metadata {
+                    QueuedSongsMetadata::Single(song_metadata) => Ok(vec![build_queued_message(
+                        self.clone(),
+                        guild_id,
+                        user_id,
+                        song_metadata.id,
+                        ResponseMessage::Queued {
+                            details: SongDetails::new(&song_metadata),
+                            song_title: song_metadata.title,
+                            song_url: song_metadata.url,
+                        },
+                    )]),
+                    QueuedSongsMetadata::Multiple(count) => Ok(vec![Message::Response {
+                        message: ResponseMessage::QueuedMultiple { count },
+                        delegate: None,
+                    }]),
+                };
+            }
+        };
+
+        let next_metadata = next_song.song.metadata.clone();
+        let skip_votes = guild_model.skip_vote_count(&ctx.cache, VoteType::Skip, channel_id);
+        let play_res = self
+            .play_to_speaker(ctx, guild_model, guild_speaker, channel_id, next_song)
+            .await;
+        if let Err(why) = play_res {
+            drop(guild_speakers_ref);
+            self.set_play_failed(guild_id, channel_id).await;
+            return Err(why);
+        }
+
+        // We could be in one of three states:
+        //  - One song was queued, and we're now playing that song. We only show a "playing"
+        //    message.
+        //  - Multiple songs were queued, and we're playing the first one. We show a "queued"
+        //    message and a "playing" message.
+        //    todo: maybe we should combine these in this case
+        // - We queued one or more songs and started a different song, which can happen if there
+        //   were other songs waiting but we weren't playing at the time.
+        match metadata {
+            QueuedSongsMetadata::Single(song_metadata) => {
+                if next_metadata.url == song_metadata.url {
+                    Ok(vec![
+                        build_playing_message(
+                            self.clone(),
+                            guild_speaker,
+                            true,
+                            channel_id,
+                            song_metadata,
+                            skip_votes,
+                        )
+                        .await,
+                    ])
+                } else {
+                    Ok(vec![
+                        build_queued_message(
+                            self.clone(),
+                            guild_id,
+                            user_id,
+                            song_metadata.id,
+                            ResponseMessage::Queued {
+                                details: SongDetails::new(&song_metadata),
+                                song_title: song_metadata.title,
+                                song_url: song_metadata.url,
+                            },
+                        ),
+                        build_playing_message(
+                            self.clone(),
+                            guild_speaker,
+                            false,
+                            channel_id,
+                            next_metadata,
+                            skip_votes,
+                        )
+                        .await,
+                    ])
+                }
+            }
+            QueuedSongsMetadata::Multiple(count) => Ok(vec![
+                Message::Response {
+                    message: ResponseMessage::QueuedMultiple { count },
+                    delegate: None,
+                },
+                build_playing_message(
+                    self.clone(),
+                    guild_speaker,
+                    false,
+                    channel_id,
+                    next_metadata,
+                    skip_votes,
+                )
+                .await,
+            ]),
+        }
+    }
+
+    async fn handle_search_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+        guild_id: GuildId,
+        term: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let guild_config = self.guild_config(guild_id).await;
+        let play_config = guild_config.get_play_config();
+        let songs = match Song::search(
+            term,
+            self.config().search_results_count,
+            user_id,
+            &play_config,
+        )
+        .await
+        {
+            Ok(songs) => songs,
+            Err(mrvn_back_ytdl::Error::LoginRequired(why)) => {
+                tracing::debug!("Song needs a logged in account: {}", why);
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::LoginRequiredError,
+                    delegate: None,
+                }]);
+            }
+            Err(why) => return Err(crate::error::Error::Backend(why)),
+        };
+
+        if songs.is_empty() {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NoMatchingSongsError,
+                delegate: None,
+            }]);
+        }
+        tracing::trace!("Found {} search results", songs.len());
+
+        let results = songs
+            .iter()
+            .map(|song| SearchMessageEntry {
+                song_title: song.metadata.title.clone(),
+                song_url: song.metadata.url.clone(),
+                duration_seconds: song.metadata.duration_seconds,
+            })
+            .collect();
+
+        let search_id = self.add_pending_search(user_id, songs).await;
+        Ok(vec![Message::Response {
+            message: ResponseMessage::SearchResults { search_id, results },
+            delegate: None,
+        }])
+    }
+
+    /// Keeps songs around until the user chooses from them. Interactions can't be responded to
+    /// after a while, so searches that are older than that can be forgotten.
+    async fn add_pending_search(&self, user_id: UserId, songs: Vec<Song>) -> Uuid {
+        let search_id = Uuid::new_v4();
+        let mut pending_searches = self.pending_searches.lock().await;
+        pending_searches.retain(|_, search| search.created_time.elapsed() < SEARCH_EXPIRY);
+        pending_searches.insert(
+            search_id,
+            PendingSearch {
+                user_id,
+                created_time: Instant::now(),
+                songs,
+            },
+        );
+        search_id
+    }
+
+    /// Takes the songs a user is choosing from and removes the menu they were chosen with, since
+    /// only one choice can be made. Only the user who the songs were found for can choose from
+    /// them, otherwise that user's ID is returned as the error.
+    async fn take_pending_search(
+        &self,
+        ctx: &Context,
+        component: &ComponentInteraction,
+        user_id: UserId,
+        search_id: Uuid,
+    ) -> Result<Option<Vec<Song>>, UserId> {
+        let maybe_songs = {
+            let mut pending_searches = self.pending_searches.lock().await;
+            match pending_searches.get(&search_id) {
+                Some(search) if search.user_id != user_id => return Err(search.user_id),
+                Some(_) => pending_searches
+                    .remove(&search_id)
+                    .map(|search| search.songs),
+                None => None,
+            }
+        };
+
+        let edit_res = component
+            .message
+            .channel_id
+            .edit_message(
+                ctx,
+                component.message.id,
+                EditMessage::new().components(Vec::new()),
+            )
+            .await;
+        if let Err(why) = edit_res {
+            tracing::error!("Error while removing choice menu: {}", why);
+        }
+        Ok(maybe_songs)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn handle_search_select(
+        self: &Arc<Self>,
+        ctx: &Context,
+        component: &ComponentInteraction,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        search_id: Uuid,
+        maybe_index: Option<usize>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let maybe_song = match self
+            .take_pending_search(ctx, component, user_id, search_id)
+            .await
+        {
+            Ok(maybe_songs) => maybe_songs
+                .zip(maybe_index)
+                .and_then(|(songs, index)| songs.into_iter().nth(index)),
+            Err(owner_id) => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::SearchNotOwnerError { user_id: owner_id },
+                    delegate: None,
+                }]);
+            }
+        };
+
+        let Some(song) = maybe_song else {
+            tracing::trace!("Search is no longer available, nothing will be queued");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::SearchExpiredError,
+                delegate: None,
+            }]);
+        };
+
+        let song = match self.remove_blocked_songs(guild_id, vec![song]).await {
+            Ok(mut songs) => songs.remove(0),
+            Err(message) => return Ok(vec![message]),
+        };
+
+        tracing::trace!(
+            "Chose search result {} (\"{}\")",
+            song.metadata.url,
+            song.metadata.title
+        );
+        let metadata = QueuedSongsMetadata::Single(song.metadata.clone());
+        let mut guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        let push_status = guild_model.push_entries(
+            user_id,
+            std::iter::once(QueuedSong {
+                song,
+                queue_message_id: None,
+            }),
+        );
+        if let PushStatus::QueueFull {
+            queued_count,
+            max_count,
+        } = push_status
+        {
+            tracing::trace!("User's queue is full, songs will not be queued");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::QueueFullError {
+                    queued_count,
+                    max_count,
+                },
+                delegate: None,
+            }]);
+        }
+
+        self.play_queued_songs(ctx, user_id, guild_id, guild_model, metadata)
+            .await
+    }
+
+    async fn handle_replace_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        term: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let guild_config = self.guild_config(guild_id).await;
+        let play_config = guild_config.get_play_config();
+
+        let songs = match Song::load(term, user_id, &play_config).await {
+            Ok(data) => data,
+            Err(mrvn_back_ytdl::Error::UnsupportedUrl) => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::UnsupportedSiteError,
+                    delegate: None,
+                }]);
+            }
+            Err(mrvn_back_ytdl::Error::LoginRequired(why)) => {
+                tracing::debug!("Song needs a logged in account: {}", why);
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::LoginRequiredError,
+                    delegate: None,
+                }]);
+            }
+            Err(why) => return Err(crate::error::Error::Backend(why)),
+        };
+
+        let songs = match self.remove_blocked_songs(guild_id, songs).await {
+            Ok(songs) => songs,
+            Err(message) => return Ok(vec![message]),
+        };
+
+        if songs.len() == 1 {
+            let song_metadata = &songs[0].metadata;
+            tracing::trace!(
+                "Resolved song query as {} (\"{}\")",
+                song_metadata.url,
+                song_metadata.title
+            );
+        } else {
+            tracing::trace!("Resolved song query as {} songs", songs.len());
+        }
+
+        let mut songs_iter = songs.into_iter().map(|song| QueuedSong {
+            song,
+            queue_message_id: None,
+        });
+        let queued_song = match songs_iter.next() {
+            Some(song) => song,
+            None => {
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::NoMatchingSongsError,
+                    delegate: None,
+                }])
+            }
+        };
+
+        let song_metadata = queued_song.song.metadata.clone();
+        // The model is only locked once the song has loaded, so other commands aren't held up
+        let mut guild_model = guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?;
+        let maybe_channel_id = get_user_voice_channel(&ctx.cache, guild_id, user_id);
+        let replace_status = guild_model.replace_entry(user_id, maybe_channel_id, queued_song);
+        if let PushStatus::QueueFull { .. } = guild_model.push_more_entries(user_id, songs_iter) {
+            tracing::trace!("User's queue is full, remaining songs will not be queued");
+        }
+
+        let channel_id = match replace_status {
+            // If the song was queued, no playback changes are needed so we send a status message
+            // and leave it there. But if the model indicated we're replacing the current song,
+            // we need to start playing the next song.
+            ReplaceStatus::Queued => {
+                tracing::trace!("No songs in queue to replace, song will be queued");
+                return Ok(vec![build_queued_message(
+                    self.clone(),
+                    guild_id,
+                    user_id,
+                    song_metadata.id,
+                    ResponseMessage::Queued {
+                        details: SongDetails::new(&song_metadata),
+                        song_title: song_metadata.title,
+                        song_url: song_metadata.url,
+                    },
+                )]);
+            }
+            ReplaceStatus::ReplacedInQueue(old_song) => {
+                tracing::trace!("Latest song in the users queue will be replaced");
+                return Ok(vec![build_queued_message(
+                    self.clone(),
+                    guild_id,
+                    user_id,
+                    song_metadata.id,
+                    ResponseMessage::Replaced {
+                        old_song_title: old_song.song.metadata.title,
+                        old_song_url: old_song.song.metadata.url,
+                        new_song_title: song_metadata.title,
+                        new_song_url: song_metadata.url,
+                    },
+                )]);
+            }
+            ReplaceStatus::ReplacedCurrent(channel_id) => channel_id,
+        };
+
+        tracing::trace!("Only song queued by user is currently playing, it will be skipped");
+
+        // We're replacing an already-playing song, so if there's no speaker for this channel
+        // something has gone very wrong :(
+        let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+        let mut guild_speakers_ref = guild_speakers_handle.lock();
+        let (guild_speaker, playing_metadata) = guild_speakers_ref
+            .find_active_in_channel(channel_id)
+            .await
+            .ok_or(crate::error::Error::ModelPlayingSpeakerNotDesync)?;
+
+        // Play a song if the model indicates one isn't playing.
+        let next_song = match guild_model.next_channel_entry_finished(&ctx.cache, channel_id) {
+            Some(song) => song,
+            None => {
+                tracing::trace!("New song is no longer accessible in queue, nothing will play");
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::NothingIsQueuedError {
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                }]);
+            }
+        };
+
+        let next_metadata = next_song.song.metadata.clone();
+        let skip_votes = guild_model.skip_vote_count(&ctx.cache, VoteType::Skip, channel_id);
+        let play_res = self
+            .play_to_speaker(ctx, guild_model, guild_speaker, channel_id, next_song)
+            .await;
+        if let Err(why) = play_res {
+            drop(guild_speakers_ref);
+            self.set_play_failed(guild_id, channel_id).await;
+            return Err(why);
+        }
+
+        // We could be in one of two states:
+        //  - The song that's now playing is the one we just queued, in which case we only show a
+        //    "playing" message.
+        //  - We queued a song and started a different song, which can happen if there were other
+        //    songs waiting but we weren't playing at the time. In this case we show a "queued"
+        //    message and a "playing" message.
+        if next_metadata.url == song_metadata.url {
+            Ok(vec![
+                build_playing_message(
+                    self.clone(),
+                    guild_speaker,
+                    true,
+                    channel_id,
+                    song_metadata,
+                    skip_votes,
+                )
+                .await,
+            ])
+        } else {
+            Ok(vec![
+                Message::Response {
+                    message: ResponseMessage::ReplaceSkipped {
+                        new_song_title: song_metadata.title,
+                        new_song_url: song_metadata.url,
+                        old_song_title: playing_metadata.title,
+                        old_song_url: playing_metadata.url,
+                        voice_channel_id: channel_id,
+                    },
+                    delegate: None,
+                },
+                build_playing_message(
+                    self.clone(),
+                    guild_speaker,
+                    false,
+                    channel_id,
+                    next_metadata,
+                    skip_votes,
+                )
+                .await,
+            ])
+        }
+    }
+
+    async fn handle_remove_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        position: usize,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let maybe_removed_song = position
+            .checked_sub(1)
+            .and_then(|index| guild_model.remove_user_entry(user_id, index));
+        let message = match maybe_removed_song {
+            Some(removed_song) => ResponseMessage::QueueEntryRemoved {
+                song_title: removed_song.song.metadata.title,
+                song_url: removed_song.song.metadata.url,
+            },
+            None => ResponseMessage::NoQueueEntryError { position },
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_jump_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        position: usize,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let maybe_moved_song = position
+            .checked_sub(1)
+            .and_then(|index| guild_model.move_user_entry_to_front(user_id, index));
+        let message = match maybe_moved_song {
+            Some(moved_song) => ResponseMessage::QueueEntryMoved {
+                song_title: moved_song.song.metadata.title.clone(),
+                song_url: moved_song.song.metadata.url.clone(),
+            },
+            None => ResponseMessage::NoQueueEntryError { position },
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_wrong_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+        guild_model: &mut GuildModel<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let Some(removed_song) = guild_model.remove_last_user_entry(user_id) else {
+            tracing::trace!("User has nothing queued, nothing will be removed");
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingToRemoveError,
+                delegate: None,
+            }]);
+        };
+
+        tracing::trace!(
+            "Removed {} (\"{}\") from the user's queue",
+            removed_song.song.metadata.url,
+            removed_song.song.metadata.title
+        );
+        Ok(vec![Message::Response {
+            message: ResponseMessage::WrongSongRemoved {
+                song_title: removed_song.song.metadata.title,
+                song_url: removed_song.song.metadata.url,
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_undo_command(
+        self: &Arc<Self>,
+        user_id: UserId,
+        guild_model: &mut GuildModel<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let message = match guild_model.undo_last_action(user_id) {
+            UndoStatus::Undone {
+                removed_count,
+                restored_count,
+            } => {
+                tracing::trace!(
+                    "Undid user's last queue change, removed {} and restored {} songs",
+                    removed_count,
+                    restored_count
+                );
+                ResponseMessage::Undone {
+                    removed_count,
+                    restored_count,
+                }
+            }
+            UndoStatus::NothingToUndo => ResponseMessage::NothingToUndoError,
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_queue_export_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        is_channel: bool,
+        format: QueueFileFormat,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let maybe_channel_id = get_user_voice_channel(&ctx.cache, guild_id, user_id);
+        if is_channel && maybe_channel_id.is_none() {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NotInVoiceChannelError,
+                delegate: None,
+            }]);
+        }
+
+        // The export starts with the song that's playing, the same as a saved playlist.
+        let mut entries = Vec::new();
+        if let Some(channel_id) = maybe_channel_id {
+            let guild_speakers_handle = self.backend_brain.guild_speakers(guild_id);
+            let mut guild_speakers_ref = guild_speakers_handle.lock();
+            if let Some((_, active_metadata)) =
+                guild_speakers_ref.find_active_in_channel(channel_id).await
+            {
+                if is_channel || active_metadata.user_id == user_id {
+                    entries.push(PlaylistEntry {
+                        title: active_metadata.title,
+                        url: active_metadata.url,
+                    });
+                }
+            }
+        }
+
+        // Everyone listening in the channel has their queue exported, one queue after another.
+        let is_exported_user = |queue_user_id: UserId| match maybe_channel_id {
+            Some(channel_id) if is_channel => {
+                get_user_voice_channel(&ctx.cache, guild_id, queue_user_id) == Some(channel_id)
+            }
+            _ => queue_user_id == user_id,
+        };
+        entries.extend(
+            guild_model
+                .queued_entries()
+                .filter(|(queue_user_id, _)| is_exported_user(*queue_user_id))
+                .map(|(_, queued_song)| PlaylistEntry {
+                    title: queued_song.song.metadata.title.clone(),
+                    url: queued_song.song.metadata.url.clone(),
+                }),
+        );
+
+        if entries.is_empty() {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::NothingToExportError,
+                delegate: None,
+            }]);
+        }
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::QueueExported {
+                count: entries.len(),
+                file_name: format.file_name().to_string(),
+                file_data: format.write(&entries),
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_queue_import_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        user_id: UserId,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+        attachment: &Attachment,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if attachment.size > MAX_QUEUE_FILE_SIZE {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::InvalidQueueFileError,
+                delegate: None,
+            }]);
+        }
+        let data = attachment
+            .download()
+            .await
+            .map_err(crate::error::Error::Serenity)?;
+
+        let mut urls = crate::queue_file::read_urls(&data);
+        if urls.is_empty() {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::InvalidQueueFileError,
+                delegate: None,
+            }]);
+        }
+        let max_playlist_size = self.guild_config(guild_id).await.max_playlist_size;
+        if max_playlist_size != 0 {
+            urls.truncate(max_playlist_size);
+        }
+        tracing::trace!("Read {} links from queue file", urls.len());
+
+        self.handle_queue_urls_command(ctx, user_id, guild_id, guild_model_handle, &urls)
+            .await
+    }
+}
@@ -0,0 +1,869 @@
+use crate::commands::{can_disable_command, register_guild_commands};
+use crate::frontend::{get_string_option, get_subcommand, Frontend};
+use crate::guild_settings::SettingKey;
+use crate::invocation::CommandInvocation;
+use crate::message::{Message, PriorityRoleMessageEntry, ResponseMessage, SettingMessageEntry};
+use crate::queued_song::QueuedSong;
+use mrvn_back_ytdl::Song;
+use mrvn_model::{GuildModel, GuildModelHandle, GuildSettings};
+use serenity::{
+    model::prelude::{GuildId, RoleId, UserId},
+    prelude::*,
+};
+use std::ops::DerefMut;
+use std::sync::Arc;
+
+impl Frontend {
+    /// Commands that change the guild's settings, like who can use the bot and how.
+    pub(super) async fn dispatch_settings_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        command: &CommandInvocation<'_>,
+        guild_id: GuildId,
+        guild_model_handle: &GuildModelHandle<QueuedSong>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let _user_id = command.user_id;
+        match command.name.as_str() {
+            "language" => {
+                let maybe_name = get_string_option(&command.options, "name")
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty());
+                tracing::debug!("Received language {:?}", maybe_name);
+                self.handle_language_command(guild_id, maybe_name).await
+            }
+            "settings" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                let maybe_key = get_string_option(options, "name").and_then(SettingKey::parse);
+                let value = get_string_option(options, "value")
+                    .unwrap_or_default()
+                    .trim();
+                match (subcommand_name, maybe_key) {
+                    ("view", _) => {
+                        tracing::debug!("Received settings view");
+                        self.handle_settings_view_command(guild_id).await
+                    }
+                    ("set", Some(key)) => {
+                        tracing::debug!("Received settings set {} \"{}\"", key.name(), value);
+                        self.handle_settings_set_command(
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            key,
+                            value,
+                        )
+                        .await
+                    }
+                    ("reset", Some(key)) => {
+                        tracing::debug!("Received settings reset {}", key.name());
+                        self.handle_settings_reset_command(
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            key,
+                        )
+                        .await
+                    }
+                    (subcommand_name, _) => Err(crate::error::Error::UnknownCommand(format!(
+                        "settings {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            "dj" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                let maybe_role_id = options
+                    .iter()
+                    .find(|option| option.name == "role")
+                    .and_then(|option| option.value.as_role_id());
+                match (subcommand_name, maybe_role_id) {
+                    ("add", Some(role_id)) => {
+                        tracing::debug!("Received dj add {}", role_id);
+                        self.handle_dj_add_command(
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            role_id,
+                        )
+                        .await
+                    }
+                    ("remove", Some(role_id)) => {
+                        tracing::debug!("Received dj remove {}", role_id);
+                        self.handle_dj_remove_command(
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            role_id,
+                        )
+                        .await
+                    }
+                    ("list", _) => {
+                        tracing::debug!("Received dj list");
+                        self.handle_dj_list_command(guild_id).await
+                    }
+                    (subcommand_name, _) => Err(crate::error::Error::UnknownCommand(format!(
+                        "dj {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            "priority" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                let maybe_role_id = options
+                    .iter()
+                    .find(|option| option.name == "role")
+                    .and_then(|option| option.value.as_role_id());
+                let maybe_priority = options
+                    .iter()
+                    .find(|option| option.name == "priority")
+                    .and_then(|option| option.value.as_i64())
+                    .and_then(|priority| u32::try_from(priority).ok());
+                match (subcommand_name, maybe_role_id, maybe_priority) {
+                    ("set", Some(role_id), Some(priority)) => {
+                        tracing::debug!("Received priority set {} {}", role_id, priority);
+                        self.handle_priority_set_command(
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            role_id,
+                            priority,
+                        )
+                        .await
+                    }
+                    ("list", _, _) => {
+                        tracing::debug!("Received priority list");
+                        self.handle_priority_list_command(guild_id).await
+                    }
+                    (subcommand_name, _, _) => Err(crate::error::Error::UnknownCommand(format!(
+                        "priority {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            "botban" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                let maybe_user_id = options
+                    .iter()
+                    .find(|option| option.name == "user")
+                    .and_then(|option| option.value.as_user_id());
+                match (subcommand_name, maybe_user_id) {
+                    ("add", Some(banned_user_id)) => {
+                        tracing::debug!("Received botban add {}", banned_user_id);
+                        self.handle_botban_add_command(
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            banned_user_id,
+                        )
+                        .await
+                    }
+                    ("remove", Some(banned_user_id)) => {
+                        tracing::debug!("Received botban remove {}", banned_user_id);
+                        self.handle_botban_remove_command(
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            banned_user_id,
+                        )
+                        .await
+                    }
+                    ("list", _) => {
+                        tracing::debug!("Received botban list");
+                        self.handle_botban_list_command(guild_id).await
+                    }
+                    (subcommand_name, _) => Err(crate::error::Error::UnknownCommand(format!(
+                        "botban {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            "clearuser" => {
+                let maybe_user_id = command
+                    .options
+                    .iter()
+                    .find(|option| option.name == "user")
+                    .and_then(|option| option.value.as_user_id());
+                match maybe_user_id {
+                    Some(cleared_user_id) => {
+                        tracing::debug!("Received clearuser {}", cleared_user_id);
+                        self.handle_clearuser_command(
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            cleared_user_id,
+                        )
+                        .await
+                    }
+                    None => Err(crate::error::Error::UnknownCommand("clearuser".to_string())),
+                }
+            }
+            "commands" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                let name = get_string_option(options, "name")
+                    .unwrap_or_default()
+                    .trim()
+                    .trim_start_matches('/');
+                match subcommand_name {
+                    "disable" => {
+                        tracing::debug!("Received commands disable \"{}\"", name);
+                        self.handle_commands_disable_command(
+                            ctx,
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            name,
+                        )
+                        .await
+                    }
+                    "enable" => {
+                        tracing::debug!("Received commands enable \"{}\"", name);
+                        self.handle_commands_enable_command(
+                            ctx,
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            name,
+                        )
+                        .await
+                    }
+                    "list" => {
+                        tracing::debug!("Received commands list");
+                        self.handle_commands_list_command(guild_id).await
+                    }
+                    subcommand_name => Err(crate::error::Error::UnknownCommand(format!(
+                        "commands {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            "block" => {
+                let (subcommand_name, options) = get_subcommand(command).unwrap_or_default();
+                let entry = get_string_option(options, "entry")
+                    .unwrap_or_default()
+                    .trim();
+                match subcommand_name {
+                    "add" => {
+                        tracing::debug!("Received block add \"{}\"", entry);
+                        self.handle_block_add_command(
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            entry,
+                        )
+                        .await
+                    }
+                    "remove" => {
+                        tracing::debug!("Received block remove \"{}\"", entry);
+                        self.handle_block_remove_command(
+                            guild_id,
+                            guild_model_handle
+                                .lock()
+                                .await
+                                .map_err(crate::error::Error::ModelLock)?
+                                .deref_mut(),
+                            entry,
+                        )
+                        .await
+                    }
+                    "list" => {
+                        tracing::debug!("Received block list");
+                        self.handle_block_list_command(guild_id).await
+                    }
+                    subcommand_name => Err(crate::error::Error::UnknownCommand(format!(
+                        "block {}",
+                        subcommand_name
+                    ))),
+                }
+            }
+            command_name => Err(crate::error::Error::UnknownCommand(
+                command_name.to_string(),
+            )),
+        }
+    }
+
+    async fn handle_language_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        maybe_name: Option<&str>,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if let Some(name) = maybe_name {
+            if !self.config().has_locale(name) {
+                let mut languages: Vec<_> = self.config().locales.keys().cloned().collect();
+                languages.sort();
+                return Ok(vec![Message::Response {
+                    message: ResponseMessage::UnknownLanguageError {
+                        name: name.to_string(),
+                        languages,
+                    },
+                    delegate: None,
+                }]);
+            }
+        }
+
+        self.settings
+            .update_guild(guild_id, |settings| {
+                settings.locale = maybe_name.map(str::to_string)
+            })
+            .await
+            .map_err(crate::error::Error::Storage)?;
+
+        let message = match maybe_name {
+            Some(name) => ResponseMessage::LanguageSet {
+                name: name.to_string(),
+            },
+            None => ResponseMessage::LanguageReset,
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_settings_view_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let guild_config = self.guild_config(guild_id).await;
+        let guild_settings = self
+            .settings
+            .read(|settings| settings.get(guild_id).cloned())
+            .await
+            .unwrap_or_default();
+
+        let settings = SettingKey::ALL
+            .into_iter()
+            .map(|key| SettingMessageEntry {
+                name: key.name().to_string(),
+                value: key.format_value(&guild_config),
+                is_overridden: key.is_overridden(&guild_settings),
+            })
+            .collect();
+        Ok(vec![Message::Response {
+            message: ResponseMessage::Settings { settings },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_settings_set_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        key: SettingKey,
+        value: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let is_valid = self
+            .update_guild_settings(guild_id, guild_model, |settings| key.set(settings, value))
+            .await?;
+        if !is_valid {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::InvalidSettingValueError {
+                    name: key.name().to_string(),
+                    value: value.to_string(),
+                },
+                delegate: None,
+            }]);
+        }
+
+        let guild_config = self.guild_config(guild_id).await;
+        Ok(vec![Message::Response {
+            message: ResponseMessage::SettingChanged {
+                name: key.name().to_string(),
+                value: key.format_value(&guild_config),
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_settings_reset_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        key: SettingKey,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        self.update_guild_settings(guild_id, guild_model, |settings| key.reset(settings))
+            .await?;
+
+        let guild_config = self.guild_config(guild_id).await;
+        Ok(vec![Message::Response {
+            message: ResponseMessage::SettingReset {
+                name: key.name().to_string(),
+                value: key.format_value(&guild_config),
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_dj_add_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        role_id: RoleId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        self.update_guild_settings(guild_id, guild_model, |settings| {
+            if !settings.dj_roles.contains(&role_id) {
+                settings.dj_roles.push(role_id);
+            }
+        })
+        .await?;
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::DjRoleAdded { role_id },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_dj_remove_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        role_id: RoleId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let was_removed = self
+            .update_guild_settings(guild_id, guild_model, |settings| {
+                let old_len = settings.dj_roles.len();
+                settings
+                    .dj_roles
+                    .retain(|dj_role_id| *dj_role_id != role_id);
+                settings.dj_roles.len() != old_len
+            })
+            .await?;
+
+        let message = if was_removed {
+            ResponseMessage::DjRoleRemoved { role_id }
+        } else {
+            ResponseMessage::NotDjRoleError { role_id }
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_dj_list_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let role_ids = self
+            .settings
+            .read(|settings| {
+                settings
+                    .get(guild_id)
+                    .map(|guild_settings| guild_settings.dj_roles.clone())
+                    .unwrap_or_default()
+            })
+            .await;
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::DjRoles { role_ids },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_priority_set_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        role_id: RoleId,
+        priority: u32,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        self.update_guild_settings(guild_id, guild_model, |settings| {
+            if priority == 0 {
+                settings.priority_roles.remove(&role_id);
+            } else {
+                settings.priority_roles.insert(role_id, priority);
+            }
+        })
+        .await?;
+
+        let message = if priority == 0 {
+            ResponseMessage::PriorityRoleRemoved { role_id }
+        } else {
+            ResponseMessage::PriorityRoleSet { role_id, priority }
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_priority_list_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let mut roles: Vec<_> = self
+            .settings
+            .read(|settings| {
+                settings
+                    .get(guild_id)
+                    .map(|guild_settings| {
+                        guild_settings
+                            .priority_roles
+                            .iter()
+                            .map(|(role_id, priority)| PriorityRoleMessageEntry {
+                                role_id: *role_id,
+                                priority: *priority,
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
+            .await;
+        roles.sort_by_key(|role| std::cmp::Reverse(role.priority));
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::PriorityRoles { roles },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_clearuser_command(
+        self: &Arc<Self>,
+        guild_model: &mut GuildModel<QueuedSong>,
+        user_id: UserId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let removed_count = guild_model.clear_user_entries(user_id);
+        tracing::trace!("Removed {} entries queued by {}", removed_count, user_id);
+
+        let message = if removed_count == 0 {
+            ResponseMessage::UserNothingQueuedError { user_id }
+        } else {
+            ResponseMessage::UserQueueCleared {
+                user_id,
+                removed_count,
+            }
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_botban_add_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        user_id: UserId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        self.update_guild_settings(guild_id, guild_model, |settings| {
+            if !settings.banned_users.contains(&user_id) {
+                settings.banned_users.push(user_id);
+            }
+        })
+        .await?;
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::UserBanned { user_id },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_botban_remove_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        user_id: UserId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let was_removed = self
+            .update_guild_settings(guild_id, guild_model, |settings| {
+                let old_len = settings.banned_users.len();
+                settings
+                    .banned_users
+                    .retain(|banned_user_id| *banned_user_id != user_id);
+                settings.banned_users.len() != old_len
+            })
+            .await?;
+
+        let message = if was_removed {
+            ResponseMessage::UserUnbanned { user_id }
+        } else {
+            ResponseMessage::NotBannedError { user_id }
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_botban_list_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let user_ids = self
+            .settings
+            .read(|settings| {
+                settings
+                    .get(guild_id)
+                    .map(|guild_settings| guild_settings.banned_users.clone())
+                    .unwrap_or_default()
+            })
+            .await;
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::BannedUsers { user_ids },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_commands_disable_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        if !can_disable_command(&self.config(), name) {
+            return Ok(vec![Message::Response {
+                message: ResponseMessage::UnknownCommandError {
+                    command_name: name.to_string(),
+                },
+                delegate: None,
+            }]);
+        }
+
+        self.update_guild_settings(guild_id, guild_model, |settings| {
+            if !settings
+                .disabled_commands
+                .iter()
+                .any(|existing| existing == name)
+            {
+                settings.disabled_commands.push(name.to_string());
+            }
+        })
+        .await?;
+        self.refresh_guild_commands(ctx, guild_id).await;
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::CommandDisabled {
+                command_name: name.to_string(),
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_commands_enable_command(
+        self: &Arc<Self>,
+        ctx: &Context,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        name: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let was_enabled = self
+            .update_guild_settings(guild_id, guild_model, |settings| {
+                let old_len = settings.disabled_commands.len();
+                settings
+                    .disabled_commands
+                    .retain(|existing| existing != name);
+                settings.disabled_commands.len() != old_len
+            })
+            .await?;
+
+        let command_name = name.to_string();
+        let message = if was_enabled {
+            self.refresh_guild_commands(ctx, guild_id).await;
+            ResponseMessage::CommandEnabled { command_name }
+        } else {
+            ResponseMessage::NotDisabledError { command_name }
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_commands_list_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let command_names = self.disabled_commands(guild_id).await;
+        Ok(vec![Message::Response {
+            message: ResponseMessage::DisabledCommands { command_names },
+            delegate: None,
+        }])
+    }
+
+    /// Commands are only registered for each guild if the bot is set up for a single guild.
+    /// Otherwise they're registered globally, and disabled commands are still shown.
+    async fn refresh_guild_commands(&self, ctx: &Context, guild_id: GuildId) {
+        let config = self.config();
+        if config.command_bot.guild_id != Some(guild_id.get()) {
+            return;
+        }
+        let disabled_commands = self.disabled_commands(guild_id).await;
+        if let Err(why) =
+            register_guild_commands(&ctx.http, guild_id, &config, &disabled_commands).await
+        {
+            tracing::error!("Error while registering guild commands: {}", why);
+        }
+    }
+
+    async fn handle_block_add_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        entry: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        self.update_guild_settings(guild_id, guild_model, |settings| {
+            if !settings.blocklist.iter().any(|existing| existing == entry) {
+                settings.blocklist.push(entry.to_string());
+            }
+        })
+        .await?;
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::BlocklistEntryAdded {
+                entry: entry.to_string(),
+            },
+            delegate: None,
+        }])
+    }
+
+    async fn handle_block_remove_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        entry: &str,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let was_removed = self
+            .update_guild_settings(guild_id, guild_model, |settings| {
+                let old_len = settings.blocklist.len();
+                settings.blocklist.retain(|existing| existing != entry);
+                settings.blocklist.len() != old_len
+            })
+            .await?;
+
+        let entry = entry.to_string();
+        let message = if was_removed {
+            ResponseMessage::BlocklistEntryRemoved { entry }
+        } else {
+            ResponseMessage::NotBlockedError { entry }
+        };
+        Ok(vec![Message::Response {
+            message,
+            delegate: None,
+        }])
+    }
+
+    async fn handle_block_list_command(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+    ) -> Result<Vec<crate::message::Message>, crate::error::Error> {
+        let entries = self
+            .settings
+            .read(|settings| {
+                settings
+                    .get(guild_id)
+                    .map(|guild_settings| guild_settings.blocklist.clone())
+                    .unwrap_or_default()
+            })
+            .await;
+
+        Ok(vec![Message::Response {
+            message: ResponseMessage::Blocklist { entries },
+            delegate: None,
+        }])
+    }
+
+    /// Removes any songs matching the guild's blocklist. If every song was blocked, a message
+    /// explaining why is returned instead.
+    pub(super) async fn remove_blocked_songs(
+        &self,
+        guild_id: GuildId,
+        songs: Vec<Song>,
+    ) -> Result<Vec<Song>, crate::message::Message> {
+        let mut first_blocked = None;
+        let allowed_songs: Vec<_> = self
+            .settings
+            .read(|settings| {
+                let Some(guild_settings) = settings.get(guild_id) else {
+                    return songs;
+                };
+                songs
+                    .into_iter()
+                    .filter(|song| {
+                        let maybe_entry = guild_settings
+                            .find_blocklist_entry(&song.metadata.url, &song.metadata.title);
+                        match maybe_entry {
+                            Some(entry) => {
+                                tracing::trace!(
+                                    "Song {} is blocked by entry \"{}\"",
+                                    song.metadata.url,
+                                    entry
+                                );
+                                if first_blocked.is_none() {
+                                    first_blocked =
+                                        Some((song.metadata.clone(), entry.to_string()));
+                                }
+                                false
+                            }
+                            None => true,
+                        }
+                    })
+                    .collect()
+            })
+            .await;
+
+        match first_blocked {
+            Some((song_metadata, entry)) if allowed_songs.is_empty() => Err(Message::Response {
+                message: ResponseMessage::BlockedSongError {
+                    song_title: song_metadata.title,
+                    song_url: song_metadata.url,
+                    entry,
+                },
+                delegate: None,
+            }),
+            _ => Ok(allowed_songs),
+        }
+    }
+
+    /// Changes a guild's settings and makes sure the new settings are used from now on.
+    async fn update_guild_settings<R>(
+        self: &Arc<Self>,
+        guild_id: GuildId,
+        guild_model: &mut GuildModel<QueuedSong>,
+        f: impl FnOnce(&mut GuildSettings) -> R,
+    ) -> Result<R, crate::error::Error> {
+        let result = self
+            .settings
+            .update_guild(guild_id, f)
+            .await
+            .map_err(crate::error::Error::Storage)?;
+
+        self.guild_configs.lock().unwrap().remove(&guild_id);
+        let model_config = self.guild_config(guild_id).await.get_model_config();
+        self.model.set_guild_config(guild_id, model_config);
+        guild_model.set_config(model_config);
+
+        Ok(result)
+    }
+}
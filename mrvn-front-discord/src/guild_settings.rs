@@ -0,0 +1,255 @@
+use crate::config::Config;
+use mrvn_model::GuildSettings;
+use serenity::model::id::ChannelId;
+use serenity::model::mention::Mentionable;
+
+/// Config values that can be overridden in each guild using `/settings`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingKey {
+    SkipVotesRequired,
+    StopVotesRequired,
+    PauseVotesRequired,
+    DisconnectMinInactiveSecs,
+    OnlyDisconnectWhenAlone,
+    ActionEmbedColor,
+    ResponseEmbedColor,
+    ErrorEmbedColor,
+    SearchPrefix,
+    SchedulingStrategy,
+    Autoplay,
+    AlwaysOn,
+    IdlePlaylist,
+    FollowMode,
+    ChannelTopic,
+    TopicChannel,
+    AnnouncementsChannel,
+    SessionThreads,
+    TtsAnnouncements,
+    LoudnessNormalization,
+    TrimSilence,
+}
+
+impl SettingKey {
+    pub const ALL: [SettingKey; 21] = [
+        SettingKey::SkipVotesRequired,
+        SettingKey::StopVotesRequired,
+        SettingKey::PauseVotesRequired,
+        SettingKey::DisconnectMinInactiveSecs,
+        SettingKey::OnlyDisconnectWhenAlone,
+        SettingKey::ActionEmbedColor,
+        SettingKey::ResponseEmbedColor,
+        SettingKey::ErrorEmbedColor,
+        SettingKey::SearchPrefix,
+        SettingKey::SchedulingStrategy,
+        SettingKey::Autoplay,
+        SettingKey::AlwaysOn,
+        SettingKey::IdlePlaylist,
+        SettingKey::FollowMode,
+        SettingKey::ChannelTopic,
+        SettingKey::TopicChannel,
+        SettingKey::AnnouncementsChannel,
+        SettingKey::SessionThreads,
+        SettingKey::TtsAnnouncements,
+        SettingKey::LoudnessNormalization,
+        SettingKey::TrimSilence,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SettingKey::SkipVotesRequired => "skip_votes_required",
+            SettingKey::StopVotesRequired => "stop_votes_required",
+            SettingKey::PauseVotesRequired => "pause_votes_required",
+            SettingKey::DisconnectMinInactiveSecs => "disconnect_min_inactive_secs",
+            SettingKey::OnlyDisconnectWhenAlone => "only_disconnect_when_alone",
+            SettingKey::ActionEmbedColor => "action_embed_color",
+            SettingKey::ResponseEmbedColor => "response_embed_color",
+            SettingKey::ErrorEmbedColor => "error_embed_color",
+            SettingKey::SearchPrefix => "search_prefix",
+            SettingKey::SchedulingStrategy => "scheduling_strategy",
+            SettingKey::Autoplay => "autoplay",
+            SettingKey::AlwaysOn => "always_on",
+            SettingKey::IdlePlaylist => "idle_playlist",
+            SettingKey::FollowMode => "follow_mode",
+            SettingKey::ChannelTopic => "channel_topic",
+            SettingKey::TopicChannel => "topic_channel",
+            SettingKey::AnnouncementsChannel => "announcements_channel",
+            SettingKey::SessionThreads => "session_threads",
+            SettingKey::TtsAnnouncements => "tts_announcements",
+            SettingKey::LoudnessNormalization => "loudness_normalization",
+            SettingKey::TrimSilence => "trim_silence",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        SettingKey::ALL.into_iter().find(|key| key.name() == name)
+    }
+
+    pub fn is_overridden(self, settings: &GuildSettings) -> bool {
+        match self {
+            SettingKey::SkipVotesRequired => settings.skip_votes_required.is_some(),
+            SettingKey::StopVotesRequired => settings.stop_votes_required.is_some(),
+            SettingKey::PauseVotesRequired => settings.pause_votes_required.is_some(),
+            SettingKey::DisconnectMinInactiveSecs => {
+                settings.disconnect_min_inactive_secs.is_some()
+            }
+            SettingKey::OnlyDisconnectWhenAlone => settings.only_disconnect_when_alone.is_some(),
+            SettingKey::ActionEmbedColor => settings.action_embed_color.is_some(),
+            SettingKey::ResponseEmbedColor => settings.response_embed_color.is_some(),
+            SettingKey::ErrorEmbedColor => settings.error_embed_color.is_some(),
+            SettingKey::SearchPrefix => settings.search_prefix.is_some(),
+            SettingKey::SchedulingStrategy => settings.scheduling_strategy.is_some(),
+            SettingKey::Autoplay => settings.autoplay.is_some(),
+            SettingKey::AlwaysOn => settings.always_on.is_some(),
+            SettingKey::IdlePlaylist => settings.idle_playlist.is_some(),
+            SettingKey::FollowMode => settings.follow_mode.is_some(),
+            SettingKey::ChannelTopic => settings.channel_topic.is_some(),
+            SettingKey::TopicChannel => settings.topic_channel.is_some(),
+            SettingKey::AnnouncementsChannel => settings.announcements_channel.is_some(),
+            SettingKey::SessionThreads => settings.session_threads.is_some(),
+            SettingKey::TtsAnnouncements => settings.tts_announcements.is_some(),
+            SettingKey::LoudnessNormalization => settings.loudness_normalization.is_some(),
+            SettingKey::TrimSilence => settings.trim_silence.is_some(),
+        }
+    }
+
+    /// Formats the setting's value in a config, in the same format it can be set with.
+    pub fn format_value(self, config: &Config) -> String {
+        match self {
+            SettingKey::SkipVotesRequired => config.skip_votes_required.to_string(),
+            SettingKey::StopVotesRequired => config.stop_votes_required.to_string(),
+            SettingKey::PauseVotesRequired => config.pause_votes_required.to_string(),
+            SettingKey::DisconnectMinInactiveSecs => {
+                config.disconnect_min_inactive_secs.to_string()
+            }
+            SettingKey::OnlyDisconnectWhenAlone => config.only_disconnect_when_alone.to_string(),
+            SettingKey::ActionEmbedColor => format!("{:06x}", config.action_embed_color),
+            SettingKey::ResponseEmbedColor => format!("{:06x}", config.response_embed_color),
+            SettingKey::ErrorEmbedColor => format!("{:06x}", config.error_embed_color),
+            SettingKey::SearchPrefix => config.search_prefix.clone(),
+            SettingKey::SchedulingStrategy => config.scheduling_strategy.to_string(),
+            SettingKey::Autoplay => config.autoplay.to_string(),
+            SettingKey::AlwaysOn => config.always_on.to_string(),
+            SettingKey::IdlePlaylist => config.idle_playlist.clone().unwrap_or_default(),
+            SettingKey::FollowMode => config.follow_mode.to_string(),
+            SettingKey::ChannelTopic => config.channel_topic.to_string(),
+            SettingKey::TopicChannel => config
+                .topic_channel
+                .map(|channel_id| channel_id.mention().to_string())
+                .unwrap_or_default(),
+            SettingKey::AnnouncementsChannel => config
+                .announcements_channel
+                .map(|channel_id| channel_id.mention().to_string())
+                .unwrap_or_default(),
+            SettingKey::SessionThreads => config.session_threads.to_string(),
+            SettingKey::TtsAnnouncements => config.tts_announcements.to_string(),
+            SettingKey::LoudnessNormalization => config.loudness_normalization.to_string(),
+            SettingKey::TrimSilence => config.trim_silence.to_string(),
+        }
+    }
+
+    /// Overrides the setting with a value parsed from a string. Returns `false` if the value is
+    /// not valid for the setting, in which case nothing is changed.
+    pub fn set(self, settings: &mut GuildSettings, value: &str) -> bool {
+        match self {
+            SettingKey::SkipVotesRequired => set_parsed(&mut settings.skip_votes_required, value),
+            SettingKey::StopVotesRequired => set_parsed(&mut settings.stop_votes_required, value),
+            SettingKey::PauseVotesRequired => set_parsed(&mut settings.pause_votes_required, value),
+            SettingKey::DisconnectMinInactiveSecs => {
+                set_parsed(&mut settings.disconnect_min_inactive_secs, value)
+            }
+            SettingKey::OnlyDisconnectWhenAlone => {
+                set_parsed(&mut settings.only_disconnect_when_alone, value)
+            }
+            SettingKey::ActionEmbedColor => set_color(&mut settings.action_embed_color, value),
+            SettingKey::ResponseEmbedColor => set_color(&mut settings.response_embed_color, value),
+            SettingKey::ErrorEmbedColor => set_color(&mut settings.error_embed_color, value),
+            SettingKey::SearchPrefix if !value.is_empty() => {
+                settings.search_prefix = Some(value.to_string());
+                true
+            }
+            SettingKey::SearchPrefix => false,
+            SettingKey::SchedulingStrategy => set_parsed(&mut settings.scheduling_strategy, value),
+            SettingKey::Autoplay => set_parsed(&mut settings.autoplay, value),
+            SettingKey::AlwaysOn => set_parsed(&mut settings.always_on, value),
+            SettingKey::IdlePlaylist if !value.is_empty() => {
+                settings.idle_playlist = Some(value.to_string());
+                true
+            }
+            SettingKey::IdlePlaylist => false,
+            SettingKey::FollowMode => set_parsed(&mut settings.follow_mode, value),
+            SettingKey::ChannelTopic => set_parsed(&mut settings.channel_topic, value),
+            SettingKey::TopicChannel => set_channel(&mut settings.topic_channel, value),
+            SettingKey::AnnouncementsChannel => {
+                set_channel(&mut settings.announcements_channel, value)
+            }
+            SettingKey::SessionThreads => set_parsed(&mut settings.session_threads, value),
+            SettingKey::TtsAnnouncements => set_parsed(&mut settings.tts_announcements, value),
+            SettingKey::LoudnessNormalization => {
+                set_parsed(&mut settings.loudness_normalization, value)
+            }
+            SettingKey::TrimSilence => set_parsed(&mut settings.trim_silence, value),
+        }
+    }
+
+    pub fn reset(self, settings: &mut GuildSettings) {
+        match self {
+            SettingKey::SkipVotesRequired => settings.skip_votes_required = None,
+            SettingKey::StopVotesRequired => settings.stop_votes_required = None,
+            SettingKey::PauseVotesRequired => settings.pause_votes_required = None,
+            SettingKey::DisconnectMinInactiveSecs => settings.disconnect_min_inactive_secs = None,
+            SettingKey::OnlyDisconnectWhenAlone => settings.only_disconnect_when_alone = None,
+            SettingKey::ActionEmbedColor => settings.action_embed_color = None,
+            SettingKey::ResponseEmbedColor => settings.response_embed_color = None,
+            SettingKey::ErrorEmbedColor => settings.error_embed_color = None,
+            SettingKey::SearchPrefix => settings.search_prefix = None,
+            SettingKey::SchedulingStrategy => settings.scheduling_strategy = None,
+            SettingKey::Autoplay => settings.autoplay = None,
+            SettingKey::AlwaysOn => settings.always_on = None,
+            SettingKey::IdlePlaylist => settings.idle_playlist = None,
+            SettingKey::FollowMode => settings.follow_mode = None,
+            SettingKey::ChannelTopic => settings.channel_topic = None,
+            SettingKey::TopicChannel => settings.topic_channel = None,
+            SettingKey::AnnouncementsChannel => settings.announcements_channel = None,
+            SettingKey::SessionThreads => settings.session_threads = None,
+            SettingKey::TtsAnnouncements => settings.tts_announcements = None,
+            SettingKey::LoudnessNormalization => settings.loudness_normalization = None,
+            SettingKey::TrimSilence => settings.trim_silence = None,
+        }
+    }
+}
+
+fn set_parsed<T: std::str::FromStr>(setting: &mut Option<T>, value: &str) -> bool {
+    match value.parse() {
+        Ok(value) => {
+            *setting = Some(value);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+fn set_color(setting: &mut Option<u32>, value: &str) -> bool {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    match u32::from_str_radix(hex, 16) {
+        Ok(color) if color <= 0xffffff => {
+            *setting = Some(color);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Channels can be given either as a mention (`<#123>`) or as a plain ID.
+fn set_channel(setting: &mut Option<ChannelId>, value: &str) -> bool {
+    let id = value
+        .strip_prefix("<#")
+        .and_then(|value| value.strip_suffix('>'))
+        .unwrap_or(value);
+    match id.parse::<u64>() {
+        Ok(id) if id != 0 => {
+            *setting = Some(ChannelId::new(id));
+            true
+        }
+        _ => false,
+    }
+}
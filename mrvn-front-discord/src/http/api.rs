@@ -0,0 +1,449 @@
+use crate::config::VoiceBot;
+use crate::http::control::{self, ChannelPlayback, ControlError};
+use crate::http::HttpState;
+use crate::voice_bots::{self, RunningVoiceBot, VoiceBotError};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use mrvn_back_ytdl::{PlaybackEvent, SongMetadata};
+use serde::{Deserialize, Serialize};
+use serenity::model::prelude::*;
+use tokio::sync::broadcast;
+
+/// A song, as it's shown everywhere in the API. IDs are strings since Discord's snowflakes are too
+/// big for some JSON parsers to hold as numbers.
+#[derive(Serialize)]
+struct SongJson {
+    id: String,
+    title: String,
+    url: String,
+    thumbnail_url: Option<String>,
+    duration_seconds: Option<f64>,
+    user_id: String,
+}
+
+impl From<&SongMetadata> for SongJson {
+    fn from(metadata: &SongMetadata) -> Self {
+        SongJson {
+            id: metadata.id.to_string(),
+            title: metadata.title.clone(),
+            url: metadata.url.clone(),
+            thumbnail_url: metadata.thumbnail_url.clone(),
+            duration_seconds: metadata.duration_seconds,
+            user_id: metadata.user_id.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PlayingJson {
+    channel_id: String,
+    song: SongJson,
+    is_paused: bool,
+    play_time_seconds: Option<f64>,
+}
+
+impl From<&ChannelPlayback> for PlayingJson {
+    fn from(playback: &ChannelPlayback) -> Self {
+        PlayingJson {
+            channel_id: playback.channel_id.to_string(),
+            song: SongJson::from(&playback.metadata),
+            is_paused: playback.is_paused,
+            play_time_seconds: playback.play_time.map(|play_time| play_time.as_secs_f64()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct QueueJson {
+    playing: Vec<PlayingJson>,
+    queue: Vec<SongJson>,
+}
+
+#[derive(Serialize)]
+struct QueuedJson {
+    /// What `/play` would have replied with.
+    messages: Vec<String>,
+    #[serde(flatten)]
+    queue: QueueJson,
+}
+
+#[derive(Serialize)]
+struct SkippedJson {
+    skipped: SongJson,
+}
+
+#[derive(Serialize)]
+struct SpeakerJson {
+    application_id: String,
+    /// Set once the voice bot's connection has failed, so it isn't playing anything.
+    is_stopped: bool,
+}
+
+impl From<&RunningVoiceBot> for SpeakerJson {
+    fn from(bot: &RunningVoiceBot) -> Self {
+        SpeakerJson {
+            application_id: bot.application_id.to_string(),
+            is_stopped: bot.speaker.is_stopped(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorJson {
+    error: String,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EventJson {
+    Started {
+        guild_id: String,
+        channel_id: String,
+        song: SongJson,
+    },
+    Paused {
+        guild_id: String,
+        channel_id: String,
+    },
+    Resumed {
+        guild_id: String,
+        channel_id: String,
+    },
+    Reconnecting {
+        guild_id: String,
+        channel_id: String,
+    },
+    Reconnected {
+        guild_id: String,
+        channel_id: String,
+    },
+    Stopped {
+        guild_id: String,
+        channel_id: Option<String>,
+    },
+}
+
+impl From<&PlaybackEvent> for EventJson {
+    fn from(event: &PlaybackEvent) -> Self {
+        match event {
+            PlaybackEvent::Started {
+                guild_id,
+                channel_id,
+                metadata,
+            } => EventJson::Started {
+                guild_id: guild_id.to_string(),
+                channel_id: channel_id.to_string(),
+                song: SongJson::from(metadata.as_ref()),
+            },
+            PlaybackEvent::Paused {
+                guild_id,
+                channel_id,
+            } => EventJson::Paused {
+                guild_id: guild_id.to_string(),
+                channel_id: channel_id.to_string(),
+            },
+            PlaybackEvent::Resumed {
+                guild_id,
+                channel_id,
+            } => EventJson::Resumed {
+                guild_id: guild_id.to_string(),
+                channel_id: channel_id.to_string(),
+            },
+            PlaybackEvent::Reconnecting {
+                guild_id,
+                channel_id,
+            } => EventJson::Reconnecting {
+                guild_id: guild_id.to_string(),
+                channel_id: channel_id.to_string(),
+            },
+            PlaybackEvent::Reconnected {
+                guild_id,
+                channel_id,
+            } => EventJson::Reconnected {
+                guild_id: guild_id.to_string(),
+                channel_id: channel_id.to_string(),
+            },
+            PlaybackEvent::Stopped {
+                guild_id,
+                channel_id,
+            } => EventJson::Stopped {
+                guild_id: guild_id.to_string(),
+                channel_id: channel_id.map(|channel_id| channel_id.to_string()),
+            },
+        }
+    }
+}
+
+fn event_guild_id(event: &PlaybackEvent) -> GuildId {
+    match event {
+        PlaybackEvent::Started { guild_id, .. }
+        | PlaybackEvent::Paused { guild_id, .. }
+        | PlaybackEvent::Resumed { guild_id, .. }
+        | PlaybackEvent::Reconnecting { guild_id, .. }
+        | PlaybackEvent::Reconnected { guild_id, .. }
+        | PlaybackEvent::Stopped { guild_id, .. } => *guild_id,
+    }
+}
+
+fn error_response(status: StatusCode, error: impl ToString) -> Response {
+    (
+        status,
+        Json(ErrorJson {
+            error: error.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+fn control_error_response(why: ControlError) -> Response {
+    match why {
+        ControlError::NothingPlaying | ControlError::NotQueued => {
+            error_response(StatusCode::CONFLICT, why)
+        }
+        ControlError::Backend(_) | ControlError::ModelLock(_) => {
+            tracing::error!("Error while controlling playback from the API: {}", why);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "internal error")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Only lets requests through if they have one of the `http.api_tokens`. Tokens are sent as a
+/// bearer token, or in the `token` query parameter for browsers that can't set headers on
+/// WebSockets.
+pub async fn require_token(
+    State(state): State<HttpState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let header_token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let maybe_token = header_token.or_else(|| {
+        Query::<TokenQuery>::try_from_uri(request.uri())
+            .ok()
+            .and_then(|query| query.0.token)
+    });
+
+    let config = state.frontend.config();
+    match maybe_token {
+        Some(token) if config.http.api_tokens.contains(&token) => next.run(request).await,
+        _ => error_response(StatusCode::UNAUTHORIZED, "missing or unknown API token"),
+    }
+}
+
+async fn get_queue_json(state: &HttpState, guild_id: GuildId) -> Result<QueueJson, ControlError> {
+    let playbacks = control::guild_playback(&state.frontend, guild_id).await;
+    let queue = control::guild_queue(&state.frontend, guild_id).await?;
+    Ok(QueueJson {
+        playing: playbacks.iter().map(PlayingJson::from).collect(),
+        queue: queue
+            .iter()
+            .map(|item| SongJson::from(&item.metadata))
+            .collect(),
+    })
+}
+
+/// What's playing in each of a guild's voice channels, and everything that's queued in the order
+/// it'll play.
+pub async fn get_queue(State(state): State<HttpState>, Path(guild_id): Path<GuildId>) -> Response {
+    if state.cache.guild(guild_id).is_none() {
+        return error_response(StatusCode::NOT_FOUND, "unknown guild");
+    }
+    match get_queue_json(&state, guild_id).await {
+        Ok(queue) => Json(queue).into_response(),
+        Err(why) => control_error_response(why),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct QueueRequest {
+    user_id: UserId,
+    query: String,
+}
+
+/// Queues a song for a user, the same as them using `/play`. It starts playing straight away if
+/// they're in a voice channel with nothing playing.
+pub async fn post_queue(
+    State(state): State<HttpState>,
+    Path(guild_id): Path<GuildId>,
+    Json(request): Json<QueueRequest>,
+) -> Response {
+    if state.cache.guild(guild_id).is_none() {
+        return error_response(StatusCode::NOT_FOUND, "unknown guild");
+    }
+    let Some(ctx) = state.frontend.command_context() else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "not connected to Discord");
+    };
+
+    tracing::debug!(
+        "Received API queue \"{}\" for {}",
+        request.query,
+        request.user_id
+    );
+    let responses = match state
+        .frontend
+        .queue_for_user(&ctx, guild_id, request.user_id, &request.query)
+        .await
+    {
+        Ok(responses) => responses,
+        Err(why) => {
+            tracing::error!("Error while queueing from the API: {}", why);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "internal error");
+        }
+    };
+
+    let guild_config = state.frontend.guild_config(guild_id).await;
+    let locale = state.frontend.guild_locale(guild_id).await;
+    if let Some(error) = responses.iter().find(|response| response.is_error()) {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            error.to_string(&guild_config, locale.as_deref()),
+        );
+    }
+    let queue = match get_queue_json(&state, guild_id).await {
+        Ok(queue) => queue,
+        Err(why) => return control_error_response(why),
+    };
+    Json(QueuedJson {
+        messages: responses
+            .iter()
+            .map(|response| response.to_string(&guild_config, locale.as_deref()))
+            .collect(),
+        queue,
+    })
+    .into_response()
+}
+
+/// Skips the song playing in a channel straight away, without a vote.
+pub async fn post_skip(
+    State(state): State<HttpState>,
+    Path((guild_id, channel_id)): Path<(GuildId, ChannelId)>,
+) -> Response {
+    match control::skip(&state.frontend, guild_id, channel_id).await {
+        Ok(skipped) => {
+            tracing::info!("Skipped {} from the API", skipped.url);
+            Json(SkippedJson {
+                skipped: SongJson::from(&skipped),
+            })
+            .into_response()
+        }
+        Err(why) => control_error_response(why),
+    }
+}
+
+/// The voice bots songs are played with.
+pub async fn get_speakers(State(state): State<HttpState>) -> Response {
+    let bots = state.frontend.voice_bots.list().await;
+    Json(
+        bots.iter()
+            .map(|bot| SpeakerJson::from(bot.as_ref()))
+            .collect::<Vec<_>>(),
+    )
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct SpeakerRequest {
+    token: String,
+    application_id: ApplicationId,
+    shard_count: Option<u32>,
+}
+
+/// Starts another voice bot and plays songs with it, without restarting. It isn't saved to the
+/// config, so it needs to be added there too to keep it after a restart.
+pub async fn post_speaker(
+    State(state): State<HttpState>,
+    Json(request): Json<SpeakerRequest>,
+) -> Response {
+    let bot_config = VoiceBot {
+        token: request.token,
+        token_file: None,
+        application_id: request.application_id.get(),
+        shard_count: request.shard_count,
+    };
+    match voice_bots::start_voice_bot(&state.frontend, &bot_config).await {
+        Ok(bot) => {
+            tracing::info!("Added voice bot {} from the API", bot.application_id);
+            (StatusCode::CREATED, Json(SpeakerJson::from(bot.as_ref()))).into_response()
+        }
+        Err(why @ VoiceBotError::AlreadyRunning(_)) => error_response(StatusCode::CONFLICT, why),
+    }
+}
+
+/// Stops a voice bot, disconnecting it from any channels it's playing in.
+pub async fn delete_speaker(
+    State(state): State<HttpState>,
+    Path(application_id): Path<ApplicationId>,
+) -> Response {
+    if voice_bots::stop_voice_bot(&state.frontend, application_id.get()).await {
+        tracing::info!("Removed voice bot {} from the API", application_id);
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        error_response(StatusCode::NOT_FOUND, "unknown speaker")
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    guild_id: Option<GuildId>,
+}
+
+/// Sends an event over a WebSocket whenever a song starts, pauses, resumes or stops, optionally
+/// only for one guild.
+pub async fn get_events(
+    State(state): State<HttpState>,
+    Query(query): Query<EventsQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let events = state.frontend.backend_brain.subscribe_playback_events();
+    upgrade.on_upgrade(move |socket| send_events(socket, events, query.guild_id))
+}
+
+async fn send_events(
+    mut socket: WebSocket,
+    mut events: broadcast::Receiver<PlaybackEvent>,
+    maybe_guild_id: Option<GuildId>,
+) {
+    loop {
+        tokio::select! {
+            event_res = events.recv() => {
+                let event = match event_res {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        tracing::warn!("API event subscriber fell behind and missed {} events", count);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                if maybe_guild_id.is_some_and(|guild_id| guild_id != event_guild_id(&event)) {
+                    continue;
+                }
+
+                let text = serde_json::to_string(&EventJson::from(&event))
+                    .expect("Events should always serialize");
+                if socket.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+            }
+            // Nothing is expected from the client, this is only to notice it going away
+            message_res = socket.recv() => {
+                if !matches!(message_res, Some(Ok(_))) {
+                    return;
+                }
+            }
+        }
+    }
+}
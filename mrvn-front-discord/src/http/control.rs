@@ -0,0 +1,145 @@
+use crate::frontend::Frontend;
+use mrvn_back_ytdl::SongMetadata;
+use serenity::model::prelude::*;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// What's playing in one of a guild's voice channels.
+pub struct ChannelPlayback {
+    pub channel_id: ChannelId,
+    pub metadata: SongMetadata,
+    pub is_paused: bool,
+    pub play_time: Option<Duration>,
+}
+
+/// A song waiting in someone's queue.
+pub struct QueueItem {
+    pub user_id: UserId,
+    pub metadata: SongMetadata,
+}
+
+#[derive(Debug)]
+pub enum ControlError {
+    NothingPlaying,
+    NotQueued,
+    Backend(mrvn_back_ytdl::Error),
+    ModelLock(mrvn_model::LockError),
+}
+
+impl Display for ControlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ControlError::NothingPlaying => write!(f, "nothing is playing in that channel"),
+            ControlError::NotQueued => write!(f, "that song isn't queued"),
+            ControlError::Backend(why) => write!(f, "backend error: {}", why),
+            ControlError::ModelLock(why) => write!(f, "unable to lock guild model: {}", why),
+        }
+    }
+}
+
+pub async fn guild_playback(frontend: &Frontend, guild_id: GuildId) -> Vec<ChannelPlayback> {
+    let guild_speakers_handle = frontend.backend_brain.guild_speakers(guild_id);
+    let mut guild_speakers_ref = guild_speakers_handle.lock();
+
+    let mut playbacks = Vec::new();
+    for guild_speaker in guild_speakers_ref.lock_all().await {
+        let (Some(channel_id), Some(metadata)) = (
+            guild_speaker.current_channel(),
+            guild_speaker.active_metadata(),
+        ) else {
+            continue;
+        };
+        playbacks.push(ChannelPlayback {
+            channel_id,
+            metadata,
+            is_paused: guild_speaker.is_paused(),
+            play_time: guild_speaker.active_play_time().await,
+        });
+    }
+    playbacks
+}
+
+pub async fn guild_queue(
+    frontend: &Frontend,
+    guild_id: GuildId,
+) -> Result<Vec<QueueItem>, ControlError> {
+    let guild_model_handle = frontend.model.get(guild_id);
+    let guild_model = guild_model_handle
+        .lock()
+        .await
+        .map_err(ControlError::ModelLock)?;
+    Ok(guild_model
+        .queued_entries()
+        .map(|(user_id, queued)| QueueItem {
+            user_id,
+            metadata: queued.song.metadata.clone(),
+        })
+        .collect())
+}
+
+pub async fn pause(
+    frontend: &Frontend,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<(), ControlError> {
+    let guild_speakers_handle = frontend.backend_brain.guild_speakers(guild_id);
+    let mut guild_speakers_ref = guild_speakers_handle.lock();
+    let (guild_speaker, _) = guild_speakers_ref
+        .find_active_in_channel(channel_id)
+        .await
+        .ok_or(ControlError::NothingPlaying)?;
+    guild_speaker.pause().map_err(ControlError::Backend)
+}
+
+/// Resumes a paused song. Unlike `/resume`, this can't start playback in a channel that has
+/// nothing playing.
+pub async fn resume(
+    frontend: &Frontend,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<(), ControlError> {
+    let guild_speakers_handle = frontend.backend_brain.guild_speakers(guild_id);
+    let mut guild_speakers_ref = guild_speakers_handle.lock();
+    let (guild_speaker, _) = guild_speakers_ref
+        .find_active_in_channel(channel_id)
+        .await
+        .ok_or(ControlError::NothingPlaying)?;
+    guild_speaker.unpause().map_err(ControlError::Backend)
+}
+
+/// Skips the current song straight away, without a vote. The next song starts playing the same
+/// way it does when a song finishes.
+pub async fn skip(
+    frontend: &Frontend,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<SongMetadata, ControlError> {
+    let guild_speakers_handle = frontend.backend_brain.guild_speakers(guild_id);
+    let mut guild_speakers_ref = guild_speakers_handle.lock();
+    let (guild_speaker, active_metadata) = guild_speakers_ref
+        .find_active_in_channel(channel_id)
+        .await
+        .ok_or(ControlError::NothingPlaying)?;
+    guild_speaker.stop().map_err(ControlError::Backend)?;
+    Ok(active_metadata)
+}
+
+pub async fn remove(
+    frontend: &Frontend,
+    guild_id: GuildId,
+    song_id: Uuid,
+) -> Result<QueueItem, ControlError> {
+    let guild_model_handle = frontend.model.get(guild_id);
+    let mut guild_model = guild_model_handle
+        .lock()
+        .await
+        .map_err(ControlError::ModelLock)?;
+    let (user_id, removed) = guild_model
+        .remove_entry(|queued| queued.song.metadata.id == song_id)
+        .ok_or(ControlError::NotQueued)?;
+    Ok(QueueItem {
+        user_id,
+        metadata: removed.song.metadata,
+    })
+}
@@ -0,0 +1,326 @@
+use crate::http::control::{self, ControlError};
+use crate::http::session::{redirect, Session};
+use crate::http::HttpState;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use serenity::model::prelude::*;
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// How often the dashboard reloads itself, so the playing songs and queue stay up to date.
+const REFRESH_SECS: u32 = 10;
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:50em;margin:2em auto;padding:0 1em}\
+    table{border-collapse:collapse;width:100%}td{padding:.3em .5em;border-bottom:1px solid #ddd}\
+    form{display:inline}.paused{color:#888}";
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn format_seconds(seconds: f64) -> String {
+    let seconds = seconds.max(0.) as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn page(title: &str, body: &str) -> Response {
+    Html(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+        <meta http-equiv=\"refresh\" content=\"{}\"><title>{}</title><style>{}</style></head>\
+        <body>{}</body></html>",
+        REFRESH_SECS,
+        escape_html(title),
+        STYLE,
+        body
+    ))
+    .into_response()
+}
+
+fn button(action: &str, label: &str) -> String {
+    format!(
+        "<form method=\"post\" action=\"{}\"><button>{}</button></form>",
+        escape_html(action),
+        escape_html(label)
+    )
+}
+
+/// The logged in user, and whether they're allowed to manage the guild. Users can only see the
+/// guilds they're in, and that the bot is in.
+fn get_guild_session(
+    state: &HttpState,
+    headers: &HeaderMap,
+    guild_id: GuildId,
+) -> Result<(Session, bool), Response> {
+    let Some(session) = state.sessions.get(headers) else {
+        return Err(redirect("/dashboard/login"));
+    };
+    match session.guild(guild_id) {
+        Some(guild) if state.cache.guild(guild_id).is_some() => {
+            let can_manage = guild.can_manage;
+            Ok((session, can_manage))
+        }
+        _ => Err(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+fn user_voice_channel(state: &HttpState, guild_id: GuildId, user_id: UserId) -> Option<ChannelId> {
+    let guild = state.cache.guild(guild_id)?;
+    let voice_state = guild.voice_states.get(&user_id)?;
+    voice_state.channel_id
+}
+
+fn user_name(state: &HttpState, guild_id: GuildId, user_id: UserId) -> String {
+    state
+        .cache
+        .guild(guild_id)
+        .and_then(|guild| {
+            guild
+                .members
+                .get(&user_id)
+                .map(|member| member.display_name().to_string())
+        })
+        .unwrap_or_else(|| user_id.to_string())
+}
+
+fn channel_name(state: &HttpState, guild_id: GuildId, channel_id: ChannelId) -> String {
+    state
+        .cache
+        .guild(guild_id)
+        .and_then(|guild| {
+            guild
+                .channels
+                .get(&channel_id)
+                .map(|channel| channel.name.clone())
+        })
+        .unwrap_or_else(|| channel_id.to_string())
+}
+
+fn control_error_response(why: ControlError) -> Response {
+    match why {
+        ControlError::NothingPlaying | ControlError::NotQueued => {
+            (StatusCode::CONFLICT, why.to_string()).into_response()
+        }
+        ControlError::Backend(_) | ControlError::ModelLock(_) => {
+            tracing::error!(
+                "Error while controlling playback from the dashboard: {}",
+                why
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Lists the guilds the user can look at.
+pub async fn get_guilds(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    let Some(session) = state.sessions.get(&headers) else {
+        return redirect("/dashboard/login");
+    };
+
+    let mut body = format!(
+        "<h1>Servers</h1><p>Logged in as {}</p><ul>",
+        escape_html(&session.username)
+    );
+    for guild in &session.guilds {
+        if state.cache.guild(guild.id).is_none() {
+            continue;
+        }
+        let _ = write!(
+            body,
+            "<li><a href=\"/dashboard/{}\">{}</a></li>",
+            guild.id,
+            escape_html(&guild.name)
+        );
+    }
+    body.push_str("</ul>");
+    page("Servers", &body)
+}
+
+/// Shows what's playing in each of a guild's voice channels and what's queued, with controls for
+/// the ones the user is allowed to change. People can control the channel they're in, and people
+/// that can manage the server can control everything.
+pub async fn get_guild(
+    State(state): State<HttpState>,
+    Path(guild_id): Path<GuildId>,
+    headers: HeaderMap,
+) -> Response {
+    let (session, can_manage) = match get_guild_session(&state, &headers, guild_id) {
+        Ok(guild_session) => guild_session,
+        Err(response) => return response,
+    };
+    let user_channel = user_voice_channel(&state, guild_id, session.user_id);
+    let guild_name = session
+        .guild(guild_id)
+        .map(|guild| guild.name.clone())
+        .unwrap_or_default();
+
+    let mut body = format!(
+        "<p><a href=\"/dashboard\">Servers</a></p><h1>{}</h1><h2>Now playing</h2>",
+        escape_html(&guild_name)
+    );
+
+    let playbacks = control::guild_playback(&state.frontend, guild_id).await;
+    if playbacks.is_empty() {
+        body.push_str("<p>Nothing is playing.</p>");
+    }
+    for playback in playbacks {
+        let time = match (playback.play_time, playback.metadata.duration_seconds) {
+            (Some(play_time), Some(duration)) => format!(
+                "{} / {}",
+                format_seconds(play_time.as_secs_f64()),
+                format_seconds(duration)
+            ),
+            (Some(play_time), None) => format_seconds(play_time.as_secs_f64()),
+            (None, _) => String::new(),
+        };
+        let _ = write!(
+            body,
+            "<p{}><b>{}</b>: <a href=\"{}\">{}</a> {}{}</p>",
+            if playback.is_paused {
+                " class=\"paused\""
+            } else {
+                ""
+            },
+            escape_html(&channel_name(&state, guild_id, playback.channel_id)),
+            escape_html(&playback.metadata.url),
+            escape_html(&playback.metadata.title),
+            time,
+            if playback.is_paused { " (paused)" } else { "" }
+        );
+
+        if can_manage || user_channel == Some(playback.channel_id) {
+            let channel_path = format!("/dashboard/{}/{}", guild_id, playback.channel_id);
+            if playback.is_paused {
+                body.push_str(&button(&format!("{}/resume", channel_path), "Resume"));
+            } else {
+                body.push_str(&button(&format!("{}/pause", channel_path), "Pause"));
+            }
+            body.push_str(&button(&format!("{}/skip", channel_path), "Skip"));
+        }
+    }
+
+    body.push_str("<h2>Queue</h2>");
+    let queue = match control::guild_queue(&state.frontend, guild_id).await {
+        Ok(queue) => queue,
+        Err(why) => return control_error_response(why),
+    };
+    if queue.is_empty() {
+        body.push_str("<p>Nothing is queued.</p>");
+    } else {
+        body.push_str("<table>");
+        for item in queue {
+            let _ = write!(
+                body,
+                "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>",
+                escape_html(&item.metadata.url),
+                escape_html(&item.metadata.title),
+                item.metadata
+                    .duration_seconds
+                    .map(format_seconds)
+                    .unwrap_or_default(),
+                escape_html(&user_name(&state, guild_id, item.user_id))
+            );
+            if can_manage || item.user_id == session.user_id {
+                body.push_str(&button(
+                    &format!(
+                        "/dashboard/{}/remove/{}",
+                        guild_id,
+                        item.metadata.id.simple()
+                    ),
+                    "Remove",
+                ));
+            }
+            body.push_str("</td></tr>");
+        }
+        body.push_str("</table>");
+    }
+
+    page(&guild_name, &body)
+}
+
+/// Pauses, resumes or skips the song playing in a channel.
+pub async fn post_channel_action(
+    State(state): State<HttpState>,
+    Path((guild_id, channel_id, action)): Path<(GuildId, ChannelId, String)>,
+    headers: HeaderMap,
+) -> Response {
+    let (session, can_manage) = match get_guild_session(&state, &headers, guild_id) {
+        Ok(guild_session) => guild_session,
+        Err(response) => return response,
+    };
+    let user_channel = user_voice_channel(&state, guild_id, session.user_id);
+    if !can_manage && user_channel != Some(channel_id) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let result = match action.as_str() {
+        "pause" => control::pause(&state.frontend, guild_id, channel_id).await,
+        "resume" => control::resume(&state.frontend, guild_id, channel_id).await,
+        "skip" => control::skip(&state.frontend, guild_id, channel_id)
+            .await
+            .map(|_| ()),
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    match result {
+        Ok(()) => {
+            tracing::info!(
+                "{} used {} from the dashboard in {}",
+                session.username,
+                action,
+                channel_id
+            );
+            redirect(&format!("/dashboard/{}", guild_id))
+        }
+        Err(why) => control_error_response(why),
+    }
+}
+
+/// Takes a song out of the queue. People can remove their own songs, and people that can manage
+/// the server can remove anyone's.
+pub async fn post_remove(
+    State(state): State<HttpState>,
+    Path((guild_id, song_id)): Path<(GuildId, String)>,
+    headers: HeaderMap,
+) -> Response {
+    let (session, can_manage) = match get_guild_session(&state, &headers, guild_id) {
+        Ok(guild_session) => guild_session,
+        Err(response) => return response,
+    };
+    let Ok(song_id) = Uuid::parse_str(&song_id) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let queue = match control::guild_queue(&state.frontend, guild_id).await {
+        Ok(queue) => queue,
+        Err(why) => return control_error_response(why),
+    };
+    let Some(item) = queue.iter().find(|item| item.metadata.id == song_id) else {
+        return control_error_response(ControlError::NotQueued);
+    };
+    if !can_manage && item.user_id != session.user_id {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    match control::remove(&state.frontend, guild_id, song_id).await {
+        Ok(removed) => {
+            tracing::info!(
+                "{} removed {} from the queue from the dashboard",
+                session.username,
+                removed.metadata.url
+            );
+            redirect(&format!("/dashboard/{}", guild_id))
+        }
+        Err(why) => control_error_response(why),
+    }
+}
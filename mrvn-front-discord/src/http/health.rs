@@ -0,0 +1,76 @@
+use crate::http::HttpState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Serialize;
+use serenity::gateway::{ConnectionStage, ShardManager};
+
+#[derive(Serialize)]
+struct ShardHealth {
+    id: u32,
+    stage: String,
+    latency_ms: Option<u128>,
+}
+
+#[derive(Serialize)]
+struct BotHealth {
+    connected: bool,
+    shards: Vec<ShardHealth>,
+}
+
+#[derive(Serialize)]
+pub struct Health {
+    healthy: bool,
+    command_bot: BotHealth,
+    voice_bots: Vec<BotHealth>,
+}
+
+/// A bot is only connected once all of its shards are, so one that's stuck reconnecting shows up.
+async fn get_bot_health(shard_manager: &ShardManager) -> BotHealth {
+    let runners = shard_manager.runners.lock().await;
+    let mut shards: Vec<_> = runners
+        .iter()
+        .map(|(shard_id, runner)| ShardHealth {
+            id: shard_id.0,
+            stage: runner.stage.to_string(),
+            latency_ms: runner.latency.map(|latency| latency.as_millis()),
+        })
+        .collect();
+    shards.sort_by_key(|shard| shard.id);
+
+    BotHealth {
+        connected: !runners.is_empty()
+            && runners
+                .values()
+                .all(|runner| runner.stage == ConnectionStage::Connected),
+        shards,
+    }
+}
+
+/// Reports whether the command bot and every voice bot are connected to the gateway. Anything
+/// other than a 200 means the bot should be restarted.
+pub async fn get_health(State(state): State<HttpState>) -> (StatusCode, Json<Health>) {
+    let command_bot = get_bot_health(&state.command_shards).await;
+    let mut voice_bots = Vec::new();
+    for shard_manager in state.frontend.voice_bots.shard_managers().await {
+        voice_bots.push(get_bot_health(&shard_manager).await);
+    }
+
+    let healthy = command_bot.connected && voice_bots.iter().all(|bot| bot.connected);
+    if !healthy {
+        tracing::warn!("Health check failed, not every bot is connected");
+    }
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(Health {
+            healthy,
+            command_bot,
+            voice_bots,
+        }),
+    )
+}
@@ -0,0 +1,95 @@
+mod api;
+mod control;
+mod dashboard;
+mod health;
+mod session;
+
+use crate::frontend::Frontend;
+use axum::middleware;
+use axum::routing::{delete, get, post};
+use axum::Router;
+use serenity::gateway::ShardManager;
+use std::sync::Arc;
+
+pub use self::session::Sessions;
+
+/// Everything the HTTP endpoints can look at.
+#[derive(Clone)]
+pub struct HttpState {
+    pub frontend: Arc<Frontend>,
+    pub cache: Arc<serenity::cache::Cache>,
+    pub command_shards: Arc<ShardManager>,
+    pub sessions: Arc<Sessions>,
+}
+
+impl HttpState {
+    /// The URL the server can be reached at from outside, which is only set when the dashboard is
+    /// turned on.
+    fn public_url(&self) -> Option<String> {
+        let http_config = self.frontend.config().http.clone();
+        http_config
+            .public_url
+            .filter(|_| http_config.client_secret.is_some())
+    }
+}
+
+/// Serves the HTTP endpoints on `http.address`. Does nothing if there's no address set, since the
+/// server is optional.
+pub async fn http_server(state: HttpState) -> ! {
+    let Some(address) = state.frontend.config().http.address.clone() else {
+        futures::future::pending::<()>().await;
+        unreachable!();
+    };
+
+    let mut router = Router::new().route("/healthz", get(health::get_health));
+    if state.public_url().is_some() {
+        router = router
+            .route("/dashboard", get(dashboard::get_guilds))
+            .route("/dashboard/login", get(session::get_login))
+            .route("/dashboard/callback", get(session::get_callback))
+            .route("/dashboard/:guild_id", get(dashboard::get_guild))
+            .route(
+                "/dashboard/:guild_id/remove/:song_id",
+                post(dashboard::post_remove),
+            )
+            .route(
+                "/dashboard/:guild_id/:channel_id/:action",
+                post(dashboard::post_channel_action),
+            );
+    }
+    if !state.frontend.config().http.api_tokens.is_empty() {
+        let api_router = Router::new()
+            .route(
+                "/api/v1/guilds/:guild_id/queue",
+                get(api::get_queue).post(api::post_queue),
+            )
+            .route(
+                "/api/v1/guilds/:guild_id/channels/:channel_id/skip",
+                post(api::post_skip),
+            )
+            .route("/api/v1/events", get(api::get_events))
+            .route(
+                "/api/v1/speakers",
+                get(api::get_speakers).post(api::post_speaker),
+            )
+            .route(
+                "/api/v1/speakers/:application_id",
+                delete(api::delete_speaker),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                api::require_token,
+            ));
+        router = router.merge(api_router);
+    }
+    let router = router.with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&address)
+        .await
+        .expect("Unable to start HTTP server");
+    tracing::info!("Serving HTTP on {}", address);
+    axum::serve(listener, router)
+        .await
+        .expect("Error while running HTTP server");
+    panic!("HTTP server stopped");
+}
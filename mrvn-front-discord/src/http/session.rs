@@ -0,0 +1,270 @@
+use crate::http::HttpState;
+use axum::extract::{Query, State};
+use axum::http::header::{COOKIE, LOCATION, SET_COOKIE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serenity::model::prelude::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const SESSION_COOKIE: &str = "mrvn_session";
+const STATE_COOKIE: &str = "mrvn_oauth_state";
+const SESSION_LENGTH: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const DISCORD_API: &str = "https://discord.com/api/v10";
+
+/// The Manage Server permission bit, in the permissions Discord sends for each of a user's guilds.
+const MANAGE_GUILD: u64 = 1 << 5;
+
+#[derive(Clone)]
+pub struct SessionGuild {
+    pub id: GuildId,
+    pub name: String,
+    /// Users that can manage a guild can control any channel's playback and remove anyone's songs.
+    pub can_manage: bool,
+}
+
+#[derive(Clone)]
+pub struct Session {
+    pub user_id: UserId,
+    pub username: String,
+    pub guilds: Vec<SessionGuild>,
+    expiry: Instant,
+}
+
+impl Session {
+    pub fn guild(&self, guild_id: GuildId) -> Option<&SessionGuild> {
+        self.guilds.iter().find(|guild| guild.id == guild_id)
+    }
+}
+
+/// Logged in dashboard users. These are only kept in memory, so everyone has to log in again after
+/// the bot restarts.
+#[derive(Default)]
+pub struct Sessions(Mutex<HashMap<String, Session>>);
+
+impl Sessions {
+    fn insert(&self, session: Session) -> String {
+        let token = Uuid::new_v4().simple().to_string();
+        let mut sessions = self.0.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, session| session.expiry > now);
+        sessions.insert(token.clone(), session);
+        token
+    }
+
+    /// Finds the session for a request from its cookie, if it has one that hasn't expired.
+    pub fn get(&self, headers: &HeaderMap) -> Option<Session> {
+        let token = get_cookie(headers, SESSION_COOKIE)?;
+        let sessions = self.0.lock().unwrap();
+        sessions
+            .get(token)
+            .filter(|session| session.expiry > Instant::now())
+            .cloned()
+    }
+}
+
+fn get_cookie<'h>(headers: &'h HeaderMap, name: &str) -> Option<&'h str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie_name, _)| *cookie_name == name)
+        .map(|(_, value)| value)
+}
+
+fn cookie(state: &HttpState, name: &str, value: &str, max_age: Duration) -> String {
+    let is_secure = state
+        .public_url()
+        .is_some_and(|url| url.starts_with("https:"));
+    format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        name,
+        value,
+        max_age.as_secs(),
+        if is_secure { "; Secure" } else { "" }
+    )
+}
+
+fn redirect_uri(public_url: &str) -> String {
+    format!("{}/dashboard/callback", public_url.trim_end_matches('/'))
+}
+
+pub fn redirect(location: &str) -> Response {
+    (StatusCode::SEE_OTHER, [(LOCATION, location.to_string())]).into_response()
+}
+
+/// Sends the user to Discord to log in. The state cookie ties the callback to this request, so
+/// nobody else can log someone in as them.
+pub async fn get_login(State(state): State<HttpState>) -> Response {
+    let Some(public_url) = state.public_url() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let oauth_state = Uuid::new_v4().simple().to_string();
+    let client_id = state
+        .frontend
+        .config()
+        .command_bot
+        .application_id
+        .to_string();
+    let authorize_url = reqwest::Url::parse_with_params(
+        "https://discord.com/oauth2/authorize",
+        &[
+            ("client_id", client_id.as_str()),
+            ("redirect_uri", &redirect_uri(&public_url)),
+            ("response_type", "code"),
+            ("scope", "identify guilds"),
+            ("state", &oauth_state),
+        ],
+    )
+    .expect("Discord's authorize URL should always parse");
+
+    (
+        StatusCode::SEE_OTHER,
+        [
+            (LOCATION, authorize_url.to_string()),
+            (
+                SET_COOKIE,
+                cookie(
+                    &state,
+                    STATE_COOKIE,
+                    &oauth_state,
+                    Duration::from_secs(10 * 60),
+                ),
+            ),
+        ],
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    code: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct UserResponse {
+    id: UserId,
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct GuildResponse {
+    id: GuildId,
+    name: String,
+    permissions: String,
+}
+
+async fn fetch_session(state: &HttpState, code: &str) -> Result<Session, reqwest::Error> {
+    let public_url = state.public_url().unwrap_or_default();
+    let client_secret = state
+        .frontend
+        .config()
+        .http
+        .client_secret
+        .clone()
+        .unwrap_or_default();
+    let client_id = state
+        .frontend
+        .config()
+        .command_bot
+        .application_id
+        .to_string();
+    let client = reqwest::Client::new();
+
+    let token: TokenResponse = client
+        .post(format!("{}/oauth2/token", DISCORD_API))
+        .form(&[
+            ("client_id", client_id.as_str()),
+            ("client_secret", &client_secret),
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &redirect_uri(&public_url)),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let user: UserResponse = client
+        .get(format!("{}/users/@me", DISCORD_API))
+        .bearer_auth(&token.access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let guilds: Vec<GuildResponse> = client
+        .get(format!("{}/users/@me/guilds", DISCORD_API))
+        .bearer_auth(&token.access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(Session {
+        user_id: user.id,
+        username: user.username,
+        guilds: guilds
+            .into_iter()
+            .filter_map(|guild| {
+                let permissions: u64 = guild.permissions.parse().ok()?;
+                Some(SessionGuild {
+                    id: guild.id,
+                    name: guild.name,
+                    can_manage: permissions & MANAGE_GUILD != 0,
+                })
+            })
+            .collect(),
+        expiry: Instant::now() + SESSION_LENGTH,
+    })
+}
+
+/// Where Discord sends the user back to after they log in.
+pub async fn get_callback(
+    State(state): State<HttpState>,
+    Query(query): Query<CallbackQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if state.public_url().is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if get_cookie(&headers, STATE_COOKIE) != Some(query.state.as_str()) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let session = match fetch_session(&state, &query.code).await {
+        Ok(session) => session,
+        Err(why) => {
+            tracing::error!("Error while logging in to the dashboard: {}", why);
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    };
+    tracing::info!("{} logged in to the dashboard", session.username);
+    let token = state.sessions.insert(session);
+
+    (
+        StatusCode::SEE_OTHER,
+        [
+            (LOCATION, "/dashboard".to_string()),
+            (
+                SET_COOKIE,
+                cookie(&state, SESSION_COOKIE, &token, SESSION_LENGTH),
+            ),
+        ],
+    )
+        .into_response()
+}
@@ -0,0 +1,58 @@
+use crate::config::Config;
+use mrvn_back_ytdl::Song;
+use mrvn_model::PlaylistStore;
+use serenity::model::prelude::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Provides songs for guilds in 24/7 mode to play when nobody has anything queued, by working
+/// through the guild's idle playlist in order.
+#[derive(Default)]
+pub struct IdleSongProvider {
+    positions: Mutex<HashMap<GuildId, usize>>,
+}
+
+impl IdleSongProvider {
+    pub async fn next_song(
+        &self,
+        playlists: &PlaylistStore,
+        guild_id: GuildId,
+        config: &Config,
+        user_id: UserId,
+    ) -> Option<Song> {
+        let playlist_name = config.idle_playlist.as_deref()?;
+        let maybe_urls: Option<Vec<String>> = playlists
+            .read(|playlists| {
+                playlists.get(guild_id, playlist_name).map(|playlist| {
+                    playlist
+                        .entries
+                        .iter()
+                        .map(|entry| entry.url.clone())
+                        .collect()
+                })
+            })
+            .await;
+        let Some(urls) = maybe_urls else {
+            tracing::warn!("Idle playlist \"{}\" doesn't exist", playlist_name);
+            return None;
+        };
+
+        // Entries might not be playable anymore, so try each one at most once before giving up.
+        let play_config = config.get_play_config();
+        for _ in 0..urls.len() {
+            let index = {
+                let mut positions = self.positions.lock().unwrap();
+                let position = positions.entry(guild_id).or_default();
+                let index = *position % urls.len();
+                *position = index + 1;
+                index
+            };
+
+            match Song::fetch_one(&urls[index], user_id, &play_config).await {
+                Ok(song) => return Some(song),
+                Err(why) => tracing::warn!("Error while resolving idle playlist entry: {}", why),
+            }
+        }
+        None
+    }
+}
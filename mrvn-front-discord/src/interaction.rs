@@ -0,0 +1,81 @@
+use serenity::all::{
+    CommandInteraction, ComponentInteraction, CreateInteractionResponse, EditInteractionResponse,
+    ModalInteraction,
+};
+use serenity::model::prelude::*;
+use serenity::prelude::Context;
+
+/// An interaction that can be responded to with messages, regardless of whether it came from an
+/// application command, a message component or a submitted modal.
+#[derive(Clone, Copy)]
+pub enum InteractionRef<'interaction> {
+    Command(&'interaction CommandInteraction),
+    Component(&'interaction ComponentInteraction),
+    Modal(&'interaction ModalInteraction),
+}
+
+impl InteractionRef<'_> {
+    pub fn guild_id(&self) -> Option<GuildId> {
+        match self {
+            InteractionRef::Command(command) => command.guild_id,
+            InteractionRef::Component(component) => component.guild_id,
+            InteractionRef::Modal(modal) => modal.guild_id,
+        }
+    }
+
+    pub fn user_id(&self) -> UserId {
+        match self {
+            InteractionRef::Command(command) => command.user.id,
+            InteractionRef::Component(component) => component.user.id,
+            InteractionRef::Modal(modal) => modal.user.id,
+        }
+    }
+
+    pub fn member(&self) -> Option<&Member> {
+        match self {
+            InteractionRef::Command(command) => command.member.as_deref(),
+            InteractionRef::Component(component) => component.member.as_ref(),
+            InteractionRef::Modal(modal) => modal.member.as_ref(),
+        }
+    }
+
+    pub fn channel_id(&self) -> ChannelId {
+        match self {
+            InteractionRef::Command(command) => command.channel_id,
+            InteractionRef::Component(component) => component.channel_id,
+            InteractionRef::Modal(modal) => modal.channel_id,
+        }
+    }
+
+    pub async fn create_response(
+        &self,
+        ctx: &Context,
+        builder: CreateInteractionResponse,
+    ) -> serenity::Result<()> {
+        match self {
+            InteractionRef::Command(command) => command.create_response(ctx, builder).await,
+            InteractionRef::Component(component) => component.create_response(ctx, builder).await,
+            InteractionRef::Modal(modal) => modal.create_response(ctx, builder).await,
+        }
+    }
+
+    pub async fn edit_response(
+        &self,
+        ctx: &Context,
+        builder: EditInteractionResponse,
+    ) -> serenity::Result<Message> {
+        match self {
+            InteractionRef::Command(command) => command.edit_response(ctx, builder).await,
+            InteractionRef::Component(component) => component.edit_response(ctx, builder).await,
+            InteractionRef::Modal(modal) => modal.edit_response(ctx, builder).await,
+        }
+    }
+
+    pub async fn get_response(&self, ctx: &Context) -> serenity::Result<Message> {
+        match self {
+            InteractionRef::Command(command) => command.get_response(ctx).await,
+            InteractionRef::Component(component) => component.get_response(ctx).await,
+            InteractionRef::Modal(modal) => modal.get_response(ctx).await,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use serenity::all::{
+    ActionRowComponent, CommandDataOption, CommandInteraction, ModalInteraction, ResolvedTarget,
+};
+use serenity::model::prelude::*;
+
+/// Where a command came from.
+#[derive(Clone, Copy)]
+pub enum InvocationSource<'source> {
+    Interaction(&'source CommandInteraction),
+    /// A message typed with the command prefix.
+    Message(&'source Message),
+    /// A modal that was opened by a command instead of running it straight away.
+    Modal,
+}
+
+/// A command being run in a guild. Slash commands, prefix commands and submitted modals are all
+/// turned into this, so each command is only handled in one place.
+pub struct CommandInvocation<'source> {
+    pub name: String,
+    pub options: Vec<CommandDataOption>,
+    pub user_id: UserId,
+    pub member: Option<Member>,
+    pub source: InvocationSource<'source>,
+}
+
+impl<'source> CommandInvocation<'source> {
+    pub fn from_interaction(command: &'source CommandInteraction) -> Self {
+        CommandInvocation {
+            name: command.data.name.clone(),
+            options: command.data.options.clone(),
+            user_id: command.user.id,
+            member: command.member.as_deref().cloned(),
+            source: InvocationSource::Interaction(command),
+        }
+    }
+
+    /// Turns a submitted modal back into the command that opened it. The modal's custom ID is the
+    /// command's name, and each text input is a string option named after the input's custom ID.
+    pub fn from_modal(modal: &'source ModalInteraction) -> Self {
+        let options = modal
+            .data
+            .components
+            .iter()
+            .flat_map(|row| &row.components)
+            .filter_map(|component| match component {
+                ActionRowComponent::InputText(input) => {
+                    let value = input.value.as_ref()?;
+                    serde_json::from_value(serde_json::json!({
+                        "name": input.custom_id,
+                        "type": CommandOptionType::String,
+                        "value": value,
+                    }))
+                    .ok()
+                }
+                _ => None,
+            })
+            .collect();
+        CommandInvocation {
+            name: modal.data.custom_id.clone(),
+            options,
+            user_id: modal.user.id,
+            member: modal.member.clone(),
+            source: InvocationSource::Modal,
+        }
+    }
+
+    /// Finds an attachment given in an option, which for prefix commands is one attached to the
+    /// message.
+    pub fn attachment(&self, attachment_id: AttachmentId) -> Option<&'source Attachment> {
+        match self.source {
+            InvocationSource::Interaction(command) => {
+                command.data.resolved.attachments.get(&attachment_id)
+            }
+            InvocationSource::Message(message) => message
+                .attachments
+                .iter()
+                .find(|attachment| attachment.id == attachment_id),
+            InvocationSource::Modal => None,
+        }
+    }
+
+    /// The message a message command was used on. Prefix commands never have one.
+    pub fn target_message(&self) -> Option<&'source Message> {
+        match self.source {
+            InvocationSource::Interaction(command) => match command.data.target() {
+                Some(ResolvedTarget::Message(message)) => Some(message),
+                _ => None,
+            },
+            InvocationSource::Message(_) | InvocationSource::Modal => None,
+        }
+    }
+}
@@ -1,93 +1,230 @@
+#![allow(clippy::result_large_err)]
+
 use futures::prelude::*;
-use mrvn_back_ytdl::{get_ytdl_version, SpeakerInit};
+use mrvn_back_ytdl::{get_ytdl_version, set_proxy_rules, ProxyRules};
 use serenity::{model::prelude::*, prelude::*};
-use std::future::IntoFuture;
 use std::sync::Arc;
+use tracing_subscriber::prelude::*;
 
 mod cleanup_loop;
 mod command_handler;
 mod commands;
+mod component;
 mod config;
 mod error;
 mod frontend;
+mod guild_settings;
+mod http;
+mod idle_songs;
+mod interaction;
+mod invocation;
 mod message;
+mod permissions;
 mod playing_message;
+mod playlist_progress;
+mod prefix_command;
+mod queue_file;
 mod queued_message;
 mod queued_song;
+mod rate_limit;
+mod reload_loop;
+mod shutdown;
+mod template;
+mod topic_message;
+mod voice_bots;
 mod voice_handler;
+mod ytdl_update_loop;
+
+/// Connects a client to the gateway with the configured number of shards, or as many as Discord
+/// recommends.
+pub(crate) async fn start_client(
+    client: &mut Client,
+    maybe_shard_count: Option<u32>,
+) -> serenity::Result<()> {
+    match maybe_shard_count {
+        Some(shard_count) => client.start_shards(shard_count).await,
+        None => client.start_autosharded().await,
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    pretty_env_logger::init();
+    // Errors are sent to Sentry along with the fields of the spans they happened in, like the
+    // guild and song. Nothing is sent unless `sentry_dsn` is set.
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .with(sentry::integrations::tracing::layer().enable_span_attributes())
+        .init();
 
     let mut args = std::env::args();
     let app_name = args.next().unwrap();
     let config_file_path = match args.next() {
         Some(path) => path,
         None => {
-            eprintln!("Usage: {} path_to_config.json", app_name);
+            eprintln!("Usage: {} path_to_config", app_name);
             std::process::exit(1);
         }
     };
 
-    log::info!("Starting with config from {}", config_file_path);
+    tracing::info!("Starting with config from {}", config_file_path);
 
-    let config_file = std::fs::File::open(config_file_path).expect("Unable to open config file");
-    let config: Arc<config::Config> =
-        Arc::new(serde_json::from_reader(config_file).expect("Unable to read config file"));
+    let config_file_path = std::path::PathBuf::from(config_file_path);
+    let config = Arc::new(
+        config::Config::load(&config_file_path)
+            .unwrap_or_else(|why| panic!("Unable to read config file: {}", why)),
+    );
 
-    let ytdl_version = get_ytdl_version(&config.get_play_config())
-        .await
-        .expect("Unable to check youtube-dl");
-    log::info!("Using youtube-dl version {}", ytdl_version);
-
-    let mut backend_brain = mrvn_back_ytdl::Brain::new();
-    let model = mrvn_model::AppModel::new(mrvn_model::AppModelConfig {
-        skip_votes_required: config.skip_votes_required,
-        stop_votes_required: config.stop_votes_required,
+    let _sentry_guard = config.sentry_dsn.as_deref().map(|dsn| {
+        tracing::info!("Reporting errors to Sentry");
+        sentry::init((
+            dsn,
+            sentry::ClientOptions {
+                release: sentry::release_name!(),
+                ..Default::default()
+            },
+        ))
     });
 
-    log::info!("Starting {} voice clients", config.voice_bots.len());
-    let mut voice_clients = future::try_join_all(config.voice_bots.iter().enumerate().map(
-        |(index, bot_config)| {
-            Client::builder(&bot_config.token, GatewayIntents::non_privileged())
-                .application_id(ApplicationId::new(bot_config.application_id))
-                .event_handler(voice_handler::VoiceHandler {
-                    client_index: index,
-                })
-                .register_speaker(&mut backend_brain)
-                .into_future()
-        },
-    ))
-    .await
-    .expect("Unable to create voice client");
+    let proxy_rules = ProxyRules::new(config.proxy.as_deref(), config.host_proxies.clone())
+        .expect("Unable to read proxy config");
+    set_proxy_rules(proxy_rules);
+
+    // Extractors that are missing are only a problem if none of them work
+    let mut has_extractor = false;
+    for extractor in config.get_extractors() {
+        match get_ytdl_version(extractor).await {
+            Ok(version) => {
+                tracing::info!("Using {} version {}", extractor.name, version);
+                has_extractor = true;
+            }
+            Err(why) => tracing::warn!("Unable to check {}: {}", extractor.name, why),
+        }
+    }
+    if !has_extractor {
+        panic!("Unable to find any extractors");
+    }
+
+    let backend_brain = mrvn_back_ytdl::Brain::new();
+    let maybe_share = match &config.redis_url {
+        Some(redis_url) => Some(
+            mrvn_model::RedisShare::connect(redis_url)
+                .await
+                .expect("Unable to connect to Redis"),
+        ),
+        None => None,
+    };
+    let model = mrvn_model::AppModel::new(config.get_model_config(), maybe_share);
+
+    let data_path = std::path::Path::new(&config.data_path);
+    let storage = config
+        .storage
+        .open(data_path)
+        .expect("Unable to open storage");
+    let playlists =
+        mrvn_model::PlaylistStore::open(storage.clone()).expect("Unable to read playlists");
+    let settings =
+        mrvn_model::SettingsStore::open(storage.clone()).expect("Unable to read settings");
+    let favorites =
+        mrvn_model::FavoriteStore::open(storage.clone()).expect("Unable to read favorites");
+    let history = mrvn_model::HistoryStore::new(storage);
+    settings
+        .read(|settings| {
+            for (guild_id, guild_settings) in settings.iter() {
+                let guild_config = config.with_guild_settings(guild_settings);
+                model.set_guild_config(*guild_id, guild_config.get_model_config());
+            }
+        })
+        .await;
+
+    // Guild configs are set first so history is trimmed to each guild's history length
+    let saved_history = history.load().expect("Unable to read history");
+    for (guild_id, channel_id, entries) in saved_history {
+        let guild_model_handle = model.get(guild_id);
+        let mut guild_model = guild_model_handle
+            .lock()
+            .await
+            .expect("Unable to lock guild model");
+        guild_model.set_history(channel_id, entries.into_iter().rev());
+    }
 
     let frontend = Arc::new(crate::frontend::Frontend::new(
         config.clone(),
+        config_file_path,
         backend_brain,
         model,
+        playlists,
+        settings,
+        favorites,
+        history,
     ));
-    let mut command_client =
-        Client::builder(&config.command_bot.token, GatewayIntents::non_privileged())
-            .application_id(ApplicationId::new(config.command_bot.application_id))
-            .event_handler(command_handler::CommandHandler::new(frontend.clone()))
+
+    tracing::info!("Starting {} voice clients", config.voice_bots.len());
+    for bot_config in &config.voice_bots {
+        voice_bots::start_voice_bot(&frontend, bot_config)
             .await
-            .expect("Unable to create command client");
+            .expect("Unable to start voice client");
+    }
+
+    // Prefix commands need to read messages, which Discord treats as privileged.
+    let command_intents = match config.command_prefix {
+        Some(_) => GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT,
+        None => GatewayIntents::non_privileged(),
+    };
+    let mut command_client = Client::builder(&config.command_bot.token, command_intents)
+        .application_id(ApplicationId::new(config.command_bot.application_id))
+        .event_handler(command_handler::CommandHandler::new(frontend.clone()))
+        .await
+        .expect("Unable to create command client");
+    let maybe_command_guild_id = config.command_bot.guild_id.map(GuildId::new);
+    let disabled_commands = match maybe_command_guild_id {
+        Some(guild_id) => frontend.disabled_commands(guild_id).await,
+        None => Vec::new(),
+    };
     commands::register_commands(
         &command_client.http,
-        config.command_bot.guild_id.map(GuildId::new),
+        maybe_command_guild_id,
+        &config,
+        &disabled_commands,
     )
     .await
     .expect("Unable to register commands");
-    log::info!("Finished registering application commands");
+    tracing::info!("Finished registering application commands");
 
+    let ytdl_update_loop_future =
+        ytdl_update_loop::ytdl_update_loop(frontend.clone()).map(|_| Ok(()));
+    let http_server_future = http::http_server(http::HttpState {
+        frontend: frontend.clone(),
+        cache: command_client.cache.clone(),
+        command_shards: command_client.shard_manager.clone(),
+        sessions: Arc::default(),
+    })
+    .map(|_| Ok(()));
+    let reload_loop_future = reload_loop::reload_loop(frontend.clone()).map(|_| Ok(()));
     let cleanup_loop_future =
-        cleanup_loop::cleanup_loop(frontend, command_client.cache.clone()).map(|_| Ok(()));
+        cleanup_loop::cleanup_loop(frontend.clone(), command_client.cache.clone()).map(|_| Ok(()));
 
-    futures::try_join!(
-        command_client.start(),
-        future::try_join_all(voice_clients.iter_mut().map(|client| client.start())),
-        cleanup_loop_future,
-    )
-    .expect("Error while running client");
+    tokio::select! {
+        res = async {
+            futures::try_join!(
+                start_client(&mut command_client, config.command_bot.shard_count),
+                cleanup_loop_future,
+                ytdl_update_loop_future,
+                reload_loop_future,
+                http_server_future,
+            )
+        } => {
+            res.expect("Error while running client");
+        }
+        _ = shutdown::wait_for_signal() => {}
+    }
+
+    // Songs are faded out and the speakers leave their channels before the gateway connections
+    // close, otherwise they'd be left sitting in the channels until Discord notices they're gone.
+    frontend.shut_down().await;
+    command_client.shard_manager.shutdown_all().await;
+    for shard_manager in frontend.voice_bots.shard_managers().await {
+        shard_manager.shutdown_all().await;
+    }
+    tracing::info!("Shut down cleanly");
 }
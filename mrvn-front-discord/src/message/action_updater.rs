@@ -11,6 +11,7 @@ pub struct ActionUpdater {
     voice_channel: ChannelId,
     is_response: bool,
     config: Arc<Config>,
+    locale: Option<String>,
     ctx: Context,
 }
 
@@ -21,6 +22,7 @@ impl ActionUpdater {
         voice_channel: ChannelId,
         is_response: bool,
         config: Arc<Config>,
+        locale: Option<String>,
         ctx: Context,
     ) -> Self {
         ActionUpdater {
@@ -29,14 +31,27 @@ impl ActionUpdater {
             voice_channel,
             is_response,
             config,
+            locale,
             ctx,
         }
     }
 
+    pub fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
     pub fn is_response(&self) -> bool {
         self.is_response
     }
 
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
     pub async fn update(&self, action_message: ActionMessage) {
         let maybe_err = self
             .channel_id
@@ -44,12 +59,19 @@ impl ActionUpdater {
                 &self.ctx,
                 self.message_id,
                 EditMessage::new()
-                    .embed(action_message.create_embed(&self.config, self.voice_channel)),
+                    .embed(action_message.create_embed(
+                        &self.config,
+                        self.locale.as_deref(),
+                        self.voice_channel,
+                    ))
+                    .components(
+                        action_message.create_components(&self.config, self.locale.as_deref()),
+                    ),
             )
             .await;
 
         if let Err(why) = maybe_err {
-            log::error!("Error while updating action: {}", why);
+            tracing::error!("Error while updating action: {}", why);
         }
     }
 
@@ -60,7 +82,7 @@ impl ActionUpdater {
             .await;
 
         if let Err(why) = maybe_err {
-            log::error!("Error while deleting action: {}", why);
+            tracing::error!("Error while deleting action: {}", why);
         };
     }
 }
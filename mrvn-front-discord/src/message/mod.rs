@@ -1,6 +1,16 @@
-use crate::message::time_bar::format_time_bar;
-use serenity::all::CreateEmbed;
+use crate::component::ComponentAction;
+use crate::message::time_bar::{format_count_bar, format_time, format_time_bar};
+use mrvn_back_ytdl::{equalizer_band_name, SongMetadata};
+use mrvn_model::VoteCount;
+use serenity::all::{
+    ButtonStyle, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed, CreateEmbedAuthor,
+    CreateEmbedFooter, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+};
 use serenity::model::prelude::*;
+use uuid::Uuid;
+
+const MAX_SELECT_OPTION_LABEL_LENGTH: usize = 100;
+const MAX_EMBED_AUTHOR_LENGTH: usize = 256;
 
 mod action_updater;
 mod default_action_delegate;
@@ -12,6 +22,99 @@ pub use self::action_updater::*;
 pub use self::message_delegate::*;
 pub use self::send_message::*;
 
+fn create_button(
+    config: &crate::config::Config,
+    locale: Option<&str>,
+    action: ComponentAction,
+    label_key: &str,
+) -> CreateButton {
+    CreateButton::new(action.to_custom_id()).label(config.get_raw_message(locale, label_key))
+}
+
+/// Lists songs to choose from, for search results and playlists.
+fn format_choices(
+    config: &crate::config::Config,
+    locale: Option<&str>,
+    choices: &[SearchMessageEntry],
+) -> String {
+    choices
+        .iter()
+        .enumerate()
+        .map(|(index, choice)| {
+            let position_string = (index + 1).to_string();
+            let duration_string = match choice.duration_seconds {
+                Some(duration) => config.format_time(locale, duration, 0).0,
+                None => config
+                    .get_raw_message(locale, "duration.unknown")
+                    .to_string(),
+            };
+            config.get_message(
+                locale,
+                "response.search_results_entry",
+                &[
+                    ("position", &position_string),
+                    ("song_title", &choice.song_title),
+                    ("song_url", &choice.song_url),
+                    ("duration", &duration_string),
+                ],
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Creates a select menu option for each song in a list of choices.
+fn create_choice_options(
+    config: &crate::config::Config,
+    locale: Option<&str>,
+    choices: &[SearchMessageEntry],
+) -> Vec<CreateSelectMenuOption> {
+    choices
+        .iter()
+        .enumerate()
+        .map(|(index, choice)| {
+            let label: String = format!("{}. {}", index + 1, choice.song_title)
+                .chars()
+                .take(MAX_SELECT_OPTION_LABEL_LENGTH)
+                .collect();
+            let option = CreateSelectMenuOption::new(label, index.to_string());
+            match choice.duration_seconds {
+                Some(duration) => option.description(config.format_time(locale, duration, 0).0),
+                None => option,
+            }
+        })
+        .collect()
+}
+
+/// Adds lines to a playing message for the radio stream's current track and the active filter
+/// preset, if there are any.
+fn with_playing_details(
+    config: &crate::config::Config,
+    locale: Option<&str>,
+    mut message: String,
+    maybe_stream_title: Option<&str>,
+    maybe_filter_preset: Option<&str>,
+    is_reconnecting: bool,
+) -> String {
+    if is_reconnecting {
+        message.push('\n');
+        message.push_str(config.get_raw_message(locale, "action.playing_reconnecting"));
+    }
+    if let Some(title) = maybe_stream_title {
+        message.push('\n');
+        message.push_str(&config.get_message(
+            locale,
+            "action.playing_stream_title",
+            &[("title", title)],
+        ));
+    }
+    if let Some(name) = maybe_filter_preset {
+        message.push('\n');
+        message.push_str(&config.get_message(locale, "action.playing_filter", &[("name", name)]));
+    }
+    message
+}
+
 pub enum Message {
     Action {
         message: ActionMessage,
@@ -32,14 +135,69 @@ impl Message {
         }
     }
 
-    pub fn create_embed(&self, config: &crate::config::Config) -> CreateEmbed {
+    /// Whether this message is about a song starting to play.
+    pub fn is_playing(&self) -> bool {
+        matches!(
+            self,
+            Message::Action {
+                message: ActionMessage::Playing { .. } | ActionMessage::PlayingResponse { .. },
+                ..
+            }
+        )
+    }
+
+    /// Whether this message is about playback in a channel coming to an end.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            Message::Action {
+                message: ActionMessage::Finished | ActionMessage::Stopped { .. },
+                ..
+            }
+        )
+    }
+
+    /// Ephemeral messages can't be found again through their channel, so only responses that
+    /// won't be updated later can be sent as ephemeral.
+    pub fn supports_ephemeral(&self) -> bool {
+        match self {
+            Message::Action { .. } => false,
+            Message::Response { message, delegate } => {
+                delegate.is_none() && !message.has_components()
+            }
+        }
+    }
+
+    pub fn create_embed(
+        &self,
+        config: &crate::config::Config,
+        locale: Option<&str>,
+    ) -> CreateEmbed {
         match self {
             Message::Action {
                 message,
                 voice_channel,
                 ..
-            } => message.create_embed(config, *voice_channel),
-            Message::Response { message, .. } => message.create_embed(config),
+            } => message.create_embed(config, locale, *voice_channel),
+            Message::Response { message, .. } => message.create_embed(config, locale),
+        }
+    }
+
+    pub fn create_attachments(&self) -> Vec<CreateAttachment> {
+        match self {
+            Message::Action { .. } => Vec::new(),
+            Message::Response { message, .. } => message.create_attachments(),
+        }
+    }
+
+    pub fn create_components(
+        &self,
+        config: &crate::config::Config,
+        locale: Option<&str>,
+    ) -> Vec<CreateActionRow> {
+        match self {
+            Message::Action { message, .. } => message.create_components(config, locale),
+            Message::Response { message, .. } => message.create_components(config, locale),
         }
     }
 }
@@ -55,21 +213,36 @@ pub enum ActionMessage {
         voice_channel_id: ChannelId,
         user_id: UserId,
         thumbnail_url: Option<String>,
+        details: SongDetails,
         time_seconds: f64,
         duration_seconds: Option<f64>,
+        skip_votes: VoteCount,
+        filter_preset: Option<String>,
+        stream_title: Option<String>,
+        is_reconnecting: bool,
     },
     PlayingResponse {
         song_title: String,
         song_url: String,
         voice_channel_id: ChannelId,
         thumbnail_url: Option<String>,
+        details: SongDetails,
         time_seconds: f64,
         duration_seconds: Option<f64>,
+        skip_votes: VoteCount,
+        filter_preset: Option<String>,
+        stream_title: Option<String>,
+        is_reconnecting: bool,
     },
     Played {
         song_title: String,
         song_url: String,
     },
+    /// The bot shut down while the song was playing.
+    Restarting {
+        song_title: String,
+        song_url: String,
+    },
     Finished,
     Paused {
         song_title: String,
@@ -82,22 +255,161 @@ pub enum ActionMessage {
         user_id: UserId,
     },
     NoSpeakersError,
+    PlaybackFailedError {
+        song_title: String,
+        song_url: String,
+        reason: String,
+    },
     UnknownError,
 }
 
+#[derive(Debug, Clone)]
+pub struct HistoryMessageEntry {
+    pub song_title: String,
+    pub song_url: String,
+    pub user_id: UserId,
+    pub ended_time: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BufferStatsMessageEntry {
+    pub song_title: String,
+    pub song_url: String,
+    pub voice_channel_id: ChannelId,
+    pub underruns: u64,
+    pub stalls: u64,
+    pub stalled_seconds: f64,
+}
+
+/// Details about a song that are shown around a message instead of in it, as the embed's author
+/// and footer.
+#[derive(Debug, Clone, Default)]
+pub struct SongDetails {
+    pub author: Option<String>,
+    pub source: Option<String>,
+    /// The site's favicon, shown next to its name.
+    pub source_icon_url: Option<String>,
+    pub view_count: Option<u64>,
+}
+
+impl SongDetails {
+    pub fn new(metadata: &SongMetadata) -> Self {
+        let source_icon_url = metadata
+            .source
+            .as_ref()
+            .and_then(|_| reqwest::Url::parse(&metadata.url).ok())
+            .and_then(|url| {
+                Some(format!(
+                    "{}://{}/favicon.ico",
+                    url.scheme(),
+                    url.host_str()?
+                ))
+            });
+        SongDetails {
+            author: metadata.author.clone(),
+            source: metadata.source.clone(),
+            source_icon_url,
+            view_count: metadata.view_count,
+        }
+    }
+
+    fn add_to_embed(
+        &self,
+        config: &crate::config::Config,
+        locale: Option<&str>,
+        mut embed: CreateEmbed,
+    ) -> CreateEmbed {
+        if let Some(author) = &self.author {
+            let author: String = author.chars().take(MAX_EMBED_AUTHOR_LENGTH).collect();
+            embed = embed.author(CreateEmbedAuthor::new(author));
+        }
+
+        let maybe_view_count = self.view_count.map(|view_count| {
+            let view_count_string = view_count.to_string();
+            config.get_message(locale, "embed.view_count", &[("count", &view_count_string)])
+        });
+        let footer_parts: Vec<_> = self
+            .source
+            .iter()
+            .cloned()
+            .chain(maybe_view_count)
+            .collect();
+        if footer_parts.is_empty() {
+            return embed;
+        }
+        let footer = CreateEmbedFooter::new(footer_parts.join(" • "));
+        match &self.source_icon_url {
+            Some(icon_url) => embed.footer(footer.icon_url(icon_url)),
+            None => embed.footer(footer),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChapterMessageEntry {
+    pub title: String,
+    pub start_seconds: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchMessageEntry {
+    pub song_title: String,
+    pub song_url: String,
+    pub duration_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingMessageEntry {
+    pub name: String,
+    pub value: String,
+    pub is_overridden: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PriorityRoleMessageEntry {
+    pub role_id: RoleId,
+    pub priority: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct FavoriteMessageEntry {
+    pub name: String,
+    pub song_title: String,
+    pub song_url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaylistMessageEntry {
+    pub name: String,
+    pub count: usize,
+    pub user_id: UserId,
+}
+
 /// Response messages are always sent directly as a response to a command invocation.
 #[derive(Debug, Clone)]
 pub enum ResponseMessage {
     Queued {
         song_title: String,
         song_url: String,
+        details: SongDetails,
     },
     QueuedMultiple {
         count: usize,
     },
+    QueueingPlaylist {
+        queued_count: usize,
+        total_count: usize,
+        /// The song that was queued most recently, as (title, URL).
+        latest_song: Option<(String, String)>,
+    },
+    QueuedPlaylistTruncated {
+        count: usize,
+        max_count: usize,
+    },
     QueuedNoSpeakers {
         song_title: String,
         song_url: String,
+        details: SongDetails,
     },
     QueuedMultipleNoSpeakers {
         count: usize,
@@ -108,6 +420,27 @@ pub enum ResponseMessage {
         new_song_title: String,
         new_song_url: String,
     },
+    Undone {
+        removed_count: usize,
+        restored_count: usize,
+    },
+    NothingToUndoError,
+    WrongSongRemoved {
+        song_title: String,
+        song_url: String,
+    },
+    NothingToRemoveError,
+    QueueEntryRemoved {
+        song_title: String,
+        song_url: String,
+    },
+    QueueEntryMoved {
+        song_title: String,
+        song_url: String,
+    },
+    NoQueueEntryError {
+        position: usize,
+    },
     ReplaceSkipped {
         new_song_title: String,
         new_song_url: String,
@@ -115,6 +448,49 @@ pub enum ResponseMessage {
         old_song_url: String,
         voice_channel_id: ChannelId,
     },
+    Restarted {
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+    },
+    Chapters {
+        song_title: String,
+        song_url: String,
+        chapters: Vec<ChapterMessageEntry>,
+    },
+    ChapterJumped {
+        chapter_title: String,
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+    },
+    NoChaptersError {
+        song_title: String,
+        song_url: String,
+    },
+    UnknownChapterError {
+        position: usize,
+        count: usize,
+        song_title: String,
+        song_url: String,
+    },
+    ChapterNotSeekableError {
+        song_title: String,
+        song_url: String,
+    },
+    Rewound {
+        amount_seconds: f64,
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+    },
+    InvalidDurationError {
+        value: String,
+    },
+    RewindNotSeekableError {
+        song_title: String,
+        song_url: String,
+    },
     Skipped {
         song_title: String,
         song_url: String,
@@ -131,9 +507,297 @@ pub enum ResponseMessage {
         voice_channel_id: ChannelId,
         count: usize,
     },
+    PauseMoreVotesNeeded {
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+        count: usize,
+    },
+    SearchResults {
+        search_id: Uuid,
+        results: Vec<SearchMessageEntry>,
+    },
+    SearchExpiredError,
+    SearchNotOwnerError {
+        user_id: UserId,
+    },
+    /// Sent instead of queueing a playlist, with a menu to choose which of its songs to queue.
+    /// Only the first songs can be chosen from if the playlist is longer than a menu allows.
+    PlaylistChoices {
+        choices_id: Uuid,
+        choices: Vec<SearchMessageEntry>,
+        is_truncated: bool,
+    },
+    PlaylistChoiceExpiredError,
+    /// Sent instead of queueing a playlist with more than `playlist_confirm_size` songs, with a
+    /// button to queue it anyway.
+    LargePlaylist {
+        confirm_id: Uuid,
+        song_count: usize,
+    },
+    PlaylistChoiceNotOwnerError {
+        user_id: UserId,
+    },
+    /// Sent instead of queueing a song that's already queued or playing, with a button to queue it
+    /// anyway.
+    DuplicateSong {
+        duplicate_id: Uuid,
+        song_title: String,
+        song_url: String,
+    },
+    DuplicateExpiredError,
+    DuplicateNotOwnerError {
+        user_id: UserId,
+    },
+    Grab {
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+        thumbnail_url: Option<String>,
+        time_seconds: f64,
+        duration_seconds: Option<f64>,
+    },
+    Grabbed {
+        song_title: String,
+        song_url: String,
+    },
+    GrabFailedError,
+    History {
+        voice_channel_id: ChannelId,
+        entries: Vec<HistoryMessageEntry>,
+    },
+    PlaylistSaved {
+        name: String,
+        count: usize,
+    },
+    PlaylistDeleted {
+        name: String,
+    },
+    FavoriteAdded {
+        name: String,
+        song_title: String,
+        song_url: String,
+    },
+    FavoriteRemoved {
+        name: String,
+    },
+    FavoriteList {
+        favorites: Vec<FavoriteMessageEntry>,
+    },
+    /// The queue is sent along with the message as a file.
+    QueueExported {
+        count: usize,
+        file_name: String,
+        file_data: Vec<u8>,
+    },
+    LanguageSet {
+        name: String,
+    },
+    LanguageReset,
+    DjRoleAdded {
+        role_id: RoleId,
+    },
+    DjRoleRemoved {
+        role_id: RoleId,
+    },
+    DjRoles {
+        role_ids: Vec<RoleId>,
+    },
+    PriorityRoleSet {
+        role_id: RoleId,
+        priority: u32,
+    },
+    PriorityRoleRemoved {
+        role_id: RoleId,
+    },
+    PriorityRoles {
+        roles: Vec<PriorityRoleMessageEntry>,
+    },
+    UserBanned {
+        user_id: UserId,
+    },
+    UserUnbanned {
+        user_id: UserId,
+    },
+    BannedUsers {
+        user_ids: Vec<UserId>,
+    },
+    CommandDisabled {
+        command_name: String,
+    },
+    CommandEnabled {
+        command_name: String,
+    },
+    DisabledCommands {
+        command_names: Vec<String>,
+    },
+    BlocklistEntryAdded {
+        entry: String,
+    },
+    BlocklistEntryRemoved {
+        entry: String,
+    },
+    Blocklist {
+        entries: Vec<String>,
+    },
+    Settings {
+        settings: Vec<SettingMessageEntry>,
+    },
+    SettingChanged {
+        name: String,
+        value: String,
+    },
+    SettingReset {
+        name: String,
+        value: String,
+    },
+    PlaylistList {
+        playlists: Vec<PlaylistMessageEntry>,
+    },
+    PlaylistNotFoundError {
+        name: String,
+    },
+    PlaylistNotOwnerError {
+        name: String,
+        user_id: UserId,
+    },
+    NoPlaylistsError,
+    FavoriteNotFoundError {
+        name: String,
+    },
+    NoFavoritesError,
+    NothingToSaveError,
+    NothingToExportError,
+    InvalidQueueFileError,
     NoMatchingSongsError,
+    NoLinksInMessageError,
+    QueueingRemainingLines {
+        line_count: usize,
+    },
+    UnknownLanguageError {
+        name: String,
+        languages: Vec<String>,
+    },
+    InvalidSettingValueError {
+        name: String,
+        value: String,
+    },
+    NotDjRoleError {
+        role_id: RoleId,
+    },
+    DjOnlyError,
+    UserQueueCleared {
+        user_id: UserId,
+        removed_count: usize,
+    },
+    UserNothingQueuedError {
+        user_id: UserId,
+    },
+    NotBannedError {
+        user_id: UserId,
+    },
+    BannedUserError,
+    MissingPermissionsError,
+    InvalidPrefixCommandError {
+        command_name: String,
+    },
+    UnknownCommandError {
+        command_name: String,
+    },
+    NotDisabledError {
+        command_name: String,
+    },
+    CommandDisabledError {
+        command_name: String,
+    },
+    NotBlockedError {
+        entry: String,
+    },
+    BlockedSongError {
+        song_title: String,
+        song_url: String,
+        entry: String,
+    },
+    QueueFullError {
+        queued_count: usize,
+        max_count: usize,
+    },
+    CooldownError {
+        command_name: String,
+        wait_secs: u64,
+    },
+    SoundEffectPlayed {
+        name: String,
+    },
+    UnknownSoundEffectError {
+        name: String,
+        sound_effects: Vec<String>,
+    },
+    VolumeSet {
+        percent: u32,
+    },
+    SpeedSet {
+        speed: f32,
+    },
+    PitchSet {
+        semitones: i32,
+    },
+    EqualizerBandSet {
+        frequency: u32,
+        gain: f32,
+    },
+    EqualizerPresetSet {
+        name: String,
+    },
+    Equalizer {
+        gains: Vec<(u32, f32)>,
+    },
+    FilterPresetSet {
+        name: String,
+    },
+    FilterPresetCleared,
+    AudioFiltersDisabledError,
     NotInVoiceChannelError,
     UnsupportedSiteError,
+    UnsupportedAttachmentError,
+    LoginRequiredError,
+    YtdlUpdated {
+        name: String,
+        old_version: String,
+        new_version: String,
+    },
+    YtdlUpToDate {
+        name: String,
+        version: String,
+    },
+    YtdlUpdateFailedError {
+        name: String,
+        reason: String,
+    },
+    NotBotOwnerError,
+    ConfigReloaded,
+    ConfigReloadFailedError {
+        reason: String,
+    },
+    Stats {
+        started_time: u64,
+        guild_count: usize,
+        songs_played: u64,
+        active_speakers: usize,
+        total_speakers: usize,
+        metadata_cache_hits: u64,
+        metadata_cache_lookups: u64,
+        audio_cache_hits: u64,
+        audio_cache_lookups: u64,
+    },
+    BufferStats {
+        capacity_kb: usize,
+        tracks: u64,
+        underruns: u64,
+        stalls: u64,
+        stalled_seconds: f64,
+        entries: Vec<BufferStatsMessageEntry>,
+    },
     SkipAlreadyVotedError {
         song_title: String,
         song_url: String,
@@ -142,6 +806,11 @@ pub enum ResponseMessage {
     StopAlreadyVotedError {
         voice_channel_id: ChannelId,
     },
+    PauseAlreadyVotedError {
+        song_title: String,
+        song_url: String,
+        voice_channel_id: ChannelId,
+    },
     NothingIsQueuedError {
         voice_channel_id: ChannelId,
     },
@@ -151,10 +820,18 @@ pub enum ResponseMessage {
     AlreadyPlayingError {
         voice_channel_id: ChannelId,
     },
+    NoPreviousSongError {
+        voice_channel_id: ChannelId,
+    },
 }
 
 impl ActionMessage {
-    pub fn to_string(&self, config: &crate::config::Config, voice_channel_id: ChannelId) -> String {
+    pub fn to_string(
+        &self,
+        config: &crate::config::Config,
+        locale: Option<&str>,
+        voice_channel_id: ChannelId,
+    ) -> String {
         match self {
             ActionMessage::Playing {
                 song_title,
@@ -163,13 +840,17 @@ impl ActionMessage {
                 user_id,
                 time_seconds,
                 duration_seconds,
+                filter_preset,
+                stream_title,
+                is_reconnecting,
                 ..
             } => {
                 let channel_id_string = voice_channel_id.get().to_string();
                 let user_id_string = user_id.get().to_string();
-                let time_string = format_time_bar(config, *time_seconds, *duration_seconds);
+                let time_string = format_time_bar(config, locale, *time_seconds, *duration_seconds);
 
-                config.get_message(
+                let message = config.get_message(
+                    locale,
                     "action.playing",
                     &[
                         ("song_title", song_title),
@@ -178,6 +859,14 @@ impl ActionMessage {
                         ("user_id", &user_id_string),
                         ("time", &time_string),
                     ],
+                );
+                with_playing_details(
+                    config,
+                    locale,
+                    message,
+                    stream_title.as_deref(),
+                    filter_preset.as_deref(),
+                    *is_reconnecting,
                 )
             }
             ActionMessage::PlayingResponse {
@@ -186,12 +875,16 @@ impl ActionMessage {
                 voice_channel_id,
                 time_seconds,
                 duration_seconds,
+                filter_preset,
+                stream_title,
+                is_reconnecting,
                 ..
             } => {
                 let channel_id_string = voice_channel_id.get().to_string();
-                let time_string = format_time_bar(config, *time_seconds, *duration_seconds);
+                let time_string = format_time_bar(config, locale, *time_seconds, *duration_seconds);
 
-                config.get_message(
+                let message = config.get_message(
+                    locale,
                     "action.playing_response",
                     &[
                         ("song_title", song_title),
@@ -199,6 +892,14 @@ impl ActionMessage {
                         ("voice_channel_id", &channel_id_string),
                         ("time", &time_string),
                     ],
+                );
+                with_playing_details(
+                    config,
+                    locale,
+                    message,
+                    stream_title.as_deref(),
+                    filter_preset.as_deref(),
+                    *is_reconnecting,
                 )
             }
             ActionMessage::Played {
@@ -208,6 +909,7 @@ impl ActionMessage {
                 let channel_id_string = voice_channel_id.get().to_string();
 
                 config.get_message(
+                    locale,
                     "action.played",
                     &[
                         ("song_title", song_title),
@@ -216,9 +918,26 @@ impl ActionMessage {
                     ],
                 )
             }
+            ActionMessage::Restarting {
+                song_title,
+                song_url,
+            } => {
+                let channel_id_string = voice_channel_id.get().to_string();
+
+                config.get_message(
+                    locale,
+                    "action.restarting",
+                    &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                    ],
+                )
+            }
             ActionMessage::Finished => {
                 let channel_id_string = voice_channel_id.get().to_string();
                 config.get_message(
+                    locale,
                     "action.finished",
                     &[("voice_channel_id", &channel_id_string)],
                 )
@@ -231,6 +950,7 @@ impl ActionMessage {
                 let channel_id_string = voice_channel_id.get().to_string();
                 let user_id_string = user_id.get().to_string();
                 config.get_message(
+                    locale,
                     "response.paused",
                     &[
                         ("song_title", song_title),
@@ -248,6 +968,7 @@ impl ActionMessage {
                 let channel_id_string = voice_channel_id.get().to_string();
                 let user_id_string = user_id.get().to_string();
                 config.get_message(
+                    locale,
                     "response.stopped",
                     &[
                         ("song_title", song_title),
@@ -260,13 +981,27 @@ impl ActionMessage {
             ActionMessage::NoSpeakersError => {
                 let channel_id_string = voice_channel_id.get().to_string();
                 config.get_message(
+                    locale,
                     "action.no_speakers_error",
                     &[("voice_channel_id", &channel_id_string)],
                 )
             }
-            ActionMessage::UnknownError => {
-                config.get_raw_message("action.unknown_error").to_string()
-            }
+            ActionMessage::PlaybackFailedError {
+                song_title,
+                song_url,
+                reason,
+            } => config.get_message(
+                locale,
+                "action.playback_failed_error",
+                &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                    ("reason", reason),
+                ],
+            ),
+            ActionMessage::UnknownError => config
+                .get_raw_message(locale, "action.unknown_error")
+                .to_string(),
         }
     }
 
@@ -284,21 +1019,75 @@ impl ActionMessage {
         }
     }
 
+    pub fn get_song_details(&self) -> Option<&SongDetails> {
+        match self {
+            ActionMessage::Playing { details, .. }
+            | ActionMessage::PlayingResponse { details, .. } => Some(details),
+            _ => None,
+        }
+    }
+
     pub fn is_error(&self) -> bool {
         match self {
             ActionMessage::Playing { .. }
             | ActionMessage::PlayingResponse { .. }
             | ActionMessage::Played { .. }
-            | ActionMessage::Finished { .. }
+            | ActionMessage::Restarting { .. }
+            | ActionMessage::Finished
             | ActionMessage::Paused { .. }
             | ActionMessage::Stopped { .. } => false,
-            ActionMessage::NoSpeakersError { .. } | ActionMessage::UnknownError => true,
+            ActionMessage::NoSpeakersError
+            | ActionMessage::PlaybackFailedError { .. }
+            | ActionMessage::UnknownError => true,
         }
     }
 
+    pub fn create_components(
+        &self,
+        config: &crate::config::Config,
+        locale: Option<&str>,
+    ) -> Vec<CreateActionRow> {
+        let buttons = match self {
+            ActionMessage::Playing { skip_votes, .. }
+            | ActionMessage::PlayingResponse { skip_votes, .. } => {
+                // Show the vote count on the skip button if skipping needs more than one vote.
+                let skip_label = if skip_votes.required > 1 {
+                    let votes_string = skip_votes.votes.to_string();
+                    let required_string = skip_votes.required.to_string();
+                    config.get_message(
+                        locale,
+                        "button.vote_skip",
+                        &[("votes", &votes_string), ("required", &required_string)],
+                    )
+                } else {
+                    config.get_raw_message(locale, "button.skip").to_string()
+                };
+
+                vec![
+                    create_button(config, locale, ComponentAction::Pause, "button.pause")
+                        .style(ButtonStyle::Secondary),
+                    CreateButton::new(ComponentAction::Skip.to_custom_id())
+                        .label(skip_label)
+                        .style(ButtonStyle::Secondary),
+                    create_button(config, locale, ComponentAction::Stop, "button.stop")
+                        .style(ButtonStyle::Danger),
+                ]
+            }
+            ActionMessage::Paused { .. } => vec![
+                create_button(config, locale, ComponentAction::Resume, "button.resume")
+                    .style(ButtonStyle::Primary),
+                create_button(config, locale, ComponentAction::Stop, "button.stop")
+                    .style(ButtonStyle::Danger),
+            ],
+            _ => return Vec::new(),
+        };
+        vec![CreateActionRow::Buttons(buttons)]
+    }
+
     pub fn create_embed(
         &self,
         config: &crate::config::Config,
+        locale: Option<&str>,
         voice_channel_id: ChannelId,
     ) -> CreateEmbed {
         let color = if self.is_error() {
@@ -307,9 +1096,12 @@ impl ActionMessage {
             config.action_embed_color
         };
 
-        let embed = CreateEmbed::new()
-            .description(self.to_string(config, voice_channel_id))
+        let mut embed = CreateEmbed::new()
+            .description(self.to_string(config, locale, voice_channel_id))
             .color(color);
+        if let Some(details) = self.get_song_details() {
+            embed = details.add_to_embed(config, locale, embed);
+        }
         match self.get_thumbnail() {
             Some(thumbnail) => embed.thumbnail(thumbnail),
             None => embed,
@@ -318,29 +1110,74 @@ impl ActionMessage {
 }
 
 impl ResponseMessage {
-    pub fn to_string(&self, config: &crate::config::Config) -> String {
+    pub fn to_string(&self, config: &crate::config::Config, locale: Option<&str>) -> String {
         match self {
             ResponseMessage::Queued {
                 song_title,
                 song_url,
+                ..
             } => config.get_message(
+                locale,
                 "response.queued",
                 &[("song_title", song_title), ("song_url", song_url)],
             ),
             ResponseMessage::QueuedMultiple { count } => {
                 let count_string = count.to_string();
-                config.get_message("response.queued_multiple", &[("count", &count_string)])
+                config.get_message(
+                    locale,
+                    "response.queued_multiple",
+                    &[("count", &count_string)],
+                )
+            }
+            ResponseMessage::QueueingPlaylist {
+                queued_count,
+                total_count,
+                latest_song,
+            } => {
+                let queued_string = queued_count.to_string();
+                let total_string = total_count.to_string();
+                let progress_string = format_count_bar(*queued_count, *total_count);
+                let mut message = config.get_message(
+                    locale,
+                    "response.queueing_playlist",
+                    &[
+                        ("queued", &queued_string),
+                        ("total", &total_string),
+                        ("progress", &progress_string),
+                    ],
+                );
+                if let Some((song_title, song_url)) = latest_song {
+                    message.push('\n');
+                    message.push_str(&config.get_message(
+                        locale,
+                        "response.queueing_playlist_latest",
+                        &[("song_title", song_title), ("song_url", song_url)],
+                    ));
+                }
+                message
+            }
+            ResponseMessage::QueuedPlaylistTruncated { count, max_count } => {
+                let count_string = count.to_string();
+                let max_string = max_count.to_string();
+                config.get_message(
+                    locale,
+                    "response.queued_playlist_truncated",
+                    &[("count", &count_string), ("max", &max_string)],
+                )
             }
             ResponseMessage::QueuedNoSpeakers {
                 song_title,
                 song_url,
+                ..
             } => config.get_message(
+                locale,
                 "response.queued_no_speakers",
                 &[("song_title", song_title), ("song_url", song_url)],
             ),
             ResponseMessage::QueuedMultipleNoSpeakers { count } => {
                 let count_string = count.to_string();
                 config.get_message(
+                    locale,
                     "response.queued_multiple_no_speakers",
                     &[("count", &count_string)],
                 )
@@ -351,6 +1188,7 @@ impl ResponseMessage {
                 new_song_title,
                 new_song_url,
             } => config.get_message(
+                locale,
                 "response.replaced",
                 &[
                     ("old_song_title", old_song_title),
@@ -359,6 +1197,56 @@ impl ResponseMessage {
                     ("new_song_url", new_song_url),
                 ],
             ),
+            ResponseMessage::Undone {
+                removed_count,
+                restored_count,
+            } => {
+                let removed_string = removed_count.to_string();
+                let restored_string = restored_count.to_string();
+                config.get_message(
+                    locale,
+                    "response.undone",
+                    &[("removed", &removed_string), ("restored", &restored_string)],
+                )
+            }
+            ResponseMessage::NothingToUndoError => config
+                .get_raw_message(locale, "response.nothing_to_undo_error")
+                .to_string(),
+            ResponseMessage::WrongSongRemoved {
+                song_title,
+                song_url,
+            } => config.get_message(
+                locale,
+                "response.wrong_song_removed",
+                &[("song_title", song_title), ("song_url", song_url)],
+            ),
+            ResponseMessage::NothingToRemoveError => config
+                .get_raw_message(locale, "response.nothing_to_remove_error")
+                .to_string(),
+            ResponseMessage::QueueEntryRemoved {
+                song_title,
+                song_url,
+            } => config.get_message(
+                locale,
+                "response.queue_entry_removed",
+                &[("song_title", song_title), ("song_url", song_url)],
+            ),
+            ResponseMessage::QueueEntryMoved {
+                song_title,
+                song_url,
+            } => config.get_message(
+                locale,
+                "response.queue_entry_moved",
+                &[("song_title", song_title), ("song_url", song_url)],
+            ),
+            ResponseMessage::NoQueueEntryError { position } => {
+                let position_string = position.to_string();
+                config.get_message(
+                    locale,
+                    "response.no_queue_entry_error",
+                    &[("position", &position_string)],
+                )
+            }
             ResponseMessage::ReplaceSkipped {
                 new_song_title,
                 new_song_url,
@@ -368,6 +1256,7 @@ impl ResponseMessage {
             } => {
                 let channel_id_string = voice_channel_id.get().to_string();
                 config.get_message(
+                    locale,
                     "response.replace_skipped",
                     &[
                         ("new_song_title", new_song_title),
@@ -378,6 +1267,140 @@ impl ResponseMessage {
                     ],
                 )
             }
+            ResponseMessage::Restarted {
+                song_title,
+                song_url,
+                voice_channel_id,
+            } => {
+                let channel_id_string = voice_channel_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.restarted",
+                    &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                    ],
+                )
+            }
+            ResponseMessage::Chapters {
+                song_title,
+                song_url,
+                chapters,
+            } => {
+                let chapters_string = chapters
+                    .iter()
+                    .enumerate()
+                    .map(|(index, chapter)| {
+                        let position_string = (index + 1).to_string();
+                        let time_string = config.format_time(locale, chapter.start_seconds, 0).0;
+                        config.get_message(
+                            locale,
+                            "response.chapters_entry",
+                            &[
+                                ("position", &position_string),
+                                ("chapter_title", &chapter.title),
+                                ("time", &time_string),
+                            ],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                config.get_message(
+                    locale,
+                    "response.chapters",
+                    &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("chapters", &chapters_string),
+                    ],
+                )
+            }
+            ResponseMessage::ChapterJumped {
+                chapter_title,
+                song_title,
+                song_url,
+                voice_channel_id,
+            } => {
+                let channel_id_string = voice_channel_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.chapter_jumped",
+                    &[
+                        ("chapter_title", chapter_title),
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                    ],
+                )
+            }
+            ResponseMessage::NoChaptersError {
+                song_title,
+                song_url,
+            } => config.get_message(
+                locale,
+                "response.no_chapters_error",
+                &[("song_title", song_title), ("song_url", song_url)],
+            ),
+            ResponseMessage::UnknownChapterError {
+                position,
+                count,
+                song_title,
+                song_url,
+            } => {
+                let position_string = position.to_string();
+                let count_string = count.to_string();
+                config.get_message(
+                    locale,
+                    "response.unknown_chapter_error",
+                    &[
+                        ("position", &position_string),
+                        ("count", &count_string),
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                    ],
+                )
+            }
+            ResponseMessage::ChapterNotSeekableError {
+                song_title,
+                song_url,
+            } => config.get_message(
+                locale,
+                "response.chapter_not_seekable_error",
+                &[("song_title", song_title), ("song_url", song_url)],
+            ),
+            ResponseMessage::Rewound {
+                amount_seconds,
+                song_title,
+                song_url,
+                voice_channel_id,
+            } => {
+                let (amount_string, _) = config.format_time(locale, *amount_seconds, 0);
+                let channel_id_string = voice_channel_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.rewound",
+                    &[
+                        ("amount", &amount_string),
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                    ],
+                )
+            }
+            ResponseMessage::InvalidDurationError { value } => config.get_message(
+                locale,
+                "response.invalid_duration_error",
+                &[("value", value)],
+            ),
+            ResponseMessage::RewindNotSeekableError {
+                song_title,
+                song_url,
+            } => config.get_message(
+                locale,
+                "response.rewind_not_seekable_error",
+                &[("song_title", song_title), ("song_url", song_url)],
+            ),
             ResponseMessage::Skipped {
                 song_title,
                 song_url,
@@ -387,6 +1410,7 @@ impl ResponseMessage {
                 let channel_id_string = voice_channel_id.get().to_string();
                 let user_id_string = user_id.get().to_string();
                 config.get_message(
+                    locale,
                     "response.skipped",
                     &[
                         ("song_title", song_title),
@@ -403,58 +1427,810 @@ impl ResponseMessage {
                 count,
             } => {
                 let channel_id_string = voice_channel_id.get().to_string();
-                if *count == 1 {
-                    config.get_message(
-                        "response.skip_more_votes_needed.singular",
-                        &[
-                            ("song_title", song_title),
-                            ("song_url", song_url),
-                            ("voice_channel_id", &channel_id_string),
-                        ],
-                    )
-                } else {
-                    let count_string = count.to_string();
-                    config.get_message(
-                        "response.skip_more_votes_needed.plural",
-                        &[
-                            ("song_title", song_title),
-                            ("song_url", song_url),
-                            ("voice_channel_id", &channel_id_string),
-                            ("count", &count_string),
-                        ],
-                    )
-                }
+                let count_string = count.to_string();
+                config.get_message(
+                    locale,
+                    "response.skip_more_votes_needed",
+                    &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                        ("count", &count_string),
+                    ],
+                )
             }
             ResponseMessage::StopMoreVotesNeeded {
                 voice_channel_id,
                 count,
             } => {
                 let channel_id_string = voice_channel_id.get().to_string();
-                if *count == 1 {
+                let count_string = count.to_string();
+                config.get_message(
+                    locale,
+                    "response.stop_more_votes_needed",
+                    &[
+                        ("voice_channel_id", &channel_id_string),
+                        ("count", &count_string),
+                    ],
+                )
+            }
+            ResponseMessage::PauseMoreVotesNeeded {
+                song_title,
+                song_url,
+                voice_channel_id,
+                count,
+            } => {
+                let channel_id_string = voice_channel_id.get().to_string();
+                let count_string = count.to_string();
+                config.get_message(
+                    locale,
+                    "response.pause_more_votes_needed",
+                    &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                        ("count", &count_string),
+                    ],
+                )
+            }
+            ResponseMessage::SearchResults { results, .. } => config.get_message(
+                locale,
+                "response.search_results",
+                &[("results", &format_choices(config, locale, results))],
+            ),
+            ResponseMessage::PlaylistChoices {
+                choices,
+                is_truncated,
+                ..
+            } => {
+                let choices_string = format_choices(config, locale, choices);
+                if *is_truncated {
+                    let count_string = choices.len().to_string();
                     config.get_message(
-                        "response.stop_more_votes_needed.singular",
-                        &[("voice_channel_id", &channel_id_string)],
+                        locale,
+                        "response.playlist_choices_truncated",
+                        &[("choices", &choices_string), ("count", &count_string)],
                     )
                 } else {
-                    let count_string = count.to_string();
                     config.get_message(
-                        "response.stop_more_votes_needed.plural",
-                        &[
-                            ("voice_channel_id", &channel_id_string),
-                            ("count", &count_string),
-                        ],
+                        locale,
+                        "response.playlist_choices",
+                        &[("choices", &choices_string)],
                     )
                 }
             }
+            ResponseMessage::SearchExpiredError => config
+                .get_raw_message(locale, "response.search_expired_error")
+                .to_string(),
+            ResponseMessage::SearchNotOwnerError { user_id } => {
+                let user_id_string = user_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.search_not_owner_error",
+                    &[("user_id", &user_id_string)],
+                )
+            }
+            ResponseMessage::LargePlaylist { song_count, .. } => {
+                let song_count_string = song_count.to_string();
+                config.get_message(
+                    locale,
+                    "response.large_playlist",
+                    &[("count", &song_count_string)],
+                )
+            }
+            ResponseMessage::PlaylistChoiceExpiredError => config
+                .get_raw_message(locale, "response.playlist_choice_expired_error")
+                .to_string(),
+            ResponseMessage::PlaylistChoiceNotOwnerError { user_id } => {
+                let user_id_string = user_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.playlist_choice_not_owner_error",
+                    &[("user_id", &user_id_string)],
+                )
+            }
+            ResponseMessage::DuplicateSong {
+                song_title,
+                song_url,
+                ..
+            } => config.get_message(
+                locale,
+                "response.duplicate_song",
+                &[("song_title", song_title), ("song_url", song_url)],
+            ),
+            ResponseMessage::DuplicateExpiredError => config
+                .get_raw_message(locale, "response.duplicate_expired_error")
+                .to_string(),
+            ResponseMessage::DuplicateNotOwnerError { user_id } => {
+                let user_id_string = user_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.duplicate_not_owner_error",
+                    &[("user_id", &user_id_string)],
+                )
+            }
+            ResponseMessage::Grab {
+                song_title,
+                song_url,
+                voice_channel_id,
+                time_seconds,
+                duration_seconds,
+                ..
+            } => {
+                let channel_id_string = voice_channel_id.get().to_string();
+                let time_string = format_time(config, locale, *time_seconds, *duration_seconds);
+                config.get_message(
+                    locale,
+                    "direct.grab",
+                    &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                        ("time", &time_string),
+                    ],
+                )
+            }
+            ResponseMessage::Grabbed {
+                song_title,
+                song_url,
+            } => config.get_message(
+                locale,
+                "response.grabbed",
+                &[("song_title", song_title), ("song_url", song_url)],
+            ),
+            ResponseMessage::GrabFailedError => config
+                .get_raw_message(locale, "response.grab_failed_error")
+                .to_string(),
+            ResponseMessage::History {
+                voice_channel_id,
+                entries,
+            } => {
+                let channel_id_string = voice_channel_id.get().to_string();
+                let entries_string = entries
+                    .iter()
+                    .enumerate()
+                    .map(|(index, entry)| {
+                        let position_string = (index + 1).to_string();
+                        let user_id_string = entry.user_id.get().to_string();
+                        let ended_time_string = entry.ended_time.to_string();
+                        config.get_message(
+                            locale,
+                            "response.history_entry",
+                            &[
+                                ("position", &position_string),
+                                ("song_title", &entry.song_title),
+                                ("song_url", &entry.song_url),
+                                ("user_id", &user_id_string),
+                                ("ended_time", &ended_time_string),
+                            ],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                config.get_message(
+                    locale,
+                    "response.history",
+                    &[
+                        ("voice_channel_id", &channel_id_string),
+                        ("entries", &entries_string),
+                    ],
+                )
+            }
+            ResponseMessage::PlaylistSaved { name, count } => {
+                let count_string = count.to_string();
+                config.get_message(
+                    locale,
+                    "response.playlist_saved",
+                    &[("name", name), ("count", &count_string)],
+                )
+            }
+            ResponseMessage::PlaylistDeleted { name } => {
+                config.get_message(locale, "response.playlist_deleted", &[("name", name)])
+            }
+            ResponseMessage::FavoriteAdded {
+                name,
+                song_title,
+                song_url,
+            } => config.get_message(
+                locale,
+                "response.favorite_added",
+                &[
+                    ("name", name),
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                ],
+            ),
+            ResponseMessage::FavoriteRemoved { name } => {
+                config.get_message(locale, "response.favorite_removed", &[("name", name)])
+            }
+            ResponseMessage::FavoriteList { favorites } => {
+                let favorites_string = favorites
+                    .iter()
+                    .map(|favorite| {
+                        config.get_message(
+                            locale,
+                            "response.favorite_list_entry",
+                            &[
+                                ("name", &favorite.name),
+                                ("song_title", &favorite.song_title),
+                                ("song_url", &favorite.song_url),
+                            ],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                config.get_message(
+                    locale,
+                    "response.favorite_list",
+                    &[("favorites", &favorites_string)],
+                )
+            }
+            ResponseMessage::QueueExported { count, .. } => {
+                let count_string = count.to_string();
+                config.get_message(
+                    locale,
+                    "response.queue_exported",
+                    &[("count", &count_string)],
+                )
+            }
+            ResponseMessage::LanguageSet { name } => {
+                config.get_message(locale, "response.language_set", &[("name", name)])
+            }
+            ResponseMessage::LanguageReset => config
+                .get_raw_message(locale, "response.language_reset")
+                .to_string(),
+            ResponseMessage::SoundEffectPlayed { name } => {
+                config.get_message(locale, "response.sound_effect_played", &[("name", name)])
+            }
+            ResponseMessage::UnknownSoundEffectError {
+                name,
+                sound_effects,
+            } => {
+                let sound_effects_string = sound_effects
+                    .iter()
+                    .map(|sound_effect| format!("`{}`", sound_effect))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                config.get_message(
+                    locale,
+                    "response.unknown_sound_effect_error",
+                    &[("name", name), ("sound_effects", &sound_effects_string)],
+                )
+            }
+            ResponseMessage::VolumeSet { percent } => config.get_message(
+                locale,
+                "response.volume_set",
+                &[("percent", &percent.to_string())],
+            ),
+            ResponseMessage::SpeedSet { speed } => config.get_message(
+                locale,
+                "response.speed_set",
+                &[("speed", &speed.to_string())],
+            ),
+            ResponseMessage::PitchSet { semitones } => config.get_message(
+                locale,
+                "response.pitch_set",
+                &[("semitones", &format!("{:+}", semitones))],
+            ),
+            ResponseMessage::EqualizerBandSet { frequency, gain } => config.get_message(
+                locale,
+                "response.equalizer_band_set",
+                &[
+                    ("band", &equalizer_band_name(*frequency)),
+                    ("gain", &format!("{:+}", gain)),
+                ],
+            ),
+            ResponseMessage::EqualizerPresetSet { name } => {
+                config.get_message(locale, "response.equalizer_preset_set", &[("name", name)])
+            }
+            ResponseMessage::Equalizer { gains } => {
+                let bands_string = gains
+                    .iter()
+                    .map(|(frequency, gain)| {
+                        config.get_message(
+                            locale,
+                            "response.equalizer_entry",
+                            &[
+                                ("band", &equalizer_band_name(*frequency)),
+                                ("gain", &format!("{:+}", gain)),
+                            ],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                config.get_message(locale, "response.equalizer", &[("bands", &bands_string)])
+            }
+            ResponseMessage::FilterPresetSet { name } => {
+                config.get_message(locale, "response.filter_preset_set", &[("name", name)])
+            }
+            ResponseMessage::FilterPresetCleared => config
+                .get_raw_message(locale, "response.filter_preset_cleared")
+                .to_string(),
+            ResponseMessage::AudioFiltersDisabledError => config
+                .get_raw_message(locale, "response.audio_filters_disabled_error")
+                .to_string(),
+            ResponseMessage::UnknownLanguageError { name, languages } => {
+                let languages_string = languages
+                    .iter()
+                    .map(|language| format!("`{}`", language))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                config.get_message(
+                    locale,
+                    "response.unknown_language_error",
+                    &[("name", name), ("languages", &languages_string)],
+                )
+            }
+            ResponseMessage::PlaylistList { playlists } => {
+                let playlists_string = playlists
+                    .iter()
+                    .map(|playlist| {
+                        let count_string = playlist.count.to_string();
+                        let user_id_string = playlist.user_id.get().to_string();
+                        config.get_message(
+                            locale,
+                            "response.playlist_list_entry",
+                            &[
+                                ("name", &playlist.name),
+                                ("count", &count_string),
+                                ("user_id", &user_id_string),
+                            ],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                config.get_message(
+                    locale,
+                    "response.playlist_list",
+                    &[("playlists", &playlists_string)],
+                )
+            }
+            ResponseMessage::Settings { settings } => {
+                let settings_string = settings
+                    .iter()
+                    .map(|setting| {
+                        let message_key = if setting.is_overridden {
+                            "response.settings_entry"
+                        } else {
+                            "response.settings_entry_default"
+                        };
+                        config.get_message(
+                            locale,
+                            message_key,
+                            &[("name", &setting.name), ("value", &setting.value)],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                config.get_message(
+                    locale,
+                    "response.settings",
+                    &[("settings", &settings_string)],
+                )
+            }
+            ResponseMessage::SettingChanged { name, value } => config.get_message(
+                locale,
+                "response.setting_changed",
+                &[("name", name), ("value", value)],
+            ),
+            ResponseMessage::SettingReset { name, value } => config.get_message(
+                locale,
+                "response.setting_reset",
+                &[("name", name), ("value", value)],
+            ),
+            ResponseMessage::DjRoleAdded { role_id } => {
+                let role_id_string = role_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.dj_role_added",
+                    &[("role_id", &role_id_string)],
+                )
+            }
+            ResponseMessage::DjRoleRemoved { role_id } => {
+                let role_id_string = role_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.dj_role_removed",
+                    &[("role_id", &role_id_string)],
+                )
+            }
+            ResponseMessage::DjRoles { role_ids } if role_ids.is_empty() => config
+                .get_raw_message(locale, "response.no_dj_roles")
+                .to_string(),
+            ResponseMessage::DjRoles { role_ids } => {
+                let roles_string = role_ids
+                    .iter()
+                    .map(|role_id| format!("<@&{}>", role_id.get()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                config.get_message(locale, "response.dj_roles", &[("roles", &roles_string)])
+            }
+            ResponseMessage::PriorityRoleSet { role_id, priority } => {
+                let role_id_string = role_id.get().to_string();
+                let priority_string = priority.to_string();
+                config.get_message(
+                    locale,
+                    "response.priority_role_set",
+                    &[("role_id", &role_id_string), ("priority", &priority_string)],
+                )
+            }
+            ResponseMessage::PriorityRoleRemoved { role_id } => {
+                let role_id_string = role_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.priority_role_removed",
+                    &[("role_id", &role_id_string)],
+                )
+            }
+            ResponseMessage::PriorityRoles { roles } if roles.is_empty() => config
+                .get_raw_message(locale, "response.no_priority_roles")
+                .to_string(),
+            ResponseMessage::PriorityRoles { roles } => {
+                let roles_string = roles
+                    .iter()
+                    .map(|role| {
+                        let role_id_string = role.role_id.get().to_string();
+                        let priority_string = role.priority.to_string();
+                        config.get_message(
+                            locale,
+                            "response.priority_roles_entry",
+                            &[("role_id", &role_id_string), ("priority", &priority_string)],
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                config.get_message(
+                    locale,
+                    "response.priority_roles",
+                    &[("roles", &roles_string)],
+                )
+            }
+            ResponseMessage::UserBanned { user_id } => {
+                let user_id_string = user_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.user_banned",
+                    &[("user_id", &user_id_string)],
+                )
+            }
+            ResponseMessage::UserUnbanned { user_id } => {
+                let user_id_string = user_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.user_unbanned",
+                    &[("user_id", &user_id_string)],
+                )
+            }
+            ResponseMessage::BannedUsers { user_ids } if user_ids.is_empty() => config
+                .get_raw_message(locale, "response.no_banned_users")
+                .to_string(),
+            ResponseMessage::BannedUsers { user_ids } => {
+                let users_string = user_ids
+                    .iter()
+                    .map(|user_id| format!("<@{}>", user_id.get()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                config.get_message(locale, "response.banned_users", &[("users", &users_string)])
+            }
+            ResponseMessage::CommandDisabled { command_name } => config.get_message(
+                locale,
+                "response.command_disabled",
+                &[("command_name", command_name)],
+            ),
+            ResponseMessage::CommandEnabled { command_name } => config.get_message(
+                locale,
+                "response.command_enabled",
+                &[("command_name", command_name)],
+            ),
+            ResponseMessage::DisabledCommands { command_names } if command_names.is_empty() => {
+                config
+                    .get_raw_message(locale, "response.disabled_commands_empty")
+                    .to_string()
+            }
+            ResponseMessage::DisabledCommands { command_names } => {
+                let commands_string = command_names
+                    .iter()
+                    .map(|command_name| format!("`/{}`", command_name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                config.get_message(
+                    locale,
+                    "response.disabled_commands",
+                    &[("commands", &commands_string)],
+                )
+            }
+            ResponseMessage::BlocklistEntryAdded { entry } => config.get_message(
+                locale,
+                "response.blocklist_entry_added",
+                &[("entry", entry)],
+            ),
+            ResponseMessage::BlocklistEntryRemoved { entry } => config.get_message(
+                locale,
+                "response.blocklist_entry_removed",
+                &[("entry", entry)],
+            ),
+            ResponseMessage::Blocklist { entries } if entries.is_empty() => config
+                .get_raw_message(locale, "response.blocklist_empty")
+                .to_string(),
+            ResponseMessage::Blocklist { entries } => {
+                let entries_string = entries
+                    .iter()
+                    .map(|entry| format!("`{}`", entry))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                config.get_message(
+                    locale,
+                    "response.blocklist",
+                    &[("entries", &entries_string)],
+                )
+            }
+            ResponseMessage::NotDjRoleError { role_id } => {
+                let role_id_string = role_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.not_dj_role_error",
+                    &[("role_id", &role_id_string)],
+                )
+            }
+            ResponseMessage::DjOnlyError => config
+                .get_raw_message(locale, "response.dj_only_error")
+                .to_string(),
+            ResponseMessage::UserQueueCleared {
+                user_id,
+                removed_count,
+            } => {
+                let user_id_string = user_id.get().to_string();
+                let removed_string = removed_count.to_string();
+                config.get_message(
+                    locale,
+                    "response.user_queue_cleared",
+                    &[("user_id", &user_id_string), ("removed", &removed_string)],
+                )
+            }
+            ResponseMessage::UserNothingQueuedError { user_id } => {
+                let user_id_string = user_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.user_nothing_queued_error",
+                    &[("user_id", &user_id_string)],
+                )
+            }
+            ResponseMessage::NotBannedError { user_id } => {
+                let user_id_string = user_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.not_banned_error",
+                    &[("user_id", &user_id_string)],
+                )
+            }
+            ResponseMessage::MissingPermissionsError => config
+                .get_raw_message(locale, "response.missing_permissions_error")
+                .to_string(),
+            ResponseMessage::InvalidPrefixCommandError { command_name } => config.get_message(
+                locale,
+                "response.invalid_prefix_command_error",
+                &[("command_name", command_name)],
+            ),
+            ResponseMessage::BannedUserError => config
+                .get_raw_message(locale, "response.banned_user_error")
+                .to_string(),
+            ResponseMessage::UnknownCommandError { command_name } => config.get_message(
+                locale,
+                "response.unknown_command_error",
+                &[("command_name", command_name)],
+            ),
+            ResponseMessage::NotDisabledError { command_name } => config.get_message(
+                locale,
+                "response.not_disabled_error",
+                &[("command_name", command_name)],
+            ),
+            ResponseMessage::CommandDisabledError { command_name } => config.get_message(
+                locale,
+                "response.command_disabled_error",
+                &[("command_name", command_name)],
+            ),
+            ResponseMessage::NotBlockedError { entry } => {
+                config.get_message(locale, "response.not_blocked_error", &[("entry", entry)])
+            }
+            ResponseMessage::BlockedSongError {
+                song_title,
+                song_url,
+                entry,
+            } => config.get_message(
+                locale,
+                "response.blocked_song_error",
+                &[
+                    ("song_title", song_title),
+                    ("song_url", song_url),
+                    ("entry", entry),
+                ],
+            ),
+            ResponseMessage::CooldownError {
+                command_name,
+                wait_secs,
+            } => {
+                let wait_secs_string = wait_secs.to_string();
+                config.get_message(
+                    locale,
+                    "response.cooldown_error",
+                    &[("command", command_name), ("secs", &wait_secs_string)],
+                )
+            }
+            ResponseMessage::QueueFullError {
+                queued_count,
+                max_count,
+            } => {
+                let queued_string = queued_count.to_string();
+                let max_string = max_count.to_string();
+                config.get_message(
+                    locale,
+                    "response.queue_full_error",
+                    &[("queued", &queued_string), ("max", &max_string)],
+                )
+            }
+            ResponseMessage::InvalidSettingValueError { name, value } => config.get_message(
+                locale,
+                "response.invalid_setting_value_error",
+                &[("name", name), ("value", value)],
+            ),
+            ResponseMessage::PlaylistNotFoundError { name } => config.get_message(
+                locale,
+                "response.playlist_not_found_error",
+                &[("name", name)],
+            ),
+            ResponseMessage::PlaylistNotOwnerError { name, user_id } => {
+                let user_id_string = user_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.playlist_not_owner_error",
+                    &[("name", name), ("user_id", &user_id_string)],
+                )
+            }
+            ResponseMessage::NoPlaylistsError => config
+                .get_raw_message(locale, "response.no_playlists_error")
+                .to_string(),
+            ResponseMessage::FavoriteNotFoundError { name } => config.get_message(
+                locale,
+                "response.favorite_not_found_error",
+                &[("name", name)],
+            ),
+            ResponseMessage::NoFavoritesError => config
+                .get_raw_message(locale, "response.no_favorites_error")
+                .to_string(),
+            ResponseMessage::NothingToSaveError => config
+                .get_raw_message(locale, "response.nothing_to_save_error")
+                .to_string(),
+            ResponseMessage::NothingToExportError => config
+                .get_raw_message(locale, "response.nothing_to_export_error")
+                .to_string(),
+            ResponseMessage::InvalidQueueFileError => config
+                .get_raw_message(locale, "response.invalid_queue_file_error")
+                .to_string(),
             ResponseMessage::NoMatchingSongsError => config
-                .get_raw_message("response.no_matching_songs_error")
+                .get_raw_message(locale, "response.no_matching_songs_error")
+                .to_string(),
+            ResponseMessage::QueueingRemainingLines { line_count } => {
+                let line_count_string = line_count.to_string();
+                config.get_message(
+                    locale,
+                    "response.queueing_remaining_lines",
+                    &[("count", &line_count_string)],
+                )
+            }
+            ResponseMessage::NoLinksInMessageError => config
+                .get_raw_message(locale, "response.no_links_in_message_error")
                 .to_string(),
             ResponseMessage::NotInVoiceChannelError => config
-                .get_raw_message("response.not_in_voice_channel_error")
+                .get_raw_message(locale, "response.not_in_voice_channel_error")
+                .to_string(),
+            ResponseMessage::UnsupportedAttachmentError => config
+                .get_raw_message(locale, "response.unsupported_attachment_error")
                 .to_string(),
             ResponseMessage::UnsupportedSiteError => config
-                .get_raw_message("response.unsupported_site_error")
+                .get_raw_message(locale, "response.unsupported_site_error")
+                .to_string(),
+            ResponseMessage::LoginRequiredError => config
+                .get_raw_message(locale, "response.login_required_error")
                 .to_string(),
+            ResponseMessage::YtdlUpdated {
+                name,
+                old_version,
+                new_version,
+            } => config.get_message(
+                locale,
+                "response.ytdl_updated",
+                &[
+                    ("name", name),
+                    ("old_version", old_version),
+                    ("new_version", new_version),
+                ],
+            ),
+            ResponseMessage::YtdlUpToDate { name, version } => config.get_message(
+                locale,
+                "response.ytdl_up_to_date",
+                &[("name", name), ("version", version)],
+            ),
+            ResponseMessage::YtdlUpdateFailedError { name, reason } => config.get_message(
+                locale,
+                "response.ytdl_update_failed_error",
+                &[("name", name), ("reason", reason)],
+            ),
+            ResponseMessage::NotBotOwnerError => config
+                .get_raw_message(locale, "response.not_bot_owner_error")
+                .to_string(),
+            ResponseMessage::ConfigReloaded => config
+                .get_raw_message(locale, "response.config_reloaded")
+                .to_string(),
+            ResponseMessage::ConfigReloadFailedError { reason } => config.get_message(
+                locale,
+                "response.config_reload_failed_error",
+                &[("reason", reason)],
+            ),
+            ResponseMessage::Stats {
+                started_time,
+                guild_count,
+                songs_played,
+                active_speakers,
+                total_speakers,
+                metadata_cache_hits,
+                metadata_cache_lookups,
+                audio_cache_hits,
+                audio_cache_lookups,
+            } => config.get_message(
+                locale,
+                "response.stats",
+                &[
+                    ("started_time", &started_time.to_string()),
+                    ("guild_count", &guild_count.to_string()),
+                    ("songs_played", &songs_played.to_string()),
+                    ("active_speakers", &active_speakers.to_string()),
+                    ("total_speakers", &total_speakers.to_string()),
+                    ("metadata_cache_hits", &metadata_cache_hits.to_string()),
+                    (
+                        "metadata_cache_lookups",
+                        &metadata_cache_lookups.to_string(),
+                    ),
+                    ("audio_cache_hits", &audio_cache_hits.to_string()),
+                    ("audio_cache_lookups", &audio_cache_lookups.to_string()),
+                ],
+            ),
+            ResponseMessage::BufferStats {
+                capacity_kb,
+                tracks,
+                underruns,
+                stalls,
+                stalled_seconds,
+                entries,
+            } => {
+                let mut message = config.get_message(
+                    locale,
+                    "response.buffer_stats",
+                    &[
+                        ("capacity_kb", &capacity_kb.to_string()),
+                        ("tracks", &tracks.to_string()),
+                        ("underruns", &underruns.to_string()),
+                        ("stalls", &stalls.to_string()),
+                        ("stalled_seconds", &format!("{:.1}", stalled_seconds)),
+                    ],
+                );
+                for entry in entries {
+                    let channel_id_string = entry.voice_channel_id.get().to_string();
+                    message.push('\n');
+                    message.push_str(&config.get_message(
+                        locale,
+                        "response.buffer_stats_entry",
+                        &[
+                            ("song_title", &entry.song_title),
+                            ("song_url", &entry.song_url),
+                            ("voice_channel_id", &channel_id_string),
+                            ("underruns", &entry.underruns.to_string()),
+                            ("stalls", &entry.stalls.to_string()),
+                            ("stalled_seconds", &format!("{:.1}", entry.stalled_seconds)),
+                        ],
+                    ));
+                }
+                message
+            }
             ResponseMessage::SkipAlreadyVotedError {
                 song_title,
                 song_url,
@@ -462,6 +2238,7 @@ impl ResponseMessage {
             } => {
                 let channel_id_string = voice_channel_id.get().to_string();
                 config.get_message(
+                    locale,
                     "response.skip_already_voted_error",
                     &[
                         ("song_title", song_title),
@@ -473,13 +2250,31 @@ impl ResponseMessage {
             ResponseMessage::StopAlreadyVotedError { voice_channel_id } => {
                 let channel_id_string = voice_channel_id.get().to_string();
                 config.get_message(
+                    locale,
                     "response.stop_already_voted_error",
                     &[("voice_channel_id", &channel_id_string)],
                 )
             }
+            ResponseMessage::PauseAlreadyVotedError {
+                song_title,
+                song_url,
+                voice_channel_id,
+            } => {
+                let channel_id_string = voice_channel_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.pause_already_voted_error",
+                    &[
+                        ("song_title", song_title),
+                        ("song_url", song_url),
+                        ("voice_channel_id", &channel_id_string),
+                    ],
+                )
+            }
             ResponseMessage::NothingIsQueuedError { voice_channel_id } => {
                 let channel_id_string = voice_channel_id.get().to_string();
                 config.get_message(
+                    locale,
                     "response.nothing_is_queued_error",
                     &[("voice_channel_id", &channel_id_string)],
                 )
@@ -487,6 +2282,7 @@ impl ResponseMessage {
             ResponseMessage::NothingIsPlayingError { voice_channel_id } => {
                 let channel_id_string = voice_channel_id.get().to_string();
                 config.get_message(
+                    locale,
                     "response.nothing_is_playing_error",
                     &[("voice_channel_id", &channel_id_string)],
                 )
@@ -494,10 +2290,19 @@ impl ResponseMessage {
             ResponseMessage::AlreadyPlayingError { voice_channel_id } => {
                 let channel_id_string = voice_channel_id.get().to_string();
                 config.get_message(
+                    locale,
                     "response.already_playing_error",
                     &[("voice_channel_id", &channel_id_string)],
                 )
             }
+            ResponseMessage::NoPreviousSongError { voice_channel_id } => {
+                let channel_id_string = voice_channel_id.get().to_string();
+                config.get_message(
+                    locale,
+                    "response.no_previous_song_error",
+                    &[("voice_channel_id", &channel_id_string)],
+                )
+            }
         }
     }
 
@@ -505,31 +2310,244 @@ impl ResponseMessage {
         match self {
             ResponseMessage::Queued { .. }
             | ResponseMessage::QueuedMultiple { .. }
+            | ResponseMessage::QueueingPlaylist { .. }
+            | ResponseMessage::QueueingRemainingLines { .. }
+            | ResponseMessage::QueuedPlaylistTruncated { .. }
             | ResponseMessage::QueuedNoSpeakers { .. }
             | ResponseMessage::QueuedMultipleNoSpeakers { .. }
             | ResponseMessage::Replaced { .. }
+            | ResponseMessage::Undone { .. }
+            | ResponseMessage::WrongSongRemoved { .. }
+            | ResponseMessage::QueueEntryRemoved { .. }
+            | ResponseMessage::QueueEntryMoved { .. }
             | ResponseMessage::ReplaceSkipped { .. }
+            | ResponseMessage::Restarted { .. }
+            | ResponseMessage::Chapters { .. }
+            | ResponseMessage::ChapterJumped { .. }
+            | ResponseMessage::Rewound { .. }
+            | ResponseMessage::YtdlUpdated { .. }
+            | ResponseMessage::ConfigReloaded
+            | ResponseMessage::YtdlUpToDate { .. }
+            | ResponseMessage::Stats { .. }
+            | ResponseMessage::BufferStats { .. }
             | ResponseMessage::Skipped { .. }
             | ResponseMessage::SkipMoreVotesNeeded { .. }
-            | ResponseMessage::StopMoreVotesNeeded { .. } => false,
+            | ResponseMessage::PauseMoreVotesNeeded { .. }
+            | ResponseMessage::StopMoreVotesNeeded { .. }
+            | ResponseMessage::SearchResults { .. }
+            | ResponseMessage::PlaylistChoices { .. }
+            | ResponseMessage::LargePlaylist { .. }
+            | ResponseMessage::DuplicateSong { .. }
+            | ResponseMessage::Grab { .. }
+            | ResponseMessage::Grabbed { .. }
+            | ResponseMessage::History { .. }
+            | ResponseMessage::PlaylistSaved { .. }
+            | ResponseMessage::PlaylistDeleted { .. }
+            | ResponseMessage::FavoriteAdded { .. }
+            | ResponseMessage::FavoriteRemoved { .. }
+            | ResponseMessage::FavoriteList { .. }
+            | ResponseMessage::QueueExported { .. }
+            | ResponseMessage::PlaylistList { .. }
+            | ResponseMessage::LanguageSet { .. }
+            | ResponseMessage::LanguageReset
+            | ResponseMessage::Settings { .. }
+            | ResponseMessage::SettingChanged { .. }
+            | ResponseMessage::SettingReset { .. }
+            | ResponseMessage::DjRoleAdded { .. }
+            | ResponseMessage::DjRoleRemoved { .. }
+            | ResponseMessage::DjRoles { .. }
+            | ResponseMessage::PriorityRoleSet { .. }
+            | ResponseMessage::PriorityRoleRemoved { .. }
+            | ResponseMessage::PriorityRoles { .. }
+            | ResponseMessage::UserBanned { .. }
+            | ResponseMessage::UserQueueCleared { .. }
+            | ResponseMessage::UserUnbanned { .. }
+            | ResponseMessage::BannedUsers { .. }
+            | ResponseMessage::CommandDisabled { .. }
+            | ResponseMessage::CommandEnabled { .. }
+            | ResponseMessage::DisabledCommands { .. }
+            | ResponseMessage::BlocklistEntryAdded { .. }
+            | ResponseMessage::BlocklistEntryRemoved { .. }
+            | ResponseMessage::Blocklist { .. }
+            | ResponseMessage::SoundEffectPlayed { .. }
+            | ResponseMessage::VolumeSet { .. }
+            | ResponseMessage::SpeedSet { .. }
+            | ResponseMessage::PitchSet { .. }
+            | ResponseMessage::EqualizerBandSet { .. }
+            | ResponseMessage::EqualizerPresetSet { .. }
+            | ResponseMessage::Equalizer { .. }
+            | ResponseMessage::FilterPresetSet { .. }
+            | ResponseMessage::FilterPresetCleared => false,
             ResponseMessage::NoMatchingSongsError
             | ResponseMessage::NotInVoiceChannelError
             | ResponseMessage::UnsupportedSiteError
+            | ResponseMessage::UnsupportedAttachmentError
+            | ResponseMessage::LoginRequiredError
+            | ResponseMessage::YtdlUpdateFailedError { .. }
+            | ResponseMessage::NotBotOwnerError
+            | ResponseMessage::ConfigReloadFailedError { .. }
+            | ResponseMessage::NoChaptersError { .. }
+            | ResponseMessage::UnknownChapterError { .. }
+            | ResponseMessage::ChapterNotSeekableError { .. }
+            | ResponseMessage::InvalidDurationError { .. }
+            | ResponseMessage::RewindNotSeekableError { .. }
             | ResponseMessage::SkipAlreadyVotedError { .. }
             | ResponseMessage::StopAlreadyVotedError { .. }
+            | ResponseMessage::PauseAlreadyVotedError { .. }
             | ResponseMessage::NothingIsQueuedError { .. }
             | ResponseMessage::NothingIsPlayingError { .. }
-            | ResponseMessage::AlreadyPlayingError { .. } => true,
+            | ResponseMessage::AlreadyPlayingError { .. }
+            | ResponseMessage::NoPreviousSongError { .. }
+            | ResponseMessage::GrabFailedError
+            | ResponseMessage::PlaylistNotFoundError { .. }
+            | ResponseMessage::PlaylistNotOwnerError { .. }
+            | ResponseMessage::NoPlaylistsError
+            | ResponseMessage::NothingToUndoError
+            | ResponseMessage::NothingToRemoveError
+            | ResponseMessage::NoQueueEntryError { .. }
+            | ResponseMessage::FavoriteNotFoundError { .. }
+            | ResponseMessage::NoFavoritesError
+            | ResponseMessage::NothingToSaveError
+            | ResponseMessage::NothingToExportError
+            | ResponseMessage::InvalidQueueFileError
+            | ResponseMessage::SearchExpiredError
+            | ResponseMessage::SearchNotOwnerError { .. }
+            | ResponseMessage::PlaylistChoiceExpiredError
+            | ResponseMessage::PlaylistChoiceNotOwnerError { .. }
+            | ResponseMessage::DuplicateExpiredError
+            | ResponseMessage::DuplicateNotOwnerError { .. }
+            | ResponseMessage::NoLinksInMessageError
+            | ResponseMessage::UnknownLanguageError { .. }
+            | ResponseMessage::InvalidSettingValueError { .. }
+            | ResponseMessage::NotDjRoleError { .. }
+            | ResponseMessage::DjOnlyError
+            | ResponseMessage::QueueFullError { .. }
+            | ResponseMessage::CooldownError { .. }
+            | ResponseMessage::NotBannedError { .. }
+            | ResponseMessage::UserNothingQueuedError { .. }
+            | ResponseMessage::BannedUserError
+            | ResponseMessage::MissingPermissionsError
+            | ResponseMessage::InvalidPrefixCommandError { .. }
+            | ResponseMessage::UnknownCommandError { .. }
+            | ResponseMessage::NotDisabledError { .. }
+            | ResponseMessage::CommandDisabledError { .. }
+            | ResponseMessage::NotBlockedError { .. }
+            | ResponseMessage::BlockedSongError { .. }
+            | ResponseMessage::UnknownSoundEffectError { .. }
+            | ResponseMessage::AudioFiltersDisabledError => true,
+        }
+    }
+
+    pub fn has_components(&self) -> bool {
+        matches!(
+            self,
+            ResponseMessage::SearchResults { .. }
+                | ResponseMessage::PlaylistChoices { .. }
+                | ResponseMessage::LargePlaylist { .. }
+                | ResponseMessage::DuplicateSong { .. }
+        )
+    }
+
+    pub fn create_attachments(&self) -> Vec<CreateAttachment> {
+        match self {
+            ResponseMessage::QueueExported {
+                file_name,
+                file_data,
+                ..
+            } => vec![CreateAttachment::bytes(
+                file_data.clone(),
+                file_name.clone(),
+            )],
+            _ => Vec::new(),
         }
     }
 
-    pub fn create_embed(&self, config: &crate::config::Config) -> CreateEmbed {
-        CreateEmbed::new()
+    pub fn create_components(
+        &self,
+        config: &crate::config::Config,
+        locale: Option<&str>,
+    ) -> Vec<CreateActionRow> {
+        match self {
+            ResponseMessage::SearchResults { search_id, results } => {
+                let options = create_choice_options(config, locale, results);
+                vec![CreateActionRow::SelectMenu(CreateSelectMenu::new(
+                    ComponentAction::SearchSelect(*search_id).to_custom_id(),
+                    CreateSelectMenuKind::String { options },
+                ))]
+            }
+            ResponseMessage::PlaylistChoices {
+                choices_id,
+                choices,
+                ..
+            } => {
+                let options = create_choice_options(config, locale, choices);
+                vec![CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(
+                        ComponentAction::PlaylistSelect(*choices_id).to_custom_id(),
+                        CreateSelectMenuKind::String { options },
+                    )
+                    .min_values(1)
+                    .max_values(choices.len() as u8),
+                )]
+            }
+            ResponseMessage::LargePlaylist { confirm_id, .. } => {
+                vec![CreateActionRow::Buttons(vec![create_button(
+                    config,
+                    locale,
+                    ComponentAction::QueuePlaylist(*confirm_id),
+                    "button.queue_playlist",
+                )
+                .style(ButtonStyle::Secondary)])]
+            }
+            ResponseMessage::DuplicateSong { duplicate_id, .. } => {
+                vec![CreateActionRow::Buttons(vec![create_button(
+                    config,
+                    locale,
+                    ComponentAction::QueueDuplicate(*duplicate_id),
+                    "button.queue_anyway",
+                )
+                .style(ButtonStyle::Secondary)])]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn get_thumbnail(&self) -> Option<&str> {
+        match self {
+            ResponseMessage::Grab {
+                thumbnail_url: Some(thumbnail),
+                ..
+            } => Some(thumbnail),
+            _ => None,
+        }
+    }
+
+    pub fn get_song_details(&self) -> Option<&SongDetails> {
+        match self {
+            ResponseMessage::Queued { details, .. }
+            | ResponseMessage::QueuedNoSpeakers { details, .. } => Some(details),
+            _ => None,
+        }
+    }
+
+    pub fn create_embed(
+        &self,
+        config: &crate::config::Config,
+        locale: Option<&str>,
+    ) -> CreateEmbed {
+        let mut embed = CreateEmbed::new()
             .color(if self.is_error() {
                 config.error_embed_color
             } else {
                 config.response_embed_color
             })
-            .description(self.to_string(config))
+            .description(self.to_string(config, locale));
+        if let Some(details) = self.get_song_details() {
+            embed = details.add_to_embed(config, locale, embed);
+        }
+        match self.get_thumbnail() {
+            Some(thumbnail) => embed.thumbnail(thumbnail),
+            None => embed,
+        }
     }
 }
@@ -1,11 +1,12 @@
 use crate::config::Config;
+use crate::interaction::InteractionRef;
 use crate::message::default_action_delegate::DefaultActionDelegate;
-use crate::message::{ActionUpdater, Message};
+use crate::message::{ActionUpdater, Message, ResponseMessage};
 use crate::queued_song::QueuedSong;
 use futures::prelude::*;
-use mrvn_model::{ChannelActionMessage, GuildModel};
+use mrvn_model::{ChannelActionMessage, GuildModelHandle};
 use serenity::all::{
-    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateThread,
     EditInteractionResponse,
 };
 use serenity::model::prelude::ChannelId;
@@ -15,22 +16,61 @@ use std::sync::Arc;
 #[derive(Clone, Copy)]
 pub enum SendMessageDestination<'interaction> {
     Channel(ChannelId),
+    /// Posts in the thread for a voice channel's listening session. If there isn't one yet, a
+    /// new thread is created in the channel when a song starts playing.
+    SessionThread {
+        channel_id: ChannelId,
+        voice_channel_id: ChannelId,
+    },
     Interaction {
-        interaction: &'interaction CommandInteraction,
+        interaction: InteractionRef<'interaction>,
         is_edit: bool,
+        is_ephemeral: bool,
     },
 }
 
+/// Sends messages for a guild. The guild's model is only locked while it's being read or changed,
+/// and not while waiting on Discord.
 pub async fn send_messages(
     config: &Arc<Config>,
+    locale: Option<&str>,
     ctx: &Context,
     destination: SendMessageDestination<'_>,
-    guild_model: &mut GuildModel<QueuedSong>,
+    guild_model_handle: &GuildModelHandle<QueuedSong>,
     mut messages: Vec<Message>,
 ) -> Result<(), crate::error::Error> {
     let message_channel_id = match destination {
         SendMessageDestination::Channel(channel) => channel,
-        SendMessageDestination::Interaction { interaction, .. } => interaction.channel_id,
+        SendMessageDestination::SessionThread {
+            channel_id,
+            voice_channel_id,
+        } => match guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?
+            .session_thread(voice_channel_id)
+        {
+            Some(thread_id) => thread_id,
+            None if messages.iter().any(Message::is_playing) => {
+                match create_session_thread(config, locale, ctx, channel_id, voice_channel_id).await
+                {
+                    Ok(thread_id) => {
+                        guild_model_handle
+                            .lock()
+                            .await
+                            .map_err(crate::error::Error::ModelLock)?
+                            .set_session_thread(voice_channel_id, Some(thread_id));
+                        thread_id
+                    }
+                    Err(why) => {
+                        tracing::error!("Error while creating session thread: {}", why);
+                        channel_id
+                    }
+                }
+            }
+            None => channel_id,
+        },
+        SendMessageDestination::Interaction { interaction, .. } => interaction.channel_id(),
     };
 
     // Action messages are special: we only keep the latest one around. This also means out of
@@ -58,7 +98,7 @@ pub async fn send_messages(
 
     // Send the first message as an interaction response, if our destination is an interaction.
     let maybe_first_message = match destination {
-        SendMessageDestination::Channel(_) => None,
+        SendMessageDestination::Channel(_) | SendMessageDestination::SessionThread { .. } => None,
         SendMessageDestination::Interaction { .. } => messages_iter.next(),
     };
     let first_message_future = async {
@@ -67,16 +107,23 @@ pub async fn send_messages(
                 SendMessageDestination::Interaction {
                     interaction,
                     is_edit,
+                    is_ephemeral,
                 },
                 Some(first_message),
             ) => {
+                // Only the interaction response itself can be ephemeral, and only if it hasn't
+                // already been deferred as a public response.
+                let is_ephemeral = is_ephemeral && !is_edit && first_message.supports_ephemeral();
+
                 let channel_message = if is_edit {
+                    let edit = first_message.create_attachments().into_iter().fold(
+                        EditInteractionResponse::new()
+                            .embed(first_message.create_embed(config, locale))
+                            .components(first_message.create_components(config, locale)),
+                        EditInteractionResponse::new_attachment,
+                    );
                     interaction
-                        .edit_response(
-                            ctx,
-                            EditInteractionResponse::new()
-                                .embed(first_message.create_embed(config)),
-                        )
+                        .edit_response(ctx, edit)
                         .await
                         .map_err(crate::error::Error::Serenity)?
                 } else {
@@ -85,7 +132,10 @@ pub async fn send_messages(
                             ctx,
                             CreateInteractionResponse::Message(
                                 CreateInteractionResponseMessage::new()
-                                    .embed(first_message.create_embed(config)),
+                                    .embed(first_message.create_embed(config, locale))
+                                    .components(first_message.create_components(config, locale))
+                                    .add_files(first_message.create_attachments())
+                                    .ephemeral(is_ephemeral),
                             ),
                         )
                         .await
@@ -110,6 +160,7 @@ pub async fn send_messages(
                                 voice_channel,
                                 true,
                                 config.clone(),
+                                locale.map(str::to_string),
                                 ctx.clone(),
                             )),
                         })
@@ -130,12 +181,27 @@ pub async fn send_messages(
     };
 
     // Send each remaining message as a regular message. If the message is the possible one
-    // action message, keep track of its ID so we can record it later.
+    // action message, keep track of its ID so we can record it later. Action messages go to the
+    // guild's announcements channel instead of the interaction's channel, if it has one.
+    let action_channel_id = match destination {
+        SendMessageDestination::Interaction { .. } => {
+            config.announcements_channel.unwrap_or(message_channel_id)
+        }
+        _ => message_channel_id,
+    };
     let remaining_messages_future = future::try_join_all(messages_iter.map(|message| async move {
-        let channel_message = message_channel_id
+        let channel_id = if message.is_action() {
+            action_channel_id
+        } else {
+            message_channel_id
+        };
+        let channel_message = channel_id
             .send_message(
                 ctx,
-                CreateMessage::new().embed(message.create_embed(config)),
+                CreateMessage::new()
+                    .embed(message.create_embed(config, locale))
+                    .components(message.create_components(config, locale))
+                    .add_files(message.create_attachments()),
             )
             .await
             .map_err(crate::error::Error::Serenity)?;
@@ -154,6 +220,7 @@ pub async fn send_messages(
                         voice_channel,
                         false,
                         config.clone(),
+                        locale.map(str::to_string),
                         ctx.clone(),
                     )),
                 }))
@@ -171,7 +238,11 @@ pub async fn send_messages(
     // Delete the guild's latest action message from before this operation, if this operation
     // sent an action message.
     if let Some((_, last_action_message_channel)) = maybe_last_action_message {
-        guild_model.clear_last_action_message(last_action_message_channel);
+        guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?
+            .clear_last_action_message(last_action_message_channel);
     }
 
     // Execute all the message sending!
@@ -181,11 +252,64 @@ pub async fn send_messages(
     // Set the channel's last action message to the message we sent, if there was one.
     if let Some((_, last_action_message_channel)) = maybe_last_action_message {
         let maybe_sent_message = std::iter::once(first_message)
-            .chain(remaining_messages.into_iter())
+            .chain(remaining_messages)
             .find_map(|maybe_message| maybe_message);
 
-        guild_model.set_last_action_message(last_action_message_channel, maybe_sent_message);
+        guild_model_handle
+            .lock()
+            .await
+            .map_err(crate::error::Error::ModelLock)?
+            .set_last_action_message(last_action_message_channel, maybe_sent_message);
     }
 
     Ok(())
 }
+
+async fn create_session_thread(
+    config: &Config,
+    locale: Option<&str>,
+    ctx: &Context,
+    channel_id: ChannelId,
+    voice_channel_id: ChannelId,
+) -> Result<ChannelId, crate::error::Error> {
+    let voice_channel_name = ctx
+        .cache
+        .channel(voice_channel_id)
+        .map(|channel| channel.name.clone())
+        .unwrap_or_default();
+    let thread_name = config.get_message(
+        locale,
+        "thread.session",
+        &[("voice_channel_name", &voice_channel_name)],
+    );
+    let thread = channel_id
+        .create_thread(
+            ctx,
+            CreateThread::new(thread_name).kind(ChannelType::PublicThread),
+        )
+        .await
+        .map_err(crate::error::Error::Serenity)?;
+    Ok(thread.id)
+}
+
+pub async fn send_direct_message(
+    config: &Config,
+    locale: Option<&str>,
+    ctx: &Context,
+    user_id: UserId,
+    message: ResponseMessage,
+) -> Result<(), crate::error::Error> {
+    let dm_channel = user_id
+        .create_dm_channel(ctx)
+        .await
+        .map_err(crate::error::Error::Serenity)?;
+    dm_channel
+        .id
+        .send_message(
+            ctx,
+            CreateMessage::new().embed(message.create_embed(config, locale)),
+        )
+        .await
+        .map_err(crate::error::Error::Serenity)?;
+    Ok(())
+}
@@ -4,25 +4,35 @@ pub const MAX_COLUMNS: usize = 54;
 pub const BEFORE_PROGRESS_BAR: &str = " [";
 pub const AFTER_PROGRESS_BAR: &str = "]";
 
-pub fn format_time(config: &Config, time_seconds: f64, duration_seconds: Option<f64>) -> String {
+pub fn format_time(
+    config: &Config,
+    locale: Option<&str>,
+    time_seconds: f64,
+    duration_seconds: Option<f64>,
+) -> String {
     match duration_seconds {
         Some(duration) => {
-            let (formatted_duration, minutes_width) = config.format_time(duration, 0);
-            let (formatted_time, _) = config.format_time(time_seconds, minutes_width);
+            let (formatted_duration, minutes_width) = config.format_time(locale, duration, 0);
+            let (formatted_time, _) = config.format_time(locale, time_seconds, minutes_width);
 
             config.get_message(
+                locale,
                 "time_and_duration",
                 &[("time", &formatted_time), ("duration", &formatted_duration)],
             )
         }
         None => {
-            let (formatted_time, _) = config.format_time(time_seconds, 0);
+            let (formatted_time, _) = config.format_time(locale, time_seconds, 0);
 
             config.get_message(
+                locale,
                 "time_and_duration",
                 &[
                     ("time", &formatted_time),
-                    ("duration", config.get_raw_message("duration.unknown")),
+                    (
+                        "duration",
+                        config.get_raw_message(locale, "duration.unknown"),
+                    ),
                 ],
             )
         }
@@ -31,30 +41,44 @@ pub fn format_time(config: &Config, time_seconds: f64, duration_seconds: Option<
 
 pub fn format_time_bar(
     config: &Config,
+    locale: Option<&str>,
     time_seconds: f64,
     duration_seconds: Option<f64>,
 ) -> String {
-    let time = format_time(config, time_seconds, duration_seconds);
+    let time = format_time(config, locale, time_seconds, duration_seconds);
     let progress_str = match duration_seconds {
-        Some(duration) => {
-            let width =
-                (MAX_COLUMNS - time.len() - BEFORE_PROGRESS_BAR.len() - AFTER_PROGRESS_BAR.len())
-                    .max(1);
-            let progress = (time_seconds / duration).clamp(0., 1.);
-            let progress_width = (width as f64 * progress) as usize;
-
-            format!(
-                "{}{:=<width$}{:->inv_width$}{}",
-                BEFORE_PROGRESS_BAR,
-                "",
-                "",
-                AFTER_PROGRESS_BAR,
-                width = progress_width,
-                inv_width = width - progress_width
-            )
-        }
+        Some(duration) => format_progress_bar(&time, time_seconds / duration),
         None => "".to_string(),
     };
 
     format!("{}{}", time, progress_str)
 }
+
+/// Shows how many of a known number of items are done, like `12/50 [==------]`.
+pub fn format_count_bar(count: usize, total_count: usize) -> String {
+    let count_str = format!("{}/{}", count, total_count);
+    let progress = if total_count == 0 {
+        1.
+    } else {
+        count as f64 / total_count as f64
+    };
+    let progress_str = format_progress_bar(&count_str, progress);
+    format!("{}{}", count_str, progress_str)
+}
+
+/// A bar filling the rest of the line after `label`, filled in to `progress` between 0 and 1.
+fn format_progress_bar(label: &str, progress: f64) -> String {
+    let width =
+        (MAX_COLUMNS - label.len() - BEFORE_PROGRESS_BAR.len() - AFTER_PROGRESS_BAR.len()).max(1);
+    let progress_width = (width as f64 * progress.clamp(0., 1.)) as usize;
+
+    format!(
+        "{}{:=<width$}{:->inv_width$}{}",
+        BEFORE_PROGRESS_BAR,
+        "",
+        "",
+        AFTER_PROGRESS_BAR,
+        width = progress_width,
+        inv_width = width - progress_width
+    )
+}
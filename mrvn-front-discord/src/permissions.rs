@@ -0,0 +1,61 @@
+use mrvn_model::GuildSettings;
+use serenity::model::prelude::*;
+
+/// Commands that only DJs can use in guilds that have DJ roles. Everyone else can still queue
+/// songs and vote to skip or stop.
+const DJ_COMMANDS: &[&str] = &["pause", "restart", "previous", "chapter", "rewind"];
+
+/// Gets the queue priority for a member, which is the highest priority of any of their roles.
+pub fn get_member_priority(settings: &GuildSettings, maybe_member: Option<&Member>) -> u32 {
+    let Some(member) = maybe_member else {
+        return 0;
+    };
+    member
+        .roles
+        .iter()
+        .filter_map(|role_id| settings.priority_roles.get(role_id))
+        .copied()
+        .max()
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberPermission {
+    /// The guild has no DJ roles, so nobody is restricted.
+    Unrestricted,
+    Dj,
+    Listener,
+}
+
+impl MemberPermission {
+    /// Members that can manage the guild are always DJs, even without a DJ role.
+    pub fn get(settings: &GuildSettings, maybe_member: Option<&Member>) -> Self {
+        if settings.dj_roles.is_empty() {
+            return MemberPermission::Unrestricted;
+        }
+        let Some(member) = maybe_member else {
+            return MemberPermission::Listener;
+        };
+
+        let can_manage_guild = member
+            .permissions
+            .is_some_and(|permissions| permissions.manage_guild());
+        let has_dj_role = member
+            .roles
+            .iter()
+            .any(|role_id| settings.dj_roles.contains(role_id));
+        if can_manage_guild || has_dj_role {
+            MemberPermission::Dj
+        } else {
+            MemberPermission::Listener
+        }
+    }
+
+    pub fn can_use_command(self, command_name: &str) -> bool {
+        self != MemberPermission::Listener || !DJ_COMMANDS.contains(&command_name)
+    }
+
+    pub fn can_force_skip(self) -> bool {
+        self == MemberPermission::Dj
+    }
+}
@@ -1,19 +1,34 @@
 use crate::frontend::Frontend;
 use crate::message::time_bar::{format_time, AFTER_PROGRESS_BAR, BEFORE_PROGRESS_BAR, MAX_COLUMNS};
-use crate::message::{ActionDelegate, ActionMessage, ActionUpdater, Message};
+use crate::message::{ActionDelegate, ActionMessage, ActionUpdater, Message, SongDetails};
+use crate::topic_message::ChannelTopicActionDelegate;
 use futures::future::{AbortHandle, Abortable};
-use mrvn_back_ytdl::{GuildSpeakerRef, SongMetadata};
+use mrvn_back_ytdl::{GuildSpeakerRef, PlaybackEvent, SongMetadata};
+use mrvn_model::VoteCount;
 use serenity::model::id::{ChannelId, GuildId};
 use std::any::Any;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast;
 use tokio::time::{Instant, MissedTickBehavior};
 
+/// Sent when the number of skip votes for the song playing in a channel changes, so the playing
+/// message can update the count shown on its skip button.
+#[derive(Debug, Clone, Copy)]
+pub struct SkipVotesChanged {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub skip_votes: VoteCount,
+}
+
 fn get_playing_action_message_at_time(
     is_response: bool,
     channel_id: ChannelId,
     current_metadata: &SongMetadata,
     play_time: Option<Duration>,
+    skip_votes: VoteCount,
+    filter_preset: Option<String>,
+    is_reconnecting: bool,
 ) -> ActionMessage {
     let time_seconds = play_time.map(|time| time.as_secs_f64()).unwrap_or(0.);
 
@@ -23,8 +38,13 @@ fn get_playing_action_message_at_time(
             song_url: current_metadata.url.clone(),
             voice_channel_id: channel_id,
             thumbnail_url: current_metadata.thumbnail_url.clone(),
+            details: SongDetails::new(current_metadata),
             time_seconds,
             duration_seconds: current_metadata.duration_seconds,
+            skip_votes,
+            filter_preset,
+            stream_title: current_metadata.stream_title.get(),
+            is_reconnecting,
         }
     } else {
         ActionMessage::Playing {
@@ -33,8 +53,13 @@ fn get_playing_action_message_at_time(
             voice_channel_id: channel_id,
             user_id: current_metadata.user_id,
             thumbnail_url: current_metadata.thumbnail_url.clone(),
+            details: SongDetails::new(current_metadata),
             time_seconds,
             duration_seconds: current_metadata.duration_seconds,
+            skip_votes,
+            filter_preset,
+            stream_title: current_metadata.stream_title.get(),
+            is_reconnecting,
         }
     }
 }
@@ -51,9 +76,23 @@ async fn get_action_message(
     channel_id: ChannelId,
     current_metadata: &SongMetadata,
     speaker_ref: &GuildSpeakerRef<'_>,
+    skip_votes: VoteCount,
 ) -> ActionMessage {
     let play_time = speaker_ref.active_play_time().await;
-    get_playing_action_message_at_time(is_response, channel_id, current_metadata, play_time)
+    let filter_preset = speaker_ref
+        .filters()
+        .settings()
+        .preset
+        .map(|preset| preset.name().to_string());
+    get_playing_action_message_at_time(
+        is_response,
+        channel_id,
+        current_metadata,
+        play_time,
+        skip_votes,
+        filter_preset,
+        speaker_ref.is_reconnecting(),
+    )
 }
 
 pub async fn build_playing_message(
@@ -62,17 +101,36 @@ pub async fn build_playing_message(
     is_response: bool,
     channel_id: ChannelId,
     current_metadata: SongMetadata,
+    skip_votes: VoteCount,
 ) -> Message {
-    let initial_action_message =
-        get_action_message(is_response, channel_id, &current_metadata, speaker_ref).await;
-    let delegate = Box::new(PlayingActionDelegate {
-        frontend,
+    let initial_action_message = get_action_message(
+        is_response,
+        channel_id,
+        &current_metadata,
+        speaker_ref,
+        skip_votes,
+    )
+    .await;
+    let guild_id = speaker_ref.guild_id();
+    let guild_config = frontend.guild_config(guild_id).await;
+    let mut delegate: Box<dyn ActionDelegate> = Box::new(PlayingActionDelegate {
+        frontend: frontend.clone(),
 
         is_response,
-        guild_id: speaker_ref.guild_id(),
+        guild_id,
         initial_channel_id: channel_id,
-        song_metadata: current_metadata,
+        song_metadata: current_metadata.clone(),
+        initial_skip_votes: skip_votes,
     });
+    if guild_config.channel_topic {
+        delegate = Box::new(ChannelTopicActionDelegate::new(
+            delegate,
+            frontend,
+            guild_id,
+            guild_config.topic_channel,
+            current_metadata,
+        ));
+    }
 
     Message::Action {
         message: initial_action_message,
@@ -88,12 +146,16 @@ struct PlayingActionDelegate {
     guild_id: GuildId,
     initial_channel_id: ChannelId,
     song_metadata: SongMetadata,
+    initial_skip_votes: VoteCount,
 }
 
 impl ActionDelegate for PlayingActionDelegate {
     fn start(&self, updater: ActionUpdater) -> Box<dyn Any + Send + Sync> {
         let metadata = ActivePlayingActionMetadata {
             updater: Some(updater),
+            skip_votes_receiver: self.frontend.skip_votes_sender.subscribe(),
+            playback_events_receiver: self.frontend.backend_brain.subscribe_playback_events(),
+            shutdown_receiver: self.frontend.shutdown_sender.subscribe(),
             frontend: self.frontend.clone(),
 
             is_response: self.is_response,
@@ -101,6 +163,7 @@ impl ActionDelegate for PlayingActionDelegate {
             song_metadata: self.song_metadata.clone(),
 
             current_channel_id: self.initial_channel_id,
+            skip_votes: self.initial_skip_votes,
         };
 
         let (abort, abort_registration) = AbortHandle::new_pair();
@@ -115,6 +178,9 @@ impl ActionDelegate for PlayingActionDelegate {
 
 struct ActivePlayingActionMetadata {
     updater: Option<ActionUpdater>,
+    skip_votes_receiver: broadcast::Receiver<SkipVotesChanged>,
+    playback_events_receiver: broadcast::Receiver<PlaybackEvent>,
+    shutdown_receiver: broadcast::Receiver<()>,
     frontend: Arc<Frontend>,
 
     is_response: bool,
@@ -122,6 +188,7 @@ struct ActivePlayingActionMetadata {
     song_metadata: SongMetadata,
 
     current_channel_id: ChannelId,
+    skip_votes: VoteCount,
 }
 
 struct ActivePlayingActionDelegate {
@@ -134,10 +201,69 @@ impl Drop for ActivePlayingActionDelegate {
     }
 }
 
+/// Voice channel statuses can't be longer than this many characters.
+const MAX_VOICE_STATUS_LENGTH: usize = 500;
+
+async fn set_playing_voice_status(
+    frontend: &Frontend,
+    guild_id: GuildId,
+    song_metadata: &SongMetadata,
+    locale: Option<&str>,
+) {
+    let status: String = frontend
+        .config()
+        .get_message(
+            locale,
+            "status.playing",
+            &[("song_title", &song_metadata.title)],
+        )
+        .chars()
+        .take(MAX_VOICE_STATUS_LENGTH)
+        .collect();
+
+    let guild_speakers = frontend.backend_brain.guild_speakers(guild_id);
+    let mut guild_speakers_ref = guild_speakers.lock();
+    if let Some((active_speaker, _)) = guild_speakers_ref.find_active_song(song_metadata.id).await {
+        if let Err(why) = active_speaker.set_voice_status(&status).await {
+            tracing::error!("Error while setting voice channel status: {}", why);
+        }
+    }
+}
+
+async fn clear_voice_status(frontend: Arc<Frontend>, guild_id: GuildId, channel_id: ChannelId) {
+    let guild_speakers = frontend.backend_brain.guild_speakers(guild_id);
+    let mut guild_speakers_ref = guild_speakers.lock();
+
+    // If another song has started in the channel, its playing message will set the status.
+    if let Some(speaker) = guild_speakers_ref.find_in_channel(channel_id).await {
+        if !speaker.is_active() {
+            if let Err(why) = speaker.set_voice_status("").await {
+                tracing::error!("Error while clearing voice channel status: {}", why);
+            }
+        }
+    }
+}
+
 impl Drop for ActivePlayingActionMetadata {
     fn drop(&mut self) {
+        if self.frontend.config().voice_channel_status {
+            tokio::task::spawn(clear_voice_status(
+                self.frontend.clone(),
+                self.guild_id,
+                self.current_channel_id,
+            ));
+        }
+
         if let Some(updater) = std::mem::take(&mut self.updater) {
-            if self.is_response {
+            if self.frontend.is_shutting_down() {
+                let final_message = ActionMessage::Restarting {
+                    song_title: self.song_metadata.title.clone(),
+                    song_url: self.song_metadata.url.clone(),
+                };
+                tokio::task::spawn(async move {
+                    updater.update(final_message).await;
+                });
+            } else if self.is_response {
                 let final_message = get_played_action_message(&self.song_metadata);
                 tokio::task::spawn(async move {
                     updater.update(final_message).await;
@@ -150,27 +276,93 @@ impl Drop for ActivePlayingActionMetadata {
 }
 
 async fn update_playing_message_loop(mut metadata: ActivePlayingActionMetadata) {
-    let min_update_secs = metadata.frontend.config.progress_min_update_secs;
-    let max_update_secs = metadata.frontend.config.progress_max_update_secs;
+    let min_update_secs = metadata.frontend.config().progress_min_update_secs;
+    let max_update_secs = metadata.frontend.config().progress_max_update_secs;
 
     // Guess how often we'd need to tick to update one piece of the progress bar each time
     let update_period_secs = match metadata.song_metadata.duration_seconds {
         Some(duration) => {
-            let time_width = format_time(&metadata.frontend.config, 0., Some(duration)).len();
+            let locale = metadata
+                .updater
+                .as_ref()
+                .and_then(|updater| updater.locale());
+            let time_width =
+                format_time(&metadata.frontend.config(), locale, 0., Some(duration)).len();
             let progress_width =
                 (MAX_COLUMNS - time_width - BEFORE_PROGRESS_BAR.len() - AFTER_PROGRESS_BAR.len())
                     .max(1);
-            (duration / progress_width as f64).clamp(min_update_secs, max_update_secs)
+            // Songs sped up with the audio filters move along the progress bar faster.
+            let speed = if metadata.frontend.config().audio_filters {
+                metadata
+                    .frontend
+                    .backend_brain
+                    .guild_filters(metadata.guild_id)
+                    .settings()
+                    .speed as f64
+            } else {
+                1.
+            };
+            (duration / progress_width as f64 / speed).clamp(min_update_secs, max_update_secs)
         }
         None => max_update_secs,
     };
     let period_duration = Duration::from_secs_f64(update_period_secs);
 
+    if metadata.frontend.config().voice_channel_status {
+        let locale = metadata
+            .updater
+            .as_ref()
+            .and_then(|updater| updater.locale());
+        set_playing_voice_status(
+            &metadata.frontend,
+            metadata.guild_id,
+            &metadata.song_metadata,
+            locale,
+        )
+        .await;
+    }
+
     let mut interval = tokio::time::interval_at(Instant::now() + period_duration, period_duration);
     interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
     loop {
-        interval.tick().await;
+        // Update when the progress bar needs to move along, or immediately if someone votes to
+        // skip the song or the speaker is moving to another voice server.
+        tokio::select! {
+            _ = interval.tick() => {}
+            changed = metadata.skip_votes_receiver.recv() => match changed {
+                Ok(changed) => {
+                    if changed.guild_id != metadata.guild_id
+                        || changed.channel_id != metadata.current_channel_id
+                    {
+                        continue;
+                    }
+                    metadata.skip_votes = changed.skip_votes;
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            event = metadata.playback_events_receiver.recv() => match event {
+                Ok(
+                    PlaybackEvent::Reconnecting { guild_id, channel_id }
+                    | PlaybackEvent::Reconnected { guild_id, channel_id },
+                ) if guild_id == metadata.guild_id && channel_id == metadata.current_channel_id => {}
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = metadata.shutdown_receiver.recv() => {
+                // Taking the updater stops the message from being cleared when this returns
+                if let Some(updater) = metadata.updater.take() {
+                    updater
+                        .update(ActionMessage::Restarting {
+                            song_title: metadata.song_metadata.title.clone(),
+                            song_url: metadata.song_metadata.url.clone(),
+                        })
+                        .await;
+                }
+                return;
+            }
+        }
 
         let updater = match &metadata.updater {
             Some(updater) => updater,
@@ -182,16 +374,18 @@ async fn update_playing_message_loop(mut metadata: ActivePlayingActionMetadata)
                 .frontend
                 .backend_brain
                 .guild_speakers(metadata.guild_id);
-            let mut guild_speakers_ref = guild_speakers.lock().await;
-
-            let (active_speaker, active_metadata) =
-                match guild_speakers_ref.find_active_song(metadata.song_metadata.id) {
-                    Some(val) => val,
-                    None => {
-                        // The song has ended, returning will drop the metadata and clear the message.
-                        return;
-                    }
-                };
+            let mut guild_speakers_ref = guild_speakers.lock();
+
+            let (active_speaker, active_metadata) = match guild_speakers_ref
+                .find_active_song(metadata.song_metadata.id)
+                .await
+            {
+                Some(val) => val,
+                None => {
+                    // The song has ended, returning will drop the metadata and clear the message.
+                    return;
+                }
+            };
 
             if let Some(channel) = active_speaker.current_channel() {
                 metadata.current_channel_id = channel;
@@ -202,6 +396,7 @@ async fn update_playing_message_loop(mut metadata: ActivePlayingActionMetadata)
                 metadata.current_channel_id,
                 &active_metadata,
                 active_speaker,
+                metadata.skip_votes,
             )
             .await
         };
@@ -0,0 +1,158 @@
+use crate::config::Config;
+use crate::message::{Message, ResponseDelegate, ResponseMessage};
+use mrvn_back_ytdl::SongMetadata;
+use serenity::all::EditMessage;
+use serenity::client::Context;
+use serenity::model::id::{ChannelId, MessageId};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Keeps a message up to date with how many songs of a playlist have been queued, while the rest
+/// of the playlist is loaded in the background. Each edit counts against the channel's rate limit,
+/// so like playing messages it's edited at most every `progress_min_update_secs`.
+pub struct PlaylistProgress {
+    message: Arc<ProgressMessage>,
+    queued_count: usize,
+    total_count: usize,
+    is_truncated: bool,
+    latest_song: Option<(String, String)>,
+    update_interval: Duration,
+    updated_time: Instant,
+}
+
+impl PlaylistProgress {
+    /// Creates the progress tracker and the message it edits, which needs to be sent by the
+    /// command. Only the first `max_count` songs of the playlist are queued, unless it's zero.
+    pub fn new(
+        ctx: Context,
+        config: Arc<Config>,
+        locale: Option<String>,
+        queued_count: usize,
+        total_count: usize,
+        max_count: usize,
+    ) -> (PlaylistProgress, Message) {
+        let is_truncated = max_count > 0 && total_count > max_count;
+        let update_interval = Duration::from_secs_f64(config.progress_min_update_secs);
+        let progress = PlaylistProgress {
+            message: Arc::new(ProgressMessage {
+                ctx,
+                config,
+                locale,
+                state: Mutex::new(ProgressMessageState::default()),
+            }),
+            queued_count,
+            total_count: if is_truncated { max_count } else { total_count },
+            is_truncated,
+            latest_song: None,
+            update_interval,
+            updated_time: Instant::now(),
+        };
+        let message = Message::Response {
+            message: progress.progress_message(),
+            delegate: Some(Box::new(ProgressResponseDelegate {
+                message: progress.message.clone(),
+            })),
+        };
+        (progress, message)
+    }
+
+    pub async fn song_queued(&mut self, song_metadata: &SongMetadata) {
+        self.queued_count += 1;
+        self.latest_song = Some((song_metadata.title.clone(), song_metadata.url.clone()));
+        if self.updated_time.elapsed() >= self.update_interval {
+            self.updated_time = Instant::now();
+            self.message.show(self.progress_message()).await;
+        }
+    }
+
+    /// Shows how many songs were queued once the playlist has finished loading.
+    pub async fn finish(self) {
+        let message = if self.is_truncated {
+            ResponseMessage::QueuedPlaylistTruncated {
+                count: self.queued_count,
+                max_count: self.total_count,
+            }
+        } else {
+            ResponseMessage::QueuedMultiple {
+                count: self.queued_count,
+            }
+        };
+        self.message.show(message).await;
+    }
+
+    /// Shows why the playlist stopped being queued part way through.
+    pub async fn fail(self, message: ResponseMessage) {
+        self.message.show(message).await;
+    }
+
+    fn progress_message(&self) -> ResponseMessage {
+        ResponseMessage::QueueingPlaylist {
+            queued_count: self.queued_count,
+            total_count: self.total_count,
+            latest_song: self.latest_song.clone(),
+        }
+    }
+}
+
+struct ProgressMessage {
+    ctx: Context,
+    config: Arc<Config>,
+    locale: Option<String>,
+
+    // Held while editing, so edits can't be applied out of order.
+    state: Mutex<ProgressMessageState>,
+}
+
+#[derive(Default)]
+struct ProgressMessageState {
+    sent_message: Option<(ChannelId, MessageId)>,
+    /// The latest message to show, if it changed before the progress message was sent.
+    pending_message: Option<ResponseMessage>,
+}
+
+impl ProgressMessage {
+    async fn show(&self, message: ResponseMessage) {
+        let mut state = self.state.lock().await;
+        match state.sent_message {
+            Some((channel_id, message_id)) => self.edit(channel_id, message_id, &message).await,
+            None => state.pending_message = Some(message),
+        }
+    }
+
+    async fn sent(&self, channel_id: ChannelId, message_id: MessageId) {
+        let mut state = self.state.lock().await;
+        state.sent_message = Some((channel_id, message_id));
+        if let Some(message) = state.pending_message.take() {
+            self.edit(channel_id, message_id, &message).await;
+        }
+    }
+
+    async fn edit(&self, channel_id: ChannelId, message_id: MessageId, message: &ResponseMessage) {
+        let maybe_err = channel_id
+            .edit_message(
+                &self.ctx,
+                message_id,
+                EditMessage::new()
+                    .embed(message.create_embed(&self.config, self.locale.as_deref())),
+            )
+            .await;
+        if let Err(why) = maybe_err {
+            tracing::error!("Error while updating playlist progress message: {}", why);
+        }
+    }
+}
+
+struct ProgressResponseDelegate {
+    message: Arc<ProgressMessage>,
+}
+
+impl ResponseDelegate for ProgressResponseDelegate {
+    fn sent(&self, channel_id: ChannelId, message_id: MessageId) {
+        let message = self.message.clone();
+        tokio::task::spawn(async move {
+            message.sent(channel_id, message_id).await;
+        });
+    }
+}
@@ -0,0 +1,176 @@
+use crate::config::Config;
+use serde::Deserialize;
+use serenity::all::{CommandDataOption, CommandOption};
+use serenity::model::prelude::*;
+
+/// The parts of a command's definition needed to read it from a message.
+#[derive(Deserialize)]
+struct CommandDefinition {
+    name: String,
+    #[serde(rename = "type")]
+    kind: Option<CommandType>,
+    #[serde(default)]
+    options: Vec<CommandOption>,
+    default_member_permissions: Option<String>,
+}
+
+/// A command typed in a message with the command prefix, like `!play never gonna give you up`,
+/// with the same options Discord would have sent for the slash command.
+pub struct PrefixCommand {
+    pub name: String,
+    pub options: Vec<CommandDataOption>,
+    /// The permissions needed to use the command. Discord checks these for slash commands, but
+    /// prefix commands have to be checked by the bot.
+    pub required_permissions: Permissions,
+}
+
+pub enum PrefixCommandError {
+    /// There's no command with this name. It might have been meant for another bot.
+    UnknownCommand,
+    /// The command exists, but what was typed after it couldn't be read.
+    InvalidOptions { command_name: String },
+}
+
+/// Reads a command from a message, after the prefix has been taken off. Options are filled in
+/// the order the slash command lists them: attachment options use the message's attachments, the
+/// last string option takes the rest of the message, and every other option takes one word.
+pub fn parse_prefix_command(
+    config: &Config,
+    text: &str,
+    attachments: &[Attachment],
+) -> Result<PrefixCommand, PrefixCommandError> {
+    let (name, rest) = split_word(text);
+    let name = name.to_lowercase();
+    let definition = crate::commands::create_commands(config)
+        .into_iter()
+        .filter_map(|command| serde_json::to_value(command).ok())
+        .filter_map(|value| serde_json::from_value::<CommandDefinition>(value).ok())
+        .find(|definition| {
+            definition.name == name
+                && matches!(definition.kind, None | Some(CommandType::ChatInput))
+        })
+        .ok_or(PrefixCommandError::UnknownCommand)?;
+
+    let invalid_options = || PrefixCommandError::InvalidOptions {
+        command_name: definition.name.clone(),
+    };
+    let mut attachments_iter = attachments.iter();
+    let raw_options = parse_options(&definition.options, rest, &mut attachments_iter)
+        .ok_or_else(invalid_options)?;
+    let options = raw_options
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<Vec<CommandDataOption>, _>>()
+        .map_err(|_| invalid_options())?;
+
+    let required_permissions = definition
+        .default_member_permissions
+        .as_deref()
+        .and_then(|bits| bits.parse().ok())
+        .map(Permissions::from_bits_truncate)
+        .unwrap_or_else(Permissions::empty);
+    Ok(PrefixCommand {
+        name: definition.name,
+        options,
+        required_permissions,
+    })
+}
+
+/// Reads options in the format Discord sends them in, so they can be turned into
+/// [`CommandDataOption`]s.
+fn parse_options<'attachment>(
+    definitions: &[CommandOption],
+    text: &str,
+    attachments: &mut impl Iterator<Item = &'attachment Attachment>,
+) -> Option<Vec<serde_json::Value>> {
+    let is_subcommand = |definition: &CommandOption| {
+        matches!(
+            definition.kind,
+            CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup
+        )
+    };
+    if definitions.iter().any(is_subcommand) {
+        let (name, rest) = split_word(text);
+        let definition = definitions.iter().find(|definition| {
+            is_subcommand(definition) && definition.name.eq_ignore_ascii_case(name)
+        })?;
+        let options = parse_options(&definition.options, rest, attachments)?;
+        return Some(vec![serde_json::json!({
+            "name": definition.name,
+            "type": definition.kind,
+            "options": options,
+        })]);
+    }
+
+    let rest_option_index = definitions
+        .iter()
+        .rposition(|definition| definition.kind == CommandOptionType::String);
+    let mut rest = text.trim();
+    let mut options = Vec::new();
+    for (index, definition) in definitions.iter().enumerate() {
+        if definition.kind == CommandOptionType::Attachment {
+            if let Some(attachment) = attachments.next() {
+                options.push(serde_json::json!({
+                    "name": definition.name,
+                    "type": definition.kind,
+                    "value": attachment.id.to_string(),
+                }));
+            }
+            continue;
+        }
+
+        if rest.is_empty() {
+            continue;
+        }
+        let value = if rest_option_index == Some(index) {
+            std::mem::take(&mut rest)
+        } else {
+            let (word, after_word) = split_word(rest);
+            rest = after_word;
+            word
+        };
+        options.push(serde_json::json!({
+            "name": definition.name,
+            "type": definition.kind,
+            "value": parse_value(definition.kind, value)?,
+        }));
+    }
+
+    // Anything left over didn't fit in any option, so the command was probably mistyped.
+    rest.is_empty().then_some(options)
+}
+
+fn parse_value(kind: CommandOptionType, value: &str) -> Option<serde_json::Value> {
+    match kind {
+        CommandOptionType::String => Some(value.into()),
+        CommandOptionType::Integer => value.parse::<i64>().ok().map(Into::into),
+        CommandOptionType::Number => value.parse::<f64>().ok().map(Into::into),
+        CommandOptionType::Boolean => match value.to_lowercase().as_str() {
+            "true" | "yes" | "on" => Some(true.into()),
+            "false" | "no" | "off" => Some(false.into()),
+            _ => None,
+        },
+        CommandOptionType::User => parse_mention(value, &["<@!", "<@"]),
+        CommandOptionType::Role => parse_mention(value, &["<@&"]),
+        CommandOptionType::Channel => parse_mention(value, &["<#"]),
+        CommandOptionType::Mentionable => parse_mention(value, &["<@&", "<@!", "<@"]),
+        _ => None,
+    }
+}
+
+/// Reads the ID out of a mention like `<@123>`. Plain IDs are read too.
+fn parse_mention(value: &str, prefixes: &[&str]) -> Option<serde_json::Value> {
+    let id = prefixes
+        .iter()
+        .find_map(|prefix| value.strip_prefix(prefix)?.strip_suffix('>'))
+        .unwrap_or(value);
+    id.parse::<u64>().ok().map(|id| id.to_string().into())
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim_start()),
+        None => (text, ""),
+    }
+}
@@ -0,0 +1,67 @@
+use mrvn_model::PlaylistEntry;
+
+/// Which kind of file a queue is exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueFileFormat {
+    /// A JSON array of `{"title", "url"}` objects.
+    Json,
+    /// An extended M3U playlist, which most media players can open.
+    M3u,
+}
+
+impl QueueFileFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(QueueFileFormat::Json),
+            "m3u" => Some(QueueFileFormat::M3u),
+            _ => None,
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            QueueFileFormat::Json => "queue.json",
+            QueueFileFormat::M3u => "queue.m3u",
+        }
+    }
+
+    pub fn write(self, entries: &[PlaylistEntry]) -> Vec<u8> {
+        match self {
+            QueueFileFormat::Json => {
+                serde_json::to_vec_pretty(entries).expect("Queue entries should always serialize")
+            }
+            QueueFileFormat::M3u => {
+                let mut file = String::from("#EXTM3U\n");
+                for entry in entries {
+                    // Titles can't span lines, or the rest would be read as a URL
+                    let title = entry.title.replace(['\r', '\n'], " ");
+                    file.push_str(&format!("#EXTINF:-1,{}\n{}\n", title, entry.url));
+                }
+                file.into_bytes()
+            }
+        }
+    }
+}
+
+/// Reads the URLs out of an exported queue. Files that aren't JSON are read as M3U, which also
+/// covers plain lists with a URL on each line.
+pub fn read_urls(data: &[u8]) -> Vec<String> {
+    if let Ok(entries) = serde_json::from_slice::<Vec<serde_json::Value>>(data) {
+        return entries
+            .iter()
+            .filter_map(|entry| match entry {
+                serde_json::Value::String(url) => Some(url.as_str()),
+                serde_json::Value::Object(object) => object.get("url")?.as_str(),
+                _ => None,
+            })
+            .map(str::to_string)
+            .collect();
+    }
+
+    String::from_utf8_lossy(data)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
@@ -38,7 +38,13 @@ impl ResponseDelegate for QueuedResponseDelegate {
 
         tokio::task::spawn(async move {
             let guild_model = ctx.frontend.model.get(ctx.guild_id);
-            let mut guild_model_ref = guild_model.lock().await;
+            let mut guild_model_ref = match guild_model.lock().await {
+                Ok(guild_model_ref) => guild_model_ref,
+                Err(why) => {
+                    tracing::error!("Error while saving queue message: {}", why);
+                    return;
+                }
+            };
 
             let queued_entry = guild_model_ref.find_user_entry_mut(ctx.user_id, |queued_song| {
                 queued_song.song.metadata.id == ctx.song_id
@@ -1,6 +1,8 @@
 use mrvn_back_ytdl::Song;
+use serde::{Deserialize, Serialize};
 use serenity::model::id::{ChannelId, MessageId};
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct QueuedSong {
     pub song: Song,
     pub queue_message_id: Option<(ChannelId, MessageId)>,
@@ -0,0 +1,34 @@
+use serenity::model::prelude::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tracks when each user last used a command, so commands with a cooldown can't be spammed.
+#[derive(Default)]
+pub struct RateLimiter {
+    available_times: Mutex<HashMap<(UserId, String), Instant>>,
+}
+
+impl RateLimiter {
+    /// Records a use of a command. If the user used it too recently, nothing is recorded and the
+    /// time left until they can use it again is returned instead.
+    pub fn check(
+        &self,
+        user_id: UserId,
+        command_name: &str,
+        cooldown: Duration,
+    ) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut available_times = self.available_times.lock().unwrap();
+
+        // Forget about cooldowns that have finished so this doesn't keep growing.
+        available_times.retain(|_, available_time| *available_time > now);
+
+        let key = (user_id, command_name.to_string());
+        if let Some(available_time) = available_times.get(&key) {
+            return Err(*available_time - now);
+        }
+        available_times.insert(key, now + cooldown);
+        Ok(())
+    }
+}
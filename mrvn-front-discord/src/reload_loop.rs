@@ -0,0 +1,24 @@
+use crate::frontend::Frontend;
+use std::sync::Arc;
+
+/// Reloads the config whenever the bot is sent a SIGHUP.
+#[cfg(unix)]
+pub async fn reload_loop(frontend: Arc<Frontend>) -> ! {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup()).expect("Unable to listen for SIGHUP");
+    loop {
+        hangups.recv().await;
+        tracing::info!("Received SIGHUP, reloading config");
+        if let Err(why) = frontend.reload_config().await {
+            tracing::error!("Error while reloading config: {}", why);
+        }
+    }
+}
+
+/// There's no SIGHUP outside of Unix, so the config can only be reloaded with `/admin reload`.
+#[cfg(not(unix))]
+pub async fn reload_loop(_frontend: Arc<Frontend>) -> ! {
+    futures::future::pending::<()>().await;
+    unreachable!();
+}
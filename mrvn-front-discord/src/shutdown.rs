@@ -0,0 +1,19 @@
+/// Waits until the bot is asked to stop, with Ctrl+C or a SIGTERM.
+#[cfg(unix)]
+pub async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminates = signal(SignalKind::terminate()).expect("Unable to listen for SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => tracing::info!("Received Ctrl+C, shutting down"),
+        _ = terminates.recv() => tracing::info!("Received SIGTERM, shutting down"),
+    }
+}
+
+/// There's no SIGTERM outside of Unix, so only Ctrl+C shuts the bot down cleanly.
+#[cfg(not(unix))]
+pub async fn wait_for_signal() {
+    if tokio::signal::ctrl_c().await.is_ok() {
+        tracing::info!("Received Ctrl+C, shutting down");
+    }
+}
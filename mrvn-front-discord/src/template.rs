@@ -0,0 +1,180 @@
+//! Fills in the message strings from the config. Most placeholders are just `{name}`, but they can
+//! also pick between versions of the text or format the value:
+//!
+//!  - `{count, plural, one {1 vote} other {{count} votes}}` picks a case by number. `=N` cases
+//!    match exactly `N`, `one` matches 1, and `other` matches anything else.
+//!  - `{enabled, select, true {on} other {off}}` picks the case named after the value, or `other`.
+//!  - `{count, number}` separates the digits of a number into groups, like `1,234,567`.
+//!
+//! The text in each case is a template itself, so it can have placeholders of its own. Anything
+//! that doesn't read as a placeholder is left as it is.
+
+/// Fills in a template, with any placeholders that have no substitution left empty.
+pub fn render(template: &str, substitutions: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    render_into(&mut output, template, substitutions);
+    output
+}
+
+fn render_into(output: &mut String, template: &str, substitutions: &[(&str, &str)]) {
+    let mut rest = template;
+    while let Some(index) = rest.find('{') {
+        output.push_str(&rest[..index]);
+        rest = &rest[index..];
+        match parse_placeholder(rest) {
+            Some((placeholder, after)) => {
+                placeholder.render_into(output, substitutions);
+                rest = after;
+            }
+            None => {
+                output.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+}
+
+enum Placeholder<'t> {
+    Value(&'t str),
+    Number(&'t str),
+    Plural(&'t str, Vec<(&'t str, &'t str)>),
+    Select(&'t str, Vec<(&'t str, &'t str)>),
+}
+
+impl Placeholder<'_> {
+    fn render_into(&self, output: &mut String, substitutions: &[(&str, &str)]) {
+        let value_of = |name: &str| {
+            substitutions
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+                .unwrap_or("")
+        };
+
+        match self {
+            Placeholder::Value(name) => output.push_str(value_of(name)),
+            Placeholder::Number(name) => {
+                let value = value_of(name);
+                match value.parse::<i64>() {
+                    Ok(number) => output.push_str(&group_digits(number)),
+                    Err(_) => output.push_str(value),
+                }
+            }
+            Placeholder::Plural(name, cases) => {
+                let maybe_number = value_of(name).parse::<i64>().ok();
+                let maybe_case = maybe_number
+                    .and_then(|number| {
+                        let exact_key = format!("={}", number);
+                        find_case(cases, &exact_key)
+                            .or_else(|| (number == 1).then(|| find_case(cases, "one")).flatten())
+                    })
+                    .or_else(|| find_case(cases, "other"));
+                if let Some(case) = maybe_case {
+                    render_into(output, case, substitutions);
+                }
+            }
+            Placeholder::Select(name, cases) => {
+                let maybe_case =
+                    find_case(cases, value_of(name)).or_else(|| find_case(cases, "other"));
+                if let Some(case) = maybe_case {
+                    render_into(output, case, substitutions);
+                }
+            }
+        }
+    }
+}
+
+fn find_case<'t>(cases: &[(&str, &'t str)], key: &str) -> Option<&'t str> {
+    cases
+        .iter()
+        .find(|(case_key, _)| *case_key == key)
+        .map(|(_, text)| *text)
+}
+
+/// Reads the placeholder at the start of `text`, returning it and the text after it.
+fn parse_placeholder(text: &str) -> Option<(Placeholder<'_>, &str)> {
+    let rest = text.strip_prefix('{')?;
+    let (name, rest) = take_while(rest, |c| c.is_alphanumeric() || c == '_');
+    if name.is_empty() {
+        return None;
+    }
+
+    let rest = rest.trim_start();
+    if let Some(after) = rest.strip_prefix('}') {
+        return Some((Placeholder::Value(name), after));
+    }
+    let rest = rest.strip_prefix(',')?.trim_start();
+    let (kind, rest) = take_while(rest, |c| c.is_alphanumeric());
+    let rest = rest.trim_start();
+
+    if kind == "number" {
+        let after = rest.strip_prefix('}')?;
+        return Some((Placeholder::Number(name), after));
+    }
+    let (cases, after) = parse_cases(rest.strip_prefix(',')?)?;
+    match kind {
+        "plural" => Some((Placeholder::Plural(name, cases), after)),
+        "select" => Some((Placeholder::Select(name, cases), after)),
+        _ => None,
+    }
+}
+
+/// Reads cases like `one {...} other {...}` up to the `}` that ends the placeholder.
+fn parse_cases(text: &str) -> Option<(Vec<(&str, &str)>, &str)> {
+    let mut cases = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() && !rest.starts_with('}') {
+        let (key, after_key) = take_while(rest, |c| !c.is_whitespace() && c != '{' && c != '}');
+        if key.is_empty() {
+            return None;
+        }
+
+        let body_start = after_key.trim_start().strip_prefix('{')?;
+        let body_len = find_closing_brace(body_start)?;
+        cases.push((key, &body_start[..body_len]));
+        rest = body_start[body_len + 1..].trim_start();
+    }
+
+    let after = rest.strip_prefix('}')?;
+    (!cases.is_empty()).then_some((cases, after))
+}
+
+/// Finds the `}` that closes a `{` just before `text`, skipping over any nested pairs.
+fn find_closing_brace(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(index),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn take_while(text: &str, predicate: impl Fn(char) -> bool) -> (&str, &str) {
+    let end = text
+        .char_indices()
+        .find(|(_, c)| !predicate(*c))
+        .map(|(index, _)| index)
+        .unwrap_or(text.len());
+    text.split_at(end)
+}
+
+/// Adds separators between every three digits of a number, like `1,234,567`.
+fn group_digits(number: i64) -> String {
+    let digits = number.unsigned_abs().to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if number < 0 {
+        grouped.push('-');
+    }
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
@@ -0,0 +1,292 @@
+use crate::frontend::Frontend;
+use crate::message::time_bar::format_time;
+use crate::message::{ActionDelegate, ActionUpdater};
+use futures::future::{AbortHandle, Abortable};
+use mrvn_back_ytdl::SongMetadata;
+use serenity::all::EditChannel;
+use serenity::client::Context;
+use serenity::model::id::{ChannelId, GuildId};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::{Instant, MissedTickBehavior};
+use uuid::Uuid;
+
+/// Channel topics can't be longer than this many characters.
+const MAX_TOPIC_LENGTH: usize = 1024;
+
+/// Tracks which song each channel's topic is showing. Consecutive songs share the channel, so
+/// they also need to share its (very strict) topic rate limit, and the channel's original topic
+/// is restored once nothing is playing.
+#[derive(Default)]
+pub struct ChannelTopics {
+    channels: Mutex<HashMap<ChannelId, ChannelTopicState>>,
+}
+
+struct ChannelTopicState {
+    original_topic: Option<String>,
+    song_id: Option<Uuid>,
+    topic: Option<String>,
+    updated_time: Option<Instant>,
+}
+
+impl ChannelTopics {
+    /// Shows a song in a channel's topic, unless the topic was changed too recently or is already
+    /// showing the same thing.
+    async fn set_song_topic(
+        &self,
+        ctx: &Context,
+        channel_id: ChannelId,
+        song_id: Uuid,
+        topic: String,
+        min_update_interval: Duration,
+    ) {
+        {
+            let mut channels = self.channels.lock().unwrap();
+            let state = channels
+                .entry(channel_id)
+                .or_insert_with(|| ChannelTopicState {
+                    original_topic: None,
+                    song_id: None,
+                    topic: None,
+                    updated_time: None,
+                });
+
+            // Remember the topic from before we took over, so it can be put back afterwards.
+            if state.song_id.is_none() {
+                state.original_topic = ctx
+                    .cache
+                    .channel(channel_id)
+                    .and_then(|channel| channel.topic.clone());
+            }
+            state.song_id = Some(song_id);
+
+            let is_unchanged = state.topic.as_deref() == Some(topic.as_str());
+            let is_throttled = state
+                .updated_time
+                .is_some_and(|time| time.elapsed() < min_update_interval);
+            if is_unchanged || is_throttled {
+                return;
+            }
+            state.topic = Some(topic.clone());
+            state.updated_time = Some(Instant::now());
+        }
+
+        edit_topic(ctx, channel_id, topic).await;
+    }
+
+    /// Puts back a channel's original topic after a song has finished, waiting for the rate limit
+    /// if needed. Nothing happens if another song has taken over the topic in the meantime.
+    async fn restore_topic(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        song_id: Uuid,
+        min_update_interval: Duration,
+    ) {
+        loop {
+            let wait_duration = {
+                let mut channels = self.channels.lock().unwrap();
+                let state = match channels.get_mut(&channel_id) {
+                    Some(state) if state.song_id == Some(song_id) => state,
+                    _ => return,
+                };
+
+                let wait_duration = state
+                    .updated_time
+                    .map(|time| min_update_interval.saturating_sub(time.elapsed()))
+                    .unwrap_or_default();
+                if wait_duration.is_zero() {
+                    state.song_id = None;
+                    if state.topic == state.original_topic {
+                        return;
+                    }
+                    state.topic = state.original_topic.clone();
+                    state.updated_time = Some(Instant::now());
+                    None
+                } else {
+                    Some(wait_duration)
+                }
+            };
+
+            match wait_duration {
+                Some(wait_duration) => tokio::time::sleep(wait_duration).await,
+                None => break,
+            }
+        }
+
+        let original_topic = self
+            .channels
+            .lock()
+            .unwrap()
+            .get(&channel_id)
+            .and_then(|state| state.original_topic.clone())
+            .unwrap_or_default();
+        edit_topic(&ctx, channel_id, original_topic).await;
+    }
+}
+
+async fn edit_topic(ctx: &Context, channel_id: ChannelId, topic: String) {
+    let maybe_err = channel_id.edit(ctx, EditChannel::new().topic(topic)).await;
+    if let Err(why) = maybe_err {
+        tracing::error!("Error while updating channel topic: {}", why);
+    }
+}
+
+/// Wraps another action delegate, additionally showing the playing song and its progress in a
+/// channel's topic. The topic goes in the channel the message was sent to, unless a topic
+/// channel is configured.
+pub struct ChannelTopicActionDelegate {
+    inner: Box<dyn ActionDelegate>,
+    frontend: Arc<Frontend>,
+
+    guild_id: GuildId,
+    topic_channel_id: Option<ChannelId>,
+    song_metadata: SongMetadata,
+}
+
+impl ChannelTopicActionDelegate {
+    pub fn new(
+        inner: Box<dyn ActionDelegate>,
+        frontend: Arc<Frontend>,
+        guild_id: GuildId,
+        topic_channel_id: Option<ChannelId>,
+        song_metadata: SongMetadata,
+    ) -> Self {
+        ChannelTopicActionDelegate {
+            inner,
+            frontend,
+            guild_id,
+            topic_channel_id,
+            song_metadata,
+        }
+    }
+}
+
+impl ActionDelegate for ChannelTopicActionDelegate {
+    fn start(&self, updater: ActionUpdater) -> Box<dyn Any + Send + Sync> {
+        let metadata = ActiveChannelTopicMetadata {
+            ctx: updater.context().clone(),
+            frontend: self.frontend.clone(),
+            locale: updater.locale().map(str::to_string),
+
+            guild_id: self.guild_id,
+            channel_id: self.topic_channel_id.unwrap_or(updater.channel_id()),
+            song_metadata: self.song_metadata.clone(),
+            min_update_interval: Duration::from_secs_f64(
+                self.frontend.config().topic_min_update_secs,
+            ),
+        };
+        let inner = self.inner.start(updater);
+
+        let (abort, abort_registration) = AbortHandle::new_pair();
+        tokio::spawn(Abortable::new(
+            update_topic_loop(metadata.clone()),
+            abort_registration,
+        ));
+
+        Box::new(ActiveChannelTopicActionDelegate {
+            _inner: inner,
+            abort,
+            metadata,
+        })
+    }
+}
+
+#[derive(Clone)]
+struct ActiveChannelTopicMetadata {
+    ctx: Context,
+    frontend: Arc<Frontend>,
+    locale: Option<String>,
+
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    song_metadata: SongMetadata,
+    min_update_interval: Duration,
+}
+
+struct ActiveChannelTopicActionDelegate {
+    _inner: Box<dyn Any + Send + Sync>,
+    abort: AbortHandle,
+    metadata: ActiveChannelTopicMetadata,
+}
+
+impl Drop for ActiveChannelTopicActionDelegate {
+    fn drop(&mut self) {
+        self.abort.abort();
+
+        let metadata = self.metadata.clone();
+        tokio::task::spawn(async move {
+            metadata
+                .frontend
+                .channel_topics
+                .restore_topic(
+                    metadata.ctx,
+                    metadata.channel_id,
+                    metadata.song_metadata.id,
+                    metadata.min_update_interval,
+                )
+                .await;
+        });
+    }
+}
+
+async fn update_topic_loop(metadata: ActiveChannelTopicMetadata) {
+    let mut interval = tokio::time::interval(metadata.min_update_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        let play_time = {
+            let guild_speakers = metadata
+                .frontend
+                .backend_brain
+                .guild_speakers(metadata.guild_id);
+            let mut guild_speakers_ref = guild_speakers.lock();
+
+            match guild_speakers_ref
+                .find_active_song(metadata.song_metadata.id)
+                .await
+            {
+                Some((active_speaker, _)) => active_speaker.active_play_time().await,
+                None => return,
+            }
+        };
+
+        let config = &metadata.frontend.config();
+        let locale = metadata.locale.as_deref();
+        let time = format_time(
+            config,
+            locale,
+            play_time.map(|time| time.as_secs_f64()).unwrap_or(0.),
+            metadata.song_metadata.duration_seconds,
+        );
+        let topic: String = config
+            .get_message(
+                locale,
+                "topic.playing",
+                &[
+                    ("song_title", &metadata.song_metadata.title),
+                    ("song_url", &metadata.song_metadata.url),
+                    ("time", &time),
+                ],
+            )
+            .chars()
+            .take(MAX_TOPIC_LENGTH)
+            .collect();
+
+        metadata
+            .frontend
+            .channel_topics
+            .set_song_topic(
+                &metadata.ctx,
+                metadata.channel_id,
+                metadata.song_metadata.id,
+                topic,
+                metadata.min_update_interval,
+            )
+            .await;
+    }
+}
@@ -0,0 +1,153 @@
+use crate::config::VoiceBot;
+use crate::frontend::Frontend;
+use mrvn_back_ytdl::{Speaker, SpeakerInit, SpeakerKey};
+use serenity::gateway::ShardManager;
+use serenity::{model::prelude::*, prelude::*};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum VoiceBotError {
+    AlreadyRunning(u64),
+}
+
+impl std::fmt::Display for VoiceBotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            VoiceBotError::AlreadyRunning(application_id) => {
+                write!(f, "Voice bot {} is already running", application_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VoiceBotError {}
+
+pub struct RunningVoiceBot {
+    /// Shown in logs to tell voice clients apart.
+    pub index: usize,
+    pub application_id: u64,
+    pub speaker: Arc<Speaker>,
+    pub shard_manager: Arc<ShardManager>,
+}
+
+/// The voice bots that have been started, either from the config or while the bot is running.
+/// Bots that stop on their own stay in here, so the health check can see they aren't connected.
+#[derive(Default)]
+pub struct VoiceBots {
+    bots: Mutex<Vec<Arc<RunningVoiceBot>>>,
+    next_index: AtomicUsize,
+}
+
+impl VoiceBots {
+    pub async fn list(&self) -> Vec<Arc<RunningVoiceBot>> {
+        self.bots.lock().await.clone()
+    }
+
+    pub async fn shard_managers(&self) -> Vec<Arc<ShardManager>> {
+        self.bots
+            .lock()
+            .await
+            .iter()
+            .map(|bot| bot.shard_manager.clone())
+            .collect()
+    }
+}
+
+/// Connects a voice bot and starts using it to play songs. It keeps running in the background
+/// until it's stopped with [`stop_voice_bot`], or its connection fails.
+pub async fn start_voice_bot(
+    frontend: &Arc<Frontend>,
+    bot_config: &VoiceBot,
+) -> Result<Arc<RunningVoiceBot>, VoiceBotError> {
+    // Held while the client is created, so the same bot can't be started twice at once
+    let mut bots = frontend.voice_bots.bots.lock().await;
+    if bots
+        .iter()
+        .any(|bot| bot.application_id == bot_config.application_id)
+    {
+        return Err(VoiceBotError::AlreadyRunning(bot_config.application_id));
+    }
+
+    let index = frontend
+        .voice_bots
+        .next_index
+        .fetch_add(1, Ordering::Relaxed);
+    // Creating a client doesn't connect to anything, so it can't fail
+    let mut client = Client::builder(&bot_config.token, GatewayIntents::non_privileged())
+        .application_id(ApplicationId::new(bot_config.application_id))
+        .event_handler(crate::voice_handler::VoiceHandler {
+            client_index: index,
+        })
+        .register_speaker(&frontend.backend_brain)
+        .await
+        .expect("Unable to create voice client");
+    let speaker = client
+        .data
+        .read()
+        .await
+        .get::<SpeakerKey>()
+        .expect("Voice clients should always have a speaker")
+        .clone();
+
+    let bot = Arc::new(RunningVoiceBot {
+        index,
+        application_id: bot_config.application_id,
+        speaker: speaker.clone(),
+        shard_manager: client.shard_manager.clone(),
+    });
+    bots.push(bot.clone());
+    drop(bots);
+
+    // Voice clients run on their own, so one of them stopping only loses its speaker instead of
+    // taking the whole bot down with it.
+    let frontend = frontend.clone();
+    let shard_count = bot_config.shard_count;
+    tokio::task::spawn(async move {
+        match crate::start_client(&mut client, shard_count).await {
+            Ok(()) => tracing::warn!("Voice client {} stopped", index),
+            Err(why) => tracing::error!("Voice client {} stopped with an error: {}", index, why),
+        }
+        speaker.set_stopped();
+        frontend.backend_brain.remove_speaker(&speaker);
+    });
+
+    tracing::info!("Started voice client {}", index);
+    Ok(bot)
+}
+
+/// Stops a voice bot and disconnects it. Anything it was playing is stopped, and nothing else is
+/// played with it. Returns false if the bot wasn't running.
+pub async fn stop_voice_bot(frontend: &Frontend, application_id: u64) -> bool {
+    let maybe_bot = {
+        let mut bots = frontend.voice_bots.bots.lock().await;
+        let maybe_index = bots
+            .iter()
+            .position(|bot| bot.application_id == application_id);
+        maybe_index.map(|index| bots.remove(index))
+    };
+    let Some(bot) = maybe_bot else {
+        return false;
+    };
+
+    bot.speaker.set_stopped();
+    frontend.backend_brain.remove_speaker(&bot.speaker);
+    let guild_speaker_handles: Vec<_> = bot.speaker.iter().collect();
+    for guild_speaker_handle in guild_speaker_handles {
+        let mut guild_speaker = guild_speaker_handle.lock().await;
+        if guild_speaker.current_channel().is_none() {
+            continue;
+        }
+        if let Err(why) = guild_speaker.stop() {
+            tracing::error!("Error while stopping song: {}", why);
+        }
+        guild_speaker.unlock();
+        if let Err(why) = guild_speaker.disconnect().await {
+            tracing::error!("Error while leaving voice channel: {}", why);
+        }
+    }
+
+    bot.shard_manager.shutdown_all().await;
+    tracing::info!("Stopped voice client {}", bot.index);
+    true
+}
@@ -1,3 +1,5 @@
+use mrvn_back_ytdl::SpeakerKey;
+use serenity::gateway::ShardStageUpdateEvent;
 use serenity::{model::prelude::*, prelude::*};
 
 pub struct VoiceHandler {
@@ -6,11 +8,46 @@ pub struct VoiceHandler {
 
 #[serenity::async_trait]
 impl EventHandler for VoiceHandler {
-    async fn ready(&self, _ctx: Context, ready: Ready) {
-        log::info!(
-            "Voice client {} is connected as {}",
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        let shard = ready.shard.unwrap_or(ShardInfo {
+            id: ctx.shard_id,
+            total: 1,
+        });
+        tracing::info!(
+            "Voice client {} shard {} of {} is connected as {}",
             self.client_index,
+            shard.id,
+            shard.total,
             ready.user.name
         );
+
+        // The speaker needs each shard to show what it's playing in the bot's presence.
+        let maybe_speaker = ctx.data.read().await.get::<SpeakerKey>().cloned();
+        if let Some(speaker) = maybe_speaker {
+            speaker
+                .presence()
+                .set_shard(ctx.shard_id, ctx.shard.clone());
+        }
+    }
+
+    async fn voice_server_update(&self, ctx: Context, event: VoiceServerUpdateEvent) {
+        let Some(guild_id) = event.guild_id else {
+            return;
+        };
+        let maybe_speaker = ctx.data.read().await.get::<SpeakerKey>().cloned();
+        if let Some(speaker) = maybe_speaker {
+            speaker
+                .voice_server_changed(guild_id, event.endpoint.as_deref())
+                .await;
+        }
+    }
+
+    async fn shard_stage_update(&self, _ctx: Context, event: ShardStageUpdateEvent) {
+        tracing::debug!(
+            "Voice client {} shard {} is {}",
+            self.client_index,
+            event.shard_id,
+            event.new
+        );
     }
 }
@@ -0,0 +1,39 @@
+use crate::frontend::Frontend;
+use mrvn_back_ytdl::update_ytdl;
+use std::sync::Arc;
+use std::time::Duration;
+
+async fn check_updates(frontend: &Frontend) {
+    for extractor in frontend.config().get_extractors() {
+        match update_ytdl(extractor).await {
+            Ok(update) if update.is_updated() => tracing::info!(
+                "Updated {} from version {} to {}",
+                extractor.name,
+                update.old_version,
+                update.new_version
+            ),
+            Ok(update) => tracing::debug!(
+                "{} is up to date at version {}",
+                extractor.name,
+                update.new_version
+            ),
+            Err(why) => tracing::error!("Error while updating {}: {}", extractor.name, why),
+        }
+    }
+}
+
+/// Keeps each extractor up to date, since sites change often enough that old versions stop working.
+/// Does nothing if `update_interval_secs` is zero.
+pub async fn ytdl_update_loop(frontend: Arc<Frontend>) -> ! {
+    if frontend.config().ytdl.update_interval_secs == 0 {
+        futures::future::pending::<()>().await;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        frontend.config().ytdl.update_interval_secs,
+    ));
+    loop {
+        interval.tick().await;
+        check_updates(&frontend).await;
+    }
+}
@@ -1,27 +1,174 @@
-use crate::{AppModelConfig, GuildModel};
+use crate::shared::SharedLock;
+use crate::{AppModelConfig, GuildModel, LockError, RedisShare, SharedEntry};
 use dashmap::DashMap;
 use serenity::model::prelude::*;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, RwLock};
+use tokio::sync::{Mutex, OwnedMutexGuard};
 
 pub struct AppModel<QueueEntry> {
-    config: AppModelConfig,
+    config: RwLock<AppModelConfig>,
+    guild_configs: DashMap<GuildId, AppModelConfig>,
     guilds: DashMap<GuildId, Arc<Mutex<GuildModel<QueueEntry>>>>,
+    maybe_share: Option<RedisShare>,
 }
 
-impl<QueueEntry> AppModel<QueueEntry> {
-    pub fn new(config: AppModelConfig) -> Self {
+impl<QueueEntry: SharedEntry> AppModel<QueueEntry> {
+    /// Creates an empty model. Guild models are shared with other instances through Redis if
+    /// there's a share, and only kept in memory otherwise.
+    pub fn new(config: AppModelConfig, maybe_share: Option<RedisShare>) -> Self {
         AppModel {
-            config,
+            config: RwLock::new(config),
+            guild_configs: DashMap::new(),
             guilds: DashMap::new(),
+            maybe_share,
         }
     }
 
-    pub fn get(&self, guild_id: GuildId) -> Arc<Mutex<GuildModel<QueueEntry>>> {
-        let handle = self
-            .guilds
-            .entry(guild_id)
-            .or_insert_with(|| Arc::new(Mutex::new(GuildModel::new(guild_id, self.config))));
-        handle.clone()
+    /// Sets the default config used when creating guild models. Guild models that already exist
+    /// need to be updated separately.
+    pub fn set_config(&self, config: AppModelConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Sets the config used when creating a guild's model, instead of the default config. Guild
+    /// models that already exist need to be updated separately.
+    pub fn set_guild_config(&self, guild_id: GuildId, config: AppModelConfig) {
+        self.guild_configs.insert(guild_id, config);
+    }
+
+    pub fn get(&self, guild_id: GuildId) -> GuildModelHandle<QueueEntry> {
+        let handle = self.guilds.entry(guild_id).or_insert_with(|| {
+            let config = self
+                .guild_configs
+                .get(&guild_id)
+                .map(|config| *config)
+                .unwrap_or_else(|| *self.config.read().unwrap());
+            Arc::new(Mutex::new(GuildModel::new(guild_id, config)))
+        });
+        GuildModelHandle {
+            guild_id,
+            model: handle.clone(),
+            maybe_share: self.maybe_share.clone(),
+        }
+    }
+
+    /// The guilds that have a model.
+    pub fn guild_ids(&self) -> Vec<GuildId> {
+        self.guilds.iter().map(|guild| *guild.key()).collect()
+    }
+}
+
+pub struct GuildModelHandle<QueueEntry> {
+    guild_id: GuildId,
+    model: Arc<Mutex<GuildModel<QueueEntry>>>,
+    maybe_share: Option<RedisShare>,
+}
+
+impl<QueueEntry: SharedEntry> GuildModelHandle<QueueEntry> {
+    /// Waits for nothing else to be using the guild's model, including other instances if it's
+    /// shared, and loads the latest shared state into it. If the shared model can't be locked or
+    /// loaded, the local one could be out of date, so this fails instead of using it.
+    pub async fn lock(&self) -> Result<GuildModelGuard<QueueEntry>, LockError> {
+        let mut guard = self.model.clone().lock_owned().await;
+        let Some(share) = &self.maybe_share else {
+            return Ok(GuildModelGuard {
+                guard: Some(guard),
+                maybe_lock: None,
+            });
+        };
+
+        let lock = share.lock(self.guild_id).await?;
+        match share.load(self.guild_id).await {
+            Ok(Some(data)) => {
+                if let Err(why) = guard.set_shared_state(&data) {
+                    tracing::error!("Unable to read shared guild model: {}", why);
+                }
+            }
+            Ok(None) => {}
+            Err(why) => {
+                // Nothing is saved, since the local model is out of date
+                if let Err(why) = lock.save_and_unlock(None).await {
+                    tracing::error!("Unable to unlock shared guild model: {}", why);
+                }
+                return Err(LockError::Redis(why));
+            }
+        }
+
+        Ok(GuildModelGuard {
+            guard: Some(guard),
+            maybe_lock: Some(lock),
+        })
+    }
+
+    /// Gets the guild's model without waiting, if nothing else is using it. The shared state isn't
+    /// loaded and nothing is saved for other instances, so this is only for quick reads where the
+    /// model being a little out of date doesn't matter, like autocomplete.
+    pub fn try_lock_local(&self) -> Option<GuildModelGuard<QueueEntry>> {
+        let guard = self.model.clone().try_lock_owned().ok()?;
+        Some(GuildModelGuard {
+            guard: Some(guard),
+            maybe_lock: None,
+        })
+    }
+}
+
+/// A locked guild model. If it's shared, it's saved and unlocked for other instances when this is
+/// dropped.
+pub struct GuildModelGuard<QueueEntry: SharedEntry> {
+    guard: Option<OwnedMutexGuard<GuildModel<QueueEntry>>>,
+    maybe_lock: Option<SharedLock>,
+}
+
+impl<QueueEntry: SharedEntry> GuildModelGuard<QueueEntry> {
+    fn unlock(&mut self) {
+        let Some(guard) = self.guard.take() else {
+            return;
+        };
+        let Some(lock) = self.maybe_lock.take() else {
+            drop(guard);
+            return;
+        };
+
+        let maybe_data = match guard.shared_state() {
+            Ok(data) => Some(data),
+            Err(why) => {
+                tracing::error!("Unable to save shared guild model: {}", why);
+                None
+            }
+        };
+        // Without a runtime, like while the bot is shutting down, the lock is left to expire.
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("Unable to save shared guild model outside of a runtime");
+            return;
+        };
+        // The model stays locked here until it's been saved, so the next lock loads what was
+        // just saved instead of an older state.
+        runtime.spawn(async move {
+            if let Err(why) = lock.save_and_unlock(maybe_data).await {
+                tracing::error!("Unable to save shared guild model: {}", why);
+            }
+            drop(guard);
+        });
+    }
+}
+
+impl<QueueEntry: SharedEntry> Deref for GuildModelGuard<QueueEntry> {
+    type Target = GuildModel<QueueEntry>;
+
+    fn deref(&self) -> &Self::Target {
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl<QueueEntry: SharedEntry> DerefMut for GuildModelGuard<QueueEntry> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.guard.as_mut().unwrap()
+    }
+}
+
+impl<QueueEntry: SharedEntry> Drop for GuildModelGuard<QueueEntry> {
+    fn drop(&mut self) {
+        self.unlock();
     }
 }
@@ -1,5 +1,99 @@
+use crate::SchedulingStrategy;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// How many votes are needed to skip, stop or pause a song. This is either a fixed number of votes, or a
+/// percentage of the people in the voice channel when the vote happens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoteThreshold {
+    Count(usize),
+    Percentage(f64),
+}
+
+impl VoteThreshold {
+    pub fn votes_required(self, listener_count: usize) -> usize {
+        match self {
+            VoteThreshold::Count(count) => count,
+            VoteThreshold::Percentage(percentage) => {
+                let votes = (listener_count as f64 * percentage / 100.).ceil() as usize;
+                votes.max(1)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseVoteThresholdError;
+
+impl fmt::Display for ParseVoteThresholdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a number of votes or a percentage")
+    }
+}
+
+impl std::error::Error for ParseVoteThresholdError {}
+
+impl FromStr for VoteThreshold {
+    type Err = ParseVoteThresholdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().strip_suffix('%') {
+            Some(percentage) => match percentage.trim().parse::<f64>() {
+                Ok(percentage) if (0. ..=100.).contains(&percentage) => {
+                    Ok(VoteThreshold::Percentage(percentage))
+                }
+                _ => Err(ParseVoteThresholdError),
+            },
+            None => s
+                .trim()
+                .parse()
+                .map(VoteThreshold::Count)
+                .map_err(|_| ParseVoteThresholdError),
+        }
+    }
+}
+
+impl fmt::Display for VoteThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VoteThreshold::Count(count) => write!(f, "{}", count),
+            VoteThreshold::Percentage(percentage) => write!(f, "{}%", percentage),
+        }
+    }
+}
+
+impl Serialize for VoteThreshold {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            VoteThreshold::Count(count) => serializer.serialize_u64(*count as u64),
+            VoteThreshold::Percentage(_) => serializer.collect_str(self),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VoteThreshold {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawThreshold {
+            Count(usize),
+            Text(String),
+        }
+
+        match RawThreshold::deserialize(deserializer)? {
+            RawThreshold::Count(count) => Ok(VoteThreshold::Count(count)),
+            RawThreshold::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct AppModelConfig {
-    pub skip_votes_required: usize,
-    pub stop_votes_required: usize,
+    pub skip_votes_required: VoteThreshold,
+    pub stop_votes_required: VoteThreshold,
+    pub pause_votes_required: VoteThreshold,
+    pub history_length: usize,
+    pub max_user_queue_length: usize,
+    pub scheduling_strategy: SchedulingStrategy,
 }
@@ -0,0 +1,100 @@
+use crate::{run_blocking, PlaylistEntry, Storage, StorageError};
+use serenity::model::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Songs each user has saved to queue again later. Favorites belong to the user rather than a
+/// guild, so they can be played anywhere the bot is.
+#[derive(Default)]
+pub struct Favorites {
+    users: HashMap<UserId, BTreeMap<String, PlaylistEntry>>,
+}
+
+impl Favorites {
+    pub fn get(&self, user_id: UserId, name: &str) -> Option<&PlaylistEntry> {
+        self.users
+            .get(&user_id)
+            .and_then(|favorites| favorites.get(name))
+    }
+
+    pub fn iter(&self, user_id: UserId) -> impl Iterator<Item = (&String, &PlaylistEntry)> + '_ {
+        self.users.get(&user_id).into_iter().flatten()
+    }
+
+    /// Saves a favorite, replacing any the user already has with the same name.
+    pub fn add(&mut self, user_id: UserId, name: &str, entry: PlaylistEntry) {
+        self.users
+            .entry(user_id)
+            .or_default()
+            .insert(name.to_string(), entry);
+    }
+
+    /// Returns false if the user doesn't have a favorite with the name.
+    pub fn remove(&mut self, user_id: UserId, name: &str) -> bool {
+        let Some(favorites) = self.users.get_mut(&user_id) else {
+            return false;
+        };
+        let is_removed = favorites.remove(name).is_some();
+        if favorites.is_empty() {
+            self.users.remove(&user_id);
+        }
+        is_removed
+    }
+}
+
+/// Keeps every user's favorites in memory, saving each favorite when it's added or removed.
+pub struct FavoriteStore {
+    storage: Arc<dyn Storage>,
+    favorites: Mutex<Favorites>,
+}
+
+impl FavoriteStore {
+    pub fn open(storage: Arc<dyn Storage>) -> Result<Self, StorageError> {
+        let mut favorites = Favorites::default();
+        for (user_id, name, entry) in storage.load_favorites()? {
+            favorites.add(user_id, &name, entry);
+        }
+
+        Ok(FavoriteStore {
+            storage,
+            favorites: Mutex::new(favorites),
+        })
+    }
+
+    pub async fn read<R>(&self, f: impl FnOnce(&Favorites) -> R) -> R {
+        let favorites = self.favorites.lock().await;
+        f(&favorites)
+    }
+
+    pub async fn add(
+        &self,
+        user_id: UserId,
+        name: &str,
+        entry: PlaylistEntry,
+    ) -> Result<(), StorageError> {
+        let mut favorites = self.favorites.lock().await;
+        favorites.add(user_id, name, entry.clone());
+
+        let name = name.to_string();
+        run_blocking(&self.storage, move |storage| {
+            storage.save_favorite(user_id, &name, &entry)
+        })
+        .await
+    }
+
+    /// Returns false if the user doesn't have a favorite with the name.
+    pub async fn remove(&self, user_id: UserId, name: &str) -> Result<bool, StorageError> {
+        let mut favorites = self.favorites.lock().await;
+        if !favorites.remove(user_id, name) {
+            return Ok(false);
+        }
+
+        let name = name.to_string();
+        run_blocking(&self.storage, move |storage| {
+            storage.delete_favorite(user_id, &name)
+        })
+        .await?;
+        Ok(true)
+    }
+}
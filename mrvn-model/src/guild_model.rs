@@ -1,23 +1,38 @@
-use crate::AppModelConfig;
+use crate::shared::instant_serde;
+use crate::{AppModelConfig, HistoryEntry, QueueCandidate, VoteThreshold};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serenity::model::prelude::*;
 use std::any::Any;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Instant;
 
-fn find_first_user_in_channel<'a, Entry: 'a>(
+fn find_queue_candidates<'a, Entry: 'a>(
     cache: &serenity::cache::Cache,
-    mut queues: impl Iterator<Item = &'a Queue<Entry>>,
+    queues: impl Iterator<Item = &'a Queue<Entry>>,
     guild_id: GuildId,
     channel_id: ChannelId,
-) -> Option<UserId> {
-    let guild = cache.guild(guild_id)?;
-    let queue = queues.find(|queue| {
-        let current_channel = guild
-            .voice_states
-            .get(&queue.user_id)
-            .and_then(|voice_state| voice_state.channel_id);
-        current_channel == Some(channel_id)
-    })?;
-    Some(queue.user_id)
+) -> Vec<QueueCandidate> {
+    let Some(guild) = cache.guild(guild_id) else {
+        return Vec::new();
+    };
+    queues
+        .filter(|queue| {
+            let current_channel = guild
+                .voice_states
+                .get(&queue.user_id)
+                .and_then(|voice_state| voice_state.channel_id);
+            current_channel == Some(channel_id)
+        })
+        .filter_map(|queue| {
+            Some(QueueCandidate {
+                user_id: queue.user_id,
+                priority: queue.priority,
+                waiting_since: queue.waiting_since,
+                next_entry_queued_time: queue.entries.front()?.queued_time,
+            })
+        })
+        .collect()
 }
 
 fn is_user_in_voice_channel(
@@ -36,9 +51,41 @@ fn is_user_in_voice_channel(
     current_channel == Some(channel_id)
 }
 
+fn count_users_in_voice_channel(
+    cache: &serenity::cache::Cache,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> usize {
+    let Some(guild) = cache.guild(guild_id) else {
+        return 0;
+    };
+    guild
+        .voice_states
+        .values()
+        .filter(|voice_state| voice_state.channel_id == Some(channel_id))
+        .filter(|voice_state| {
+            let user = guild
+                .members
+                .get(&voice_state.user_id)
+                .map(|member| &member.user)
+                .or_else(|| voice_state.member.as_ref().map(|member| &member.user));
+            !user.map(|user| user.bot).unwrap_or(false)
+        })
+        .count()
+}
+
+/// The number of votes cast to skip, stop or pause the current entry, and how many are needed for the
+/// vote to pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VoteCount {
+    pub votes: usize,
+    pub required: usize,
+}
+
 pub enum VoteType {
     Skip,
     Stop,
+    Pause,
 }
 
 pub enum VoteStatus {
@@ -48,23 +95,71 @@ pub enum VoteStatus {
     NothingPlaying,
 }
 
+pub enum PushStatus {
+    Pushed,
+    /// Nothing was queued because the user would have more than the maximum number of entries
+    /// queued.
+    QueueFull {
+        queued_count: usize,
+        max_count: usize,
+    },
+}
+
 pub enum ReplaceStatus<QueueEntry> {
     Queued,
     ReplacedInQueue(QueueEntry),
     ReplacedCurrent(ChannelId),
 }
 
+pub enum UndoStatus {
+    NothingToUndo,
+    Undone {
+        removed_count: usize,
+        restored_count: usize,
+    },
+}
+
 pub enum NextEntry<QueueEntry> {
     NoneAvailable,
     AlreadyPlaying,
     Entry(QueueEntry),
 }
 
+#[derive(Serialize, Deserialize)]
 struct Queue<Entry> {
     user_id: UserId,
-    entries: VecDeque<Entry>,
+    /// Queues with a higher priority are always played from before queues with a lower priority.
+    priority: u32,
+    #[serde(with = "instant_serde")]
+    waiting_since: Instant,
+    entries: VecDeque<QueuedEntry<Entry>>,
 }
 
+/// The last change a user made to their queue, kept so it can be undone. Entries are only ever
+/// played from the front of a queue, so entries added to the back can be found again by counting.
+struct QueueAction<Entry> {
+    pushed_count: usize,
+    /// Entries taken out of the queue, along with where they were.
+    removed: Vec<(usize, QueuedEntry<Entry>)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct QueuedEntry<Entry> {
+    entry: Entry,
+    #[serde(with = "instant_serde")]
+    queued_time: Instant,
+}
+
+impl<Entry> QueuedEntry<Entry> {
+    fn new(entry: Entry) -> Self {
+        QueuedEntry {
+            entry,
+            queued_time: Instant::now(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 enum ChannelPlayingState {
     NotPlaying,
     Stopped,
@@ -72,6 +167,9 @@ enum ChannelPlayingState {
         playing_user_id: UserId,
         skip_votes: HashSet<UserId>,
         stop_votes: HashSet<UserId>,
+        /// Pausing doesn't end the entry, so these are cleared whenever a pause vote passes.
+        #[serde(default)]
+        pause_votes: HashSet<UserId>,
     },
 }
 
@@ -85,9 +183,30 @@ pub struct ChannelActionMessage {
     pub frontend_handle: Box<dyn Any + Send + Sync>,
 }
 
+#[derive(Serialize, Deserialize)]
 struct ChannelModel {
     playing: ChannelPlayingState,
+    /// Messages are handled by whichever instance sent them, so they aren't shared.
+    #[serde(skip)]
     last_action_message: Option<ChannelActionMessage>,
+    /// The thread messages about the channel's current listening session are posted in, if
+    /// session threads are enabled.
+    session_thread: Option<ChannelId>,
+}
+
+/// The parts of a guild model that are shared with other instances of the bot.
+#[derive(Serialize)]
+struct SharedStateRef<'a, QueueEntry> {
+    message_channel: Option<ChannelId>,
+    queues: &'a [Queue<QueueEntry>],
+    channels: &'a HashMap<ChannelId, ChannelModel>,
+}
+
+#[derive(Deserialize)]
+struct SharedState<QueueEntry> {
+    message_channel: Option<ChannelId>,
+    queues: Vec<Queue<QueueEntry>>,
+    channels: HashMap<ChannelId, ChannelModel>,
 }
 
 pub struct GuildModel<QueueEntry> {
@@ -95,7 +214,11 @@ pub struct GuildModel<QueueEntry> {
     config: AppModelConfig,
     message_channel: Option<ChannelId>,
     queues: Vec<Queue<QueueEntry>>,
+    user_priorities: HashMap<UserId, u32>,
     channels: HashMap<ChannelId, ChannelModel>,
+    history: HashMap<ChannelId, VecDeque<HistoryEntry>>,
+    /// Only changes made through this instance can be undone, since these aren't shared.
+    last_actions: HashMap<UserId, QueueAction<QueueEntry>>,
 }
 
 impl<QueueEntry> GuildModel<QueueEntry> {
@@ -105,10 +228,21 @@ impl<QueueEntry> GuildModel<QueueEntry> {
             config,
             message_channel: None,
             queues: Vec::new(),
+            user_priorities: HashMap::new(),
             channels: HashMap::new(),
+            history: HashMap::new(),
+            last_actions: HashMap::new(),
         }
     }
 
+    pub fn guild_id(&self) -> GuildId {
+        self.guild_id
+    }
+
+    pub fn set_config(&mut self, config: AppModelConfig) {
+        self.config = config;
+    }
+
     pub fn message_channel(&self) -> Option<ChannelId> {
         self.message_channel
     }
@@ -134,6 +268,16 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         self.create_channel(channel_id).last_action_message = status_message;
     }
 
+    pub fn session_thread(&self, channel_id: ChannelId) -> Option<ChannelId> {
+        self.channels
+            .get(&channel_id)
+            .and_then(|channel| channel.session_thread)
+    }
+
+    pub fn set_session_thread(&mut self, channel_id: ChannelId, thread_id: Option<ChannelId>) {
+        self.create_channel(channel_id).session_thread = thread_id;
+    }
+
     pub fn is_channel_stopped(&self, channel_id: ChannelId) -> bool {
         matches!(
             self.get_channel_playing_state(channel_id),
@@ -141,26 +285,242 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         )
     }
 
+    /// Marks a channel as not playing anything, without choosing what plays next. This is for
+    /// when the bot is leaving the channel.
+    pub fn set_channel_not_playing(&mut self, channel_id: ChannelId) {
+        if let Some(channel) = self.channels.get_mut(&channel_id) {
+            channel.playing = ChannelPlayingState::NotPlaying;
+        }
+    }
+
+    /// Moves what's playing in a channel to another channel, along with its votes and messages,
+    /// for when the speaker playing it moves. Returns false if nothing is playing in the channel,
+    /// or something is already playing in the other one.
+    pub fn move_channel(&mut self, from_channel_id: ChannelId, to_channel_id: ChannelId) -> bool {
+        let is_playing = |channel_id| {
+            self.get_channel_playing_state(channel_id)
+                .is_some_and(ChannelPlayingState::is_playing)
+        };
+        if !is_playing(from_channel_id) || is_playing(to_channel_id) {
+            return false;
+        }
+
+        if let Some(channel) = self.channels.remove(&from_channel_id) {
+            self.channels.insert(to_channel_id, channel);
+        }
+        true
+    }
+
     pub fn set_channel_stopped(&mut self, channel_id: ChannelId) {
         self.create_channel(channel_id).playing = ChannelPlayingState::Stopped;
     }
 
+    /// Marks a channel as playing an entry that didn't come from anyone's queue.
+    pub fn set_channel_playing(&mut self, channel_id: ChannelId, user_id: UserId) {
+        self.create_channel(channel_id).playing = ChannelPlayingState::Playing {
+            playing_user_id: user_id,
+            skip_votes: HashSet::new(),
+            stop_votes: HashSet::new(),
+            pause_votes: HashSet::new(),
+        };
+    }
+
+    pub fn set_user_priority(&mut self, user_id: UserId, priority: u32) {
+        if priority == 0 {
+            self.user_priorities.remove(&user_id);
+        } else {
+            self.user_priorities.insert(user_id, priority);
+        }
+        if let Some(queue) = self.get_user_queue_mut(user_id) {
+            queue.priority = priority;
+        }
+    }
+
     pub fn find_user_entry_mut(
         &mut self,
         user_id: UserId,
         mut f: impl FnMut(&QueueEntry) -> bool,
     ) -> Option<&mut QueueEntry> {
         if let Some(queue) = self.get_user_queue_mut(user_id) {
-            queue.entries.iter_mut().find(|entry| f(*entry))
+            queue
+                .entries
+                .iter_mut()
+                .map(|queued| &mut queued.entry)
+                .find(|entry| f(*entry))
         } else {
             None
         }
     }
 
+    pub fn user_entries(&self, user_id: UserId) -> impl Iterator<Item = &QueueEntry> + '_ {
+        self.queues
+            .iter()
+            .filter(move |queue| queue.user_id == user_id)
+            .flat_map(|queue| queue.entries.iter().map(|queued| &queued.entry))
+    }
+
+    /// Every queued entry along with the user that queued it, one user's queue at a time.
+    pub fn queued_entries(&self) -> impl Iterator<Item = (UserId, &QueueEntry)> + '_ {
+        self.queues.iter().flat_map(|queue| {
+            queue
+                .entries
+                .iter()
+                .map(move |queued| (queue.user_id, &queued.entry))
+        })
+    }
+
+    /// Takes the first entry matching `f` out of whichever queue it's in.
+    pub fn remove_entry(
+        &mut self,
+        mut f: impl FnMut(&QueueEntry) -> bool,
+    ) -> Option<(UserId, QueueEntry)>
+    where
+        QueueEntry: Clone,
+    {
+        let (user_id, index, removed) = self.queues.iter_mut().find_map(|queue| {
+            let index = queue.entries.iter().position(|queued| f(&queued.entry))?;
+            let removed = queue.entries.remove(index)?;
+            Some((queue.user_id, index, removed))
+        })?;
+
+        let entry = removed.entry.clone();
+        self.last_actions.insert(
+            user_id,
+            QueueAction {
+                pushed_count: 0,
+                removed: vec![(index, removed)],
+            },
+        );
+        Some((user_id, entry))
+    }
+
+    /// Takes the most recently queued entry out of a user's queue.
+    pub fn remove_last_user_entry(&mut self, user_id: UserId) -> Option<QueueEntry>
+    where
+        QueueEntry: Clone,
+    {
+        let index = self.user_entries(user_id).count().checked_sub(1)?;
+        self.remove_user_entry(user_id, index)
+    }
+
+    /// Takes the entry at an index out of a user's queue.
+    pub fn remove_user_entry(&mut self, user_id: UserId, index: usize) -> Option<QueueEntry>
+    where
+        QueueEntry: Clone,
+    {
+        let queue = self.get_user_queue_mut(user_id)?;
+        let removed = queue.entries.remove(index)?;
+
+        let entry = removed.entry.clone();
+        self.last_actions.insert(
+            user_id,
+            QueueAction {
+                pushed_count: 0,
+                removed: vec![(index, removed)],
+            },
+        );
+        self.queues.retain(|queue| !queue.entries.is_empty());
+        Some(entry)
+    }
+
+    /// Moves the entry at an index to the front of a user's queue, so it's the next of their
+    /// entries to play. Entries keep the queue positions' times, so moving one doesn't change
+    /// when the user's next turn is.
+    pub fn move_user_entry_to_front(
+        &mut self,
+        user_id: UserId,
+        index: usize,
+    ) -> Option<&QueueEntry> {
+        let queue = self.get_user_queue_mut(user_id)?;
+        let queued_times: Vec<_> = queue
+            .entries
+            .iter()
+            .map(|queued| queued.queued_time)
+            .collect();
+        let moved = queue.entries.remove(index)?;
+        queue.entries.push_front(moved);
+        for (queued, queued_time) in queue.entries.iter_mut().zip(queued_times) {
+            queued.queued_time = queued_time;
+        }
+        queue.entries.front().map(|queued| &queued.entry)
+    }
+
+    /// Takes everything out of a user's queue, returning how many entries were removed. The user
+    /// can't undo this, or undo what they did before it.
+    pub fn clear_user_entries(&mut self, user_id: UserId) -> usize {
+        self.last_actions.remove(&user_id);
+        let removed_count = self.user_entries(user_id).count();
+        self.queues.retain(|queue| queue.user_id != user_id);
+        removed_count
+    }
+
     // User commands:
-    pub fn push_entries(&mut self, user_id: UserId, entries: impl IntoIterator<Item = QueueEntry>) {
+    pub fn push_entries(
+        &mut self,
+        user_id: UserId,
+        entries: impl IntoIterator<Item = QueueEntry>,
+    ) -> PushStatus {
+        self.push_entries_for_action(user_id, entries, false)
+    }
+
+    /// Adds entries to the back of a user's queue as part of the last change they made, like the
+    /// rest of a playlist, so undoing it takes them all out together.
+    pub fn push_more_entries(
+        &mut self,
+        user_id: UserId,
+        entries: impl IntoIterator<Item = QueueEntry>,
+    ) -> PushStatus {
+        self.push_entries_for_action(user_id, entries, true)
+    }
+
+    fn push_entries_for_action(
+        &mut self,
+        user_id: UserId,
+        entries: impl IntoIterator<Item = QueueEntry>,
+        is_continued: bool,
+    ) -> PushStatus {
+        let max_queue_length = self.config.max_user_queue_length;
         let queue = self.create_user_queue(user_id);
-        queue.entries.extend(entries);
+        let queued_count = queue.entries.len();
+        queue
+            .entries
+            .extend(entries.into_iter().map(QueuedEntry::new));
+        let pushed_count = queue.entries.len() - queued_count;
+
+        // A maximum of 0 means users can queue as many entries as they like.
+        if max_queue_length != 0 && queue.entries.len() > max_queue_length {
+            queue.entries.truncate(queued_count);
+            return PushStatus::QueueFull {
+                queued_count,
+                max_count: max_queue_length,
+            };
+        }
+
+        match self.last_actions.get_mut(&user_id) {
+            Some(action) if is_continued => action.pushed_count += pushed_count,
+            _ => {
+                self.last_actions.insert(
+                    user_id,
+                    QueueAction {
+                        pushed_count,
+                        removed: Vec::new(),
+                    },
+                );
+            }
+        }
+        PushStatus::Pushed
+    }
+
+    pub fn push_entry_front(&mut self, user_id: UserId, entry: QueueEntry) {
+        let queue = self.create_user_queue(user_id);
+
+        // The entry takes the place of the one that was at the front, so it shouldn't lose its
+        // spot when entries are played in the order they were queued.
+        let queued_time = match queue.entries.front() {
+            Some(front) => front.queued_time,
+            None => Instant::now(),
+        };
+        queue.entries.push_front(QueuedEntry { entry, queued_time });
     }
 
     pub fn replace_entry(
@@ -168,12 +528,32 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         user_id: UserId,
         maybe_channel_id: Option<ChannelId>,
         entry: QueueEntry,
-    ) -> ReplaceStatus<QueueEntry> {
+    ) -> ReplaceStatus<QueueEntry>
+    where
+        QueueEntry: Clone,
+    {
         let queue = self.create_user_queue(user_id);
         let removed_entry = queue.entries.pop_back();
-        queue.entries.push_back(entry);
+        let removed_index = queue.entries.len();
+        let queued_time = match &removed_entry {
+            Some(removed) => removed.queued_time,
+            None => Instant::now(),
+        };
+        queue.entries.push_back(QueuedEntry { entry, queued_time });
 
-        match removed_entry {
+        let maybe_removed_entry = removed_entry.as_ref().map(|removed| removed.entry.clone());
+        self.last_actions.insert(
+            user_id,
+            QueueAction {
+                pushed_count: 1,
+                removed: removed_entry
+                    .map(|removed| (removed_index, removed))
+                    .into_iter()
+                    .collect(),
+            },
+        );
+
+        match maybe_removed_entry {
             Some(entry) => ReplaceStatus::ReplacedInQueue(entry),
             None => {
                 // If the current channel is playing this user, the current song should be skipped.
@@ -189,7 +569,77 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         }
     }
 
+    /// Reverses the last change a user made to their queue. Entries they added are taken out again,
+    /// unless they've already been played, and entries that were replaced or removed are put back.
+    pub fn undo_last_action(&mut self, user_id: UserId) -> UndoStatus {
+        let Some(action) = self.last_actions.remove(&user_id) else {
+            return UndoStatus::NothingToUndo;
+        };
+
+        let queue = self.create_user_queue(user_id);
+        let removed_count = action.pushed_count.min(queue.entries.len());
+        queue.entries.truncate(queue.entries.len() - removed_count);
+
+        // Entries are put back in the opposite order they were taken out, so each one ends up
+        // where it was. Entries in front of them might have been played since.
+        let restored_count = action.removed.len();
+        for (index, removed) in action.removed.into_iter().rev() {
+            let index = index.min(queue.entries.len());
+            queue.entries.insert(index, removed);
+        }
+        self.queues.retain(|queue| !queue.entries.is_empty());
+
+        if removed_count == 0 && restored_count == 0 {
+            return UndoStatus::NothingToUndo;
+        }
+        UndoStatus::Undone {
+            removed_count,
+            restored_count,
+        }
+    }
+
+    pub fn last_history_entry(&self, channel_id: ChannelId) -> Option<&HistoryEntry> {
+        self.history
+            .get(&channel_id)
+            .and_then(|channel_history| channel_history.back())
+    }
+
+    pub fn history_entries(
+        &self,
+        channel_id: ChannelId,
+    ) -> impl Iterator<Item = &HistoryEntry> + '_ {
+        self.history
+            .get(&channel_id)
+            .into_iter()
+            .flat_map(|channel_history| channel_history.iter().rev())
+    }
+
+    /// Replaces a channel's history with entries loaded from storage, oldest first.
+    pub fn set_history(
+        &mut self,
+        channel_id: ChannelId,
+        entries: impl IntoIterator<Item = HistoryEntry>,
+    ) {
+        let mut channel_history: VecDeque<_> = entries.into_iter().collect();
+        while channel_history.len() > self.config.history_length {
+            channel_history.pop_front();
+        }
+        self.history.insert(channel_id, channel_history);
+    }
+
     // Events:
+    pub fn push_history_entry(&mut self, channel_id: ChannelId, entry: HistoryEntry) {
+        if self.config.history_length == 0 {
+            return;
+        }
+
+        let channel_history = self.history.entry(channel_id).or_default();
+        while channel_history.len() >= self.config.history_length {
+            channel_history.pop_front();
+        }
+        channel_history.push_back(entry);
+    }
+
     pub fn next_channel_entry_finished(
         &mut self,
         cache: &serenity::cache::Cache,
@@ -200,45 +650,17 @@ impl<QueueEntry> GuildModel<QueueEntry> {
             ChannelPlayingState::NotPlaying,
         );
 
-        // Round-robin to the next user
-        let next_user_id = match old_playing_state {
-            ChannelPlayingState::Playing {
-                playing_user_id: user_id,
-                ..
-            } => {
-                let last_playing_queue_index = self
-                    .queues
-                    .iter_mut()
-                    .position(|queue| queue.user_id == user_id);
-                match last_playing_queue_index {
-                    Some(last_playing_index) => {
-                        // Search queues from after the last active one, back around to it again
-                        let queues_iter = self
-                            .queues
-                            .iter()
-                            .skip(last_playing_index + 1)
-                            .chain(self.queues.iter().take(last_playing_index + 1));
-                        find_first_user_in_channel(cache, queues_iter, self.guild_id, channel_id)
-                    }
-                    None => find_first_user_in_channel(
-                        cache,
-                        self.queues.iter(),
-                        self.guild_id,
-                        channel_id,
-                    ),
-                }
-            }
-            _ => find_first_user_in_channel(cache, self.queues.iter(), self.guild_id, channel_id),
-        }?;
-
+        let next_user_id = self.choose_next_user(cache, channel_id, &old_playing_state)?;
         let next_queue = self.get_user_queue_mut(next_user_id)?;
-        let next_entry = next_queue.entries.pop_front()?;
+        let next_entry = next_queue.entries.pop_front()?.entry;
+        next_queue.waiting_since = Instant::now();
 
         // Update channel state to indicate it's playing
         self.create_channel(channel_id).playing = ChannelPlayingState::Playing {
             playing_user_id: next_queue.user_id,
             skip_votes: HashSet::new(),
             stop_votes: HashSet::new(),
+            pause_votes: HashSet::new(),
         };
 
         // Remove any empty queues and channels
@@ -249,6 +671,67 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         Some(next_entry)
     }
 
+    /// The entry that will play next in a channel, if the queues don't change before the current
+    /// one finishes.
+    pub fn peek_channel_entry(
+        &self,
+        cache: &serenity::cache::Cache,
+        channel_id: ChannelId,
+    ) -> Option<&QueueEntry> {
+        let playing_state = self
+            .get_channel_playing_state(channel_id)
+            .unwrap_or(&ChannelPlayingState::NotPlaying);
+        let next_user_id = self.choose_next_user(cache, channel_id, playing_state)?;
+        self.queues
+            .iter()
+            .find(|queue| queue.user_id == next_user_id)?
+            .entries
+            .front()
+            .map(|queued| &queued.entry)
+    }
+
+    /// Chooses whose queue the next entry in a channel comes from, given what's playing there.
+    fn choose_next_user(
+        &self,
+        cache: &serenity::cache::Cache,
+        channel_id: ChannelId,
+        playing_state: &ChannelPlayingState,
+    ) -> Option<UserId> {
+        // Order the queues round-robin, starting after the last active one, and let the
+        // scheduling policy choose between them.
+        let last_playing_index = match playing_state {
+            ChannelPlayingState::Playing {
+                playing_user_id: user_id,
+                ..
+            } => self
+                .queues
+                .iter()
+                .position(|queue| queue.user_id == *user_id),
+            _ => None,
+        };
+        let mut candidates = match last_playing_index {
+            Some(last_playing_index) => find_queue_candidates(
+                cache,
+                self.queues
+                    .iter()
+                    .skip(last_playing_index + 1)
+                    .chain(self.queues.iter().take(last_playing_index + 1)),
+                self.guild_id,
+                channel_id,
+            ),
+            None => find_queue_candidates(cache, self.queues.iter(), self.guild_id, channel_id),
+        };
+        // Only the highest priority queues that are waiting get a chance to play.
+        let top_priority = candidates
+            .iter()
+            .map(|candidate| candidate.priority)
+            .max()?;
+        candidates.retain(|candidate| candidate.priority == top_priority);
+
+        let policy = self.config.scheduling_strategy.policy();
+        Some(candidates[policy.choose(&candidates)?].user_id)
+    }
+
     pub fn next_channel_entry(
         &mut self,
         cache: &serenity::cache::Cache,
@@ -269,32 +752,34 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         vote_type: VoteType,
         channel_id: ChannelId,
         user_id: UserId,
+        can_force: bool,
     ) -> VoteStatus {
-        let votes_required = match vote_type {
-            VoteType::Skip => self.config.skip_votes_required,
-            VoteType::Stop => self.config.stop_votes_required,
-        };
+        let votes_required = self.votes_required(cache, &vote_type, channel_id);
         let guild_id = self.guild_id;
         match self.get_channel_playing_state_mut(channel_id) {
             Some(ChannelPlayingState::Playing {
                 playing_user_id,
                 skip_votes,
                 stop_votes,
-                ..
+                pause_votes,
             }) => {
                 let votes = match vote_type {
                     VoteType::Skip => skip_votes,
                     VoteType::Stop => stop_votes,
+                    VoteType::Pause => pause_votes,
                 };
 
-                // We can skip immediately if this was the user who's currently playing
-                if user_id == *playing_user_id {
+                // We can skip immediately if this was the user who's currently playing, or if the
+                // user is allowed to skip anything.
+                if can_force || user_id == *playing_user_id {
+                    votes.clear();
                     return VoteStatus::Success;
                 }
 
                 // We can skip immediately if the user who played this entry is not in the channel
                 // anymore.
                 if !is_user_in_voice_channel(cache, guild_id, channel_id, user_id) {
+                    votes.clear();
                     return VoteStatus::Success;
                 }
 
@@ -305,6 +790,7 @@ impl<QueueEntry> GuildModel<QueueEntry> {
 
                 // We can succeed immediately if we will have the required number of votes
                 if votes.len() + 1 >= votes_required {
+                    votes.clear();
                     return VoteStatus::Success;
                 }
 
@@ -316,6 +802,49 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         }
     }
 
+    pub fn skip_vote_count(
+        &self,
+        cache: &serenity::cache::Cache,
+        vote_type: VoteType,
+        channel_id: ChannelId,
+    ) -> VoteCount {
+        let required = self.votes_required(cache, &vote_type, channel_id);
+        let votes = match self.get_channel_playing_state(channel_id) {
+            Some(ChannelPlayingState::Playing {
+                skip_votes,
+                stop_votes,
+                pause_votes,
+                ..
+            }) => match vote_type {
+                VoteType::Skip => skip_votes.len(),
+                VoteType::Stop => stop_votes.len(),
+                VoteType::Pause => pause_votes.len(),
+            },
+            _ => 0,
+        };
+        VoteCount { votes, required }
+    }
+
+    fn votes_required(
+        &self,
+        cache: &serenity::cache::Cache,
+        vote_type: &VoteType,
+        channel_id: ChannelId,
+    ) -> usize {
+        let threshold = match vote_type {
+            VoteType::Skip => self.config.skip_votes_required,
+            VoteType::Stop => self.config.stop_votes_required,
+            VoteType::Pause => self.config.pause_votes_required,
+        };
+        let listener_count = match threshold {
+            VoteThreshold::Count(_) => 0,
+            VoteThreshold::Percentage(_) => {
+                count_users_in_voice_channel(cache, self.guild_id, channel_id)
+            }
+        };
+        threshold.votes_required(listener_count)
+    }
+
     fn get_user_queue_mut(&mut self, user_id: UserId) -> Option<&mut Queue<QueueEntry>> {
         self.queues
             .iter_mut()
@@ -335,6 +864,8 @@ impl<QueueEntry> GuildModel<QueueEntry> {
 
         self.queues.push(Queue {
             user_id,
+            priority: self.user_priorities.get(&user_id).copied().unwrap_or(0),
+            waiting_since: Instant::now(),
             entries: VecDeque::new(),
         });
         self.queues.last_mut().unwrap()
@@ -344,6 +875,7 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         self.channels.entry(channel_id).or_insert(ChannelModel {
             playing: ChannelPlayingState::NotPlaying,
             last_action_message: None,
+            session_thread: None,
         })
     }
 
@@ -362,7 +894,8 @@ impl<QueueEntry> GuildModel<QueueEntry> {
             .map(|channel| &mut channel.playing)
     }
 
-    fn get_channel_playing_user(&self, channel_id: ChannelId) -> Option<UserId> {
+    /// Whose queue the entry playing in a channel came from.
+    pub fn get_channel_playing_user(&self, channel_id: ChannelId) -> Option<UserId> {
         match self.get_channel_playing_state(channel_id) {
             Some(ChannelPlayingState::Playing {
                 playing_user_id: user_id,
@@ -372,3 +905,30 @@ impl<QueueEntry> GuildModel<QueueEntry> {
         }
     }
 }
+
+impl<QueueEntry: Serialize> GuildModel<QueueEntry> {
+    pub(crate) fn shared_state(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(&SharedStateRef {
+            message_channel: self.message_channel,
+            queues: &self.queues,
+            channels: &self.channels,
+        })
+    }
+}
+
+impl<QueueEntry: DeserializeOwned> GuildModel<QueueEntry> {
+    /// Replaces the shared parts of the model with what another instance saved. Action messages
+    /// sent by this instance are kept, since they're only known about here.
+    pub(crate) fn set_shared_state(&mut self, data: &[u8]) -> Result<(), serde_json::Error> {
+        let state: SharedState<QueueEntry> = serde_json::from_slice(data)?;
+        let mut old_channels = std::mem::replace(&mut self.channels, state.channels);
+        for (channel_id, old_channel) in old_channels.iter_mut() {
+            if let Some(message) = old_channel.last_action_message.take() {
+                self.create_channel(*channel_id).last_action_message = Some(message);
+            }
+        }
+        self.message_channel = state.message_channel;
+        self.queues = state.queues;
+        Ok(())
+    }
+}
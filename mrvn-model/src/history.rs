@@ -0,0 +1,46 @@
+use crate::{run_blocking, Storage, StorageError};
+use serde::{Deserialize, Serialize};
+use serenity::model::prelude::*;
+use std::sync::Arc;
+
+/// A song that has finished playing in a channel. Only enough information is kept to be able to
+/// resolve the song again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub user_id: UserId,
+    pub title: String,
+    pub url: String,
+    pub ended_time: Timestamp,
+}
+
+/// Saves each channel's history so it's still there after a restart. The history itself lives in
+/// each guild's model, so this only loads it once at startup and saves a channel's history after
+/// it changes.
+pub struct HistoryStore {
+    storage: Arc<dyn Storage>,
+}
+
+impl HistoryStore {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        HistoryStore { storage }
+    }
+
+    /// Each channel's history is newest first.
+    pub fn load(&self) -> Result<Vec<(GuildId, ChannelId, Vec<HistoryEntry>)>, StorageError> {
+        self.storage.load_history()
+    }
+
+    /// Entries are newest first. This writes to storage, so it shouldn't be called with the
+    /// guild locked.
+    pub async fn save_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        entries: Vec<HistoryEntry>,
+    ) -> Result<(), StorageError> {
+        run_blocking(&self.storage, move |storage| {
+            storage.save_channel_history(guild_id, channel_id, &entries)
+        })
+        .await
+    }
+}
@@ -1,7 +1,21 @@
 mod app_model;
 mod config;
+mod favorites;
 mod guild_model;
+mod history;
+mod playlists;
+mod scheduling;
+mod settings;
+mod shared;
+mod storage;
 
 pub use self::app_model::*;
 pub use self::config::*;
+pub use self::favorites::*;
 pub use self::guild_model::*;
+pub use self::history::*;
+pub use self::playlists::*;
+pub use self::scheduling::*;
+pub use self::settings::*;
+pub use self::shared::{LockError, RedisShare, SharedEntry};
+pub use self::storage::*;
@@ -0,0 +1,159 @@
+use crate::{run_blocking, Storage, StorageError};
+use serde::{Deserialize, Serialize};
+use serenity::model::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub title: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Playlist {
+    pub user_id: UserId,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+pub enum SavePlaylistStatus {
+    Created,
+    Replaced,
+    NotOwner(UserId),
+}
+
+pub enum DeletePlaylistStatus {
+    Deleted,
+    NotFound,
+    NotOwner(UserId),
+}
+
+/// Named playlists saved in each guild. Playlists can be loaded by anyone, but only replaced or
+/// deleted by the user who saved them.
+#[derive(Default)]
+pub struct Playlists {
+    guilds: HashMap<GuildId, BTreeMap<String, Playlist>>,
+}
+
+impl Playlists {
+    pub fn get(&self, guild_id: GuildId, name: &str) -> Option<&Playlist> {
+        self.guilds
+            .get(&guild_id)
+            .and_then(|playlists| playlists.get(name))
+    }
+
+    pub fn iter(&self, guild_id: GuildId) -> impl Iterator<Item = (&String, &Playlist)> + '_ {
+        self.guilds.get(&guild_id).into_iter().flatten()
+    }
+
+    pub fn save(
+        &mut self,
+        guild_id: GuildId,
+        name: &str,
+        playlist: Playlist,
+    ) -> SavePlaylistStatus {
+        let playlists = self.guilds.entry(guild_id).or_default();
+        match playlists.get(name) {
+            Some(existing) if existing.user_id != playlist.user_id => {
+                SavePlaylistStatus::NotOwner(existing.user_id)
+            }
+            Some(_) => {
+                playlists.insert(name.to_string(), playlist);
+                SavePlaylistStatus::Replaced
+            }
+            None => {
+                playlists.insert(name.to_string(), playlist);
+                SavePlaylistStatus::Created
+            }
+        }
+    }
+
+    pub fn delete(
+        &mut self,
+        guild_id: GuildId,
+        name: &str,
+        user_id: UserId,
+    ) -> DeletePlaylistStatus {
+        let Some(playlists) = self.guilds.get_mut(&guild_id) else {
+            return DeletePlaylistStatus::NotFound;
+        };
+
+        match playlists.get(name) {
+            Some(existing) if existing.user_id != user_id => {
+                DeletePlaylistStatus::NotOwner(existing.user_id)
+            }
+            Some(_) => {
+                playlists.remove(name);
+                if playlists.is_empty() {
+                    self.guilds.remove(&guild_id);
+                }
+                DeletePlaylistStatus::Deleted
+            }
+            None => DeletePlaylistStatus::NotFound,
+        }
+    }
+}
+
+/// Keeps every guild's playlists in memory, saving each playlist when it's saved or deleted.
+pub struct PlaylistStore {
+    storage: Arc<dyn Storage>,
+    playlists: Mutex<Playlists>,
+}
+
+impl PlaylistStore {
+    pub fn open(storage: Arc<dyn Storage>) -> Result<Self, StorageError> {
+        let mut playlists = Playlists::default();
+        for (guild_id, name, playlist) in storage.load_playlists()? {
+            playlists.save(guild_id, &name, playlist);
+        }
+
+        Ok(PlaylistStore {
+            storage,
+            playlists: Mutex::new(playlists),
+        })
+    }
+
+    pub async fn read<R>(&self, f: impl FnOnce(&Playlists) -> R) -> R {
+        let playlists = self.playlists.lock().await;
+        f(&playlists)
+    }
+
+    pub async fn save(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+        playlist: Playlist,
+    ) -> Result<SavePlaylistStatus, StorageError> {
+        let mut playlists = self.playlists.lock().await;
+        let status = playlists.save(guild_id, name, playlist.clone());
+        if let SavePlaylistStatus::NotOwner(_) = status {
+            return Ok(status);
+        }
+
+        let name = name.to_string();
+        run_blocking(&self.storage, move |storage| {
+            storage.save_playlist(guild_id, &name, &playlist)
+        })
+        .await?;
+        Ok(status)
+    }
+
+    pub async fn delete(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+        user_id: UserId,
+    ) -> Result<DeletePlaylistStatus, StorageError> {
+        let mut playlists = self.playlists.lock().await;
+        let status = playlists.delete(guild_id, name, user_id);
+        if let DeletePlaylistStatus::Deleted = status {
+            let name = name.to_string();
+            run_blocking(&self.storage, move |storage| {
+                storage.delete_playlist(guild_id, &name)
+            })
+            .await?;
+        }
+        Ok(status)
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+/// A user's queue that could have its next entry played in a channel.
+#[derive(Debug, Clone, Copy)]
+pub struct QueueCandidate {
+    pub user_id: UserId,
+    pub priority: u32,
+    /// When an entry from this queue last started playing, or when the queue was created if
+    /// nothing has been played from it yet.
+    pub waiting_since: Instant,
+    /// When the entry at the front of the queue was queued.
+    pub next_entry_queued_time: Instant,
+}
+
+/// Decides whose queue is played from next when a channel finishes an entry.
+pub trait SchedulingPolicy: Send + Sync {
+    /// Chooses the index of the candidate to play next. Candidates are in round-robin order,
+    /// starting with the queue after the one that played last.
+    fn choose(&self, candidates: &[QueueCandidate]) -> Option<usize>;
+}
+
+/// Takes turns between users in the order they started queueing.
+pub struct RoundRobinPolicy;
+
+impl SchedulingPolicy for RoundRobinPolicy {
+    fn choose(&self, candidates: &[QueueCandidate]) -> Option<usize> {
+        if candidates.is_empty() {
+            None
+        } else {
+            Some(0)
+        }
+    }
+}
+
+/// Plays from the user who has gone the longest without hearing one of their entries.
+pub struct LongestWaitingPolicy;
+
+impl SchedulingPolicy for LongestWaitingPolicy {
+    fn choose(&self, candidates: &[QueueCandidate]) -> Option<usize> {
+        candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, candidate)| candidate.waiting_since)
+            .map(|(index, _)| index)
+    }
+}
+
+/// Plays entries in the order they were queued, regardless of who queued them.
+pub struct FifoPolicy;
+
+impl SchedulingPolicy for FifoPolicy {
+    fn choose(&self, candidates: &[QueueCandidate]) -> Option<usize> {
+        candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, candidate)| candidate.next_entry_queued_time)
+            .map(|(index, _)| index)
+    }
+}
+
+/// The scheduling policies that can be selected in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulingStrategy {
+    #[default]
+    RoundRobin,
+    LongestWaiting,
+    Fifo,
+}
+
+impl SchedulingStrategy {
+    pub const ALL: [SchedulingStrategy; 3] = [
+        SchedulingStrategy::RoundRobin,
+        SchedulingStrategy::LongestWaiting,
+        SchedulingStrategy::Fifo,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SchedulingStrategy::RoundRobin => "round_robin",
+            SchedulingStrategy::LongestWaiting => "longest_waiting",
+            SchedulingStrategy::Fifo => "fifo",
+        }
+    }
+
+    pub fn policy(self) -> &'static dyn SchedulingPolicy {
+        match self {
+            SchedulingStrategy::RoundRobin => &RoundRobinPolicy,
+            SchedulingStrategy::LongestWaiting => &LongestWaitingPolicy,
+            SchedulingStrategy::Fifo => &FifoPolicy,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseSchedulingStrategyError;
+
+impl fmt::Display for ParseSchedulingStrategyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown scheduling strategy")
+    }
+}
+
+impl std::error::Error for ParseSchedulingStrategyError {}
+
+impl FromStr for SchedulingStrategy {
+    type Err = ParseSchedulingStrategyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SchedulingStrategy::ALL
+            .into_iter()
+            .find(|strategy| strategy.name() == s)
+            .ok_or(ParseSchedulingStrategyError)
+    }
+}
+
+impl fmt::Display for SchedulingStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{run_blocking, SchedulingStrategy, Storage, StorageError, VoteThreshold};
+use serde::{Deserialize, Serialize};
+use serenity::model::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Settings that have been changed at runtime for a guild. Anything left unset falls back to the
+/// global config.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_votes_required: Option<VoteThreshold>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_votes_required: Option<VoteThreshold>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_votes_required: Option<VoteThreshold>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disconnect_min_inactive_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_disconnect_when_alone: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_embed_color: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_embed_color: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_embed_color: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduling_strategy: Option<SchedulingStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autoplay: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub always_on: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_playlist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follow_mode: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_topic: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic_channel: Option<ChannelId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub announcements_channel: Option<ChannelId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_threads: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_announcements: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loudness_normalization: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trim_silence: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dj_roles: Vec<RoleId>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub priority_roles: HashMap<RoleId, u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocklist: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub banned_users: Vec<UserId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub disabled_commands: Vec<String>,
+}
+
+impl GuildSettings {
+    /// Finds the blocklist entry that matches a song, if there is one. Entries can be URLs, video
+    /// IDs or keywords, so a song matches if its URL or title contains the entry.
+    pub fn find_blocklist_entry(&self, url: &str, title: &str) -> Option<&str> {
+        let url = url.to_lowercase();
+        let title = title.to_lowercase();
+        self.blocklist
+            .iter()
+            .find(|entry| {
+                let entry = entry.to_lowercase();
+                url.contains(&entry) || title.contains(&entry)
+            })
+            .map(|entry| entry.as_str())
+    }
+}
+
+#[derive(Default)]
+pub struct Settings {
+    guilds: HashMap<GuildId, GuildSettings>,
+}
+
+impl Settings {
+    pub fn get(&self, guild_id: GuildId) -> Option<&GuildSettings> {
+        self.guilds.get(&guild_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&GuildId, &GuildSettings)> {
+        self.guilds.iter()
+    }
+
+    pub fn get_mut(&mut self, guild_id: GuildId) -> &mut GuildSettings {
+        self.guilds.entry(guild_id).or_default()
+    }
+}
+
+/// Keeps every guild's settings in memory, saving a guild's settings whenever they change.
+pub struct SettingsStore {
+    storage: Arc<dyn Storage>,
+    settings: Mutex<Settings>,
+}
+
+impl SettingsStore {
+    pub fn open(storage: Arc<dyn Storage>) -> Result<Self, StorageError> {
+        let mut settings = Settings::default();
+        for (guild_id, guild_settings) in storage.load_guild_settings()? {
+            *settings.get_mut(guild_id) = guild_settings;
+        }
+        for (guild_id, user_id) in storage.load_bans()? {
+            settings.get_mut(guild_id).banned_users.push(user_id);
+        }
+
+        Ok(SettingsStore {
+            storage,
+            settings: Mutex::new(settings),
+        })
+    }
+
+    pub async fn read<R>(&self, f: impl FnOnce(&Settings) -> R) -> R {
+        let settings = self.settings.lock().await;
+        f(&settings)
+    }
+
+    /// Changes a guild's settings and saves them. Bans that were added or removed are saved on
+    /// their own.
+    pub async fn update_guild<R>(
+        &self,
+        guild_id: GuildId,
+        f: impl FnOnce(&mut GuildSettings) -> R,
+    ) -> Result<R, StorageError> {
+        let mut settings = self.settings.lock().await;
+        let guild_settings = settings.get_mut(guild_id);
+        let old_banned_users = guild_settings.banned_users.clone();
+        let result = f(guild_settings);
+
+        let added_bans: Vec<_> = (guild_settings.banned_users.iter())
+            .filter(|user_id| !old_banned_users.contains(user_id))
+            .copied()
+            .collect();
+        let removed_bans: Vec<_> = (old_banned_users.iter())
+            .filter(|user_id| !guild_settings.banned_users.contains(user_id))
+            .copied()
+            .collect();
+        let saved_settings = GuildSettings {
+            banned_users: Vec::new(),
+            ..guild_settings.clone()
+        };
+        run_blocking(&self.storage, move |storage| {
+            storage.save_guild_settings(guild_id, &saved_settings)?;
+            for user_id in added_bans {
+                storage.add_ban(guild_id, user_id)?;
+            }
+            for user_id in removed_bans {
+                storage.remove_ban(guild_id, user_id)?;
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,233 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serenity::model::prelude::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long another instance can hold a guild's lock before it's assumed to have gone away.
+const LOCK_EXPIRY: Duration = Duration::from_secs(60);
+/// How often a held lock is kept from expiring. This is well within the expiry, so a renewal that
+/// fails once doesn't lose the lock.
+const LOCK_RENEW_INTERVAL: Duration = Duration::from_secs(20);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+/// How long to wait for another instance to give up a guild's lock. This is shorter than the
+/// expiry, so a command isn't held up for a minute by an instance that's stuck.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a shared guild model couldn't be locked. The local model could be out of date, so it
+/// shouldn't be used instead.
+#[derive(Debug)]
+pub enum LockError {
+    Redis(redis::RedisError),
+    TimedOut,
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LockError::Redis(err) => err.fmt(f),
+            LockError::TimedOut => write!(f, "Timed out waiting for another instance"),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+/// Entries that can be sent between instances of the bot.
+pub trait SharedEntry: Serialize + DeserializeOwned + Send + 'static {}
+
+impl<T: Serialize + DeserializeOwned + Send + 'static> SharedEntry for T {}
+
+/// Shares guild models with other instances of the bot through Redis, so they see the same queues
+/// and votes. Each guild is locked in Redis while one instance is using its model, the same way it
+/// is locked in memory.
+#[derive(Clone)]
+pub struct RedisShare {
+    connection: redis::aio::ConnectionManager,
+    instance_id: String,
+}
+
+impl RedisShare {
+    pub async fn connect(url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(url)?;
+        let connection = redis::aio::ConnectionManager::new(client).await?;
+        let started_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Ok(RedisShare {
+            connection,
+            instance_id: format!("{}-{}", std::process::id(), started_nanos),
+        })
+    }
+
+    fn lock_key(guild_id: GuildId) -> String {
+        format!("mrvn:guild:{}:lock", guild_id)
+    }
+
+    fn state_key(guild_id: GuildId) -> String {
+        format!("mrvn:guild:{}:state", guild_id)
+    }
+
+    /// Waits until this instance holds a guild's lock. Gives up if another instance holds it for
+    /// longer than [`LOCK_TIMEOUT`].
+    pub(crate) async fn lock(&self, guild_id: GuildId) -> Result<SharedLock, LockError> {
+        static NEXT_LOCK: AtomicU64 = AtomicU64::new(0);
+        let token = format!(
+            "{}-{}",
+            self.instance_id,
+            NEXT_LOCK.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut connection = self.connection.clone();
+        let started_time = tokio::time::Instant::now();
+        loop {
+            let locked: bool = redis::cmd("SET")
+                .arg(Self::lock_key(guild_id))
+                .arg(&token)
+                .arg("NX")
+                .arg("PX")
+                .arg(LOCK_EXPIRY.as_millis() as u64)
+                .query_async::<Option<String>>(&mut connection)
+                .await
+                .map_err(LockError::Redis)?
+                .is_some();
+            if locked {
+                return Ok(SharedLock {
+                    share: self.clone(),
+                    guild_id,
+                    renewal: self.renew_lock(guild_id, token.clone()),
+                    token,
+                });
+            }
+            if started_time.elapsed() >= LOCK_TIMEOUT {
+                return Err(LockError::TimedOut);
+            }
+            tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+        }
+    }
+
+    pub(crate) async fn load(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<Option<Vec<u8>>, redis::RedisError> {
+        let mut connection = self.connection.clone();
+        redis::cmd("GET")
+            .arg(Self::state_key(guild_id))
+            .query_async(&mut connection)
+            .await
+    }
+
+    /// Keeps a held lock from expiring until the returned task is aborted, or until the lock is
+    /// found to have been lost.
+    fn renew_lock(&self, guild_id: GuildId, token: String) -> tokio::task::JoinHandle<()> {
+        let mut connection = self.connection.clone();
+        tokio::task::spawn(async move {
+            loop {
+                tokio::time::sleep(LOCK_RENEW_INTERVAL).await;
+                let renew_res = renew_script()
+                    .key(Self::lock_key(guild_id))
+                    .arg(&token)
+                    .arg(LOCK_EXPIRY.as_millis() as u64)
+                    .invoke_async::<bool>(&mut connection)
+                    .await;
+                match renew_res {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        tracing::error!("Lost the shared lock on guild {}", guild_id);
+                        return;
+                    }
+                    Err(why) => tracing::warn!("Unable to renew shared guild lock: {}", why),
+                }
+            }
+        })
+    }
+}
+
+/// A guild's lock in Redis, held by this instance. It's kept from expiring until it's given up.
+pub(crate) struct SharedLock {
+    share: RedisShare,
+    guild_id: GuildId,
+    token: String,
+    renewal: tokio::task::JoinHandle<()>,
+}
+
+impl SharedLock {
+    /// Saves a guild's state and gives up its lock, unless the lock has expired and been taken by
+    /// someone else in the meantime. Without any data, the saved state is left as it was.
+    pub(crate) async fn save_and_unlock(
+        self,
+        maybe_data: Option<Vec<u8>>,
+    ) -> Result<(), redis::RedisError> {
+        self.renewal.abort();
+        save_and_unlock_script()
+            .key(RedisShare::lock_key(self.guild_id))
+            .key(RedisShare::state_key(self.guild_id))
+            .arg(&self.token)
+            .arg(maybe_data.unwrap_or_default())
+            .invoke_async(&mut self.share.connection.clone())
+            .await
+    }
+}
+
+impl Drop for SharedLock {
+    fn drop(&mut self) {
+        // Locks that are dropped without being given up are left to expire
+        self.renewal.abort();
+    }
+}
+
+fn renew_script() -> &'static redis::Script {
+    static SCRIPT: std::sync::OnceLock<redis::Script> = std::sync::OnceLock::new();
+    SCRIPT.get_or_init(|| {
+        redis::Script::new(
+            r"
+            if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+                return 0
+            end
+            return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+            ",
+        )
+    })
+}
+
+fn save_and_unlock_script() -> &'static redis::Script {
+    static SCRIPT: std::sync::OnceLock<redis::Script> = std::sync::OnceLock::new();
+    SCRIPT.get_or_init(|| {
+        redis::Script::new(
+            r"
+            if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+                return 0
+            end
+            if ARGV[2] ~= '' then
+                redis.call('SET', KEYS[2], ARGV[2])
+            end
+            return redis.call('DEL', KEYS[1])
+            ",
+        )
+    })
+}
+
+/// Turns instants into Unix timestamps and back, so how long someone has been waiting survives
+/// being sent to another instance.
+pub(crate) mod instant_serde {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: Serializer>(instant: &Instant, serializer: S) -> Result<S::Ok, S::Error> {
+        let time = SystemTime::now() - instant.elapsed();
+        let millis = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        millis.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Instant, D::Error> {
+        let millis = u64::deserialize(deserializer)?;
+        let age = SystemTime::now()
+            .duration_since(UNIX_EPOCH + Duration::from_millis(millis))
+            .unwrap_or_default();
+        Ok(Instant::now().checked_sub(age).unwrap_or_else(Instant::now))
+    }
+}
@@ -0,0 +1,939 @@
+use crate::{GuildSettings, HistoryEntry, Playlist, PlaylistEntry};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serenity::model::prelude::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    Sled(sled::Error),
+    Sqlite(rusqlite::Error),
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            StorageError::Io(err) => err.fmt(f),
+            StorageError::Parse(err) => err.fmt(f),
+            StorageError::Sled(err) => err.fmt(f),
+            StorageError::Sqlite(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// Where everything the bot keeps between restarts is saved. Each guild's settings, each ban,
+/// playlist and favorite, and each channel's history is saved and removed on its own, so a change
+/// only writes what it changed.
+///
+/// Everything is loaded once at startup and kept in memory by the stores that use this, like
+/// [`crate::SettingsStore`].
+pub trait Storage: Send + Sync {
+    fn load_guild_settings(&self) -> Result<Vec<(GuildId, GuildSettings)>, StorageError>;
+    /// Bans are saved on their own, so `banned_users` is left empty here.
+    fn save_guild_settings(
+        &self,
+        guild_id: GuildId,
+        settings: &GuildSettings,
+    ) -> Result<(), StorageError>;
+
+    fn load_bans(&self) -> Result<Vec<(GuildId, UserId)>, StorageError>;
+    fn add_ban(&self, guild_id: GuildId, user_id: UserId) -> Result<(), StorageError>;
+    fn remove_ban(&self, guild_id: GuildId, user_id: UserId) -> Result<(), StorageError>;
+
+    fn load_playlists(&self) -> Result<Vec<(GuildId, String, Playlist)>, StorageError>;
+    /// Saves a playlist, replacing any with the same name in the guild.
+    fn save_playlist(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+        playlist: &Playlist,
+    ) -> Result<(), StorageError>;
+    fn delete_playlist(&self, guild_id: GuildId, name: &str) -> Result<(), StorageError>;
+
+    fn load_favorites(&self) -> Result<Vec<(UserId, String, PlaylistEntry)>, StorageError>;
+    fn save_favorite(
+        &self,
+        user_id: UserId,
+        name: &str,
+        entry: &PlaylistEntry,
+    ) -> Result<(), StorageError>;
+    fn delete_favorite(&self, user_id: UserId, name: &str) -> Result<(), StorageError>;
+
+    /// Each channel's history is newest first, like [`crate::GuildModel::history_entries`].
+    fn load_history(&self) -> Result<Vec<(GuildId, ChannelId, Vec<HistoryEntry>)>, StorageError>;
+    /// Replaces a channel's history, removing it if there are no entries.
+    fn save_channel_history(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        entries: &[HistoryEntry],
+    ) -> Result<(), StorageError>;
+}
+
+/// Runs storage operations on a blocking thread, since the backends read and write files.
+pub async fn run_blocking<R: Send + 'static>(
+    storage: &Arc<dyn Storage>,
+    f: impl FnOnce(&dyn Storage) -> Result<R, StorageError> + Send + 'static,
+) -> Result<R, StorageError> {
+    let storage = storage.clone();
+    tokio::task::spawn_blocking(move || f(storage.as_ref()))
+        .await
+        .map_err(|why| StorageError::Io(std::io::Error::other(why)))?
+}
+
+/// Which kind of storage to keep data in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// A JSON file for each guild's settings, bans, playlists and history, and each user's
+    /// favorites.
+    Json,
+    Sled,
+    Sqlite,
+}
+
+impl StorageBackend {
+    /// Opens the storage in a data directory, creating it if it doesn't exist yet.
+    pub fn open(self, data_path: &Path) -> Result<Arc<dyn Storage>, StorageError> {
+        Ok(match self {
+            StorageBackend::Json => Arc::new(JsonFileStorage::open(data_path)?),
+            StorageBackend::Sled => Arc::new(SledStorage::open(&data_path.join("mrvn.sled"))?),
+            StorageBackend::Sqlite => {
+                std::fs::create_dir_all(data_path).map_err(StorageError::Io)?;
+                Arc::new(SqliteStorage::open(&data_path.join("mrvn.sqlite"))?)
+            }
+        })
+    }
+}
+
+/// Keeps a JSON file for each guild or user in a directory for each kind of data, like
+/// `settings/<guild id>.json`.
+pub struct JsonFileStorage {
+    path: PathBuf,
+    /// Held while a file is read, changed and written back, so two changes can't race.
+    write_lock: std::sync::Mutex<()>,
+}
+
+impl JsonFileStorage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Ok(JsonFileStorage {
+            path: path.as_ref().to_path_buf(),
+            write_lock: std::sync::Mutex::new(()),
+        })
+    }
+
+    fn file_path(&self, kind: &str, id: u64) -> PathBuf {
+        self.path.join(kind).join(format!("{}.json", id))
+    }
+
+    /// Reads every file of a kind, along with the ID it's named after.
+    fn load_kind<T: DeserializeOwned>(&self, kind: &str) -> Result<Vec<(u64, T)>, StorageError> {
+        let entries = match std::fs::read_dir(self.path.join(kind)) {
+            Ok(entries) => entries,
+            Err(why) if why.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(why) => return Err(StorageError::Io(why)),
+        };
+
+        let mut values = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(StorageError::Io)?.path();
+            let maybe_id = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| file_name.strip_suffix(".json"))
+                .and_then(|id| id.parse().ok());
+            let Some(id) = maybe_id else {
+                continue;
+            };
+            if let Some(value) = read_json(&path)? {
+                values.push((id, value));
+            }
+        }
+        Ok(values)
+    }
+
+    /// Changes the collection in a file, removing the file if it's left empty.
+    fn update_file<K: Ord + Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(
+        &self,
+        kind: &str,
+        id: u64,
+        f: impl FnOnce(&mut BTreeMap<K, V>),
+    ) -> Result<(), StorageError> {
+        let _write_guard = self.write_lock.lock().unwrap();
+        let path = self.file_path(kind, id);
+        let mut collection = read_json(&path)?.unwrap_or_default();
+        f(&mut collection);
+        if collection.is_empty() {
+            match std::fs::remove_file(&path) {
+                Err(why) if why.kind() != std::io::ErrorKind::NotFound => {
+                    Err(StorageError::Io(why))
+                }
+                _ => Ok(()),
+            }
+        } else {
+            write_json(&path, &collection)
+        }
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, StorageError> {
+    match std::fs::read(path) {
+        Ok(data) => Ok(Some(
+            serde_json::from_slice(&data).map_err(StorageError::Parse)?,
+        )),
+        Err(why) if why.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(why) => Err(StorageError::Io(why)),
+    }
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), StorageError> {
+    // Write to a temporary file first so a failed write can't corrupt the existing data.
+    let data = serde_json::to_vec_pretty(value).map_err(StorageError::Parse)?;
+    let temp_path = path.with_extension("tmp");
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(StorageError::Io)?;
+    }
+    std::fs::write(&temp_path, data).map_err(StorageError::Io)?;
+    std::fs::rename(&temp_path, path).map_err(StorageError::Io)
+}
+
+impl Storage for JsonFileStorage {
+    fn load_guild_settings(&self) -> Result<Vec<(GuildId, GuildSettings)>, StorageError> {
+        let settings = self.load_kind("settings")?;
+        Ok(settings
+            .into_iter()
+            .map(|(guild_id, settings)| (GuildId::new(guild_id), settings))
+            .collect())
+    }
+
+    fn save_guild_settings(
+        &self,
+        guild_id: GuildId,
+        settings: &GuildSettings,
+    ) -> Result<(), StorageError> {
+        write_json(&self.file_path("settings", guild_id.get()), settings)
+    }
+
+    fn load_bans(&self) -> Result<Vec<(GuildId, UserId)>, StorageError> {
+        let bans: Vec<(u64, Vec<UserId>)> = self.load_kind("bans")?;
+        Ok(bans
+            .into_iter()
+            .flat_map(|(guild_id, user_ids)| {
+                let guild_id = GuildId::new(guild_id);
+                user_ids.into_iter().map(move |user_id| (guild_id, user_id))
+            })
+            .collect())
+    }
+
+    fn add_ban(&self, guild_id: GuildId, user_id: UserId) -> Result<(), StorageError> {
+        let _write_guard = self.write_lock.lock().unwrap();
+        let path = self.file_path("bans", guild_id.get());
+        let mut user_ids: Vec<UserId> = read_json(&path)?.unwrap_or_default();
+        if !user_ids.contains(&user_id) {
+            user_ids.push(user_id);
+        }
+        write_json(&path, &user_ids)
+    }
+
+    fn remove_ban(&self, guild_id: GuildId, user_id: UserId) -> Result<(), StorageError> {
+        let _write_guard = self.write_lock.lock().unwrap();
+        let path = self.file_path("bans", guild_id.get());
+        let Some(mut user_ids) = read_json::<Vec<UserId>>(&path)? else {
+            return Ok(());
+        };
+        user_ids.retain(|banned_user_id| *banned_user_id != user_id);
+        if user_ids.is_empty() {
+            std::fs::remove_file(&path).map_err(StorageError::Io)
+        } else {
+            write_json(&path, &user_ids)
+        }
+    }
+
+    fn load_playlists(&self) -> Result<Vec<(GuildId, String, Playlist)>, StorageError> {
+        let playlists: Vec<(u64, BTreeMap<String, Playlist>)> = self.load_kind("playlists")?;
+        Ok(playlists
+            .into_iter()
+            .flat_map(|(guild_id, playlists)| {
+                let guild_id = GuildId::new(guild_id);
+                playlists
+                    .into_iter()
+                    .map(move |(name, playlist)| (guild_id, name, playlist))
+            })
+            .collect())
+    }
+
+    fn save_playlist(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+        playlist: &Playlist,
+    ) -> Result<(), StorageError> {
+        self.update_file("playlists", guild_id.get(), |playlists| {
+            playlists.insert(name.to_string(), playlist.clone());
+        })
+    }
+
+    fn delete_playlist(&self, guild_id: GuildId, name: &str) -> Result<(), StorageError> {
+        self.update_file::<String, Playlist>("playlists", guild_id.get(), |playlists| {
+            playlists.remove(name);
+        })
+    }
+
+    fn load_favorites(&self) -> Result<Vec<(UserId, String, PlaylistEntry)>, StorageError> {
+        let favorites: Vec<(u64, BTreeMap<String, PlaylistEntry>)> = self.load_kind("favorites")?;
+        Ok(favorites
+            .into_iter()
+            .flat_map(|(user_id, favorites)| {
+                let user_id = UserId::new(user_id);
+                favorites
+                    .into_iter()
+                    .map(move |(name, entry)| (user_id, name, entry))
+            })
+            .collect())
+    }
+
+    fn save_favorite(
+        &self,
+        user_id: UserId,
+        name: &str,
+        entry: &PlaylistEntry,
+    ) -> Result<(), StorageError> {
+        self.update_file("favorites", user_id.get(), |favorites| {
+            favorites.insert(name.to_string(), entry.clone());
+        })
+    }
+
+    fn delete_favorite(&self, user_id: UserId, name: &str) -> Result<(), StorageError> {
+        self.update_file::<String, PlaylistEntry>("favorites", user_id.get(), |favorites| {
+            favorites.remove(name);
+        })
+    }
+
+    fn load_history(&self) -> Result<Vec<(GuildId, ChannelId, Vec<HistoryEntry>)>, StorageError> {
+        let history: Vec<(u64, BTreeMap<ChannelId, Vec<HistoryEntry>>)> =
+            self.load_kind("history")?;
+        Ok(history
+            .into_iter()
+            .flat_map(|(guild_id, channels)| {
+                let guild_id = GuildId::new(guild_id);
+                channels
+                    .into_iter()
+                    .map(move |(channel_id, entries)| (guild_id, channel_id, entries))
+            })
+            .collect())
+    }
+
+    fn save_channel_history(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        entries: &[HistoryEntry],
+    ) -> Result<(), StorageError> {
+        self.update_file("history", guild_id.get(), |channels| {
+            if entries.is_empty() {
+                channels.remove(&channel_id);
+            } else {
+                channels.insert(channel_id, entries.to_vec());
+            }
+        })
+    }
+}
+
+/// Keeps each kind of data in its own tree, keyed by the IDs it belongs to, like a guild ID
+/// followed by a playlist name.
+pub struct SledStorage {
+    db: sled::Db,
+    guild_settings: sled::Tree,
+    bans: sled::Tree,
+    playlists: sled::Tree,
+    favorites: sled::Tree,
+    history: sled::Tree,
+}
+
+impl SledStorage {
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        let db = sled::open(path).map_err(StorageError::Sled)?;
+        let open_tree = |name: &str| db.open_tree(name).map_err(StorageError::Sled);
+        Ok(SledStorage {
+            guild_settings: open_tree("guild_settings")?,
+            bans: open_tree("bans")?,
+            playlists: open_tree("playlists")?,
+            favorites: open_tree("favorites")?,
+            history: open_tree("history")?,
+            db,
+        })
+    }
+
+    fn flush(&self) -> Result<(), StorageError> {
+        self.db.flush().map_err(StorageError::Sled)?;
+        Ok(())
+    }
+}
+
+/// Makes a key from an ID followed by anything else, so keys sort by ID first.
+fn sled_key(id: u64, rest: &[u8]) -> Vec<u8> {
+    let mut key = id.to_be_bytes().to_vec();
+    key.extend_from_slice(rest);
+    key
+}
+
+fn split_sled_key(key: &[u8]) -> Option<(u64, &[u8])> {
+    let (id_bytes, rest) = key.split_first_chunk::<8>()?;
+    Some((u64::from_be_bytes(*id_bytes), rest))
+}
+
+/// Reads every entry in a tree, along with the ID and the rest of its key.
+fn load_sled_tree<T: DeserializeOwned>(
+    tree: &sled::Tree,
+) -> Result<Vec<(u64, Vec<u8>, T)>, StorageError> {
+    let mut values = Vec::new();
+    for item in tree.iter() {
+        let (key, data) = item.map_err(StorageError::Sled)?;
+        let Some((id, rest)) = split_sled_key(&key) else {
+            continue;
+        };
+        let value = serde_json::from_slice(&data).map_err(StorageError::Parse)?;
+        values.push((id, rest.to_vec(), value));
+    }
+    Ok(values)
+}
+
+impl Storage for SledStorage {
+    fn load_guild_settings(&self) -> Result<Vec<(GuildId, GuildSettings)>, StorageError> {
+        Ok(load_sled_tree(&self.guild_settings)?
+            .into_iter()
+            .map(|(guild_id, _, settings)| (GuildId::new(guild_id), settings))
+            .collect())
+    }
+
+    fn save_guild_settings(
+        &self,
+        guild_id: GuildId,
+        settings: &GuildSettings,
+    ) -> Result<(), StorageError> {
+        let data = serde_json::to_vec(settings).map_err(StorageError::Parse)?;
+        self.guild_settings
+            .insert(sled_key(guild_id.get(), &[]), data)
+            .map_err(StorageError::Sled)?;
+        self.flush()
+    }
+
+    fn load_bans(&self) -> Result<Vec<(GuildId, UserId)>, StorageError> {
+        let mut bans = Vec::new();
+        for item in self.bans.iter() {
+            let (key, _) = item.map_err(StorageError::Sled)?;
+            let maybe_ids = split_sled_key(&key)
+                .and_then(|(guild_id, rest)| Some((guild_id, split_sled_key(rest)?.0)));
+            if let Some((guild_id, user_id)) = maybe_ids {
+                bans.push((GuildId::new(guild_id), UserId::new(user_id)));
+            }
+        }
+        Ok(bans)
+    }
+
+    fn add_ban(&self, guild_id: GuildId, user_id: UserId) -> Result<(), StorageError> {
+        let key = sled_key(guild_id.get(), &user_id.get().to_be_bytes());
+        self.bans.insert(key, &[]).map_err(StorageError::Sled)?;
+        self.flush()
+    }
+
+    fn remove_ban(&self, guild_id: GuildId, user_id: UserId) -> Result<(), StorageError> {
+        let key = sled_key(guild_id.get(), &user_id.get().to_be_bytes());
+        self.bans.remove(key).map_err(StorageError::Sled)?;
+        self.flush()
+    }
+
+    fn load_playlists(&self) -> Result<Vec<(GuildId, String, Playlist)>, StorageError> {
+        Ok(load_sled_tree(&self.playlists)?
+            .into_iter()
+            .map(|(guild_id, name, playlist)| {
+                let name = String::from_utf8_lossy(&name).into_owned();
+                (GuildId::new(guild_id), name, playlist)
+            })
+            .collect())
+    }
+
+    fn save_playlist(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+        playlist: &Playlist,
+    ) -> Result<(), StorageError> {
+        let data = serde_json::to_vec(playlist).map_err(StorageError::Parse)?;
+        self.playlists
+            .insert(sled_key(guild_id.get(), name.as_bytes()), data)
+            .map_err(StorageError::Sled)?;
+        self.flush()
+    }
+
+    fn delete_playlist(&self, guild_id: GuildId, name: &str) -> Result<(), StorageError> {
+        self.playlists
+            .remove(sled_key(guild_id.get(), name.as_bytes()))
+            .map_err(StorageError::Sled)?;
+        self.flush()
+    }
+
+    fn load_favorites(&self) -> Result<Vec<(UserId, String, PlaylistEntry)>, StorageError> {
+        Ok(load_sled_tree(&self.favorites)?
+            .into_iter()
+            .map(|(user_id, name, entry)| {
+                let name = String::from_utf8_lossy(&name).into_owned();
+                (UserId::new(user_id), name, entry)
+            })
+            .collect())
+    }
+
+    fn save_favorite(
+        &self,
+        user_id: UserId,
+        name: &str,
+        entry: &PlaylistEntry,
+    ) -> Result<(), StorageError> {
+        let data = serde_json::to_vec(entry).map_err(StorageError::Parse)?;
+        self.favorites
+            .insert(sled_key(user_id.get(), name.as_bytes()), data)
+            .map_err(StorageError::Sled)?;
+        self.flush()
+    }
+
+    fn delete_favorite(&self, user_id: UserId, name: &str) -> Result<(), StorageError> {
+        self.favorites
+            .remove(sled_key(user_id.get(), name.as_bytes()))
+            .map_err(StorageError::Sled)?;
+        self.flush()
+    }
+
+    fn load_history(&self) -> Result<Vec<(GuildId, ChannelId, Vec<HistoryEntry>)>, StorageError> {
+        let mut history = Vec::new();
+        for (guild_id, rest, entries) in load_sled_tree(&self.history)? {
+            if let Some((channel_id, _)) = split_sled_key(&rest) {
+                history.push((GuildId::new(guild_id), ChannelId::new(channel_id), entries));
+            }
+        }
+        Ok(history)
+    }
+
+    fn save_channel_history(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        entries: &[HistoryEntry],
+    ) -> Result<(), StorageError> {
+        let key = sled_key(guild_id.get(), &channel_id.get().to_be_bytes());
+        if entries.is_empty() {
+            self.history.remove(key).map_err(StorageError::Sled)?;
+        } else {
+            let data = serde_json::to_vec(entries).map_err(StorageError::Parse)?;
+            self.history.insert(key, data).map_err(StorageError::Sled)?;
+        }
+        self.flush()
+    }
+}
+
+/// Keeps each kind of data in its own table. Guild settings are stored as JSON, since most of
+/// them are usually left unset.
+pub struct SqliteStorage {
+    connection: std::sync::Mutex<rusqlite::Connection>,
+}
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS guild_settings (
+        guild_id INTEGER PRIMARY KEY,
+        settings TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS bans (
+        guild_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, user_id)
+    );
+    CREATE TABLE IF NOT EXISTS playlists (
+        guild_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        user_id INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, name)
+    );
+    CREATE TABLE IF NOT EXISTS playlist_entries (
+        guild_id INTEGER NOT NULL,
+        playlist_name TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        url TEXT NOT NULL,
+        PRIMARY KEY (guild_id, playlist_name, position)
+    );
+    CREATE TABLE IF NOT EXISTS favorites (
+        user_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        title TEXT NOT NULL,
+        url TEXT NOT NULL,
+        PRIMARY KEY (user_id, name)
+    );
+    CREATE TABLE IF NOT EXISTS history (
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        url TEXT NOT NULL,
+        ended_time INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, channel_id, position)
+    );
+";
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        let connection = rusqlite::Connection::open(path).map_err(StorageError::Sqlite)?;
+        connection
+            .execute_batch(SQLITE_SCHEMA)
+            .map_err(StorageError::Sqlite)?;
+        Ok(SqliteStorage {
+            connection: std::sync::Mutex::new(connection),
+        })
+    }
+}
+
+// SQLite only has signed integers, but Discord IDs always fit in them.
+fn to_sql_id(id: u64) -> i64 {
+    id as i64
+}
+
+fn from_sql_id(id: i64) -> u64 {
+    id as u64
+}
+
+impl Storage for SqliteStorage {
+    fn load_guild_settings(&self) -> Result<Vec<(GuildId, GuildSettings)>, StorageError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT guild_id, settings FROM guild_settings")
+            .map_err(StorageError::Sqlite)?;
+        let rows = statement
+            .query_map((), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(StorageError::Sqlite)?;
+
+        let mut settings = Vec::new();
+        for row in rows {
+            let (guild_id, data) = row.map_err(StorageError::Sqlite)?;
+            let guild_settings = serde_json::from_str(&data).map_err(StorageError::Parse)?;
+            settings.push((GuildId::new(from_sql_id(guild_id)), guild_settings));
+        }
+        Ok(settings)
+    }
+
+    fn save_guild_settings(
+        &self,
+        guild_id: GuildId,
+        settings: &GuildSettings,
+    ) -> Result<(), StorageError> {
+        let data = serde_json::to_string(settings).map_err(StorageError::Parse)?;
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT INTO guild_settings (guild_id, settings) VALUES (?1, ?2) \
+                ON CONFLICT (guild_id) DO UPDATE SET settings = excluded.settings",
+                (to_sql_id(guild_id.get()), data),
+            )
+            .map_err(StorageError::Sqlite)?;
+        Ok(())
+    }
+
+    fn load_bans(&self) -> Result<Vec<(GuildId, UserId)>, StorageError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT guild_id, user_id FROM bans")
+            .map_err(StorageError::Sqlite)?;
+        let rows = statement
+            .query_map((), |row| {
+                Ok((
+                    GuildId::new(from_sql_id(row.get(0)?)),
+                    UserId::new(from_sql_id(row.get(1)?)),
+                ))
+            })
+            .map_err(StorageError::Sqlite)?;
+        rows.collect::<Result<_, _>>().map_err(StorageError::Sqlite)
+    }
+
+    fn add_ban(&self, guild_id: GuildId, user_id: UserId) -> Result<(), StorageError> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT OR IGNORE INTO bans (guild_id, user_id) VALUES (?1, ?2)",
+                (to_sql_id(guild_id.get()), to_sql_id(user_id.get())),
+            )
+            .map_err(StorageError::Sqlite)?;
+        Ok(())
+    }
+
+    fn remove_ban(&self, guild_id: GuildId, user_id: UserId) -> Result<(), StorageError> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "DELETE FROM bans WHERE guild_id = ?1 AND user_id = ?2",
+                (to_sql_id(guild_id.get()), to_sql_id(user_id.get())),
+            )
+            .map_err(StorageError::Sqlite)?;
+        Ok(())
+    }
+
+    fn load_playlists(&self) -> Result<Vec<(GuildId, String, Playlist)>, StorageError> {
+        let connection = self.connection.lock().unwrap();
+        let mut playlists: BTreeMap<(i64, String), Playlist> = BTreeMap::new();
+
+        let mut statement = connection
+            .prepare("SELECT guild_id, name, user_id FROM playlists")
+            .map_err(StorageError::Sqlite)?;
+        let rows = statement
+            .query_map((), |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })
+            .map_err(StorageError::Sqlite)?;
+        for row in rows {
+            let (guild_id, name, user_id) = row.map_err(StorageError::Sqlite)?;
+            let playlist = Playlist {
+                user_id: UserId::new(from_sql_id(user_id)),
+                entries: Vec::new(),
+            };
+            playlists.insert((guild_id, name), playlist);
+        }
+
+        let mut statement = connection
+            .prepare(
+                "SELECT guild_id, playlist_name, title, url FROM playlist_entries \
+                ORDER BY guild_id, playlist_name, position",
+            )
+            .map_err(StorageError::Sqlite)?;
+        let rows = statement
+            .query_map((), |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    PlaylistEntry {
+                        title: row.get(2)?,
+                        url: row.get(3)?,
+                    },
+                ))
+            })
+            .map_err(StorageError::Sqlite)?;
+        for row in rows {
+            let (guild_id, name, entry) = row.map_err(StorageError::Sqlite)?;
+            if let Some(playlist) = playlists.get_mut(&(guild_id, name)) {
+                playlist.entries.push(entry);
+            }
+        }
+
+        Ok(playlists
+            .into_iter()
+            .map(|((guild_id, name), playlist)| {
+                (GuildId::new(from_sql_id(guild_id)), name, playlist)
+            })
+            .collect())
+    }
+
+    fn save_playlist(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+        playlist: &Playlist,
+    ) -> Result<(), StorageError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(StorageError::Sqlite)?;
+        let guild_id = to_sql_id(guild_id.get());
+        transaction
+            .execute(
+                "INSERT INTO playlists (guild_id, name, user_id) VALUES (?1, ?2, ?3) \
+                ON CONFLICT (guild_id, name) DO UPDATE SET user_id = excluded.user_id",
+                (guild_id, name, to_sql_id(playlist.user_id.get())),
+            )
+            .map_err(StorageError::Sqlite)?;
+        transaction
+            .execute(
+                "DELETE FROM playlist_entries WHERE guild_id = ?1 AND playlist_name = ?2",
+                (guild_id, name),
+            )
+            .map_err(StorageError::Sqlite)?;
+        {
+            let mut statement = transaction
+                .prepare(
+                    "INSERT INTO playlist_entries (guild_id, playlist_name, position, title, url) \
+                    VALUES (?1, ?2, ?3, ?4, ?5)",
+                )
+                .map_err(StorageError::Sqlite)?;
+            for (position, entry) in playlist.entries.iter().enumerate() {
+                statement
+                    .execute((guild_id, name, position as i64, &entry.title, &entry.url))
+                    .map_err(StorageError::Sqlite)?;
+            }
+        }
+        transaction.commit().map_err(StorageError::Sqlite)
+    }
+
+    fn delete_playlist(&self, guild_id: GuildId, name: &str) -> Result<(), StorageError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(StorageError::Sqlite)?;
+        let guild_id = to_sql_id(guild_id.get());
+        transaction
+            .execute(
+                "DELETE FROM playlist_entries WHERE guild_id = ?1 AND playlist_name = ?2",
+                (guild_id, name),
+            )
+            .map_err(StorageError::Sqlite)?;
+        transaction
+            .execute(
+                "DELETE FROM playlists WHERE guild_id = ?1 AND name = ?2",
+                (guild_id, name),
+            )
+            .map_err(StorageError::Sqlite)?;
+        transaction.commit().map_err(StorageError::Sqlite)
+    }
+
+    fn load_favorites(&self) -> Result<Vec<(UserId, String, PlaylistEntry)>, StorageError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT user_id, name, title, url FROM favorites")
+            .map_err(StorageError::Sqlite)?;
+        let rows = statement
+            .query_map((), |row| {
+                Ok((
+                    UserId::new(from_sql_id(row.get(0)?)),
+                    row.get(1)?,
+                    PlaylistEntry {
+                        title: row.get(2)?,
+                        url: row.get(3)?,
+                    },
+                ))
+            })
+            .map_err(StorageError::Sqlite)?;
+        rows.collect::<Result<_, _>>().map_err(StorageError::Sqlite)
+    }
+
+    fn save_favorite(
+        &self,
+        user_id: UserId,
+        name: &str,
+        entry: &PlaylistEntry,
+    ) -> Result<(), StorageError> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT INTO favorites (user_id, name, title, url) VALUES (?1, ?2, ?3, ?4) \
+                ON CONFLICT (user_id, name) DO UPDATE \
+                SET title = excluded.title, url = excluded.url",
+                (to_sql_id(user_id.get()), name, &entry.title, &entry.url),
+            )
+            .map_err(StorageError::Sqlite)?;
+        Ok(())
+    }
+
+    fn delete_favorite(&self, user_id: UserId, name: &str) -> Result<(), StorageError> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "DELETE FROM favorites WHERE user_id = ?1 AND name = ?2",
+                (to_sql_id(user_id.get()), name),
+            )
+            .map_err(StorageError::Sqlite)?;
+        Ok(())
+    }
+
+    fn load_history(&self) -> Result<Vec<(GuildId, ChannelId, Vec<HistoryEntry>)>, StorageError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare(
+                "SELECT guild_id, channel_id, user_id, title, url, ended_time FROM history \
+                ORDER BY guild_id, channel_id, position",
+            )
+            .map_err(StorageError::Sqlite)?;
+        let rows = statement
+            .query_map((), |row| {
+                let ended_time = Timestamp::from_unix_timestamp(row.get(5)?).map_err(|why| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        5,
+                        rusqlite::types::Type::Integer,
+                        Box::new(why),
+                    )
+                })?;
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    HistoryEntry {
+                        user_id: UserId::new(from_sql_id(row.get(2)?)),
+                        title: row.get(3)?,
+                        url: row.get(4)?,
+                        ended_time,
+                    },
+                ))
+            })
+            .map_err(StorageError::Sqlite)?;
+
+        let mut history: BTreeMap<(i64, i64), Vec<HistoryEntry>> = BTreeMap::new();
+        for row in rows {
+            let (guild_id, channel_id, entry) = row.map_err(StorageError::Sqlite)?;
+            history
+                .entry((guild_id, channel_id))
+                .or_default()
+                .push(entry);
+        }
+        Ok(history
+            .into_iter()
+            .map(|((guild_id, channel_id), entries)| {
+                (
+                    GuildId::new(from_sql_id(guild_id)),
+                    ChannelId::new(from_sql_id(channel_id)),
+                    entries,
+                )
+            })
+            .collect())
+    }
+
+    fn save_channel_history(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        entries: &[HistoryEntry],
+    ) -> Result<(), StorageError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(StorageError::Sqlite)?;
+        let guild_id = to_sql_id(guild_id.get());
+        let channel_id = to_sql_id(channel_id.get());
+        transaction
+            .execute(
+                "DELETE FROM history WHERE guild_id = ?1 AND channel_id = ?2",
+                (guild_id, channel_id),
+            )
+            .map_err(StorageError::Sqlite)?;
+        {
+            let mut statement = transaction
+                .prepare(
+                    "INSERT INTO history \
+                    (guild_id, channel_id, position, user_id, title, url, ended_time) \
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )
+                .map_err(StorageError::Sqlite)?;
+            for (position, entry) in entries.iter().enumerate() {
+                statement
+                    .execute((
+                        guild_id,
+                        channel_id,
+                        position as i64,
+                        to_sql_id(entry.user_id.get()),
+                        &entry.title,
+                        &entry.url,
+                        entry.ended_time.unix_timestamp(),
+                    ))
+                    .map_err(StorageError::Sqlite)?;
+            }
+        }
+        transaction.commit().map_err(StorageError::Sqlite)
+    }
+}